@@ -0,0 +1,256 @@
+//! Planning constraints for Aniimax.
+//!
+//! Players often keep some facilities busy for their own needs, insist on
+//! making a particular item, or never want to produce others. This module
+//! parses those rules and applies them to the items and facilities a plan
+//! may use.
+//!
+//! Pinned items are planned as an order (see [`crate::orders::plan_order`]);
+//! the facilities it keeps busy are busy for the main plan only as long as
+//! the pinned batches need them.
+
+use std::collections::HashSet;
+
+use crate::models::{
+    CurrencyValuation, FacilityCounts, ForbiddenRule, InProgressBatch, Inventory, ModuleLevels, OrderLine, OrderPlan,
+    PinnedItem, PlanConstraints, ProductionItem,
+};
+use crate::optimizer::items_at_levels;
+use crate::orders::bill_of_materials;
+
+/// Items and facilities left for planning once constraints are applied.
+#[derive(Debug, Clone)]
+pub struct ConstrainedSetup {
+    /// Items that may be produced
+    pub items: Vec<ProductionItem>,
+    /// Facilities left after reservations and forbidden facilities
+    pub facility_counts: FacilityCounts,
+    /// Pinned items as an order, in units
+    pub pins: Vec<OrderLine>,
+}
+
+impl ConstrainedSetup {
+    /// Returns the facilities the plan of the pinned items keeps busy, as
+    /// batches in progress for the main plan: on each facility type, as many
+    /// instances as the pinned batches there (at most all of them), until
+    /// the last of those batches is done. The rest of every facility type,
+    /// and these instances once they are free, go to the main plan.
+    pub fn pinned_batches(&self, pinned: &OrderPlan) -> Vec<InProgressBatch> {
+        let mut busy: Vec<InProgressBatch> = Vec::new();
+        for scheduled in &pinned.schedule {
+            let available = self.facility_counts.get_count(&scheduled.facility);
+            match busy.iter_mut().find(|b| b.facility == scheduled.facility) {
+                Some(batch) => {
+                    batch.facilities = (batch.facilities + scheduled.batches).min(available);
+                    batch.remaining = batch.remaining.max(scheduled.finish_time);
+                }
+                None => busy.push(InProgressBatch {
+                    item: scheduled.name.clone(),
+                    name: scheduled.name.clone(),
+                    facility: scheduled.facility.clone(),
+                    facilities: scheduled.batches.min(available),
+                    remaining: scheduled.finish_time,
+                    units: 0,
+                    sold: true,
+                }),
+            }
+        }
+        busy
+    }
+
+    /// Returns the value of the pinned items when sold, in the valuation's
+    /// base currency.
+    pub fn pinned_value(&self, valuation: &CurrencyValuation) -> f64 {
+        self.pins
+            .iter()
+            .filter_map(|pin| {
+                let item = self.items.iter().find(|i| i.name == pin.item)?;
+                Some(pin.quantity as f64 * item.sell_value * valuation.rate(&item.sell_currency))
+            })
+            .sum()
+    }
+}
+
+/// Parses facility reservations such as `crafting_table=1,farmland=2`.
+///
+/// Facility names are matched case-insensitively with `_`, `-` or spaces
+/// between words.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::constraints::parse_reservations;
+///
+/// let reserved = parse_reservations("crafting-table=1, farmland=2").unwrap();
+/// assert_eq!(reserved, vec![("Crafting Table".to_string(), 1), ("Farmland".to_string(), 2)]);
+/// ```
+pub fn parse_reservations(spec: &str) -> Result<Vec<(String, u32)>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|entry| {
+            let (name, count) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected FACILITY=COUNT in reservation '{}'", entry))?;
+            let facility =
+                FacilityCounts::find_facility(name).ok_or_else(|| format!("Unknown facility '{}'", name.trim()))?;
+            let count: u32 = count
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid count in reservation '{}'", entry))?;
+            Ok((facility.to_string(), count))
+        })
+        .collect()
+}
+
+/// Parses pinned items such as `advanced_wood_sculpture=5` (fewest batches),
+/// optionally pinned to a facility as `advanced_wood_sculpture@crafting_table=5`.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::constraints::parse_pins;
+///
+/// let pins = parse_pins("strawberry@farmland=2, wheatmeal=1").unwrap();
+/// assert_eq!(pins[0].item, "strawberry");
+/// assert_eq!(pins[0].facility.as_deref(), Some("Farmland"));
+/// assert_eq!(pins[1].facility, None);
+/// ```
+pub fn parse_pins(spec: &str) -> Result<Vec<PinnedItem>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|entry| {
+            let (item, batches) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected ITEM=BATCHES or ITEM@FACILITY=BATCHES in pin '{}'", entry))?;
+            let (item, facility) = match item.split_once('@') {
+                Some((item, facility)) => {
+                    let facility = FacilityCounts::find_facility(facility)
+                        .ok_or_else(|| format!("Unknown facility '{}'", facility.trim()))?;
+                    (item, Some(facility.to_string()))
+                }
+                None => (item, None),
+            };
+            let min_batches: u32 = batches
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid batch count in pin '{}'", entry))?;
+            if min_batches == 0 {
+                return Err(format!("Pinned item '{}' needs at least one batch", item.trim()));
+            }
+            Ok(PinnedItem {
+                item: item.trim().to_string(),
+                min_batches,
+                facility,
+            })
+        })
+        .collect()
+}
+
+/// Parses forbidden items and facilities such as `wheat,nimbus_bed`.
+///
+/// Names matching a facility forbid the facility; anything else is taken as
+/// an item name.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::constraints::parse_forbidden;
+/// use aniimax::models::ForbiddenRule;
+///
+/// let rules = parse_forbidden("wheat, nimbus-bed");
+/// assert_eq!(rules[0], ForbiddenRule::Item("wheat".to_string()));
+/// assert_eq!(rules[1], ForbiddenRule::Facility("Nimbus Bed".to_string()));
+/// ```
+pub fn parse_forbidden(spec: &str) -> Vec<ForbiddenRule> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|name| match FacilityCounts::find_facility(name) {
+            Some(facility) => ForbiddenRule::Facility(facility.to_string()),
+            None => ForbiddenRule::Item(name.to_string()),
+        })
+        .collect()
+}
+
+/// Applies planning constraints to the items and facilities.
+///
+/// Reserved facilities are taken from the lowest level first. Forbidding an
+/// item also forbids its high-speed variant, and recipes needing it can no
+/// longer be made. Pinned items share the facilities with the main plan (see
+/// [`ConstrainedSetup::pinned_batches`]).
+///
+/// # Errors
+///
+/// Returns an error if more facilities are reserved than exist, a forbidden
+/// or pinned item is unknown, a pinned item is forbidden or pinned to a
+/// facility that doesn't make it, or a pinned item can't be produced with
+/// the remaining facilities and modules.
+pub fn apply_constraints(
+    items: &[ProductionItem],
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+    constraints: &PlanConstraints,
+) -> Result<ConstrainedSetup, String> {
+    let mut counts = facility_counts.clone();
+    for (facility, reserved) in &constraints.reservations {
+        let available = counts.get_count(facility);
+        if *reserved > available {
+            return Err(format!(
+                "Can't reserve {} {}: only {} available",
+                reserved, facility, available
+            ));
+        }
+        counts.set_count(facility, available - reserved);
+    }
+
+    let mut forbidden_items: HashSet<String> = HashSet::new();
+    let mut forbidden_facilities: HashSet<&str> = HashSet::new();
+    for rule in &constraints.forbidden {
+        match rule {
+            ForbiddenRule::Item(name) => {
+                if !items.iter().any(|i| i.name == *name) {
+                    return Err(format!("Forbidden item '{}' is unknown", name));
+                }
+                forbidden_items.insert(name.clone());
+                forbidden_items.insert(format!("high_speed_{}", name));
+            }
+            ForbiddenRule::Facility(facility) => {
+                forbidden_facilities.insert(facility);
+                counts.set_count(facility, 0);
+            }
+        }
+    }
+    let items: Vec<ProductionItem> = items
+        .iter()
+        .filter(|i| !forbidden_items.contains(&i.name) && !forbidden_facilities.contains(i.facility.as_str()))
+        .cloned()
+        .collect();
+
+    let leveled = items_at_levels(&items, &counts);
+    let mut pins = Vec::new();
+    for pin in &constraints.pins {
+        let item = leveled
+            .iter()
+            .find(|i| i.name == pin.item)
+            .ok_or_else(|| format!("Pinned item '{}' is unknown or forbidden", pin.item))?;
+        if let Some(facility) = pin.facility.as_ref().filter(|f| **f != item.facility) {
+            return Err(format!("Pinned item '{}' is made on {}, not {}", pin.item, item.facility, facility));
+        }
+        pins.push(OrderLine {
+            item: pin.item.clone(),
+            quantity: pin.min_batches * item.yield_amount.max(1),
+        });
+    }
+
+    if !pins.is_empty() {
+        bill_of_materials(&items, &pins, &counts, module_levels, &mut Inventory::default())?;
+    }
+
+    Ok(ConstrainedSetup {
+        items,
+        facility_counts: counts,
+        pins,
+    })
+}
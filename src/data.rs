@@ -2,17 +2,22 @@
 //!
 //! This module handles loading production data from CSV files located
 //! in the `data/` directory. Each facility type has its own CSV format
-//! and dedicated loading function.
+//! and dedicated loading function. Plan reports can be exported as CSV
+//! as well.
 
-use csv::ReaderBuilder;
+use csv::{ReaderBuilder, Writer};
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
+use std::io::Write;
 use std::path::Path;
 
 use crate::models::{
-    FarmlandRow, MineralRow, NimbusBedRow, ProcessingRowNoEnergy, ProcessingRowWithEnergy,
-    ProductionItem, WoodlandRow,
+    BoardOrder, ConsumableRow, FacilityCounts, FacilityLevelRow, FacilityLevelStats, FarmlandRow, InventorySeries, ItemOverride, MineralRow, NimbusBedRow, OrderLine, OrderRow, OverrideRow,
+    ProcessingRowNoEnergy, ProcessingRowWithEnergy, ProductionItem, SaleCurve, SaleLimitRow, UpgradeCost, UpgradeKind,
+    UpgradeRow, WoodlandRow,
 };
+use crate::overrides::parse_override_row;
 
 /// Parses a module requirement string (e.g., "ecological_module:1") into a tuple.
 ///
@@ -56,6 +61,12 @@ fn parse_required_amounts(s: &str) -> Vec<u32> {
         .collect()
 }
 
+/// Parses a number that may be written as "NULL", treating "NULL" (and
+/// anything unparseable) as `None`.
+fn parse_nullable_f64(field: Option<String>) -> Option<f64> {
+    field.and_then(|e| if e == "NULL" { None } else { e.parse().ok() })
+}
+
 /// Loads farmland crop data from a CSV file.
 ///
 /// # Arguments
@@ -69,7 +80,8 @@ fn parse_required_amounts(s: &str) -> Vec<u32> {
 ///
 /// # CSV Format
 ///
-/// Expected columns: `name, cost, sell_value, production_time, yield, energy, facility_level, module_requirement`
+/// Expected columns: `name, cost, sell_value, production_time, yield, energy_value, facility_level, module_requirement`.
+/// An optional `energy_cost` column gives the energy spent to produce one batch.
 pub fn load_farmland(path: &Path) -> Result<Vec<ProductionItem>, Box<dyn Error>> {
     let file = File::open(path)?;
     let mut rdr = ReaderBuilder::new()
@@ -89,10 +101,14 @@ pub fn load_farmland(path: &Path) -> Result<Vec<ProductionItem>, Box<dyn Error>>
             sell_value: row.sell_value,
             production_time: row.production_time,
             yield_amount: row.yield_amount,
-            energy: row.energy,
+            energy_cost: row.energy_cost,
+            energy_value: row.energy_value,
             facility_level: row.facility_level,
             module_requirement: parse_module_requirement(&row.module_requirement),
             requires_fertilizer: row.facility_level >= 4, // Farmland level 4+ requires fertilizer
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
         });
     }
     Ok(items)
@@ -111,11 +127,12 @@ pub fn load_farmland(path: &Path) -> Result<Vec<ProductionItem>, Box<dyn Error>>
 ///
 /// # CSV Format
 ///
-/// Expected columns: `name, cost, sell_currency, sell_value, production_time, yield, energy, facility_level, module_requirement`
+/// Expected columns: `name, cost, sell_currency, sell_value, production_time, yield, energy_value, facility_level, module_requirement`.
+/// An optional `energy_cost` column gives the energy spent to produce one batch.
 ///
 /// # Notes
 ///
-/// The energy field may contain "NULL" as a string value, which is converted to `None`.
+/// The energy fields may contain "NULL" as a string value, which is converted to `None`.
 pub fn load_woodland(path: &Path) -> Result<Vec<ProductionItem>, Box<dyn Error>> {
     let file = File::open(path)?;
     let mut rdr = ReaderBuilder::new()
@@ -125,9 +142,6 @@ pub fn load_woodland(path: &Path) -> Result<Vec<ProductionItem>, Box<dyn Error>>
     let mut items = Vec::new();
     for result in rdr.deserialize() {
         let row: WoodlandRow = result?;
-        let energy = row
-            .energy
-            .and_then(|e| if e == "NULL" { None } else { e.parse().ok() });
         items.push(ProductionItem {
             name: row.name,
             facility: "Woodland".to_string(),
@@ -138,10 +152,14 @@ pub fn load_woodland(path: &Path) -> Result<Vec<ProductionItem>, Box<dyn Error>>
             sell_value: row.sell_value,
             production_time: row.production_time,
             yield_amount: row.yield_amount,
-            energy,
+            energy_cost: parse_nullable_f64(row.energy_cost),
+            energy_value: parse_nullable_f64(row.energy_value),
             facility_level: row.facility_level,
             module_requirement: parse_module_requirement(&row.module_requirement),
             requires_fertilizer: row.facility_level >= 3, // Woodland level 3+ requires fertilizer
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
         });
     }
     Ok(items)
@@ -180,10 +198,14 @@ pub fn load_mineral_pile(path: &Path) -> Result<Vec<ProductionItem>, Box<dyn Err
             sell_value: row.sell_value,
             production_time: row.production_time,
             yield_amount: row.yield_amount,
-            energy: None,
+            energy_cost: None,
+            energy_value: None,
             facility_level: row.facility_level,
             module_requirement: parse_module_requirement(&row.module_requirement),
             requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
         });
     }
     Ok(items)
@@ -203,7 +225,8 @@ pub fn load_mineral_pile(path: &Path) -> Result<Vec<ProductionItem>, Box<dyn Err
 ///
 /// # CSV Format
 ///
-/// Expected columns: `name, raw_materials, required_amount, sell_value, production_time, energy, facility_level, module_requirement`
+/// Expected columns: `name, raw_materials, required_amount, sell_value, production_time, energy_value, facility_level, module_requirement`.
+/// An optional `energy_cost` column gives the energy spent to produce one batch.
 pub fn load_processing_with_energy(
     path: &Path,
     facility_name: &str,
@@ -228,10 +251,14 @@ pub fn load_processing_with_energy(
             sell_value: row.sell_value,
             production_time: row.production_time,
             yield_amount: 1,
-            energy: row.energy,
+            energy_cost: row.energy_cost,
+            energy_value: row.energy_value,
             facility_level: row.facility_level,
             module_requirement: parse_module_requirement(&row.module_requirement),
             requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
         });
     }
     Ok(items)
@@ -276,10 +303,14 @@ pub fn load_processing_no_energy(
             sell_value: row.sell_value,
             production_time: row.production_time,
             yield_amount: 1,
-            energy: None,
+            energy_cost: None,
+            energy_value: None,
             facility_level: row.facility_level,
             module_requirement: parse_module_requirement(&row.module_requirement),
             requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
         });
     }
     Ok(items)
@@ -318,21 +349,299 @@ pub fn load_nimbus_bed(path: &Path) -> Result<Vec<ProductionItem>, Box<dyn Error
             sell_value: row.sell_value,
             production_time: row.production_time,
             yield_amount: row.yield_amount,
-            energy: None,
+            energy_cost: None,
+            energy_value: None,
             facility_level: 1,
             module_requirement: None,
             requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
         });
     }
     Ok(items)
 }
 
+/// Loads upgrade cost data from a CSV file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the upgrades CSV file
+///
+/// # Returns
+///
+/// A vector of [`UpgradeCost`] describing the price of every facility level,
+/// facility slot and module level, or an error if the file cannot be read or parsed.
+///
+/// # CSV Format
+///
+/// Expected columns: `kind, target, level, coins, coupons`
+///
+/// `kind` is one of `facility_level`, `facility_slot` or `module`. For slots,
+/// `level` is the facility count reached after buying.
+pub fn load_upgrades(path: &Path) -> Result<Vec<UpgradeCost>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let mut rdr = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(file);
+
+    let mut upgrades = Vec::new();
+    for result in rdr.deserialize() {
+        let row: UpgradeRow = result?;
+        let kind = UpgradeKind::parse(&row.kind)
+            .ok_or_else(|| format!("Unknown upgrade kind '{}'", row.kind))?;
+        upgrades.push(UpgradeCost {
+            kind,
+            target: row.target,
+            level: row.level,
+            coins: row.coins,
+            coupons: row.coupons,
+        });
+    }
+    Ok(upgrades)
+}
+
+/// Loads an order board from a CSV file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the orders CSV file
+///
+/// # Returns
+///
+/// A vector of [`BoardOrder`], or an error if the file cannot be read or parsed.
+///
+/// # CSV Format
+///
+/// Expected columns: `name, items, amounts, reward, currency`
+///
+/// `items` and `amounts` are semicolon-separated like processing recipes,
+/// e.g. `dried_flowers;sf_medium_exp_gem, 30;10`.
+pub fn load_orders(path: &Path) -> Result<Vec<BoardOrder>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let mut rdr = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(file);
+
+    let mut orders = Vec::new();
+    for result in rdr.deserialize() {
+        let row: OrderRow = result?;
+        let items = parse_raw_materials(&row.items);
+        let amounts = parse_required_amounts(&row.amounts);
+        if items.is_empty() || items.len() != amounts.len() {
+            return Err(format!("Order '{}' needs one amount per item", row.name).into());
+        }
+        orders.push(BoardOrder {
+            name: row.name,
+            lines: items
+                .into_iter()
+                .zip(amounts)
+                .map(|(item, quantity)| OrderLine { item, quantity })
+                .collect(),
+            reward: row.reward,
+            reward_currency: row.currency,
+        });
+    }
+    Ok(orders)
+}
+
+/// Loads the values gained by consuming items from a CSV file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the consumables CSV file
+///
+/// # Returns
+///
+/// A vector of [`ConsumableRow`], or an error if the file cannot be read or parsed.
+///
+/// # CSV Format
+///
+/// Expected columns: `name, objective, value`, e.g. `sf_basic_exp_gem, experience, 100`.
+pub fn load_consumables(path: &Path) -> Result<Vec<ConsumableRow>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let mut rdr = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(file);
+
+    let mut rows = Vec::new();
+    for result in rdr.deserialize() {
+        rows.push(result?);
+    }
+    Ok(rows)
+}
+
+/// Records consumable values on the items they belong to.
+///
+/// Returns an error naming the first row whose item doesn't exist.
+pub fn apply_consumables(items: &mut [ProductionItem], rows: &[ConsumableRow]) -> Result<(), String> {
+    for row in rows {
+        let mut found = false;
+        for item in items.iter_mut().filter(|i| i.name == row.name) {
+            item.consumable_values.insert(row.objective.clone(), row.value);
+            found = true;
+        }
+        if !found {
+            return Err(format!("Consumable value given for unknown item '{}'", row.name));
+        }
+    }
+    Ok(())
+}
+
+/// Loads sale limit tiers from a CSV file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the sale limits CSV file
+///
+/// # Returns
+///
+/// A vector of [`SaleLimitRow`], or an error if the file cannot be read or parsed.
+///
+/// # CSV Format
+///
+/// Expected columns: `name, from_units, price_factor`, one row per tier.
+/// For example `wheatmeal, 200, 0.5` halves the price of wheatmeal once 200
+/// have been sold in a day, and `wheatmeal, 500, 0` stops sales at 500.
+pub fn load_sale_limits(path: &Path) -> Result<Vec<SaleLimitRow>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let mut rdr = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(file);
+
+    let mut rows = Vec::new();
+    for result in rdr.deserialize() {
+        rows.push(result?);
+    }
+    Ok(rows)
+}
+
+/// Builds sale curves from tiers and records them on the items they belong to.
+///
+/// Returns an error naming the first row whose item doesn't exist or whose
+/// price factor is negative.
+pub fn apply_sale_limits(items: &mut [ProductionItem], rows: &[SaleLimitRow]) -> Result<(), String> {
+    for row in rows {
+        if row.price_factor < 0.0 {
+            return Err(format!("Negative price factor for '{}'", row.name));
+        }
+        let mut found = false;
+        for item in items.iter_mut().filter(|i| i.name == row.name) {
+            let curve = item.sale_curve.get_or_insert_with(SaleCurve::default);
+            curve.tiers.push((row.from_units, row.price_factor));
+            curve.tiers.sort_by_key(|&(from, _)| from);
+            found = true;
+        }
+        if !found {
+            return Err(format!("Sale limit given for unknown item '{}'", row.name));
+        }
+    }
+    Ok(())
+}
+
+/// Loads facility level stats from a CSV file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the facility levels CSV file
+///
+/// # Returns
+///
+/// A vector of [`FacilityLevelRow`], or an error if the file cannot be read or parsed.
+///
+/// # CSV Format
+///
+/// Expected columns: `facility, level, speed_multiplier, yield_bonus, requires_fertilizer`,
+/// one row per level where something changes. For example
+/// `Farmland, 5, 1.25, 0.1, ` makes level 5 Farmland and up grow 25% faster
+/// with 10% more per batch; `requires_fertilizer` is left empty unless the
+/// level changes which crops need fertilizer.
+pub fn load_facility_levels(path: &Path) -> Result<Vec<FacilityLevelRow>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let mut rdr = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(file);
+
+    let mut rows = Vec::new();
+    for result in rdr.deserialize() {
+        rows.push(result?);
+    }
+    Ok(rows)
+}
+
+/// Records facility level stats on every item made in that facility.
+///
+/// Returns an error naming the first row with an unknown facility, a speed
+/// multiplier that isn't positive or a negative yield bonus.
+pub fn apply_facility_levels(items: &mut [ProductionItem], rows: &[FacilityLevelRow]) -> Result<(), String> {
+    for row in rows {
+        if !FacilityCounts::FACILITY_NAMES.contains(&row.facility.as_str()) {
+            return Err(format!("Level stats given for unknown facility '{}'", row.facility));
+        }
+        if !(row.speed_multiplier > 0.0 && row.speed_multiplier.is_finite()) {
+            return Err(format!("Invalid speed multiplier for {} level {}", row.facility, row.level));
+        }
+        if !(row.yield_bonus >= 0.0 && row.yield_bonus.is_finite()) {
+            return Err(format!("Invalid yield bonus for {} level {}", row.facility, row.level));
+        }
+        for item in items.iter_mut().filter(|i| i.facility == row.facility) {
+            item.level_stats.push(FacilityLevelStats {
+                level: row.level,
+                speed_multiplier: row.speed_multiplier,
+                yield_bonus: row.yield_bonus,
+                requires_fertilizer: row.requires_fertilizer,
+            });
+            item.level_stats.sort_by_key(|s| s.level);
+        }
+    }
+    Ok(())
+}
+
+/// Loads price and timing overrides from a CSV file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the overrides CSV file
+///
+/// # Returns
+///
+/// A vector of [`ItemOverride`] in file order, or an error if the file cannot
+/// be read or parsed or a row is invalid (see [`parse_override_row`]).
+///
+/// # CSV Format
+///
+/// Expected columns: `scope, target, field, operation, value, start_hours, end_hours`
+///
+/// `scope` is `global`, `facility` or `item` (with the facility or item name
+/// as `target`), `field` is `sell_value`, `production_time` or `yield`, and
+/// `operation` is `multiply` or `set`. The window is in hours from plan start;
+/// leave either end empty for an open window. For example
+/// `item, wheat, sell_value, multiply, 1.5, 2, 26` raises wheat prices by 50%
+/// from hour 2 to hour 26.
+pub fn load_overrides(path: &Path) -> Result<Vec<ItemOverride>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let mut rdr = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(file);
+
+    let mut overrides = Vec::new();
+    for result in rdr.deserialize() {
+        let row: OverrideRow = result?;
+        overrides.push(parse_override_row(&row)?);
+    }
+    Ok(overrides)
+}
+
 /// Loads all production data from the data directory.
 ///
 /// This function loads data from all facility types:
 /// - Raw materials: Farmland, Woodland, Mineral Pile, Nimbus Bed
 /// - Processing: Carousel Mill, Jukebox Dryer, Crafting Table, Dance Pad Polisher, Aniipod Maker
 ///
+/// Facility level stats from `facility_levels.csv`, consumable values from
+/// `consumables.csv` and sale limits from `sale_limits.csv` are attached if
+/// the files exist.
+///
 /// # Arguments
 ///
 /// * `data_dir` - Path to the directory containing CSV files
@@ -382,5 +691,57 @@ pub fn load_all_data(data_dir: &Path) -> Result<Vec<ProductionItem>, Box<dyn Err
         "Aniipod Maker",
     )?);
 
+    // Facility level stats, consumable values and sale limits are optional
+    let facility_levels_path = data_dir.join("facility_levels.csv");
+    if facility_levels_path.exists() {
+        apply_facility_levels(&mut all_items, &load_facility_levels(&facility_levels_path)?)?;
+    }
+    let consumables_path = data_dir.join("consumables.csv");
+    if consumables_path.exists() {
+        apply_consumables(&mut all_items, &load_consumables(&consumables_path)?)?;
+    }
+    let sale_limits_path = data_dir.join("sale_limits.csv");
+    if sale_limits_path.exists() {
+        apply_sale_limits(&mut all_items, &load_sale_limits(&sale_limits_path)?)?;
+    }
+
     Ok(all_items)
 }
+
+/// Writes the stock of every item over a plan as CSV: a `time_seconds`
+/// column and one column per item, with a row for every time any level
+/// changes.
+///
+/// # Arguments
+///
+/// * `writer` - Where to write the CSV (a file, or a buffer)
+/// * `series` - Stock levels, e.g. a plan's `inventory_over_time`
+///
+/// # Example
+///
+/// ```
+/// use aniimax::data::write_inventory_csv;
+/// use aniimax::models::InventorySeries;
+///
+/// let wheat = InventorySeries::from_points("wheat", vec![(0.0, 0), (60.0, 10), (90.0, 0)]);
+/// let mut csv = Vec::new();
+/// write_inventory_csv(&mut csv, &[wheat]).unwrap();
+/// assert_eq!(String::from_utf8(csv).unwrap(), "time_seconds,wheat\n0,0\n60,10\n90,0\n");
+/// ```
+pub fn write_inventory_csv<W: Write>(writer: W, series: &[InventorySeries]) -> Result<(), Box<dyn Error>> {
+    let mut times: Vec<f64> = series.iter().flat_map(|s| s.points.iter().map(|p| p.0)).collect();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    times.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+
+    let mut csv = Writer::from_writer(writer);
+    let mut header = vec!["time_seconds".to_string()];
+    header.extend(series.iter().map(|s| s.name.clone()));
+    csv.write_record(&header)?;
+    for time in times {
+        let mut row = vec![format!("{:.0}", time)];
+        row.extend(series.iter().map(|s| s.level_at(time).to_string()));
+        csv.write_record(&row)?;
+    }
+    csv.flush()?;
+    Ok(())
+}
@@ -3,7 +3,11 @@
 //! This module provides functions for formatting output and displaying
 //! optimization results to the user in a readable format.
 
-use crate::models::{ProductionEfficiency, ProductionPath, ProductionStep};
+use crate::models::{
+    CurrencyLedger, CurrencyValuation, DeadlinePlan, EnergyBudget, Inventory, OrderBoardPlan, OrderPlan, ProductionEfficiency, ProductionPath, ProductionStep,
+    ProgressionPlan, UpgradeCost, UpgradeKind, UpgradeRoi,
+};
+use crate::optimizer::energy_spends;
 
 /// Formats a duration in seconds to a human-readable string.
 ///
@@ -99,10 +103,11 @@ pub fn display_results(
             let chain_profit: f64 = steps.iter().map(|s| s.profit_contribution).sum();
             let chain_time = steps.iter().map(|s| s.time).fold(0.0, f64::max);
             
-            println!("  Chain {}: {} ({:.0} coins in {})", 
+            println!("  Chain {}: {} ({:.0} {} in {})", 
                 chain_num + 1, 
                 chain_desc,
                 chain_profit,
+                path.currency,
                 format_time(chain_time)
             );
             
@@ -143,6 +148,7 @@ pub fn display_results(
     println!("[SUMMARY]");
     println!("----------------------------------------------------------------");
     println!("  Total Profit:     {:.0} {}", path.total_profit, path.currency);
+    display_ledger(&path.ledger);
     println!("  Total Time:       {}", format_time(path.total_time));
     if path.startup_time > 0.0 {
         println!("    - Startup:      {} (first batch)", format_time(path.startup_time));
@@ -152,6 +158,21 @@ pub fn display_results(
         println!("  Total Energy:     {:.0}", energy);
     }
     println!("  Items Produced:   {}", path.items_produced);
+    // Known once the plan has been simulated batch by batch
+    if path.unsimulated.is_empty() {
+        println!("  Peak Inventory:   {} units", path.peak_inventory);
+    }
+    // Items whose sale limits brought the price down
+    for (name, price) in &path.average_prices {
+        if let Some(item) = efficiencies.iter().map(|e| &e.item).find(|i| &i.name == name) {
+            if *price < item.sell_value {
+                println!(
+                    "  Avg Price:        {} {:.2} {} (list {:.0})",
+                    name, price, item.sell_currency, item.sell_value
+                );
+            }
+        }
+    }
     
     if path.is_energy_self_sufficient {
         println!();
@@ -161,6 +182,8 @@ pub fn display_results(
                 println!("  Energy Item:      {}x {}", energy_count, energy_item);
             }
         }
+        let gained = path.steps.iter().filter_map(|s| s.energy_gained).fold(0.0, |a, b| a + b);
+        println!("  Energy Eaten:     {:.0}", gained);
     }
 
     println!();
@@ -174,8 +197,8 @@ pub fn display_results(
     );
     println!("----------------------------------------------------------------");
     println!(
-        "{:<20} {:>12} {:>12} {:>12}",
-        "Item", "Profit/sec", "Profit/energy", "Time/unit"
+        "{:<20} {:>12} {:>12} {:>12} {:>12}",
+        "Item", "Profit/sec", "Profit/energy", "Time/unit", "Other cost"
     );
     println!("----------------------------------------------------------------");
 
@@ -201,14 +224,280 @@ pub fn display_results(
             .map(|e| format!("{:.4}", e))
             .unwrap_or_else(|| "N/A".to_string());
         println!(
-            "{:<20} {:>12.4} {:>12} {:>12}",
+            "{:<20} {:>12.4} {:>12} {:>12} {:>12}",
             eff.item.name,
             eff.profit_per_second,
             energy_str,
-            format_time(eff.total_time_per_unit)
+            format_time(eff.total_time_per_unit),
+            other_costs(&eff.ledger, &eff.valuation)
+        );
+    }
+
+    println!();
+}
+
+/// Prints income, expenses and net for every currency the plan touches.
+fn display_ledger(ledger: &CurrencyLedger) {
+    for currency in ledger.currencies() {
+        println!(
+            "    {:<13} +{:.0} income, -{:.0} expenses, {:.0} net",
+            format!("{}:", currency),
+            ledger.earned(currency),
+            ledger.spent(currency),
+            ledger.net(currency)
+        );
+    }
+}
+
+/// Formats a batch's expenses in currencies its profit doesn't count
+/// (e.g., "5 coins" for a coupon item), or "-" if there are none.
+fn other_costs(ledger: &CurrencyLedger, valuation: &CurrencyValuation) -> String {
+    let costs: Vec<String> = ledger
+        .currencies()
+        .into_iter()
+        .filter(|&c| valuation.rate(c) == 0.0 && ledger.spent(c) > 0.0)
+        .map(|c| format!("{:.0} {}", ledger.spent(c), c))
+        .collect();
+    if costs.is_empty() {
+        "-".to_string()
+    } else {
+        costs.join(", ")
+    }
+}
+
+/// Displays how a plan uses an energy budget: the energy regenerated over
+/// the plan's duration, what it spends and what is left.
+///
+/// # Arguments
+///
+/// * `path` - A plan from [`crate::optimizer::find_energy_budget_path`] or
+///   [`crate::optimizer::find_parallel_energy_budget_path`]
+/// * `budget` - The energy budget it was planned for
+pub fn display_energy_budget(path: &ProductionPath, budget: &EnergyBudget) {
+    let used = path.total_energy.unwrap_or(0.0);
+    // What regenerates while the pool is at its cap is lost
+    let left = budget
+        .spend(&energy_spends(path), path.total_time)
+        .map_or(0.0, |(_, left)| left);
+
+    println!("[ENERGY BUDGET]");
+    println!("----------------------------------------------------------------");
+    println!("  Starting Energy:  {:.0}", budget.starting_energy());
+    if budget.regen_per_min > 0.0 {
+        println!(
+            "  Regenerated:      {:.0} ({}/min over {})",
+            (left + used - budget.starting_energy()).max(0.0),
+            budget.regen_per_min,
+            format_time(path.total_time)
+        );
+    }
+    println!("  Energy Used:      {:.0}", used);
+    println!("  Energy Left:      {:.0}", left);
+    if used <= 0.0 {
+        println!("  (no item in this plan costs energy, so the budget doesn't limit it)");
+    }
+    println!();
+}
+
+/// Displays how a deadline plan compares with its deadline and what it
+/// costs, or that the deadline can't be met and how long the fastest plan
+/// takes.
+///
+/// # Arguments
+///
+/// * `plan` - A plan from [`crate::optimizer::find_deadline_path`]
+pub fn display_deadline_plan(plan: &DeadlinePlan) {
+    let Some(ref path) = plan.path else {
+        return;
+    };
+    if !plan.meets_deadline {
+        println!(
+            "[WARNING] The target can't be reached within {}; the fastest plan (above) takes {}.",
+            format_time(plan.deadline),
+            format_time(path.total_time)
+        );
+        println!();
+        return;
+    }
+
+    println!("[DEADLINE]");
+    println!("----------------------------------------------------------------");
+    println!("  Deadline:         {}", format_time(plan.deadline));
+    println!(
+        "  Finishes:         {} ({} to spare)",
+        format_time(path.total_time),
+        format_time(plan.deadline - path.total_time)
+    );
+    println!("  Cost:             {:.0} {} (lowest in time)", plan.cost, plan.objective.label());
+    println!();
+}
+
+/// Displays which item finishes a target soonest at each target size, with
+/// the range holding `target` marked.
+///
+/// # Arguments
+///
+/// * `ranges` - (smallest target, item) pairs from
+///   [`crate::optimizer::fastest_item_by_target`]
+/// * `target` - The target of the current plan
+/// * `currency` - The currency the targets are in
+pub fn display_fastest_by_target(ranges: &[(f64, String)], target: f64, currency: &str) {
+    if ranges.len() < 2 {
+        return;
+    }
+
+    println!("[FASTEST ITEM BY TARGET]");
+    println!("----------------------------------------------------------------");
+    for (i, (from, item)) in ranges.iter().enumerate() {
+        let upto = ranges.get(i + 1).map(|(next, _)| *next);
+        let range = match upto {
+            Some(next) if i == 0 => format!("Up to {:.0} {}", next - 1.0, currency),
+            Some(next) => format!("{:.0}-{:.0} {}", from, next - 1.0, currency),
+            None => format!("From {:.0} {}", from, currency),
+        };
+        let current = target >= *from && upto.is_none_or(|next| target < next);
+        println!("  {:<30} {}{}", range, item, if current { "  <- this target" } else { "" });
+    }
+    println!();
+}
+
+/// Displays how the coin balance moves over a plan made with a starting
+/// balance: its lowest point, the final balance and the balance at evenly
+/// spread times.
+///
+/// # Arguments
+///
+/// * `path` - A plan with a non-empty `balance_over_time`
+pub fn display_cash_flow(path: &ProductionPath) {
+    let series = &path.balance_over_time;
+    let Some(&(_, start)) = series.first() else {
+        return;
+    };
+    let (end_time, end) = series.last().copied().unwrap_or((0.0, start));
+    let (low_time, low) = series
+        .iter()
+        .copied()
+        .fold((0.0, start), |lowest, point| if point.1 < lowest.1 { point } else { lowest });
+
+    println!("[CASH FLOW]");
+    println!("----------------------------------------------------------------");
+    println!("  Starting Coins:   {:.0}", start);
+    println!("  Lowest Balance:   {:.0} (at {})", low, format_time(low_time));
+    println!("  Final Balance:    {:.0}", end);
+    println!();
+    println!("  {:<16}{:>12}", "Time", "Coins");
+
+    let samples = if end_time > 0.0 { 12 } else { 0 };
+    for i in 0..=samples {
+        let time = end_time * i as f64 / samples.max(1) as f64;
+        let coins = series
+            .iter()
+            .take_while(|point| point.0 <= time + 1e-9)
+            .last()
+            .map_or(start, |point| point.1);
+        println!("  {:<16}{:>12.0}", format_time(time), coins);
+    }
+    println!();
+}
+
+/// Displays what a plan does with every item it takes from stock or
+/// makes: units used, and the surplus whole batches leave over or sell.
+/// With units left over, also shows the inventory to start the next plan
+/// from.
+///
+/// # Arguments
+///
+/// * `path` - The plan
+/// * `starting` - The inventory the plan started from
+pub fn display_material_balance(path: &ProductionPath, starting: &Inventory) {
+    // A single item made and sold has nothing to balance
+    if path.material_balance.len() < 2 {
+        return;
+    }
+
+    println!("[MATERIAL BALANCE]");
+    println!("----------------------------------------------------------------");
+    println!(
+        "{:<22} {:>10} {:>10} {:>10} {:>10}",
+        "Item", "From stock", "Produced", "Used", "Left over"
+    );
+    println!("----------------------------------------------------------------");
+    for balance in &path.material_balance {
+        let left_over = if balance.surplus_sold > 0 {
+            format!("{} sold", balance.surplus_sold)
+        } else {
+            balance.left_over.to_string()
+        };
+        println!(
+            "{:<22} {:>10} {:>10} {:>10} {:>10}",
+            balance.name, balance.from_stock, balance.produced, balance.consumed, left_over
         );
     }
+    println!();
 
+    if path.material_balance.iter().any(|b| b.left_over > 0) {
+        println!(
+            "  Next plan:        --inventory \"{}\"",
+            path.leftover_inventory(starting).to_spec()
+        );
+        println!();
+    }
+}
+
+/// Displays how the stock of every item rises and falls over a plan: its
+/// level at the start, its peak, its lowest point while in use and at the
+/// end, then the level of each item at evenly spread times.
+///
+/// # Arguments
+///
+/// * `path` - The plan
+pub fn display_inventory_over_time(path: &ProductionPath) {
+    let series = &path.inventory_over_time;
+    if series.is_empty() {
+        return;
+    }
+    let end_time = series
+        .iter()
+        .filter_map(|s| s.points.last())
+        .map(|point| point.0)
+        .fold(0.0, f64::max);
+
+    println!("[INVENTORY OVER TIME]");
+    println!("----------------------------------------------------------------");
+    println!(
+        "{:<22} {:>6} {:>6} {:>12} {:>6} {:>12} {:>6}",
+        "Item", "Start", "Peak", "at", "Lowest", "at", "End"
+    );
+    println!("----------------------------------------------------------------");
+    for item in series {
+        println!(
+            "{:<22} {:>6} {:>6} {:>12} {:>6} {:>12} {:>6}",
+            item.name,
+            item.points.first().map_or(0, |point| point.1),
+            item.peak,
+            format_time(item.peak_time),
+            item.minimum,
+            format_time(item.minimum_time),
+            item.level_at(end_time)
+        );
+    }
+    println!();
+
+    let widths: Vec<usize> = series.iter().map(|s| s.name.len().max(6)).collect();
+    print!("  {:<16}", "Time");
+    for (item, width) in series.iter().zip(&widths) {
+        print!(" {:>width$}", item.name, width = width);
+    }
+    println!();
+    let samples = if end_time > 0.0 { 12 } else { 0 };
+    for i in 0..=samples {
+        let time = end_time * i as f64 / samples.max(1) as f64;
+        print!("  {:<16}", format_time(time));
+        for (item, width) in series.iter().zip(&widths) {
+            print!(" {:>width$}", item.level_at(time), width = width);
+        }
+        println!();
+    }
     println!();
 }
 
@@ -258,3 +547,291 @@ pub fn display_energy_recommendations(efficiencies: &[ProductionEfficiency]) {
         );
     }
 }
+
+/// Displays upgrade return-on-investment rankings.
+///
+/// Shows every next-step upgrade with its price, the profit rate gain it
+/// brings and how long that gain takes to repay the price.
+///
+/// # Arguments
+///
+/// * `rois` - ROI figures, as returned by [`crate::upgrades::calculate_upgrade_roi`]
+/// * `unpriced` - Next steps without a price, as returned by [`crate::upgrades::unpriced_upgrades`]
+/// * `currency` - The currency profit is measured in
+pub fn display_upgrade_roi(rois: &[UpgradeRoi], unpriced: &[UpgradeCost], currency: &str) {
+    println!();
+    println!("[UPGRADE RETURN ON INVESTMENT] (profit in {})", currency);
+    println!("----------------------------------------------------------------");
+
+    if rois.is_empty() {
+        println!("  No further upgrades available.");
+        display_unpriced_upgrades(unpriced);
+        return;
+    }
+
+    println!(
+        "{:<26} {:>8} {:>8} {:>9} {:>12} {:>6}",
+        "Upgrade", "Coins", "Coupons", "Gain/sec", "Payback", "Afford"
+    );
+    println!("----------------------------------------------------------------");
+
+    for roi in rois {
+        let gain = roi.upgraded_profit_per_second - roi.current_profit_per_second;
+        let payback = match roi.payback_time {
+            Some(t) => format_time(t),
+            None if gain > 1e-9 => "n/a".to_string(),
+            None => "never".to_string(),
+        };
+        println!(
+            "{:<26} {:>8.0} {:>8.0} {:>9.4} {:>12} {:>6}",
+            roi.upgrade.describe(),
+            roi.upgrade.coins,
+            roi.upgrade.coupons,
+            gain,
+            payback,
+            if roi.affordable { "yes" } else { "no" }
+        );
+    }
+    display_unpriced_upgrades(unpriced);
+}
+
+/// Lists next steps missing from the upgrade price list, so they aren't
+/// mistaken for upgrades that don't exist.
+fn display_unpriced_upgrades(unpriced: &[UpgradeCost]) {
+    if unpriced.is_empty() {
+        return;
+    }
+    let names: Vec<String> = unpriced.iter().map(|u| u.describe()).collect();
+    println!();
+    println!("  Not ranked (no price in upgrades.csv): {}", names.join(", "));
+    println!("  Add rows for any of these your game offers to rank them.");
+}
+
+/// Displays a multi-step progression plan as a milestone timeline.
+///
+/// For each milestone this shows when the upgrade is bought, what to produce
+/// beforehand to afford it, and the balance left afterwards.
+///
+/// # Arguments
+///
+/// * `plan` - The plan, as returned by [`crate::upgrades::plan_progression`]
+pub fn display_progression_plan(plan: &ProgressionPlan) {
+    println!();
+    println!("[PROGRESSION PLAN]");
+    println!("----------------------------------------------------------------");
+
+    if plan.milestones.is_empty() {
+        println!("  No upgrades worth buying within the plan limits.");
+        return;
+    }
+
+    for (i, milestone) in plan.milestones.iter().enumerate() {
+        println!(
+            "  Milestone {} at {}: buy {} ({:.0} coins, {:.0} coupons)",
+            i + 1,
+            format_time(milestone.purchase_time),
+            milestone.upgrade.describe(),
+            milestone.upgrade.coins,
+            milestone.upgrade.coupons
+        );
+
+        if milestone.production.is_empty() {
+            println!("    → Already affordable");
+        }
+        for path in &milestone.production {
+            for step in path.steps.iter().filter(|s| s.profit_contribution > 0.0) {
+                println!(
+                    "    → Produce {} x {} at {} ({:.0} {})",
+                    step.quantity, step.item_name, step.facility, step.profit_contribution, path.currency
+                );
+            }
+        }
+        let now = match milestone.upgrade.kind {
+            UpgradeKind::Module => format!(
+                "{} Lv.{}",
+                milestone.upgrade.target,
+                milestone.module_levels.get_level(&milestone.upgrade.target)
+            ),
+            _ => format!(
+                "{} {} x Lv.{}",
+                milestone.upgrade.target,
+                milestone.facility_counts.get_count(&milestone.upgrade.target),
+                milestone.facility_counts.get_level(&milestone.upgrade.target)
+            ),
+        };
+        println!("    Now:           {}", now);
+        println!(
+            "    Balance after: {:.0} coins, {:.0} coupons",
+            milestone.balance_after.coins, milestone.balance_after.coupons
+        );
+    }
+
+    println!();
+    println!("  Plan length:      {}", format_time(plan.total_time));
+    if plan.goal_reached {
+        println!("  Goal configuration reached.");
+    }
+}
+
+/// Displays a produce-to-order plan: the bill of materials, the facility
+/// schedule and a summary of time, seed cost and energy.
+///
+/// # Arguments
+///
+/// * `plan` - The plan, as returned by [`crate::orders::plan_order`]
+pub fn display_order_plan(plan: &OrderPlan) {
+    println!();
+    println!("[ORDER]");
+    println!("----------------------------------------------------------------");
+    for line in &plan.order {
+        println!("  {} x {}", line.quantity, line.item);
+    }
+
+    println!();
+    println!("[BILL OF MATERIALS]");
+    println!("----------------------------------------------------------------");
+    println!(
+        "{:<20} {:<18} {:>7} {:>7} {:>7}",
+        "Item", "Facility", "Need", "Stock", "Batches"
+    );
+    println!("----------------------------------------------------------------");
+    for usage in &plan.materials {
+        println!(
+            "{:<20} {:<18} {:>7} {:>7} {:>7}",
+            usage.variant, usage.facility, usage.required, usage.from_stock, usage.batches
+        );
+    }
+
+    println!();
+    println!("[SCHEDULE]");
+    println!("----------------------------------------------------------------");
+    if plan.schedule.is_empty() {
+        println!("  Everything is already in stock.");
+    } else {
+        println!(
+            "{:<20} {:<18} {:>7} {:>11} {:>11}",
+            "Item", "Facility", "Batches", "Start", "Finish"
+        );
+        println!("----------------------------------------------------------------");
+        for item in &plan.schedule {
+            println!(
+                "{:<20} {:<18} {:>7} {:>11} {:>11}",
+                item.name,
+                item.facility,
+                item.batches,
+                format_time(item.start_time),
+                format_time(item.finish_time)
+            );
+        }
+    }
+
+    println!();
+    println!("[SUMMARY]");
+    println!("----------------------------------------------------------------");
+    println!("  Completion Time:  {}", format_time(plan.completion_time));
+    println!("  Seed Cost:        {:.0} coins", plan.seed_cost);
+    if let Some(energy) = plan.energy {
+        println!("  Total Energy:     {:.0}", energy);
+    }
+    println!("  Peak Inventory:   {} units", plan.peak_inventory);
+}
+
+/// Displays the plan for pinned items, which runs alongside the main plan on
+/// its own facilities.
+///
+/// # Arguments
+///
+/// * `plan` - The pinned items' plan, as returned by [`crate::orders::plan_order`]
+/// * `value` - What the pinned items sell for, in `currency`
+/// * `currency` - Currency the value is given in
+pub fn display_pinned_plan(plan: &OrderPlan, value: f64, currency: &str) {
+    println!();
+    println!("[PINNED ITEMS] (run alongside the plan below)");
+    println!("----------------------------------------------------------------");
+    for item in &plan.schedule {
+        println!(
+            "  {} x {} at {} ({} to {})",
+            item.batches,
+            item.name,
+            item.facility,
+            format_time(item.start_time),
+            format_time(item.finish_time)
+        );
+    }
+    println!("  Completion Time:  {}", format_time(plan.completion_time));
+    println!("  Value:            {:.0} {}", value, currency);
+}
+
+/// Displays an order board: every order with its reward, the market value of
+/// its goods and whether it was picked, followed by the plan for the picked
+/// orders and a comparison with selling produce instead.
+///
+/// # Arguments
+///
+/// * `board` - The board, as returned by [`crate::orders::optimize_order_board`]
+pub fn display_order_board(board: &OrderBoardPlan) {
+    let currency = &board.valuation.base;
+    println!();
+    println!(
+        "[ORDER BOARD] (budget {}, valued in {})",
+        format_time(board.time_budget),
+        currency
+    );
+    println!("----------------------------------------------------------------");
+    println!(
+        "{:<20} {:>8} {:<8} {:>8} {:>11} {:>4}",
+        "Order", "Reward", "", "Market", "Alone", "Pick"
+    );
+    println!("----------------------------------------------------------------");
+    for eval in &board.evaluations {
+        println!(
+            "{:<20} {:>8.0} {:<8} {:>8.0} {:>11} {:>4}",
+            eval.order.name,
+            eval.order.reward,
+            eval.order.reward_currency,
+            eval.market_value,
+            eval.completion_time
+                .map(format_time)
+                .unwrap_or_else(|| "n/a".to_string()),
+            if eval.selected { "yes" } else { "no" }
+        );
+    }
+
+    if let Some(ref plan) = board.plan {
+        display_order_plan(plan);
+    }
+
+    println!();
+    println!("[ORDER BOARD SUMMARY]");
+    println!("----------------------------------------------------------------");
+    let reward_currencies = board.rewards.currencies();
+    if reward_currencies.is_empty() {
+        println!("  Order Rewards:    0 {}", currency);
+    }
+    for reward_currency in reward_currencies {
+        let reward = board.rewards.earned(reward_currency);
+        if reward_currency == currency {
+            println!("  Order Rewards:    {:.0} {}", reward, currency);
+        } else {
+            println!(
+                "  Order Rewards:    {:.0} {} (worth {:.0} {})",
+                reward,
+                reward_currency,
+                reward * board.valuation.rate(reward_currency),
+                currency
+            );
+        }
+    }
+    if board.seed_cost() > 0.0 {
+        println!("  Seed Cost:        -{:.0} {}", board.seed_cost(), currency);
+    }
+    if board.sales_during > 0.0 {
+        println!("  Sales During:     {:.0} {}", board.sales_during, currency);
+    }
+    println!("  Sales After:      {:.0} {}", board.sale_value, currency);
+    println!("  Total:            {:.0} {}", board.total_value(), currency);
+    println!("  Selling Only:     {:.0} {}", board.sell_only_value, currency);
+    if board.plan.is_none() {
+        println!("  No order beats selling produce within the budget.");
+    }
+}
@@ -18,6 +18,12 @@
 //! - [`data`] - CSV data loading functionality
 //! - [`optimizer`] - Production optimization algorithms
 //! - [`display`] - Output formatting and display utilities
+//! - [`upgrades`] - Upgrade costs and return-on-investment planning
+//! - [`orders`] - Produce-to-order planning for specific item quantities
+//! - [`overrides`] - Price and timing overrides for events and boosts
+//! - [`constraints`] - Facility reservations, pinned items and forbidden items
+//! - [`progress`] - Batches already in progress when planning starts
+//! - [`schedule`] - Batch-level production scheduling with storage limits
 //!
 //! ## Example Usage
 //!
@@ -44,6 +50,7 @@
 //!     dance_pad_polisher: (1, 1),
 //!     aniipod_maker: (1, 1),
 //!     nimbus_bed: (1, 1),      // Produces fertilizer for level 4+ farmland and level 3+ woodland
+//!     instance_levels: Default::default(),
 //! };
 //!
 //! // Define module levels (0 = not unlocked)
@@ -68,10 +75,16 @@
 //! 2. **Energy Optimization**: Finds the most energy-efficient production path,
 //!    useful when energy is a limited resource.
 
+pub mod constraints;
 pub mod data;
 pub mod display;
 pub mod models;
 pub mod optimizer;
+pub mod orders;
+pub mod overrides;
+pub mod progress;
+pub mod schedule;
+pub mod upgrades;
 pub mod wasm;
 
 use serde::{Deserialize, Deserializer};
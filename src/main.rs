@@ -8,10 +8,25 @@ use std::error::Error;
 use std::path::Path;
 
 use aniimax::{
-    data::load_all_data,
-    display::{display_energy_recommendations, display_results},
-    models::{FacilityCounts, ModuleLevels},
-    optimizer::{calculate_efficiencies, calculate_energy_efficiencies, find_best_production_path, find_parallel_production_path, find_self_sufficient_path},
+    data::{load_all_data, load_orders, load_overrides, load_upgrades, write_inventory_csv},
+    display::{
+        display_cash_flow, display_deadline_plan, display_energy_budget, display_energy_recommendations, display_fastest_by_target, display_inventory_over_time, display_material_balance,
+        display_order_board, display_order_plan, display_pinned_plan, display_progression_plan, display_results, display_upgrade_roi, format_time,
+    },
+    models::{
+        CostObjective, CurrencyBalance, CurrencyValuation, EnergyBudget, FacilityCounts, ForbiddenRule, Inventory, ModuleLevels,
+        OverrideTimeline, PlanConstraints, PlanningOptions, ProgressionGoal, StorageCapacity,
+    },
+    optimizer::{
+        calculate_energy_efficiencies, calculate_valued_efficiencies, find_best_production_path_with_options,
+        fastest_item_by_target, find_deadline_path, find_energy_budget_path, find_parallel_energy_budget_path,
+        find_parallel_production_path_with_options, find_self_sufficient_path_with_options, simulate_stock_levels,
+    },
+    constraints::{apply_constraints, parse_forbidden, parse_pins, parse_reservations},
+    orders::{optimize_order_board, parse_order, plan_order},
+    overrides::{apply_overrides, check_override_targets},
+    progress::{credit_in_progress, parse_duration, parse_in_progress},
+    upgrades::{calculate_upgrade_roi, parse_goal_spec, plan_progression, unpriced_upgrades},
 };
 
 /// Command-line arguments for Aniimax.
@@ -20,13 +35,17 @@ use aniimax::{
 #[command(author, version, about = "Optimize production paths for currency generation in Aniimo Homeland", long_about = None)]
 struct Args {
     /// Target amount of currency to produce
-    #[arg(short, long)]
-    target: f64,
+    #[arg(short, long, required_unless_present_any = ["order", "orders_file", "energy_budget"])]
+    target: Option<f64>,
 
-    /// Currency type to optimize for (coins or coupons)
+    /// Currency type to optimize for (coins, coupons, or a consumable value such as experience)
     #[arg(short, long, default_value = "coins")]
     currency: String,
 
+    /// Worth of other currencies in --currency, to earn any mix of them (e.g. "coupons=20")
+    #[arg(long)]
+    valuation: Option<String>,
+
     /// Energy cost per minute (for energy self-sufficiency calculation)
     #[arg(short, long, default_value = "0.0")]
     energy_cost: f64,
@@ -39,6 +58,32 @@ struct Args {
     #[arg(long, default_value = "false")]
     parallel: bool,
 
+    /// Rank items by profit per energy instead of profit per second
+    #[arg(long, default_value = "false")]
+    optimize_energy: bool,
+
+    // ========== Energy Budget ==========
+    /// Energy available; plan the most profit it pays for instead of a target
+    #[arg(long)]
+    energy_budget: Option<f64>,
+
+    /// Energy regenerated per minute (with --energy-budget)
+    #[arg(long, default_value = "0.0")]
+    energy_regen: f64,
+
+    /// Most energy that can be stored (with --energy-budget)
+    #[arg(long)]
+    energy_cap: Option<f64>,
+
+    // ========== Deadline ==========
+    /// Reach the target by this time (e.g. "14h" or "1h30m") as cheaply as possible, instead of as fast as possible
+    #[arg(long, requires = "target", conflicts_with_all = ["order", "orders_file", "energy_budget"])]
+    deadline: Option<String>,
+
+    /// Cost to keep lowest with --deadline: energy, seed_coins or interactions
+    #[arg(long, default_value = "energy")]
+    minimize: String,
+
     // ========== Farmland ==========
     /// Number of Farmland plots available
     #[arg(long, default_value = "1")]
@@ -120,6 +165,11 @@ struct Args {
     #[arg(long, default_value = "1")]
     nimbus_bed_level: u32,
 
+    /// Facilities of one type at different levels, replacing that type's count and level
+    /// (e.g. "farmland=6x5+4x3,woodland=2x3+1x1")
+    #[arg(long)]
+    facility_levels: Option<String>,
+
     // ========== Item Upgrade Modules ==========
     /// Ecological Module level (1=high-speed wheat, 2=high-speed willow)
     #[arg(long, default_value = "0")]
@@ -136,6 +186,90 @@ struct Args {
     /// Crafting Module level (1=advanced wood sculpture)
     #[arg(long, default_value = "0")]
     crafting_module: u32,
+
+    // ========== Orders ==========
+    /// Produce specific items instead of a currency target (e.g. "dried_flowers=30,sf_medium_exp_gem=10")
+    #[arg(long)]
+    order: Option<String>,
+
+    /// Order board CSV to pick the most rewarding orders from (columns: name, items, amounts, reward, currency)
+    #[arg(long)]
+    orders_file: Option<String>,
+
+    /// Time budget for --orders-file and --energy-budget, in hours
+    #[arg(long, default_value = "24")]
+    time_budget: f64,
+
+    // ========== Events ==========
+    /// Price and timing overrides CSV for events and boosts
+    /// (columns: scope, target, field, operation, value, start_hours, end_hours)
+    #[arg(long)]
+    overrides: Option<String>,
+
+    // ========== Constraints ==========
+    /// Facilities to keep free for your own use (e.g. "crafting_table=1,farmland=2")
+    #[arg(long)]
+    reserve: Option<String>,
+
+    /// Items to always produce, with the fewest batches and optionally the facility
+    /// (e.g. "advanced_wood_sculpture=5" or "strawberry@farmland=1")
+    #[arg(long)]
+    pin: Option<String>,
+
+    /// Items or whole facilities never to use (e.g. "wheat,nimbus_bed")
+    #[arg(long)]
+    forbid: Option<String>,
+
+    // ========== Inventory ==========
+    /// Items already in storage, used before producing anything (e.g. "wheat=300,fertilizer=40")
+    #[arg(long)]
+    inventory: Option<String>,
+
+    /// Storage capacity, in total and/or per category (e.g. "2000" or "2000,crops=800,goods=500")
+    #[arg(long)]
+    storage: Option<String>,
+
+    /// Batches already running, with the facilities running them and time left (e.g. "wheat*4=45m,wooden_sculpture=2h")
+    #[arg(long)]
+    in_progress: Option<String>,
+
+    /// Sell the units whole batches make beyond what the plan uses, counting them in the profit
+    #[arg(long, default_value = "false")]
+    sell_surplus: bool,
+
+    /// Show the stock of every item over the plan, with peak and lowest levels
+    /// (simulating the plan batch by batch, which also reports its peak inventory)
+    #[arg(long, default_value = "false")]
+    stock_levels: bool,
+
+    /// Write the stock of every item over the plan to a CSV file
+    #[arg(long)]
+    stock_csv: Option<String>,
+
+    // ========== Upgrades ==========
+    /// Show payback time for every next-step upgrade (uses data/upgrades.csv)
+    #[arg(long, default_value = "false")]
+    roi: bool,
+
+    /// Coins currently on hand; seeds are only planted once affordable (also flags affordable upgrades)
+    #[arg(long)]
+    coin_balance: Option<f64>,
+
+    /// Coupons currently on hand (used to flag affordable upgrades)
+    #[arg(long)]
+    coupon_balance: Option<f64>,
+
+    /// Plan a multi-step earn-and-upgrade progression
+    #[arg(long, default_value = "false")]
+    plan: bool,
+
+    /// Goal configuration for --plan (e.g. "farmland=10x5,kitchen_module=2")
+    #[arg(long)]
+    goal: Option<String>,
+
+    /// Time horizon for --plan, in hours
+    #[arg(long)]
+    plan_horizon: Option<f64>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -149,7 +283,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 
     // Build facility counts from args (count, level) tuples
-    let facility_counts = FacilityCounts {
+    let mut facility_counts = FacilityCounts {
         farmland: (args.farmland, args.farmland_level),
         woodland: (args.woodland, args.woodland_level),
         mineral_pile: (args.mineral_pile, args.mineral_pile_level),
@@ -159,7 +293,11 @@ fn main() -> Result<(), Box<dyn Error>> {
         dance_pad_polisher: (args.dance_pad_polisher, args.dance_pad_polisher_level),
         aniipod_maker: (args.aniipod_maker, args.aniipod_maker_level),
         nimbus_bed: (args.nimbus_bed, args.nimbus_bed_level),
+        instance_levels: Default::default(),
     };
+    if let Some(spec) = args.facility_levels.as_deref() {
+        facility_counts.set_levels_spec(spec)?;
+    }
 
     // Build module levels from args
     let module_levels = ModuleLevels {
@@ -169,16 +307,85 @@ fn main() -> Result<(), Box<dyn Error>> {
         crafting_module: args.crafting_module,
     };
 
+    // Build planning options from args
+    let inventory = match args.inventory.as_deref() {
+        Some(spec) => Inventory::parse(spec)?,
+        None => Inventory::default(),
+    };
+    let storage = match args.storage.as_deref() {
+        Some(spec) => StorageCapacity::parse(spec)?,
+        None => StorageCapacity::default(),
+    };
+    let valuation = match args.valuation.as_deref() {
+        Some(spec) => CurrencyValuation::parse(&args.currency, spec)?,
+        None => CurrencyValuation::new(&args.currency),
+    };
+    let mut planning_options = PlanningOptions {
+        inventory,
+        storage,
+        starting_coins: args.coin_balance,
+        in_progress: Vec::new(),
+        sell_surplus: args.sell_surplus,
+        overrides: OverrideTimeline::default(),
+    };
+    let energy_budget = args.energy_budget.map(|pool| EnergyBudget {
+        pool,
+        regen_per_min: args.energy_regen,
+        cap: args.energy_cap,
+    });
+    let deadline = args.deadline.as_deref().map(parse_duration).transpose()?;
+    let objective = CostObjective::parse(&args.minimize)?;
+
     println!("Aniimax - Aniimo Production Optimizer");
     println!("================================================================");
     println!();
     println!("Configuration:");
-    println!("  Target:          {:.0} {}", args.target, args.currency);
+    if let Some(ref spec) = args.order {
+        println!("  Order:           {}", spec);
+    } else if let Some(ref path) = args.orders_file {
+        println!("  Order Board:     {} ({}h budget)", path, args.time_budget);
+    } else if let Some(budget) = energy_budget {
+        print!("  Energy Budget:   {:.0}", budget.pool);
+        if budget.regen_per_min > 0.0 {
+            print!(" +{}/min", budget.regen_per_min);
+        }
+        if let Some(cap) = budget.cap {
+            print!(" (cap {:.0})", cap);
+        }
+        println!(" over {}h, {}", args.time_budget, args.currency);
+    } else if let Some(target) = args.target {
+        println!("  Target:          {:.0} {}", target, args.currency);
+        if let Some(deadline) = deadline {
+            println!("  Deadline:        {} (least {})", format_time(deadline), objective.label());
+        }
+    }
+    let mut rates: Vec<(&String, &f64)> = valuation.rates.iter().collect();
+    rates.sort_by(|a, b| a.0.cmp(b.0));
+    for (currency, rate) in rates {
+        println!("  Valuation:       1 {} = {} {}", currency, rate, valuation.base);
+    }
     println!("  Energy Cost:     {}/min", args.energy_cost);
+    if let Some(coins) = planning_options.starting_coins {
+        println!("  Starting Coins:  {:.0}", coins);
+    }
     println!(
         "  Mode:            {}",
-        if args.energy_self_sufficient { 
+        if args.order.is_some() {
+            "Produce to Order"
+        } else if args.orders_file.is_some() {
+            "Order Board"
+        } else if deadline.is_some() {
+            "Deadline (Lowest Cost)"
+        } else if energy_budget.is_some() {
+            if args.parallel {
+                "Energy Budget (Cross-Facility Parallel)"
+            } else {
+                "Energy Budget"
+            }
+        } else if args.energy_self_sufficient { 
             "Energy Self-Sufficient" 
+        } else if args.optimize_energy {
+            "Energy Optimization"
         } else if args.parallel {
             "Cross-Facility Parallel"
         } else { 
@@ -188,15 +395,9 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     println!();
     println!("Facilities (count x level):");
-    println!("  Farmland:           {} x Lv.{}", args.farmland, args.farmland_level);
-    println!("  Woodland:           {} x Lv.{}", args.woodland, args.woodland_level);
-    println!("  Mineral Pile:       {} x Lv.{}", args.mineral_pile, args.mineral_pile_level);
-    println!("  Carousel Mill:      {} x Lv.{}", args.carousel_mill, args.carousel_mill_level);
-    println!("  Jukebox Dryer:      {} x Lv.{}", args.jukebox_dryer, args.jukebox_dryer_level);
-    println!("  Crafting Table:     {} x Lv.{}", args.crafting_table, args.crafting_table_level);
-    println!("  Dance Pad Polisher: {} x Lv.{}", args.dance_pad_polisher, args.dance_pad_polisher_level);
-    println!("  Aniipod Maker:      {} x Lv.{}", args.aniipod_maker, args.aniipod_maker_level);
-    println!("  Nimbus Bed:         {} x Lv.{}", args.nimbus_bed, args.nimbus_bed_level);
+    for facility in FacilityCounts::FACILITY_NAMES {
+        println!("  {:<19} {}", format!("{}:", facility), facility_counts.describe_levels(facility));
+    }
 
     println!();
     println!("Item Modules:");
@@ -205,14 +406,179 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("  Mineral Detector:   Lv.{}", args.mineral_detector);
     println!("  Crafting Module:    Lv.{}", args.crafting_module);
 
+    if !planning_options.inventory.is_empty() {
+        let mut held: Vec<(&String, &u32)> = planning_options.inventory.items.iter().collect();
+        held.sort();
+        println!();
+        println!("Inventory:");
+        for (name, amount) in held {
+            println!("  {:<20}{}", format!("{}:", name), amount);
+        }
+    }
+
+    let storage = &planning_options.storage;
+    if !storage.is_unlimited() {
+        println!();
+        println!("Storage Capacity:");
+        if let Some(total) = storage.total {
+            println!("  {:<20}{}", "total:", total);
+        }
+        for category in StorageCapacity::CATEGORIES {
+            if let Some(limit) = storage.limit_for(category) {
+                println!("  {:<20}{}", format!("{}:", category), limit);
+            }
+        }
+    }
+
     // Load all data
-    let items = load_all_data(data_dir)?;
+    let base_items = load_all_data(data_dir)?;
     println!();
-    println!("Loaded {} production items.", items.len());
+    println!("Loaded {} production items.", base_items.len());
+
+    // Ranking items uses the overrides in effect at the start; the planners
+    // then make and sell each batch as the overrides are at the time
+    let overrides = match args.overrides.as_deref() {
+        Some(path) => load_overrides(Path::new(path))?,
+        None => Vec::new(),
+    };
+    check_override_targets(&base_items, &overrides)?;
+    let items = apply_overrides(&base_items, &overrides, 0.0);
+    planning_options.overrides = OverrideTimeline::new(&base_items, &overrides);
+    if !overrides.is_empty() {
+        println!();
+        println!("Overrides:");
+        for rule in &overrides {
+            let window = match (rule.start, rule.end) {
+                (None, None) => String::new(),
+                (start, end) => format!(
+                    " ({} to {})",
+                    start.map_or("start".to_string(), format_time),
+                    end.map_or("end".to_string(), format_time)
+                ),
+            };
+            println!("  {}{}", rule.describe(), window);
+        }
+    }
+
+    // Energy modes rank or limit plans by production energy costs, so data
+    // without any would quietly plan as if energy were free
+    let energy_mode = if energy_budget.is_some() {
+        Some("--energy-budget")
+    } else if args.optimize_energy {
+        Some("--optimize-energy")
+    } else if deadline.is_some() && objective == CostObjective::Energy {
+        Some("--minimize energy")
+    } else {
+        None
+    };
+    if let Some(mode) = energy_mode {
+        if !items.iter().any(|i| i.energy_cost.is_some_and(|e| e > 0.0)) {
+            return Err(format!("{} needs production energy costs, but no item has an energy_cost", mode).into());
+        }
+    }
+
+    // Reservations, pins and forbidden items apply to every planner below;
+    // upgrades are still judged on every facility
+    let constraints = PlanConstraints {
+        reservations: match args.reserve.as_deref() {
+            Some(spec) => parse_reservations(spec)?,
+            None => Vec::new(),
+        },
+        pins: match args.pin.as_deref() {
+            Some(spec) => parse_pins(spec)?,
+            None => Vec::new(),
+        },
+        forbidden: args.forbid.as_deref().map(parse_forbidden).unwrap_or_default(),
+    };
+    let setup = apply_constraints(&items, &facility_counts, &module_levels, &constraints)?;
+    if !constraints.is_empty() {
+        println!();
+        println!("Constraints:");
+        for (facility, count) in &constraints.reservations {
+            println!("  Reserve {} x {}", count, facility);
+        }
+        for pin in &constraints.pins {
+            match &pin.facility {
+                Some(facility) => println!("  Pin {} on {} (at least {} batches)", pin.item, facility, pin.min_batches),
+                None => println!("  Pin {} (at least {} batches)", pin.item, pin.min_batches),
+            }
+        }
+        for rule in &constraints.forbidden {
+            match rule {
+                ForbiddenRule::Item(name) => println!("  Forbid {}", name),
+                ForbiddenRule::Facility(facility) => println!("  Forbid everything at {}", facility),
+            }
+        }
+    }
+
+    // Batches in progress keep their facilities busy; their output is stocked or sold
+    let mut in_progress_value = 0.0;
+    if let Some(spec) = args.in_progress.as_deref() {
+        planning_options.in_progress = parse_in_progress(spec, &items, &facility_counts)?;
+        in_progress_value = credit_in_progress(
+            &planning_options.in_progress,
+            &items,
+            &valuation,
+            &mut planning_options.inventory,
+        );
+        println!();
+        println!("In Progress:");
+        for batch in &planning_options.in_progress {
+            println!(
+                "  {} x {} on {}, done in {} ({})",
+                batch.units,
+                batch.item,
+                batch.facility,
+                format_time(batch.remaining),
+                if batch.sold { "sold" } else { "stocked" }
+            );
+        }
+    }
+
+    if let Some(ref spec) = args.order {
+        // Pinned items are simply added to the order
+        let mut order = parse_order(spec)?;
+        order.extend(setup.pins.iter().cloned());
+        let plan = plan_order(&setup.items, &order, &setup.facility_counts, &module_levels, &planning_options)?;
+        display_order_plan(&plan);
+        return Ok(());
+    }
+
+    // Pinned items run first; the rest of the plan gets their facilities once they are done
+    let mut pinned_value = 0.0;
+    if !setup.pins.is_empty() {
+        let pinned = plan_order(&setup.items, &setup.pins, &setup.facility_counts, &module_levels, &planning_options)?;
+        for usage in &pinned.materials {
+            planning_options.inventory.take(&usage.name, usage.from_stock);
+        }
+        planning_options.in_progress.extend(setup.pinned_batches(&pinned));
+        pinned_value = setup.pinned_value(&valuation);
+        display_pinned_plan(&pinned, pinned_value, &valuation.base);
+    }
+    let plan_counts = setup.facility_counts.clone();
+
+    if let Some(ref path) = args.orders_file {
+        let orders = load_orders(Path::new(path))?;
+        let board = optimize_order_board(
+            &setup.items,
+            &orders,
+            &valuation,
+            &plan_counts,
+            &module_levels,
+            args.time_budget * 3600.0,
+            &planning_options,
+        );
+        display_order_board(&board);
+        return Ok(());
+    }
+
+    // Present unless an order or order board was given (enforced by clap);
+    // the value of pinned items and sold in-progress output counts towards it
+    let target = args.target.unwrap_or_default() - pinned_value - in_progress_value;
+    let credits_reach_target = pinned_value + in_progress_value > 0.0 && energy_budget.is_none() && target <= 0.0;
 
     // Calculate efficiencies
-    let efficiencies =
-        calculate_efficiencies(&items, &args.currency, &facility_counts, &module_levels);
+    let efficiencies = calculate_valued_efficiencies(&setup.items, &valuation, &plan_counts, &module_levels);
 
     if efficiencies.is_empty() {
         println!();
@@ -224,24 +590,67 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 
     // Find best production path based on mode
-    let path_result = if args.energy_self_sufficient && args.energy_cost > 0.0 {
-        let energy_efficiencies = calculate_energy_efficiencies(&items, &facility_counts, &module_levels);
-        find_self_sufficient_path(
+    let deadline_plan = match deadline {
+        Some(deadline) if !credits_reach_target => Some(find_deadline_path(
+            &efficiencies,
+            target,
+            deadline,
+            objective,
+            &plan_counts,
+            &planning_options,
+        )),
+        _ => None,
+    };
+    let path_result = if credits_reach_target {
+        None
+    } else if let Some(ref plan) = deadline_plan {
+        plan.path.clone()
+    } else if let Some(ref budget) = energy_budget {
+        let time_budget = args.time_budget * 3600.0;
+        let single_path =
+            find_energy_budget_path(&efficiencies, budget, time_budget, &plan_counts, &planning_options);
+        let parallel_path = if args.parallel {
+            find_parallel_energy_budget_path(&efficiencies, budget, time_budget, &plan_counts, &planning_options)
+        } else {
+            None
+        };
+
+        // Use whichever earns more
+        match (parallel_path, single_path) {
+            (Some(p), Some(s)) => {
+                if p.total_profit >= s.total_profit {
+                    Some(p)
+                } else {
+                    Some(s)
+                }
+            }
+            (p, s) => p.or(s),
+        }
+    } else if args.energy_self_sufficient && args.energy_cost > 0.0 {
+        let energy_efficiencies = calculate_energy_efficiencies(&setup.items, &plan_counts, &module_levels);
+        find_self_sufficient_path_with_options(
             &efficiencies,
             &energy_efficiencies,
-            args.target,
+            target,
             args.energy_cost,
-            &facility_counts,
+            &plan_counts,
+            &planning_options,
         )
-    } else if args.parallel {
+    } else if args.parallel && !args.optimize_energy {
         // Compare parallel vs single-facility approach, use whichever is faster
-        let parallel_path = find_parallel_production_path(&efficiencies, args.target, &facility_counts);
-        let single_path = find_best_production_path(
+        let parallel_path = find_parallel_production_path_with_options(
             &efficiencies,
-            args.target,
+            target,
+            &plan_counts,
+            &planning_options,
+        );
+        let single_path = find_best_production_path_with_options(
+            &efficiencies,
+            target,
             false,
             0.0,
-            &facility_counts,
+            &plan_counts,
+            &planning_options,
         );
         
         match (parallel_path, single_path) {
@@ -258,30 +667,114 @@ fn main() -> Result<(), Box<dyn Error>> {
             (None, None) => None,
         }
     } else {
-        find_best_production_path(
+        find_best_production_path_with_options(
             &efficiencies,
-            args.target,
-            false,
+            target,
+            args.optimize_energy,
             0.0,
-            &facility_counts,
+            &plan_counts,
+            &planning_options,
         )
     };
 
-    if let Some(path) = path_result {
-        display_results(&path, &efficiencies, false);
+    if let Some(mut path) = path_result {
+        if args.stock_levels || args.stock_csv.is_some() {
+            simulate_stock_levels(&mut path, &plan_counts);
+        }
+        display_results(&path, &efficiencies, args.optimize_energy);
+        display_material_balance(&path, &planning_options.inventory);
+        if args.stock_levels {
+            display_inventory_over_time(&path);
+        }
+        if let Some(ref file) = args.stock_csv {
+            write_inventory_csv(std::fs::File::create(file)?, &path.inventory_over_time)?;
+            println!("Stock levels written to {}", file);
+            println!();
+        }
+        if !path.balance_over_time.is_empty() {
+            display_cash_flow(&path);
+        }
+        if let Some(ref budget) = energy_budget {
+            display_energy_budget(&path, budget);
+        }
+        if let Some(ref plan) = deadline_plan {
+            display_deadline_plan(plan);
+        }
+        if deadline_plan.is_none()
+            && energy_budget.is_none()
+            && !args.optimize_energy
+            && args.energy_cost == 0.0
+        {
+            let ranges = fastest_item_by_target(&efficiencies, &plan_counts, target * 10.0, &planning_options);
+            display_fastest_by_target(&ranges, target, &args.currency);
+        }
 
         if args.energy_cost > 0.0 && !args.energy_self_sufficient {
             display_energy_recommendations(&efficiencies);
         }
     } else {
         println!();
-        if args.energy_self_sufficient {
+        if credits_reach_target {
+            println!("The pinned items and batches in progress alone reach the target.");
+        } else if energy_budget.is_some() {
+            println!("[WARNING] The energy budget doesn't pay for a single batch of anything.");
+        } else if args.energy_self_sufficient {
             println!("[WARNING] Cannot achieve energy self-sufficiency with current setup.");
             println!("Try increasing facility counts or reducing energy cost.");
+        } else if planning_options.starting_coins.is_some() {
+            println!("[WARNING] Could not find a production path the starting coins can pay for.");
         } else {
             println!("[WARNING] Could not find a valid production path.");
         }
     }
 
+    if args.roi || args.plan {
+        let upgrades = load_upgrades(&data_dir.join("upgrades.csv"))?;
+        let balance = if args.coin_balance.is_some() || args.coupon_balance.is_some() {
+            Some(CurrencyBalance {
+                coins: args.coin_balance.unwrap_or(0.0),
+                coupons: args.coupon_balance.unwrap_or(0.0),
+            })
+        } else {
+            None
+        };
+
+        if args.roi {
+            let rois = calculate_upgrade_roi(
+                &items,
+                &args.currency,
+                &facility_counts,
+                &module_levels,
+                &upgrades,
+                balance.as_ref(),
+            );
+            let unpriced = unpriced_upgrades(&upgrades, &facility_counts, &module_levels);
+            display_upgrade_roi(&rois, &unpriced, &args.currency);
+        }
+
+        if args.plan {
+            let mut goal = ProgressionGoal {
+                time_horizon: args.plan_horizon.map(|h| h * 3600.0),
+                ..Default::default()
+            };
+            if let Some(ref spec) = args.goal {
+                let (goal_counts, goal_modules) = parse_goal_spec(spec, &facility_counts, &module_levels)?;
+                goal.facility_counts = Some(goal_counts);
+                goal.module_levels = Some(goal_modules);
+            }
+            let plan = plan_progression(
+                &base_items,
+                &overrides,
+                &args.currency,
+                &facility_counts,
+                &module_levels,
+                &upgrades,
+                &balance.unwrap_or_default(),
+                &goal,
+            );
+            display_progression_plan(&plan);
+        }
+    }
+
     Ok(())
 }
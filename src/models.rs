@@ -4,7 +4,7 @@
 //! including production items, efficiency calculations, and production paths.
 
 use serde::Deserialize;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Represents a single production item that can be produced in the game.
 ///
@@ -15,6 +15,7 @@ use std::collections::HashSet;
 ///
 /// ```
 /// use aniimax::models::ProductionItem;
+/// use std::collections::HashMap;
 ///
 /// let wheat = ProductionItem {
 ///     name: "wheat".to_string(),
@@ -26,10 +27,14 @@ use std::collections::HashSet;
 ///     sell_value: 1.0,
 ///     production_time: 90.0,
 ///     yield_amount: 10,
-///     energy: Some(809.0),
+///     energy_cost: Some(809.0),
+///     energy_value: Some(809.0),
 ///     facility_level: 1,
 ///     module_requirement: None,
 ///     requires_fertilizer: false,
+///     consumable_values: HashMap::new(),
+///     sale_curve: None,
+///     level_stats: Vec::new(),
 /// };
 /// ```
 #[derive(Debug, Clone)]
@@ -52,14 +57,160 @@ pub struct ProductionItem {
     pub production_time: f64,
     /// Number of items yielded per production cycle
     pub yield_amount: u32,
-    /// Energy gained when this item is consumed (None = cannot be consumed for energy)
-    pub energy: Option<f64>,
+    /// Energy spent to produce one batch (None = production costs no energy)
+    pub energy_cost: Option<f64>,
+    /// Energy gained when one batch is consumed (None = cannot be consumed for energy)
+    pub energy_value: Option<f64>,
     /// Minimum facility level required to produce this item
     pub facility_level: u32,
     /// Module requirement: (module_name, required_level) - None if no module needed
     pub module_requirement: Option<(String, u32)>,
     /// Whether this item requires fertilizer to produce
     pub requires_fertilizer: bool,
+    /// Value gained per unit when consumed instead of sold, by objective
+    /// (e.g., "experience" for experience gems)
+    pub consumable_values: HashMap<String, f64>,
+    /// How the price falls as more is sold in a day (None = unlimited sales
+    /// at `sell_value`)
+    pub sale_curve: Option<SaleCurve>,
+    /// How the item's facility performs by level, sorted by level (empty =
+    /// the same at every level)
+    pub level_stats: Vec<FacilityLevelStats>,
+}
+
+impl ProductionItem {
+    /// Returns the item as produced by its facility at `level`.
+    ///
+    /// Uses the stats of the highest level at or below `level`: production
+    /// time is divided by the speed multiplier, the yield bonus is added
+    /// (rounded to whole items) and, if the level says whether fertilizer is
+    /// needed, that replaces the item's own need. Without stats for the level
+    /// the item is returned unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aniimax::models::{FacilityLevelStats, ProductionItem};
+    /// use std::collections::HashMap;
+    ///
+    /// let stats = |level, speed_multiplier, yield_bonus, requires_fertilizer| FacilityLevelStats {
+    ///     level,
+    ///     speed_multiplier,
+    ///     yield_bonus,
+    ///     requires_fertilizer,
+    /// };
+    /// let wheat = ProductionItem {
+    ///     name: "wheat".to_string(),
+    ///     facility: "Farmland".to_string(),
+    ///     raw_materials: None,
+    ///     required_amount: None,
+    ///     cost: Some(0.0),
+    ///     sell_currency: "coins".to_string(),
+    ///     sell_value: 1.0,
+    ///     production_time: 90.0,
+    ///     yield_amount: 10,
+    ///     energy_cost: Some(809.0),
+    ///     energy_value: Some(809.0),
+    ///     facility_level: 1,
+    ///     module_requirement: None,
+    ///     requires_fertilizer: false,
+    ///     consumable_values: HashMap::new(),
+    ///     sale_curve: None,
+    ///     level_stats: vec![stats(1, 1.0, 0.0, None), stats(4, 1.5, 0.2, Some(true))],
+    /// };
+    ///
+    /// assert_eq!(wheat.at_facility_level(3).production_time, 90.0);
+    /// let upgraded = wheat.at_facility_level(5);
+    /// assert_eq!(upgraded.production_time, 60.0);
+    /// assert_eq!(upgraded.yield_amount, 12);
+    /// assert!(upgraded.requires_fertilizer);
+    /// ```
+    pub fn at_facility_level(&self, level: u32) -> ProductionItem {
+        let mut item = self.clone();
+        if let Some(stats) = self.level_stats.iter().take_while(|s| s.level <= level).last() {
+            item.production_time /= stats.speed_multiplier;
+            item.yield_amount = (self.yield_amount as f64 * (1.0 + stats.yield_bonus)).round() as u32;
+            if let Some(requires_fertilizer) = stats.requires_fertilizer {
+                item.requires_fertilizer = requires_fertilizer;
+            }
+        }
+        item
+    }
+}
+
+/// How a facility performs from a given level on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FacilityLevelStats {
+    /// Facility level these stats start at
+    pub level: u32,
+    /// Production speed relative to the data (2.0 = batches take half as long)
+    pub speed_multiplier: f64,
+    /// Extra yield per batch as a fraction of the data's yield (0.2 = 20% more)
+    pub yield_bonus: f64,
+    /// Whether items grown at this level need fertilizer (`None` keeps each
+    /// item's own need)
+    pub requires_fertilizer: Option<bool>,
+}
+
+/// How an item's price falls as more of it is sold in a day.
+///
+/// Each tier sets the price, as a fraction of `sell_value`, of units sold
+/// once that many have already been sold that day. A factor of 0 is a daily
+/// sale limit.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::SaleCurve;
+///
+/// // Full price for 100 units a day, half price for the next 100, then nothing
+/// let curve = SaleCurve { tiers: vec![(100, 0.5), (200, 0.0)] };
+/// assert_eq!(curve.full_price_units(150, 1), 125.0);
+/// assert_eq!(curve.full_price_units(300, 1), 150.0);
+/// assert_eq!(curve.full_price_units(300, 2), 250.0);
+/// assert_eq!(curve.daily_limit(), Some(200));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SaleCurve {
+    /// (units already sold that day, price factor), sorted by units
+    pub tiers: Vec<(u32, f64)>,
+}
+
+impl SaleCurve {
+    /// Returns the price factor of the next unit after `sold` units that day.
+    pub fn factor_at(&self, sold: u32) -> f64 {
+        self.tiers
+            .iter()
+            .take_while(|&&(from, _)| from <= sold)
+            .last()
+            .map_or(1.0, |&(_, factor)| factor)
+    }
+
+    /// Returns the most units that can be sold in a day, if capped.
+    pub fn daily_limit(&self) -> Option<u32> {
+        self.tiers.iter().find(|&&(_, factor)| factor <= 0.0).map(|&(from, _)| from)
+    }
+
+    /// Returns what selling `units` spread evenly over `days` days earns,
+    /// counted in units sold at full price.
+    pub fn full_price_units(&self, units: u32, days: u32) -> f64 {
+        let days = days.max(1);
+        let (per_day, extra) = (units / days, units % days);
+        extra as f64 * self.day_units(per_day + 1) + (days - extra) as f64 * self.day_units(per_day)
+    }
+
+    /// Full-price equivalent of selling `units` in one day.
+    fn day_units(&self, units: u32) -> f64 {
+        let mut total = 0.0;
+        let mut from = 0;
+        let mut factor = 1.0;
+        for &(next, next_factor) in &self.tiers {
+            total += units.min(next).saturating_sub(from) as f64 * factor;
+            from = next;
+            factor = next_factor;
+        }
+        total + units.saturating_sub(from) as f64 * factor
+    }
 }
 
 /// Efficiency metrics for an item when consumed for energy.
@@ -73,8 +224,10 @@ pub struct EnergyItemEfficiency {
     pub time_per_batch: f64,
     /// Energy gained per batch when consumed
     pub energy_per_batch: f64,
-    /// Cost (in coins) per batch
+    /// Cost (in coins) per batch, including the seeds of its ingredients
     pub cost_per_batch: f64,
+    /// The item's production chain (ingredients, facilities and startup time)
+    pub production: ProductionEfficiency,
 }
 
 /// Represents an optimized production path to achieve a target currency goal.
@@ -91,10 +244,12 @@ pub struct ProductionPath {
     pub startup_time: f64,
     /// Total energy consumed (calculated as time * energy_cost_per_min / 60)
     pub total_energy: Option<f64>,
-    /// Total profit generated
+    /// Total profit generated (net of expenses in the same currency)
     pub total_profit: f64,
     /// The currency type being produced
     pub currency: String,
+    /// Income and expenses of the whole plan, by currency
+    pub ledger: CurrencyLedger,
     /// Total number of items that will be produced for sale
     pub items_produced: u32,
     /// Whether this path is energy self-sufficient
@@ -103,6 +258,66 @@ pub struct ProductionPath {
     pub energy_items_produced: Option<u32>,
     /// Name of item used for energy (if self-sufficient)
     pub energy_item_name: Option<String>,
+    /// Most units held in storage at once (ingredients waiting to be processed)
+    pub peak_inventory: u32,
+    /// Coin balance over the plan as (time in seconds, coins); empty unless
+    /// planned with a starting balance
+    pub balance_over_time: Vec<(f64, f64)>,
+    /// Average price per unit of each item sold, after sale limits
+    pub average_prices: Vec<(String, f64)>,
+    /// Units of every item taken from stock, produced, used and left over
+    pub material_balance: Vec<MaterialBalance>,
+    /// Stock of every item over the plan (empty if it couldn't be simulated)
+    pub inventory_over_time: Vec<InventorySeries>,
+    /// Batch-level jobs not simulated while planning (see
+    /// [`crate::optimizer::simulate_stock_levels`]); `peak_inventory` and
+    /// `inventory_over_time` leave them out until they are
+    pub unsimulated: Vec<PlannedJobs>,
+}
+
+impl ProductionPath {
+    /// Returns what is held once the plan is done: the starting inventory
+    /// less the stock the plan used, plus what it left over. Use it as the
+    /// starting inventory of the next plan.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use aniimax::models::{FacilityCounts, ModuleLevels, PlanningOptions};
+    /// use aniimax::optimizer::{calculate_efficiencies, find_best_production_path_with_options};
+    /// use aniimax::data::load_all_data;
+    /// use std::path::Path;
+    ///
+    /// let items = load_all_data(Path::new("data")).unwrap();
+    /// let counts = FacilityCounts {
+    ///     farmland: (4, 3),
+    ///     woodland: (1, 2),
+    ///     mineral_pile: (1, 1),
+    ///     carousel_mill: (2, 2),
+    ///     jukebox_dryer: (1, 1),
+    ///     crafting_table: (1, 1),
+    ///     dance_pad_polisher: (1, 1),
+    ///     aniipod_maker: (1, 1),
+    ///     nimbus_bed: (1, 1),
+    ///     instance_levels: Default::default(),
+    /// };
+    /// let efficiencies = calculate_efficiencies(&items, "coins", &counts, &ModuleLevels::default());
+    ///
+    /// let today = PlanningOptions::default();
+    /// let path = find_best_production_path_with_options(&efficiencies, 5000.0, false, 0.0, &counts, &today).unwrap();
+    /// let tomorrow = PlanningOptions {
+    ///     inventory: path.leftover_inventory(&today.inventory),
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub fn leftover_inventory(&self, starting: &Inventory) -> Inventory {
+        let mut inventory = starting.clone();
+        for balance in &self.material_balance {
+            inventory.take(&balance.name, balance.from_stock);
+            inventory.add(&balance.name, balance.left_over);
+        }
+        inventory
+    }
 }
 
 /// Represents a single step in a production path.
@@ -121,6 +336,8 @@ pub struct ProductionStep {
     pub time: f64,
     /// Energy consumed by this step
     pub energy: Option<f64>,
+    /// Energy gained by eating what this step produces (steps producing for energy)
+    pub energy_gained: Option<f64>,
     /// Profit contribution from this step
     pub profit_contribution: f64,
     /// Chain ID for parallel production (steps with same ID run together)
@@ -147,8 +364,15 @@ pub struct ProductionEfficiency {
     pub total_energy_per_unit: Option<f64>,
     /// Name of required raw material (if any)
     pub requires_raw: Option<String>,
-    /// Cost of raw materials per production
+    /// Cost of raw materials per production (paid in coins)
     pub raw_cost: f64,
+    /// Income and expenses of one batch, by currency
+    pub ledger: CurrencyLedger,
+    /// How the ledger is turned into profit (the target currency, or a
+    /// personal exchange rate between currencies)
+    pub valuation: CurrencyValuation,
+    /// Profit of one batch, in the valuation's base currency
+    pub profit_per_batch: f64,
     /// Facility that produces the raw material
     pub raw_facility: Option<String>,
     /// All facilities used in this production chain (including intermediate processing)
@@ -157,12 +381,454 @@ pub struct ProductionEfficiency {
     pub intermediate_steps: Vec<(String, String, u32)>,
     /// Time to produce the first batch (startup delay before steady-state)
     pub startup_time: f64,
+    /// Seconds per batch at steady state with every facility running
+    /// (the slower of gathering and processing)
+    pub steady_state_time: f64,
     /// Effective profit per second considering parallel facility usage
     pub effective_profit_per_second: f64,
     /// Raw material details for optimal allocation: Vec<(name, amount_per_batch, time_per_batch)>
     pub raw_material_details: Option<Vec<(String, u32, f64)>>,
     /// Fertilizer batches needed per production batch (0 if no fertilizer required)
     pub fertilizer_per_batch: u32,
+    /// Nimbus Beds kept busy making fertilizer for this item at its
+    /// steady-state rate (0 if no fertilizer required)
+    pub nimbus_bed_load: f64,
+    /// Production time and yield of one batch of fertilizer on a Nimbus Bed
+    /// (None if no bed can make it)
+    pub fertilizer_batch: Option<(f64, u32)>,
+    /// Every ingredient in the production chain, parents before children
+    /// (empty for raw materials)
+    pub materials: Vec<MaterialRequirement>,
+    /// Batch stats of the item and its ingredients (by variant) on every
+    /// level of a facility type with facilities at different levels (see
+    /// [`BatchJob::level_stats`])
+    pub level_stats: HashMap<String, Vec<(u32, f64, u32)>>,
+}
+
+/// One ingredient in an item's production chain.
+///
+/// Chains are stored as a flat list where each entry points at the entry it
+/// is consumed by, so nested recipes (e.g., walnut → nuts → caramel_nut_chips)
+/// can be expanded with exact counts.
+#[derive(Debug, Clone)]
+pub struct MaterialRequirement {
+    /// Name of the ingredient as used in recipes (stock is held under this name)
+    pub name: String,
+    /// Item actually produced (the high-speed variant when usable)
+    pub variant: String,
+    /// Facility producing the ingredient
+    pub facility: String,
+    /// Facility level the ingredient needs
+    pub facility_level: u32,
+    /// Index of the ingredient this one is consumed by (None = the final product)
+    pub parent: Option<usize>,
+    /// Units consumed per batch of the parent
+    pub amount_per_parent: u32,
+    /// Units yielded per batch of this ingredient
+    pub yield_amount: u32,
+    /// Time in seconds for one batch of this ingredient
+    pub production_time: f64,
+    /// Whether this is a raw material (gathered rather than processed)
+    pub is_raw: bool,
+    /// Whether each batch of this ingredient needs one fertilizer
+    pub requires_fertilizer: bool,
+    /// Coins paid per batch of this ingredient (seeds; 0 if free)
+    pub cost: f64,
+    /// What one unit sells for, in `sell_currency`
+    pub sell_value: f64,
+    /// Currency the ingredient sells for
+    pub sell_currency: String,
+}
+
+/// How much of one ingredient a plan needs, after drawing on the inventory.
+#[derive(Debug, Clone)]
+pub struct MaterialUsage {
+    /// Name of the ingredient
+    pub name: String,
+    /// Item actually produced (the high-speed variant when usable)
+    pub variant: String,
+    /// Facility producing the ingredient
+    pub facility: String,
+    /// Whether this is a raw material
+    pub is_raw: bool,
+    /// Units consumed by the plan
+    pub required: u32,
+    /// Units taken from the starting inventory
+    pub from_stock: u32,
+    /// Units that still have to be produced
+    pub to_produce: u32,
+    /// Batches needed to produce `to_produce` units
+    pub batches: u32,
+}
+
+/// Items already held in the warehouse, by item name.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::Inventory;
+///
+/// let mut inventory = Inventory::parse("wheat=300, fertilizer=40").unwrap();
+/// assert_eq!(inventory.get("wheat"), 300);
+/// assert_eq!(inventory.take("wheat", 120), 120);
+/// assert_eq!(inventory.take("wheat", 500), 180);
+/// assert_eq!(inventory.get("wheat"), 0);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Inventory {
+    /// Units held per item name
+    pub items: HashMap<String, u32>,
+}
+
+impl Inventory {
+    /// Parses an inventory such as `wheat=300,strawberry=12`.
+    pub fn parse(spec: &str) -> Result<Inventory, String> {
+        let mut inventory = Inventory::default();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, amount) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected ITEM=AMOUNT in inventory entry '{}'", entry))?;
+            let amount: u32 = amount
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid amount in inventory entry '{}'", entry))?;
+            if inventory.get(name.trim()).checked_add(amount).is_none() {
+                return Err(format!("Too many units of '{}' in inventory", name.trim()));
+            }
+            inventory.add(name.trim(), amount);
+        }
+        Ok(inventory)
+    }
+
+    /// Returns the units held of an item.
+    pub fn get(&self, name: &str) -> u32 {
+        self.items.get(name).copied().unwrap_or(0)
+    }
+
+    /// Adds units of an item, holding at most `u32::MAX`.
+    pub fn add(&mut self, name: &str, amount: u32) {
+        if amount > 0 {
+            let held = self.items.entry(name.to_string()).or_insert(0);
+            *held = held.saturating_add(amount);
+        }
+    }
+
+    /// Takes up to `amount` units of an item, returning how many were taken.
+    pub fn take(&mut self, name: &str, amount: u32) -> u32 {
+        match self.items.get_mut(name) {
+            Some(held) => {
+                let taken = (*held).min(amount);
+                *held -= taken;
+                taken
+            }
+            None => 0,
+        }
+    }
+
+    /// Returns `true` if nothing is held.
+    pub fn is_empty(&self) -> bool {
+        self.items.values().all(|&amount| amount == 0)
+    }
+
+    /// Formats the inventory the way [`Inventory::parse`] reads it, items in
+    /// alphabetical order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use aniimax::models::Inventory;
+    ///
+    /// let inventory = Inventory::parse("wheat=30, strawberry=2, rice=0").unwrap();
+    /// assert_eq!(inventory.to_spec(), "strawberry=2,wheat=30");
+    /// ```
+    pub fn to_spec(&self) -> String {
+        let mut held: Vec<(&String, &u32)> = self.items.iter().filter(|(_, &amount)| amount > 0).collect();
+        held.sort();
+        held.iter()
+            .map(|(name, amount)| format!("{}={}", name, amount))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Warehouse capacity, in total and per storage category.
+///
+/// Items fall into a category by the facility producing them: `crops`
+/// (Farmland), `timber` (Woodland), `minerals` (Mineral Pile), `materials`
+/// (Nimbus Bed) and `goods` (every processing facility). The default is
+/// unlimited storage.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::StorageCapacity;
+///
+/// let storage = StorageCapacity::parse("2000,crops=800").unwrap();
+/// assert_eq!(storage.total, Some(2000));
+/// assert_eq!(storage.limit_for("crops"), Some(800));
+/// assert_eq!(StorageCapacity::category("Carousel Mill"), "goods");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StorageCapacity {
+    /// Maximum units held at once across all items (None = unlimited)
+    pub total: Option<u32>,
+    /// Maximum units held at once per category
+    pub per_category: HashMap<String, u32>,
+}
+
+impl StorageCapacity {
+    /// Storage categories, in display order.
+    pub const CATEGORIES: [&'static str; 5] = ["crops", "timber", "minerals", "materials", "goods"];
+
+    /// Parses a capacity such as `2000` (total), `crops=800,goods=500` or both.
+    pub fn parse(spec: &str) -> Result<StorageCapacity, String> {
+        let mut storage = StorageCapacity::default();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, amount) = match entry.split_once('=') {
+                Some((name, amount)) => (name.trim(), amount.trim()),
+                None => ("total", entry),
+            };
+            let amount: u32 = amount
+                .parse()
+                .map_err(|_| format!("Invalid capacity in storage entry '{}'", entry))?;
+            if name == "total" {
+                storage.total = Some(amount);
+            } else if Self::CATEGORIES.contains(&name) {
+                storage.per_category.insert(name.to_string(), amount);
+            } else {
+                return Err(format!(
+                    "Unknown storage category '{}' (expected one of: total, {})",
+                    name,
+                    Self::CATEGORIES.join(", ")
+                ));
+            }
+        }
+        Ok(storage)
+    }
+
+    /// Returns the storage category of items produced at a facility.
+    pub fn category(facility: &str) -> &'static str {
+        match facility {
+            "Farmland" => "crops",
+            "Woodland" => "timber",
+            "Mineral Pile" => "minerals",
+            "Nimbus Bed" => "materials",
+            _ => "goods",
+        }
+    }
+
+    /// Returns the limit of a category (None = unlimited).
+    pub fn limit_for(&self, category: &str) -> Option<u32> {
+        self.per_category.get(category).copied()
+    }
+
+    /// Returns `true` if no limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        self.total.is_none() && self.per_category.is_empty()
+    }
+}
+
+/// Energy available to a plan: a starting pool that optionally regenerates
+/// over time, up to a cap.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::EnergyBudget;
+///
+/// let budget = EnergyBudget { pool: 3000.0, regen_per_min: 10.0, cap: Some(4000.0) };
+/// assert_eq!(budget.available(3600.0), 3600.0);
+/// assert_eq!(budget.time_to_afford(3600.0), Some(3600.0));
+/// // Regeneration stops at the cap, so spending 4000 late leaves less for later
+/// assert_eq!(budget.spend(&[(7200.0, 4000.0), (7200.0, 600.0)], 7200.0), Some((3600.0, 0.0)));
+/// assert_eq!(budget.spend(&[(0.0, 3000.0), (7200.0, 1600.0)], 7200.0), Some((2400.0, 0.0)));
+/// assert_eq!(EnergyBudget { pool: 100.0, ..Default::default() }.time_to_afford(200.0), None);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EnergyBudget {
+    /// Energy on hand at the start
+    pub pool: f64,
+    /// Energy regained per minute
+    pub regen_per_min: f64,
+    /// Most energy that can be stored (None = unlimited); regeneration stops at the cap
+    pub cap: Option<f64>,
+}
+
+impl EnergyBudget {
+    /// Usable part of the starting pool.
+    pub fn starting_energy(&self) -> f64 {
+        self.cap.map_or(self.pool, |cap| self.pool.min(cap))
+    }
+
+    /// Energy held after `duration` seconds with nothing spent; regeneration
+    /// stops at the cap.
+    pub fn available(&self, duration: f64) -> f64 {
+        let held = self.starting_energy() + self.regen_per_min * duration / 60.0;
+        self.cap.map_or(held, |cap| held.min(cap))
+    }
+
+    /// Time in seconds until `energy` units are held at once, or `None` if
+    /// that never happens (more than the cap, or nothing regenerates).
+    pub fn time_to_afford(&self, energy: f64) -> Option<f64> {
+        self.spend(&[(0.0, energy)], 0.0).map(|(wait, _)| wait)
+    }
+
+    /// Spends energy at (time in seconds, energy) points in time order. Each
+    /// spend waits until the pool holds its energy, and every later spend
+    /// moves back by the same wait; nothing regenerates while the pool is
+    /// at its cap.
+    ///
+    /// Returns the total wait and the energy held at `end` (on the timeline
+    /// with the waits, or after the last spend if that is later), or `None`
+    /// if a spend never fits (more than the cap, or nothing regenerates).
+    pub fn spend(&self, spends: &[(f64, f64)], end: f64) -> Option<(f64, f64)> {
+        let cap = self.cap.unwrap_or(f64::INFINITY);
+        let regenerate = |held: f64, seconds: f64| (held + self.regen_per_min * seconds / 60.0).min(cap);
+        let mut held = self.starting_energy();
+        let mut now: f64 = 0.0;
+        let mut wait = 0.0;
+        for &(time, energy) in spends {
+            let at = (time + wait).max(now);
+            held = regenerate(held, at - now);
+            now = at;
+            if held + 1e-9 < energy {
+                if energy > cap + 1e-9 || self.regen_per_min <= 0.0 {
+                    return None;
+                }
+                let extra = (energy - held) / self.regen_per_min * 60.0;
+                wait += extra;
+                now += extra;
+                held = energy;
+            }
+            held = (held - energy).max(0.0);
+        }
+        Some((wait, regenerate(held, (end - now).max(0.0))))
+    }
+}
+
+/// Optional planning inputs shared by the path finders.
+///
+/// The default is an empty warehouse with unlimited storage and coins,
+/// which reproduces the plain `find_*_path` functions.
+#[derive(Debug, Clone, Default)]
+pub struct PlanningOptions {
+    /// Items already held; ingredients and fertilizer are drawn from here first
+    pub inventory: Inventory,
+    /// Warehouse capacity the plan must respect
+    pub storage: StorageCapacity,
+    /// Coins on hand; seeds are only planted once they can be paid for (None = unlimited)
+    pub starting_coins: Option<f64>,
+    /// Batches already running; their facilities are busy until they finish
+    pub in_progress: Vec<InProgressBatch>,
+    /// Sell the units whole batches make beyond what the plan uses, counting
+    /// them in the profit (otherwise they're left in the warehouse)
+    pub sell_surplus: bool,
+    /// Overrides that start or end during the plan: each batch is made as
+    /// the items are when it starts and sold at the price when it finishes
+    pub overrides: OverrideTimeline,
+}
+
+/// Batches of an item already running when planning starts.
+///
+/// The facilities running them can't start anything else until they
+/// finish. Stocked output is added to the planning inventory but only
+/// arrives when the batches finish; sold output counts towards the target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InProgressBatch {
+    /// Item being produced (the high-speed variant if that is what's running)
+    pub item: String,
+    /// Name the output is stocked under
+    pub name: String,
+    /// Facility running the batches
+    pub facility: String,
+    /// Facilities running a batch each
+    pub facilities: u32,
+    /// Time in seconds until the batches finish
+    pub remaining: f64,
+    /// Units the batches yield between them
+    pub units: u32,
+    /// Whether the output is sold rather than stocked (no recipe uses it)
+    pub sold: bool,
+}
+
+/// One item to produce in a batch-level schedule.
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    /// Name the item is stocked and consumed under
+    pub name: String,
+    /// Item actually produced (the high-speed variant when usable)
+    pub variant: String,
+    /// Facility producing the item
+    pub facility: String,
+    /// Time in seconds for one batch
+    pub production_time: f64,
+    /// Units yielded per batch
+    pub yield_amount: u32,
+    /// Units of each ingredient consumed per batch
+    pub inputs: Vec<(String, u32)>,
+    /// Batches to produce
+    pub batches: u32,
+    /// Whether output leaves storage on completion (sold) instead of being stocked
+    pub sold: bool,
+    /// Coins paid when a batch starts (seeds)
+    pub seed_cost: f64,
+    /// Coins received when a sold batch finishes (0 if sold for another currency)
+    pub sale_value: f64,
+    /// Longest processing time still ahead of this item (used for priority)
+    pub tail_time: f64,
+    /// (level, batch time, yield) on the facilities of each level able to
+    /// make the item, highest first, when the facility type's levels differ;
+    /// empty if every facility makes it at `production_time` and `yield_amount`
+    pub level_stats: Vec<(u32, f64, u32)>,
+    /// Stats from each time an override starts or ends on, in time order;
+    /// empty if they stay the same throughout
+    pub timed_stats: Vec<TimedStats>,
+}
+
+impl BatchJob {
+    /// Batch time, yield and per-level stats (see [`BatchJob::level_stats`])
+    /// of batches started at `time` (in seconds from the schedule's start).
+    pub fn stats_at(&self, time: f64) -> (f64, u32, &[(u32, f64, u32)]) {
+        match self.timed_stats.iter().rev().find(|s| s.start <= time) {
+            Some(s) => (s.production_time, s.yield_amount, &s.level_stats),
+            None => (self.production_time, self.yield_amount, &self.level_stats),
+        }
+    }
+
+    /// Price of batches sold at `time`, relative to `sale_value`.
+    pub fn price_factor_at(&self, time: f64) -> f64 {
+        self.timed_stats
+            .iter()
+            .rev()
+            .find(|s| s.start <= time)
+            .map_or(1.0, |s| s.price_factor)
+    }
+}
+
+/// A batch job's stats from some time on, while overrides change them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedStats {
+    /// Time in seconds from the schedule's start these apply from
+    pub start: f64,
+    /// Time in seconds for one batch
+    pub production_time: f64,
+    /// Units yielded per batch
+    pub yield_amount: u32,
+    /// (level, batch time, yield) per facility level, as in [`BatchJob::level_stats`]
+    pub level_stats: Vec<(u32, f64, u32)>,
+    /// Sale price relative to the job's `sale_value`
+    pub price_factor: f64,
+}
+
+/// Batch-level jobs of part of a plan, run side by side.
+#[derive(Debug, Clone, Default)]
+pub struct PlannedJobs {
+    /// Time in seconds from the start of the plan the jobs start at
+    pub start: f64,
+    /// Jobs to simulate
+    pub jobs: Vec<BatchJob>,
+    /// Stock the jobs draw on
+    pub initial_stock: HashMap<String, u32>,
 }
 
 /// Tracks the number of each facility type available.
@@ -185,11 +851,36 @@ pub struct ProductionEfficiency {
 ///     dance_pad_polisher: (1, 1),
 ///     aniipod_maker: (1, 1),
 ///     nimbus_bed: (1, 1),
+///     instance_levels: Default::default(),
 /// };
 ///
 /// assert_eq!(counts.get_count("Farmland"), 4);
 /// assert_eq!(counts.get_level("Farmland"), 2);
 /// ```
+///
+/// Facilities of one type can be at different levels:
+///
+/// ```
+/// # use aniimax::models::FacilityCounts;
+/// # let mut counts = FacilityCounts {
+/// #     farmland: (4, 2),
+/// #     woodland: (2, 1),
+/// #     mineral_pile: (1, 1),
+/// #     carousel_mill: (2, 2),
+/// #     jukebox_dryer: (1, 1),
+/// #     crafting_table: (1, 1),
+/// #     dance_pad_polisher: (1, 1),
+/// #     aniipod_maker: (1, 1),
+/// #     nimbus_bed: (1, 1),
+/// #     instance_levels: Default::default(),
+/// # };
+/// // 6 Farmland plots at level 5 and 4 still at level 3
+/// counts.set_levels_spec("farmland=6x5+4x3").unwrap();
+/// assert_eq!(counts.get_count("Farmland"), 10);
+/// assert_eq!(counts.get_level("Farmland"), 5);
+/// assert_eq!(counts.count_at_level("Farmland", 4), 6);
+/// assert_eq!(counts.describe_levels("Farmland"), "6 x Lv.5 + 4 x Lv.3");
+/// ```
 #[derive(Debug, Clone)]
 pub struct FacilityCounts {
     /// (count, level) for Farmland plots
@@ -210,9 +901,27 @@ pub struct FacilityCounts {
     pub aniipod_maker: (u32, u32),
     /// (count, level) for Nimbus Bed slots
     pub nimbus_bed: (u32, u32),
+    /// Level of every facility, highest first, for types whose facilities
+    /// are at different levels (by facility name). The (count, level) tuple
+    /// then holds the count and the highest level; types not listed have
+    /// every facility at the tuple's level.
+    pub instance_levels: HashMap<String, Vec<u32>>,
 }
 
 impl FacilityCounts {
+    /// Names of every facility type tracked by [`FacilityCounts`].
+    pub const FACILITY_NAMES: [&'static str; 9] = [
+        "Farmland",
+        "Woodland",
+        "Mineral Pile",
+        "Carousel Mill",
+        "Jukebox Dryer",
+        "Crafting Table",
+        "Dance Pad Polisher",
+        "Aniipod Maker",
+        "Nimbus Bed",
+    ];
+
     /// Returns the count for a given facility name.
     ///
     /// # Arguments
@@ -223,6 +932,9 @@ impl FacilityCounts {
     ///
     /// The number of that facility type available. Returns 1 for unknown facility types.
     pub fn get_count(&self, facility: &str) -> u32 {
+        if let Some(levels) = self.instance_levels.get(facility) {
+            return levels.len() as u32;
+        }
         match facility {
             "Farmland" => self.farmland.0,
             "Woodland" => self.woodland.0,
@@ -247,6 +959,9 @@ impl FacilityCounts {
     ///
     /// The level of that facility type. Returns 1 for unknown facility types.
     pub fn get_level(&self, facility: &str) -> u32 {
+        if let Some(levels) = self.instance_levels.get(facility) {
+            return levels.first().copied().unwrap_or(1);
+        }
         match facility {
             "Farmland" => self.farmland.1,
             "Woodland" => self.woodland.1,
@@ -274,6 +989,161 @@ impl FacilityCounts {
     pub fn can_produce(&self, facility: &str, required_level: u32) -> bool {
         self.get_level(facility) >= required_level
     }
+
+    /// Returns a mutable reference to the (count, level) entry for a facility.
+    ///
+    /// Returns `None` for unknown facility types.
+    fn entry_mut(&mut self, facility: &str) -> Option<&mut (u32, u32)> {
+        match facility {
+            "Farmland" => Some(&mut self.farmland),
+            "Woodland" => Some(&mut self.woodland),
+            "Mineral Pile" => Some(&mut self.mineral_pile),
+            "Carousel Mill" => Some(&mut self.carousel_mill),
+            "Jukebox Dryer" => Some(&mut self.jukebox_dryer),
+            "Crafting Table" => Some(&mut self.crafting_table),
+            "Dance Pad Polisher" => Some(&mut self.dance_pad_polisher),
+            "Aniipod Maker" => Some(&mut self.aniipod_maker),
+            "Nimbus Bed" => Some(&mut self.nimbus_bed),
+            _ => None,
+        }
+    }
+
+    /// Sets the count for a given facility name. Unknown facilities are ignored.
+    ///
+    /// With mixed levels, the lowest facilities are removed first and new
+    /// ones are added at the lowest level.
+    pub fn set_count(&mut self, facility: &str, count: u32) {
+        if let Some(levels) = self.instance_levels.get(facility) {
+            let mut levels = levels.clone();
+            let lowest = levels.last().copied().unwrap_or(1);
+            levels.resize(count as usize, lowest);
+            self.set_instance_levels(facility, levels);
+        } else if let Some(entry) = self.entry_mut(facility) {
+            entry.0 = count;
+        }
+    }
+
+    /// Sets the level for a given facility name. Unknown facilities are ignored.
+    ///
+    /// With mixed levels, every facility below `level` is raised to it.
+    pub fn set_level(&mut self, facility: &str, level: u32) {
+        if let Some(levels) = self.instance_levels.get(facility) {
+            let levels = levels.iter().map(|&l| l.max(level)).collect();
+            self.set_instance_levels(facility, levels);
+        } else if let Some(entry) = self.entry_mut(facility) {
+            entry.1 = level;
+        }
+    }
+
+    /// Returns the level of every facility of a type, highest first.
+    pub fn instance_levels(&self, facility: &str) -> Vec<u32> {
+        match self.instance_levels.get(facility) {
+            Some(levels) => levels.clone(),
+            None => vec![self.get_level(facility); self.get_count(facility) as usize],
+        }
+    }
+
+    /// Sets the level of every facility of a type. Unknown facilities are ignored.
+    ///
+    /// The count becomes the number of levels given and the level the highest
+    /// of them; the per-facility levels are only kept if they differ.
+    pub fn set_instance_levels(&mut self, facility: &str, mut levels: Vec<u32>) {
+        let Some(entry) = self.entry_mut(facility) else {
+            return;
+        };
+        levels.sort_unstable_by(|a, b| b.cmp(a));
+        *entry = (levels.len() as u32, levels.first().copied().unwrap_or(entry.1));
+        if levels.windows(2).any(|w| w[0] != w[1]) {
+            self.instance_levels.insert(facility.to_string(), levels);
+        } else {
+            self.instance_levels.remove(facility);
+        }
+    }
+
+    /// Returns how many facilities of a type are at `level` or above.
+    pub fn count_at_level(&self, facility: &str, level: u32) -> u32 {
+        match self.instance_levels.get(facility) {
+            Some(levels) => levels.iter().filter(|&&l| l >= level).count() as u32,
+            None if self.get_level(facility) >= level => self.get_count(facility),
+            None => 0,
+        }
+    }
+
+    /// Returns how many facilities can produce an item.
+    pub fn count_for(&self, item: &ProductionItem) -> u32 {
+        self.count_at_level(&item.facility, item.facility_level)
+    }
+
+    /// Returns the lowest level among the facilities of a type that are at
+    /// `required_level` or above (the facility's level if none are).
+    pub fn producing_level(&self, facility: &str, required_level: u32) -> u32 {
+        self.instance_levels(facility)
+            .into_iter()
+            .filter(|&l| l >= required_level)
+            .min()
+            .unwrap_or_else(|| self.get_level(facility))
+    }
+
+    /// Describes the levels of a facility type (e.g., "6 x Lv.5 + 4 x Lv.3").
+    pub fn describe_levels(&self, facility: &str) -> String {
+        let levels = self.instance_levels(facility);
+        if levels.is_empty() {
+            return format!("0 x Lv.{}", self.get_level(facility));
+        }
+        let mut groups: Vec<(u32, u32)> = Vec::new();
+        for level in levels {
+            match groups.last_mut() {
+                Some((count, l)) if *l == level => *count += 1,
+                _ => groups.push((1, level)),
+            }
+        }
+        groups
+            .iter()
+            .map(|(count, level)| format!("{} x Lv.{}", count, level))
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+
+    /// Returns the facility type a name refers to, matched case-insensitively
+    /// with `_`, `-` or spaces between words (e.g., "carousel-mill").
+    pub fn find_facility(name: &str) -> Option<&'static str> {
+        let normalize = |n: &str| n.trim().to_lowercase().replace(['-', ' '], "_");
+        let key = normalize(name);
+        Self::FACILITY_NAMES.iter().copied().find(|f| normalize(f) == key)
+    }
+
+    /// Sets mixed facility levels from a spec such as `farmland=6x5+4x3`.
+    ///
+    /// Each facility takes groups of `<count>x<level>` joined by `+`; the
+    /// groups replace its count and level. Facilities not mentioned keep
+    /// their configuration.
+    pub fn set_levels_spec(&mut self, spec: &str) -> Result<(), String> {
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, groups) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected FACILITY=COUNTxLEVEL in '{}'", entry))?;
+            let facility =
+                Self::find_facility(name).ok_or_else(|| format!("Unknown facility '{}'", name.trim()))?;
+            let mut levels = Vec::new();
+            for group in groups.split('+') {
+                let (count, level) = group
+                    .trim()
+                    .split_once('x')
+                    .ok_or_else(|| format!("Expected COUNTxLEVEL in '{}'", group.trim()))?;
+                let count: usize = count
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid count in '{}'", entry))?;
+                let level: u32 = level
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid level in '{}'", entry))?;
+                levels.extend(std::iter::repeat_n(level, count));
+            }
+            self.set_instance_levels(facility, levels);
+        }
+        Ok(())
+    }
 }
 
 /// Tracks the levels of item upgrade modules.
@@ -294,7 +1164,7 @@ impl FacilityCounts {
 ///
 /// assert!(modules.can_use("ecological_module", 1));
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ModuleLevels {
     /// Level of Ecological Module (unlocks high-speed wheat at 1, high-speed willow at 2)
     pub ecological_module: u32,
@@ -306,18 +1176,15 @@ pub struct ModuleLevels {
     pub crafting_module: u32,
 }
 
-impl Default for ModuleLevels {
-    fn default() -> Self {
-        ModuleLevels {
-            ecological_module: 0,
-            kitchen_module: 0,
-            mineral_detector: 0,
-            crafting_module: 0,
-        }
-    }
-}
-
 impl ModuleLevels {
+    /// Names of every module tracked by [`ModuleLevels`].
+    pub const MODULE_NAMES: [&'static str; 4] = [
+        "ecological_module",
+        "kitchen_module",
+        "mineral_detector",
+        "crafting_module",
+    ];
+
     /// Checks if a module meets the required level.
     ///
     /// # Arguments
@@ -342,63 +1209,1087 @@ impl ModuleLevels {
             _ => 0,
         }
     }
+
+    /// Sets the level for a given module name. Unknown modules are ignored.
+    pub fn set_level(&mut self, module_name: &str, level: u32) {
+        match module_name {
+            "ecological_module" => self.ecological_module = level,
+            "kitchen_module" => self.kitchen_module = level,
+            "mineral_detector" => self.mineral_detector = level,
+            "crafting_module" => self.crafting_module = level,
+            _ => {}
+        }
+    }
 }
 
 // ============================================================================
-// CSV Row Structures
+// Overrides
 // ============================================================================
 
-/// CSV row structure for Farmland items.
-#[derive(Debug, Deserialize)]
-pub struct FarmlandRow {
-    /// Item name
-    pub name: String,
-    /// Cost to plant
-    pub cost: f64,
-    /// Sell value per unit
-    pub sell_value: f64,
-    /// Production time in seconds
-    pub production_time: f64,
-    /// Number of items yielded
-    #[serde(rename = "yield")]
-    pub yield_amount: u32,
-    /// Energy consumed (optional)
-    pub energy: Option<f64>,
-    /// Required facility level
-    pub facility_level: u32,
-    /// Module requirement (format: "module_name:level" or empty)
-    #[serde(default)]
-    pub module_requirement: Option<String>,
+/// An item stat that an [`ItemOverride`] changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverrideField {
+    /// Value received per unit sold
+    SellValue,
+    /// Seconds per production cycle
+    ProductionTime,
+    /// Items yielded per production cycle
+    YieldAmount,
 }
 
-/// CSV row structure for Woodland items.
-#[derive(Debug, Deserialize)]
-pub struct WoodlandRow {
-    /// Item name
-    pub name: String,
-    /// Cost to plant
-    pub cost: f64,
-    /// Currency type when selling
-    pub sell_currency: String,
-    /// Sell value per unit
-    pub sell_value: f64,
-    /// Production time in seconds
-    pub production_time: f64,
-    /// Number of items yielded
-    #[serde(rename = "yield")]
-    pub yield_amount: u32,
-    /// Energy consumed (may be "NULL" string)
-    pub energy: Option<String>,
-    /// Required facility level
-    pub facility_level: u32,
-    /// Module requirement (format: "module_name:level" or empty)
-    #[serde(default)]
-    pub module_requirement: Option<String>,
+impl OverrideField {
+    /// Parses a field from its CSV name (`sell_value`, `production_time` or `yield`).
+    pub fn parse(s: &str) -> Option<OverrideField> {
+        match s.trim() {
+            "sell_value" => Some(OverrideField::SellValue),
+            "production_time" => Some(OverrideField::ProductionTime),
+            "yield" | "yield_amount" => Some(OverrideField::YieldAmount),
+            _ => None,
+        }
+    }
+
+    /// Returns the CSV name of the field.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OverrideField::SellValue => "sell_value",
+            OverrideField::ProductionTime => "production_time",
+            OverrideField::YieldAmount => "yield",
+        }
+    }
 }
 
-/// CSV row structure for Mineral Pile items.
-#[derive(Debug, Deserialize)]
-pub struct MineralRow {
+/// Which items an [`ItemOverride`] applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverrideScope {
+    /// Every item
+    Global,
+    /// Every item made in a facility (e.g., "Carousel Mill")
+    Facility(String),
+    /// A single item (e.g., "wheat")
+    Item(String),
+}
+
+/// How an [`ItemOverride`] changes a stat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverrideOperation {
+    /// Multiplies the stat (e.g., 1.5 for +50% prices, 0.8 for 20% faster production)
+    Multiply(f64),
+    /// Replaces the stat
+    Set(f64),
+}
+
+/// A change to item stats from an event or boost, optionally limited to a
+/// time window.
+///
+/// Yields are rounded to the nearest whole item and never drop below 1.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::{ItemOverride, OverrideField, OverrideOperation, OverrideScope};
+///
+/// // Wheat sells for 50% more during a day-long event starting in 2 hours
+/// let event = ItemOverride {
+///     scope: OverrideScope::Item("wheat".to_string()),
+///     field: OverrideField::SellValue,
+///     operation: OverrideOperation::Multiply(1.5),
+///     start: Some(2.0 * 3600.0),
+///     end: Some(26.0 * 3600.0),
+/// };
+/// assert!(!event.is_active_at(0.0));
+/// assert!(event.is_active_at(3.0 * 3600.0));
+/// assert!(!event.is_active_at(26.0 * 3600.0));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemOverride {
+    /// Items the override applies to
+    pub scope: OverrideScope,
+    /// Stat that is changed
+    pub field: OverrideField,
+    /// How the stat is changed
+    pub operation: OverrideOperation,
+    /// When the override starts, in seconds from plan start (None = already active)
+    pub start: Option<f64>,
+    /// When the override ends, in seconds from plan start (None = never)
+    pub end: Option<f64>,
+}
+
+impl ItemOverride {
+    /// Returns `true` if the override applies to the item.
+    pub fn applies_to(&self, item: &ProductionItem) -> bool {
+        match &self.scope {
+            OverrideScope::Global => true,
+            OverrideScope::Facility(facility) => item.facility == *facility,
+            OverrideScope::Item(name) => item.name == *name,
+        }
+    }
+
+    /// Returns `true` if the override is in effect at `time` (seconds from plan start).
+    pub fn is_active_at(&self, time: f64) -> bool {
+        self.start.is_none_or(|start| time >= start) && self.end.is_none_or(|end| time < end)
+    }
+
+    /// Changes the item's stat, regardless of scope and time window.
+    pub fn apply(&self, item: &mut ProductionItem) {
+        let change = |value: f64| match self.operation {
+            OverrideOperation::Multiply(factor) => value * factor,
+            OverrideOperation::Set(replacement) => replacement,
+        };
+        match self.field {
+            OverrideField::SellValue => item.sell_value = change(item.sell_value),
+            OverrideField::ProductionTime => item.production_time = change(item.production_time),
+            OverrideField::YieldAmount => {
+                item.yield_amount = (change(item.yield_amount as f64).round() as u32).max(1)
+            }
+        }
+    }
+
+    /// Returns a short human-readable description (e.g., "sell_value x1.5 for wheat").
+    pub fn describe(&self) -> String {
+        let change = match self.operation {
+            OverrideOperation::Multiply(factor) => format!("x{}", factor),
+            OverrideOperation::Set(value) => format!("= {}", value),
+        };
+        let scope = match &self.scope {
+            OverrideScope::Global => "all items".to_string(),
+            OverrideScope::Facility(facility) => facility.clone(),
+            OverrideScope::Item(name) => name.clone(),
+        };
+        format!("{} {} for {}", self.field.name(), change, scope)
+    }
+}
+
+/// Overrides over the course of a plan, with the items they change as they
+/// are without any, so an item can be looked up as it is at any time.
+///
+/// Times are in seconds from the start of the plan part being made; items
+/// are ranked as they were at `planned_at`, which is before that start once
+/// an earlier part has run.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::{ItemOverride, OverrideField, OverrideOperation, OverrideScope, OverrideTimeline, ProductionItem};
+/// use std::collections::HashMap;
+///
+/// let wheat = ProductionItem {
+///     name: "wheat".to_string(),
+///     facility: "Farmland".to_string(),
+///     raw_materials: None,
+///     required_amount: None,
+///     cost: Some(0.0),
+///     sell_currency: "coins".to_string(),
+///     sell_value: 1.0,
+///     production_time: 90.0,
+///     yield_amount: 10,
+///     energy_cost: Some(809.0),
+///     energy_value: Some(809.0),
+///     facility_level: 1,
+///     module_requirement: None,
+///     requires_fertilizer: false,
+///     consumable_values: HashMap::new(),
+///     sale_curve: None,
+///     level_stats: Vec::new(),
+/// };
+/// let event = ItemOverride {
+///     scope: OverrideScope::Item("wheat".to_string()),
+///     field: OverrideField::SellValue,
+///     operation: OverrideOperation::Multiply(2.0),
+///     start: Some(3600.0),
+///     end: None,
+/// };
+/// let timeline = OverrideTimeline::new(&[wheat], &[event]);
+/// assert_eq!(timeline.change_times(), vec![3600.0]);
+/// assert_eq!(timeline.item_at("wheat", 0.0).unwrap().sell_value, 1.0);
+/// assert_eq!(timeline.item_at("wheat", 7200.0).unwrap().sell_value, 2.0);
+/// // Half an hour into the plan, the event starts after another half hour
+/// assert_eq!(timeline.after(1800.0).change_times(), vec![1800.0]);
+/// assert_eq!(timeline.after(1800.0).planned_at, -1800.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OverrideTimeline {
+    /// Production items without overrides applied
+    pub items: Vec<ProductionItem>,
+    /// Overrides, in the order they are applied
+    pub overrides: Vec<ItemOverride>,
+    /// Time the planners' item stats are as of (0 unless the timeline was
+    /// moved on with [`OverrideTimeline::after`])
+    pub planned_at: f64,
+}
+
+impl OverrideTimeline {
+    /// Creates a timeline of the overrides to `items` (without overrides applied).
+    pub fn new(items: &[ProductionItem], overrides: &[ItemOverride]) -> Self {
+        OverrideTimeline {
+            items: items.to_vec(),
+            overrides: overrides.to_vec(),
+            planned_at: 0.0,
+        }
+    }
+
+    /// Times in seconds after the start at which an override starts or
+    /// ends, in order.
+    pub fn change_times(&self) -> Vec<f64> {
+        let mut times: Vec<f64> = self
+            .overrides
+            .iter()
+            .flat_map(|o| [o.start, o.end])
+            .flatten()
+            .filter(|&t| t > 0.0)
+            .collect();
+        times.sort_by(|a, b| a.total_cmp(b));
+        times.dedup();
+        times
+    }
+
+    /// Returns `true` if any override starts or ends after `planned_at`, so
+    /// items change from how the planners see them.
+    pub fn is_timed(&self) -> bool {
+        self.overrides
+            .iter()
+            .flat_map(|o| [o.start, o.end])
+            .flatten()
+            .any(|t| t > self.planned_at)
+    }
+
+    /// The item as it is at `time` (seconds from the start), with every
+    /// override in effect then applied, or `None` if the timeline doesn't
+    /// know it.
+    pub fn item_at(&self, name: &str, time: f64) -> Option<ProductionItem> {
+        let mut item = self.items.iter().find(|i| i.name == name)?.clone();
+        for rule in self.overrides.iter().filter(|o| o.is_active_at(time)) {
+            if rule.applies_to(&item) {
+                rule.apply(&mut item);
+            }
+        }
+        Some(item)
+    }
+
+    /// Returns `items` as they are at `time`: each one the timeline knows
+    /// is replaced by its state then.
+    pub fn items_at(&self, items: &[ProductionItem], time: f64) -> Vec<ProductionItem> {
+        items
+            .iter()
+            .map(|item| self.item_at(&item.name, time).unwrap_or_else(|| item.clone()))
+            .collect()
+    }
+
+    /// The timeline as seen `elapsed` seconds after the start.
+    pub fn after(&self, elapsed: f64) -> OverrideTimeline {
+        OverrideTimeline {
+            items: self.items.clone(),
+            overrides: self
+                .overrides
+                .iter()
+                .map(|o| ItemOverride {
+                    start: o.start.map(|t| t - elapsed),
+                    end: o.end.map(|t| t - elapsed),
+                    ..o.clone()
+                })
+                .collect(),
+            planned_at: self.planned_at - elapsed,
+        }
+    }
+}
+
+// ============================================================================
+// Upgrades
+// ============================================================================
+
+/// The kind of purchase an upgrade represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradeKind {
+    /// Raises a facility type to the given level
+    FacilityLevel,
+    /// Buys an extra facility slot, bringing the count to the given level
+    FacilitySlot,
+    /// Raises an item upgrade module to the given level
+    Module,
+}
+
+impl UpgradeKind {
+    /// Parses an upgrade kind from its CSV name
+    /// (`facility_level`, `facility_slot` or `module`).
+    pub fn parse(s: &str) -> Option<UpgradeKind> {
+        match s.trim() {
+            "facility_level" => Some(UpgradeKind::FacilityLevel),
+            "facility_slot" => Some(UpgradeKind::FacilitySlot),
+            "module" => Some(UpgradeKind::Module),
+            _ => None,
+        }
+    }
+}
+
+/// The cost of a single upgrade step.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::{UpgradeCost, UpgradeKind};
+///
+/// // Raising Farmland to level 4 costs 18000 coins
+/// let upgrade = UpgradeCost {
+///     kind: UpgradeKind::FacilityLevel,
+///     target: "Farmland".to_string(),
+///     level: 4,
+///     coins: 18000.0,
+///     coupons: 0.0,
+/// };
+/// assert_eq!(upgrade.cost_in("coins"), 18000.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct UpgradeCost {
+    /// What is being upgraded
+    pub kind: UpgradeKind,
+    /// Facility name (e.g., "Farmland") or module name (e.g., "kitchen_module")
+    pub target: String,
+    /// Level reached after buying (for slots, the facility count after buying)
+    pub level: u32,
+    /// Price in coins
+    pub coins: f64,
+    /// Price in coupons
+    pub coupons: f64,
+}
+
+impl UpgradeCost {
+    /// Returns the price of this upgrade in the given currency.
+    pub fn cost_in(&self, currency: &str) -> f64 {
+        match currency {
+            "coins" => self.coins,
+            "coupons" => self.coupons,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns a short human-readable description (e.g., "Farmland Lv.4").
+    pub fn describe(&self) -> String {
+        match self.kind {
+            UpgradeKind::FacilityLevel => format!("{} Lv.{}", self.target, self.level),
+            UpgradeKind::FacilitySlot => format!("{} slot #{}", self.target, self.level),
+            UpgradeKind::Module => format!("{} Lv.{}", self.target, self.level),
+        }
+    }
+}
+
+/// A player's currency balance.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CurrencyBalance {
+    /// Coins on hand
+    pub coins: f64,
+    /// Coupons on hand
+    pub coupons: f64,
+}
+
+impl CurrencyBalance {
+    /// Returns the amount held in the given currency.
+    pub fn get(&self, currency: &str) -> f64 {
+        match currency {
+            "coins" => self.coins,
+            "coupons" => self.coupons,
+            _ => 0.0,
+        }
+    }
+
+    /// Adds an amount (negative to spend) to the given currency.
+    /// Unknown currencies are ignored.
+    pub fn add(&mut self, currency: &str, amount: f64) {
+        match currency {
+            "coins" => self.coins += amount,
+            "coupons" => self.coupons += amount,
+            _ => {}
+        }
+    }
+
+    /// Returns `true` if the balance covers the upgrade's price in every currency.
+    pub fn can_afford(&self, upgrade: &UpgradeCost) -> bool {
+        self.coins >= upgrade.coins && self.coupons >= upgrade.coupons
+    }
+}
+
+/// Income and expenses split by currency.
+///
+/// Seeds are bought with coins even when the harvest sells for coupons, so a
+/// single profit figure would mix the two. The ledger keeps them apart.
+/// Values gained by consuming items (e.g., experience) are booked as income
+/// in their own entry.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CurrencyLedger {
+    /// Earned from sales or from consuming items, by currency
+    pub income: HashMap<String, f64>,
+    /// Spent on seeds and materials, by currency
+    pub expenses: HashMap<String, f64>,
+}
+
+impl CurrencyLedger {
+    /// Returns the amount earned in a currency.
+    pub fn earned(&self, currency: &str) -> f64 {
+        self.income.get(currency).copied().unwrap_or(0.0)
+    }
+
+    /// Returns the amount spent in a currency.
+    pub fn spent(&self, currency: &str) -> f64 {
+        self.expenses.get(currency).copied().unwrap_or(0.0)
+    }
+
+    /// Books income in a currency.
+    pub fn earn(&mut self, currency: &str, amount: f64) {
+        if amount != 0.0 {
+            *self.income.entry(currency.to_string()).or_insert(0.0) += amount;
+        }
+    }
+
+    /// Books an expense in a currency.
+    pub fn spend(&mut self, currency: &str, amount: f64) {
+        if amount != 0.0 {
+            *self.expenses.entry(currency.to_string()).or_insert(0.0) += amount;
+        }
+    }
+
+    /// Returns income minus expenses in the given currency.
+    pub fn net(&self, currency: &str) -> f64 {
+        self.earned(currency) - self.spent(currency)
+    }
+
+    /// Returns the ledger with every entry multiplied by `factor`
+    /// (e.g., a per-batch ledger scaled to a number of batches).
+    pub fn scaled(&self, factor: f64) -> CurrencyLedger {
+        let scale = |entries: &HashMap<String, f64>| entries.iter().map(|(c, a)| (c.clone(), a * factor)).collect();
+        CurrencyLedger {
+            income: scale(&self.income),
+            expenses: scale(&self.expenses),
+        }
+    }
+
+    /// Adds another ledger's entries to this one.
+    pub fn merge(&mut self, other: &CurrencyLedger) {
+        for (currency, amount) in &other.income {
+            self.earn(currency, *amount);
+        }
+        for (currency, amount) in &other.expenses {
+            self.spend(currency, *amount);
+        }
+    }
+
+    /// Returns `true` if nothing was earned or spent in the given currency.
+    pub fn is_empty(&self, currency: &str) -> bool {
+        self.earned(currency) == 0.0 && self.spent(currency) == 0.0
+    }
+
+    /// Returns every currency with an entry, coins and coupons first and the
+    /// rest alphabetically.
+    pub fn currencies(&self) -> Vec<&str> {
+        let mut currencies: Vec<&str> = self.income.keys().chain(self.expenses.keys()).map(String::as_str).collect();
+        currencies.sort_by_key(|&c| (!matches!(c, "coins" | "coupons"), c != "coins", c));
+        currencies.dedup();
+        currencies
+    }
+
+    /// Returns the net of every currency converted to the valuation's base
+    /// currency. Currencies the valuation doesn't list are worth nothing.
+    pub fn value(&self, valuation: &CurrencyValuation) -> f64 {
+        self.currencies()
+            .iter()
+            .map(|c| self.net(c) * valuation.rate(c))
+            .fold(0.0, |a, b| a + b)
+    }
+}
+
+/// Personal exchange rates that fold several currencies into one objective.
+///
+/// The default valuation of a currency counts only that currency, which is
+/// how plain coin or coupon targets are planned.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CurrencyValuation {
+    /// Currency that profit is measured in (worth 1)
+    pub base: String,
+    /// Worth of one unit of each other currency, in the base currency
+    pub rates: HashMap<String, f64>,
+}
+
+impl CurrencyValuation {
+    /// Creates a valuation that only counts `base`.
+    pub fn new(base: &str) -> CurrencyValuation {
+        CurrencyValuation {
+            base: base.to_string(),
+            rates: HashMap::new(),
+        }
+    }
+
+    /// Parses exchange rates such as `coupons=20` (1 coupon is worth 20 of
+    /// `base`) on top of the base currency.
+    pub fn parse(base: &str, spec: &str) -> Result<CurrencyValuation, String> {
+        let mut valuation = CurrencyValuation::new(base);
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (currency, rate) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected CURRENCY=RATE in valuation entry '{}'", entry))?;
+            let rate: f64 = rate
+                .trim()
+                .parse()
+                .ok()
+                .filter(|r: &f64| r.is_finite() && *r >= 0.0)
+                .ok_or_else(|| format!("Invalid rate in valuation entry '{}'", entry))?;
+            let currency = currency.trim();
+            if currency == base {
+                return Err(format!("'{}' is the base currency and always worth 1", currency));
+            }
+            valuation.rates.insert(currency.to_string(), rate);
+        }
+        Ok(valuation)
+    }
+
+    /// Returns the worth of one unit of a currency in the base currency.
+    pub fn rate(&self, currency: &str) -> f64 {
+        if currency == self.base {
+            1.0
+        } else {
+            self.rates.get(currency).copied().unwrap_or(0.0)
+        }
+    }
+
+    /// Returns `true` if currencies other than the base are counted.
+    pub fn is_combined(&self) -> bool {
+        self.rates.values().any(|&r| r > 0.0)
+    }
+}
+
+/// Return-on-investment figures for a single upgrade.
+#[derive(Debug, Clone)]
+pub struct UpgradeRoi {
+    /// The upgrade being evaluated
+    pub upgrade: UpgradeCost,
+    /// Best profit per second with the current configuration
+    pub current_profit_per_second: f64,
+    /// Best profit per second after buying the upgrade
+    pub upgraded_profit_per_second: f64,
+    /// Time in seconds for the extra profit to repay the upgrade's price in the
+    /// target currency (None if the upgrade doesn't increase profit, or is
+    /// priced only in another currency)
+    pub payback_time: Option<f64>,
+    /// Whether the upgrade is affordable with the given balance (true if no balance was given)
+    pub affordable: bool,
+}
+
+/// Where a progression plan should stop.
+///
+/// Planning stops at whichever limit is reached first. With neither set, the
+/// planner keeps buying upgrades while they increase profit.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressionGoal {
+    /// Facility configuration to reach (None = no facility goal)
+    pub facility_counts: Option<FacilityCounts>,
+    /// Module levels to reach (None = no module goal)
+    pub module_levels: Option<ModuleLevels>,
+    /// Maximum plan length in seconds (None = no limit)
+    pub time_horizon: Option<f64>,
+}
+
+/// A single purchase in a progression plan.
+#[derive(Debug, Clone)]
+pub struct ProgressionMilestone {
+    /// The upgrade bought at this milestone
+    pub upgrade: UpgradeCost,
+    /// What to produce to afford the upgrade (one path per currency that was
+    /// short, coupons first; the coin plan may run alongside the coupon plan,
+    /// see `purchase_time`)
+    pub production: Vec<ProductionPath>,
+    /// Time when production for this milestone starts (in seconds from plan start)
+    pub start_time: f64,
+    /// Time when the upgrade is bought (in seconds from plan start)
+    pub purchase_time: f64,
+    /// Balance left after buying the upgrade
+    pub balance_after: CurrencyBalance,
+    /// Facility configuration after the purchase
+    pub facility_counts: FacilityCounts,
+    /// Module levels after the purchase
+    pub module_levels: ModuleLevels,
+}
+
+/// A multi-step earn-and-upgrade timeline.
+#[derive(Debug, Clone)]
+pub struct ProgressionPlan {
+    /// Purchases in the order they are made
+    pub milestones: Vec<ProgressionMilestone>,
+    /// Time of the last purchase (in seconds)
+    pub total_time: f64,
+    /// Whether the goal configuration was reached (false if no configuration goal was set)
+    pub goal_reached: bool,
+}
+
+// ============================================================================
+// Orders
+// ============================================================================
+
+/// A quantity of a specific item to deliver (e.g., 30 dried_flowers).
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderLine {
+    /// Name of the item
+    pub item: String,
+    /// Units to deliver
+    pub quantity: u32,
+}
+
+/// When and where one item of an order is produced.
+#[derive(Debug, Clone)]
+pub struct ScheduledItem {
+    /// Name of the item
+    pub name: String,
+    /// Facility producing the item
+    pub facility: String,
+    /// Batches produced
+    pub batches: u32,
+    /// Start of the first batch (in seconds from plan start)
+    pub start_time: f64,
+    /// End of the last batch (in seconds from plan start)
+    pub finish_time: f64,
+    /// Batches' worth of output sold at the job's own yield and price, which
+    /// facility levels and overrides change (0 if the output is stocked)
+    pub sales: f64,
+}
+
+/// Result of simulating a batch-level schedule.
+#[derive(Debug, Clone)]
+pub struct ScheduleOutcome {
+    /// When and where each job ran (same order as the jobs)
+    pub items: Vec<ScheduledItem>,
+    /// Time until the last batch finishes (in seconds)
+    pub completion_time: f64,
+    /// Most units held in storage at once
+    pub peak_inventory: u32,
+    /// Coin balance after every purchase and sale as (time in seconds, coins);
+    /// empty unless a starting balance was given
+    pub balance: Vec<(f64, f64)>,
+    /// Stock of every item over time, in order of first appearance
+    pub stock_levels: Vec<InventorySeries>,
+}
+
+/// A full produce-to-order plan: bill of materials and facility schedule.
+#[derive(Debug, Clone)]
+pub struct OrderPlan {
+    /// Items and quantities ordered
+    pub order: Vec<OrderLine>,
+    /// Every item the order needs, ordered items first and raw materials last
+    pub materials: Vec<MaterialUsage>,
+    /// Production schedule, in order of first start
+    pub schedule: Vec<ScheduledItem>,
+    /// Time until the last batch finishes (in seconds)
+    pub completion_time: f64,
+    /// Coins spent on seeds and other raw material costs
+    pub seed_cost: f64,
+    /// Energy consumed by production (None if no item in the plan uses energy)
+    pub energy: Option<f64>,
+    /// Most units held in storage at once
+    pub peak_inventory: u32,
+}
+
+/// An order on the in-game order board: goods to deliver for a fixed reward.
+#[derive(Debug, Clone)]
+pub struct BoardOrder {
+    /// Name shown for the order
+    pub name: String,
+    /// Goods to deliver
+    pub lines: Vec<OrderLine>,
+    /// Reward for filling the order
+    pub reward: f64,
+    /// Currency of the reward
+    pub reward_currency: String,
+}
+
+/// How one order compares to selling the same goods.
+#[derive(Debug, Clone)]
+pub struct OrderEvaluation {
+    /// The order being evaluated
+    pub order: BoardOrder,
+    /// What the goods would fetch at `sell_value` (in the reward currency)
+    pub market_value: f64,
+    /// Time to fill this order on its own (None if it can't be produced)
+    pub completion_time: Option<f64>,
+    /// Whether the order was selected
+    pub selected: bool,
+}
+
+/// The chosen subset of an order board and its production plan.
+#[derive(Debug, Clone)]
+pub struct OrderBoardPlan {
+    /// Every order on the board, in input order
+    pub evaluations: Vec<OrderEvaluation>,
+    /// Production plan for the selected orders (None if nothing was selected)
+    pub plan: Option<OrderPlan>,
+    /// Currency the board was optimized for, with the worth of any others
+    pub valuation: CurrencyValuation,
+    /// Available time (in seconds)
+    pub time_budget: f64,
+    /// Rewards of the selected orders, by currency
+    pub rewards: CurrencyLedger,
+    /// Profit from selling produce on the facilities the orders leave free
+    /// while they are made
+    pub sales_during: f64,
+    /// Profit from selling produce in the time left after the orders
+    pub sale_value: f64,
+    /// Profit from spending the whole budget selling produce instead
+    pub sell_only_value: f64,
+}
+
+impl OrderBoardPlan {
+    /// Worth of the selected orders' rewards in the valuation's base currency.
+    pub fn reward(&self) -> f64 {
+        self.rewards
+            .currencies()
+            .iter()
+            .map(|currency| self.rewards.earned(currency) * self.valuation.rate(currency))
+            .sum()
+    }
+
+    /// Worth of the selected orders' seed costs in the valuation's base
+    /// currency (seeds are bought with coins).
+    pub fn seed_cost(&self) -> f64 {
+        self.plan
+            .as_ref()
+            .map_or(0.0, |plan| plan.seed_cost * self.valuation.rate("coins"))
+    }
+
+    /// Total value of the plan: order rewards less seed costs, plus sales
+    /// while the orders are made and in the remaining time.
+    pub fn total_value(&self) -> f64 {
+        self.reward() - self.seed_cost() + self.sales_during + self.sale_value
+    }
+}
+
+// ============================================================================
+// Constraints
+// ============================================================================
+
+/// An item the plan must produce, whatever the optimizer would pick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PinnedItem {
+    /// Name of the item
+    pub item: String,
+    /// Fewest batches to produce
+    pub min_batches: u32,
+    /// Facility type the item is pinned to, if given (it must be the one
+    /// making the item)
+    pub facility: Option<String>,
+}
+
+/// Something the plan must not produce.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForbiddenRule {
+    /// An item (and its high-speed variant); recipes needing it are dropped too
+    Item(String),
+    /// Every item made at a facility type
+    Facility(String),
+}
+
+/// Limits on what a plan may use and produce.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::{ForbiddenRule, PinnedItem, PlanConstraints};
+///
+/// // Keep a Crafting Table free, always make 5 batches of advanced wood
+/// // sculptures and never grow wheat
+/// let constraints = PlanConstraints {
+///     reservations: vec![("Crafting Table".to_string(), 1)],
+///     pins: vec![PinnedItem {
+///         item: "advanced_wood_sculpture".to_string(),
+///         min_batches: 5,
+///         facility: Some("Crafting Table".to_string()),
+///     }],
+///     forbidden: vec![ForbiddenRule::Item("wheat".to_string())],
+/// };
+/// assert!(!constraints.is_empty());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PlanConstraints {
+    /// Facilities kept free for the player's own use, as (facility, count)
+    pub reservations: Vec<(String, u32)>,
+    /// Items that must be produced
+    pub pins: Vec<PinnedItem>,
+    /// Items and facilities the plan must not use
+    pub forbidden: Vec<ForbiddenRule>,
+}
+
+impl PlanConstraints {
+    /// Returns `true` if no constraint is set.
+    pub fn is_empty(&self) -> bool {
+        self.reservations.is_empty() && self.pins.is_empty() && self.forbidden.is_empty()
+    }
+}
+
+// ============================================================================
+// Deadline Planning
+// ============================================================================
+
+/// Cost a deadline plan keeps as low as possible.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::CostObjective;
+///
+/// assert_eq!(CostObjective::parse("seed-coins").unwrap(), CostObjective::SeedCoins);
+/// assert_eq!(CostObjective::Interactions.label(), "interactions");
+/// assert!(CostObjective::parse("time").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CostObjective {
+    /// Energy spent producing
+    #[default]
+    Energy,
+    /// Coins paid for seeds
+    SeedCoins,
+    /// Batches started (each one a planting or collection)
+    Interactions,
+}
+
+impl CostObjective {
+    /// Parses `energy`, `seed_coins` (or `seeds`) or `interactions`.
+    pub fn parse(name: &str) -> Result<CostObjective, String> {
+        match name.trim().to_lowercase().replace('-', "_").as_str() {
+            "energy" => Ok(CostObjective::Energy),
+            "seed_coins" | "seeds" => Ok(CostObjective::SeedCoins),
+            "interactions" => Ok(CostObjective::Interactions),
+            _ => Err(format!(
+                "Unknown cost '{}' (expected one of: energy, seed_coins, interactions)",
+                name
+            )),
+        }
+    }
+
+    /// Unit the cost is counted in.
+    pub fn label(&self) -> &'static str {
+        match self {
+            CostObjective::Energy => "energy",
+            CostObjective::SeedCoins => "coins of seeds",
+            CostObjective::Interactions => "interactions",
+        }
+    }
+}
+
+/// Result of planning to reach a target by a deadline.
+#[derive(Debug, Clone)]
+pub struct DeadlinePlan {
+    /// The cheapest plan finishing by the deadline, or the fastest plan if
+    /// none does (None if the target can't be reached at all)
+    pub path: Option<ProductionPath>,
+    /// Whether `path` finishes by the deadline
+    pub meets_deadline: bool,
+    /// Deadline in seconds from now
+    pub deadline: f64,
+    /// Cost being minimized
+    pub objective: CostObjective,
+    /// Cost of `path` under the objective
+    pub cost: f64,
+}
+
+// ============================================================================
+// Material Balance
+// ============================================================================
+
+/// What a plan does with one item: where its units come from and where
+/// they go.
+///
+/// Crops come in fixed yields and recipes take fixed amounts, so whole
+/// batches often make more than the plan uses; what is neither used nor
+/// sold is left in the warehouse. Units always add up:
+/// `from_stock + produced == consumed + surplus_sold + left_over`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaterialBalance {
+    /// Name of the item (the name stock is held under)
+    pub name: String,
+    /// Units taken from the starting inventory
+    pub from_stock: u32,
+    /// Units produced, in whole batches
+    pub produced: u32,
+    /// Units used by recipes, or sold or eaten as the plan's product
+    pub consumed: u32,
+    /// Surplus units sold at their sale value (with `sell_surplus`)
+    pub surplus_sold: u32,
+    /// Units still held when the plan is done
+    pub left_over: u32,
+}
+
+impl MaterialBalance {
+    /// Adds another balance of the same item to this one.
+    pub fn merge(&mut self, other: &MaterialBalance) {
+        self.from_stock += other.from_stock;
+        self.produced += other.produced;
+        self.consumed += other.consumed;
+        self.surplus_sold += other.surplus_sold;
+        self.left_over += other.left_over;
+    }
+}
+
+// ============================================================================
+// Inventory Over Time
+// ============================================================================
+
+/// How much of one item is held over a plan, from a batch-level simulation.
+///
+/// Ingredients and fertilizer enter storage when a batch finishes and leave
+/// when a batch using them starts. Finished goods are sold as they finish,
+/// so their level counts the units made so far.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::InventorySeries;
+///
+/// let series = InventorySeries::from_points("wheat", vec![(0.0, 0), (60.0, 10), (90.0, 4), (120.0, 14), (150.0, 0)]);
+/// assert_eq!((series.peak, series.peak_time), (14, 120.0));
+/// // The plan's end, when the last units are used, doesn't count as the lowest point
+/// assert_eq!((series.minimum, series.minimum_time), (4, 90.0));
+/// assert_eq!(series.level_at(100.0), 4);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InventorySeries {
+    /// Name of the item (the name stock is held under)
+    pub name: String,
+    /// Units held after every change as (time in seconds, units), starting
+    /// with the units held at the start of the plan
+    pub points: Vec<(f64, u32)>,
+    /// Most units held at once
+    pub peak: u32,
+    /// When the peak is first reached (in seconds)
+    pub peak_time: f64,
+    /// Fewest units held between the first units arriving and the last
+    /// change (0 means consumers ran through all of it mid-plan)
+    pub minimum: u32,
+    /// When the minimum is first reached (in seconds)
+    pub minimum_time: f64,
+}
+
+impl InventorySeries {
+    /// Builds a series from its points (in time order), finding its peak
+    /// and minimum.
+    pub fn from_points(name: &str, points: Vec<(f64, u32)>) -> InventorySeries {
+        let (peak_time, peak) = points
+            .iter()
+            .copied()
+            .fold((0.0, 0), |best, point| if point.1 > best.1 { point } else { best });
+
+        // Before anything arrives and after the last change the level says
+        // nothing about whether supply keeps up
+        let first = points.iter().position(|p| p.1 > 0).unwrap_or(points.len());
+        let last = points.len().saturating_sub(1);
+        let (minimum_time, minimum) = points
+            .get(first..last)
+            .filter(|held| !held.is_empty())
+            .map(|held| {
+                held.iter()
+                    .copied()
+                    .fold(held[0], |lowest, point| if point.1 < lowest.1 { point } else { lowest })
+            })
+            .or_else(|| points.last().copied())
+            .unwrap_or((0.0, 0));
+
+        InventorySeries {
+            name: name.to_string(),
+            points,
+            peak,
+            peak_time,
+            minimum,
+            minimum_time,
+        }
+    }
+
+    /// Returns the units held at `time` (in seconds).
+    pub fn level_at(&self, time: f64) -> u32 {
+        self.points
+            .iter()
+            .take_while(|point| point.0 <= time + 1e-9)
+            .last()
+            .map_or(0, |point| point.1)
+    }
+
+    /// Adds the stock of another series of the same item, held from `offset`
+    /// seconds on, to this one.
+    pub fn add(&mut self, other: &InventorySeries, offset: f64) {
+        // Walk both series in time order, keeping the level of each
+        let mut points = Vec::with_capacity(self.points.len() + other.points.len());
+        let (mut mine, mut theirs) = (self.points.iter().peekable(), other.points.iter().peekable());
+        let (mut held, mut added) = (0, 0);
+        loop {
+            let time = match (mine.peek(), theirs.peek()) {
+                (Some(a), Some(b)) => a.0.min(b.0 + offset),
+                (Some(a), None) => a.0,
+                (None, Some(b)) => b.0 + offset,
+                (None, None) => break,
+            };
+            while let Some(point) = mine.next_if(|p| p.0 <= time + 1e-9) {
+                held = point.1;
+            }
+            while let Some(point) = theirs.next_if(|p| p.0 + offset <= time + 1e-9) {
+                added = point.1;
+            }
+            points.push((time, held + added));
+        }
+        *self = InventorySeries::from_points(&self.name, points);
+    }
+}
+
+// ============================================================================
+// CSV Row Structures
+// ============================================================================
+
+/// CSV row structure for Farmland items.
+#[derive(Debug, Deserialize)]
+pub struct FarmlandRow {
+    /// Item name
+    pub name: String,
+    /// Cost to plant
+    pub cost: f64,
+    /// Sell value per unit
+    pub sell_value: f64,
+    /// Production time in seconds
+    pub production_time: f64,
+    /// Number of items yielded
+    #[serde(rename = "yield")]
+    pub yield_amount: u32,
+    /// Energy spent to produce one batch (optional column)
+    #[serde(default)]
+    pub energy_cost: Option<f64>,
+    /// Energy gained when one batch is consumed (optional)
+    pub energy_value: Option<f64>,
+    /// Required facility level
+    pub facility_level: u32,
+    /// Module requirement (format: "module_name:level" or empty)
+    #[serde(default)]
+    pub module_requirement: Option<String>,
+}
+
+/// CSV row structure for Woodland items.
+#[derive(Debug, Deserialize)]
+pub struct WoodlandRow {
+    /// Item name
+    pub name: String,
+    /// Cost to plant
+    pub cost: f64,
+    /// Currency type when selling
+    pub sell_currency: String,
+    /// Sell value per unit
+    pub sell_value: f64,
+    /// Production time in seconds
+    pub production_time: f64,
+    /// Number of items yielded
+    #[serde(rename = "yield")]
+    pub yield_amount: u32,
+    /// Energy spent to produce one batch (optional column, may be "NULL" string)
+    #[serde(default)]
+    pub energy_cost: Option<String>,
+    /// Energy gained when one batch is consumed (may be "NULL" string)
+    pub energy_value: Option<String>,
+    /// Required facility level
+    pub facility_level: u32,
+    /// Module requirement (format: "module_name:level" or empty)
+    #[serde(default)]
+    pub module_requirement: Option<String>,
+}
+
+/// CSV row structure for Mineral Pile items.
+#[derive(Debug, Deserialize)]
+pub struct MineralRow {
     /// Item name
     pub name: String,
     /// Currency type when selling
@@ -430,9 +2321,12 @@ pub struct ProcessingRowWithEnergy {
     pub sell_value: f64,
     /// Production time in seconds
     pub production_time: f64,
-    /// Energy consumed (optional for items that don't consume energy)
+    /// Energy spent to produce one batch (optional column)
     #[serde(default, deserialize_with = "crate::deserialize_optional_f64")]
-    pub energy: Option<f64>,
+    pub energy_cost: Option<f64>,
+    /// Energy gained when one batch is consumed (optional for inedible items)
+    #[serde(default, deserialize_with = "crate::deserialize_optional_f64")]
+    pub energy_value: Option<f64>,
     /// Required facility level
     pub facility_level: u32,
     /// Module requirement (format: "module_name:level" or empty)
@@ -473,3 +2367,93 @@ pub struct NimbusBedRow {
     #[serde(rename = "yield")]
     pub yield_amount: u32,
 }
+
+/// CSV row structure for upgrade costs.
+#[derive(Debug, Deserialize)]
+pub struct UpgradeRow {
+    /// Upgrade kind: `facility_level`, `facility_slot` or `module`
+    pub kind: String,
+    /// Facility or module name
+    pub target: String,
+    /// Level (or slot count) reached after buying
+    pub level: u32,
+    /// Price in coins
+    pub coins: f64,
+    /// Price in coupons
+    pub coupons: f64,
+}
+
+/// CSV row structure for facility level stats.
+#[derive(Debug, Deserialize)]
+pub struct FacilityLevelRow {
+    /// Facility name (e.g., "Farmland")
+    pub facility: String,
+    /// Facility level the stats start at
+    pub level: u32,
+    /// Production speed relative to the data (2.0 = twice as fast)
+    pub speed_multiplier: f64,
+    /// Extra yield as a fraction of the data's yield
+    pub yield_bonus: f64,
+    /// Whether items grown at this level need fertilizer (empty keeps each
+    /// item's own need)
+    pub requires_fertilizer: Option<bool>,
+}
+
+/// CSV row structure for sale limit tiers.
+#[derive(Debug, Deserialize)]
+pub struct SaleLimitRow {
+    /// Item name
+    pub name: String,
+    /// Units already sold that day when the tier starts
+    pub from_units: u32,
+    /// Price from then on, as a fraction of the item's sell value (0 = no more sales)
+    pub price_factor: f64,
+}
+
+/// CSV row structure for price and timing overrides.
+#[derive(Debug, Deserialize)]
+pub struct OverrideRow {
+    /// Scope: `global`, `facility` or `item`
+    pub scope: String,
+    /// Facility or item name (empty for global overrides)
+    #[serde(default)]
+    pub target: String,
+    /// Stat to change: `sell_value`, `production_time` or `yield`
+    pub field: String,
+    /// `multiply` or `set`
+    pub operation: String,
+    /// Factor or replacement value
+    pub value: f64,
+    /// Hours from plan start when the override begins (empty = already active)
+    #[serde(default, deserialize_with = "crate::deserialize_optional_f64")]
+    pub start_hours: Option<f64>,
+    /// Hours from plan start when the override ends (empty = never)
+    #[serde(default, deserialize_with = "crate::deserialize_optional_f64")]
+    pub end_hours: Option<f64>,
+}
+
+/// CSV row structure for values gained by consuming items.
+#[derive(Debug, Deserialize)]
+pub struct ConsumableRow {
+    /// Item name
+    pub name: String,
+    /// What consuming the item is worth (e.g., "experience")
+    pub objective: String,
+    /// Amount gained per unit consumed
+    pub value: f64,
+}
+
+/// CSV row structure for order board entries.
+#[derive(Debug, Deserialize)]
+pub struct OrderRow {
+    /// Order name
+    pub name: String,
+    /// Items to deliver (semicolon-separated)
+    pub items: String,
+    /// Quantity of each item (semicolon-separated, parallel to `items`)
+    pub amounts: String,
+    /// Reward for filling the order
+    pub reward: f64,
+    /// Currency of the reward
+    pub currency: String,
+}
@@ -7,15 +7,21 @@
 use std::collections::{HashMap, HashSet};
 
 use crate::models::{
-    EnergyItemEfficiency, FacilityCounts, ModuleLevels, ProductionEfficiency, ProductionItem, ProductionPath,
-    ProductionStep,
+    BatchJob, CostObjective, CurrencyLedger, CurrencyValuation, DeadlinePlan, EnergyBudget, EnergyItemEfficiency, FacilityCounts, Inventory, InventorySeries, MaterialBalance, MaterialRequirement, MaterialUsage,
+    ModuleLevels, OverrideTimeline, PlannedJobs, PlanningOptions, ProductionEfficiency, ProductionItem, ProductionPath, ProductionStep,
+    ScheduleOutcome, StorageCapacity, TimedStats,
 };
+use crate::schedule::simulate_batches;
 
 /// Calculates the optimal allocation of facilities to minimize production time
 /// when producing multiple different materials.
 /// 
 /// Given:
 /// - `materials`: Vec of (material_name, batches_needed, time_per_batch)
+/// - `eligible`: How many of the facilities can produce each material (by
+///   index into `materials`). Facilities are levelled, so a material that
+///   needs a higher level can use a subset of the facilities open to one
+///   that needs a lower level.
 /// - `total_facilities`: Total number of facilities available
 /// 
 /// Returns: Vec of (material_name, batches_needed, optimal_facilities_to_allocate)
@@ -24,6 +30,7 @@ use crate::models::{
 /// where M = number of materials, B = max batches.
 fn calculate_optimal_facility_allocation(
     materials: &[(String, u32, f64)],
+    eligible: &[u32],
     total_facilities: u32,
 ) -> Vec<(String, u32, u32)> {
     if materials.is_empty() {
@@ -36,15 +43,21 @@ fn calculate_optimal_facility_allocation(
             .collect();
     }
     
+    // Never allocate more facilities than can produce a material
+    let eligible: Vec<u32> = (0..materials.len())
+        .map(|i| eligible.get(i).copied().unwrap_or(total_facilities).min(total_facilities))
+        .collect();
+
     if materials.len() == 1 {
-        // Single material gets all facilities
-        return vec![(materials[0].0.clone(), materials[0].1, total_facilities)];
+        // Single material gets every facility that can produce it
+        return vec![(materials[0].0.clone(), materials[0].1, eligible[0])];
     }
     
     // Filter to only materials that need production (batches > 0 and time > 0)
+    // and that some facility can produce
     let active_materials: Vec<(usize, u32, f64)> = materials.iter()
         .enumerate()
-        .filter(|(_, (_, batches, time))| *batches > 0 && *time > 0.0)
+        .filter(|(i, (_, batches, time))| *batches > 0 && *time > 0.0 && eligible[*i] > 0)
         .map(|(i, (_, batches, time))| (i, *batches, *time))
         .collect();
     
@@ -64,13 +77,13 @@ fn calculate_optimal_facility_allocation(
     // Collect all possible completion times using sqrt decomposition
     // For ceil(b/k) where k goes from 1 to b, there are only O(sqrt(b)) distinct values
     let mut candidate_times: Vec<f64> = Vec::new();
-    for (_, batches, time) in &active_materials {
+    for (idx, batches, time) in &active_materials {
         if *batches == 0 || *time <= 0.0 {
             continue;
         }
         let mut k = 1u32;
         while k <= *batches {
-            let rounds = (*batches + k - 1) / k; // ceil(batches / k)
+            let rounds = batches.div_ceil(k); // ceil(batches / k)
             candidate_times.push(rounds as f64 * time);
             // Jump to next k that gives a different ceil value
             if rounds > 1 {
@@ -82,10 +95,8 @@ fn calculate_optimal_facility_allocation(
                 break;
             }
         }
-        // Also add the case where we use all facilities for this material
-        if total_facilities > 0 {
-            candidate_times.push(((*batches + total_facilities - 1) / total_facilities) as f64 * time);
-        }
+        // Also add the case where we use all eligible facilities for this material
+        candidate_times.push(batches.div_ceil(eligible[*idx]) as f64 * time);
     }
     
     // Handle empty candidates
@@ -104,17 +115,36 @@ fn calculate_optimal_facility_allocation(
     candidate_times.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
     
     // Binary search for minimum feasible time
-    let optimal_time = binary_search_min_time(&candidate_times, &active_materials, total_facilities);
+    let optimal_time = binary_search_min_time(&candidate_times, &active_materials, &eligible);
+    if !is_time_feasible(optimal_time, &active_materials, &eligible) {
+        // Too few eligible facilities to give every material its own
+        return distribute_proportionally(materials, total_facilities);
+    }
     
     // Calculate the allocation for this optimal time
-    calculate_allocation_for_time(materials, &active_materials, optimal_time, total_facilities)
+    calculate_allocation_for_time(materials, &active_materials, optimal_time, &eligible, total_facilities)
+}
+
+/// Checks that an allocation only uses facilities able to produce each material.
+///
+/// Eligible facilities are nested (a facility that can produce a high-level
+/// material can produce every lower-level one), so it's enough that every
+/// group of the most restricted materials fits in the facilities open to them.
+fn fits_eligible(allocations: &[(usize, u32)], eligible: &[u32]) -> bool {
+    let mut sorted: Vec<(usize, u32)> = allocations.to_vec();
+    sorted.sort_by_key(|&(idx, _)| eligible[idx]);
+    let mut used = 0u32;
+    sorted.iter().all(|&(idx, facilities)| {
+        used = used.saturating_add(facilities);
+        used <= eligible[idx]
+    })
 }
 
 /// Binary search to find the minimum feasible completion time
 fn binary_search_min_time(
     candidate_times: &[f64],
     active_materials: &[(usize, u32, f64)],
-    total_facilities: u32,
+    eligible: &[u32],
 ) -> f64 {
     if candidate_times.is_empty() {
         return 0.0;
@@ -125,7 +155,7 @@ fn binary_search_min_time(
     
     while lo < hi {
         let mid = lo + (hi - lo) / 2;
-        if is_time_feasible(candidate_times[mid], active_materials, total_facilities) {
+        if is_time_feasible(candidate_times[mid], active_materials, eligible) {
             hi = mid;
         } else {
             lo = mid + 1;
@@ -144,11 +174,11 @@ fn binary_search_min_time(
 fn is_time_feasible(
     target_time: f64,
     active_materials: &[(usize, u32, f64)],
-    total_facilities: u32,
+    eligible: &[u32],
 ) -> bool {
-    let mut facilities_needed = 0u32;
+    let mut facilities_needed = Vec::with_capacity(active_materials.len());
     
-    for (_, batches, time) in active_materials {
+    for (idx, batches, time) in active_materials {
         if *time <= 0.0 {
             continue;
         }
@@ -159,15 +189,10 @@ fn is_time_feasible(
         }
         
         // Minimum facilities needed: ceil(batches / max_rounds)
-        let min_facilities = (*batches + max_rounds - 1) / max_rounds;
-        facilities_needed = facilities_needed.saturating_add(min_facilities);
-        
-        if facilities_needed > total_facilities {
-            return false;
-        }
+        facilities_needed.push((*idx, batches.div_ceil(max_rounds)));
     }
     
-    true
+    fits_eligible(&facilities_needed, eligible)
 }
 
 /// Calculate the actual facility allocation for a given target time
@@ -175,6 +200,7 @@ fn calculate_allocation_for_time(
     materials: &[(String, u32, f64)],
     active_materials: &[(usize, u32, f64)],
     target_time: f64,
+    eligible: &[u32],
     total_facilities: u32,
 ) -> Vec<(String, u32, u32)> {
     let mut result: Vec<(String, u32, u32)> = materials.iter()
@@ -196,7 +222,7 @@ fn calculate_allocation_for_time(
         let min_facilities = if max_rounds == 0 {
             *batches // Need one facility per batch (shouldn't happen if time is feasible)
         } else {
-            (*batches + max_rounds - 1) / max_rounds
+            batches.div_ceil(max_rounds)
         };
         
         allocations.push((*idx, min_facilities));
@@ -218,11 +244,11 @@ fn calculate_allocation_for_time(
         
         for (idx, batches, time) in active_materials {
             let current_facilities = result[*idx].2;
-            if current_facilities == 0 {
+            if current_facilities == 0 || !fits_with_one_more(&result, active_materials, *idx, eligible) {
                 continue;
             }
             
-            let current_rounds = (*batches + current_facilities - 1) / current_facilities;
+            let current_rounds = batches.div_ceil(current_facilities);
             let new_rounds = (*batches + current_facilities) / (current_facilities + 1);
             
             if new_rounds < current_rounds {
@@ -238,9 +264,12 @@ fn calculate_allocation_for_time(
             result[idx].2 += 1;
             remaining -= 1;
         } else {
-            // No improvement possible, distribute to first active material
-            if let Some((idx, _, _)) = active_materials.first() {
-                result[*idx].2 += remaining;
+            // No improvement possible, give the rest to the first materials able to use them
+            for (idx, _, _) in active_materials {
+                while remaining > 0 && fits_with_one_more(&result, active_materials, *idx, eligible) {
+                    result[*idx].2 += 1;
+                    remaining -= 1;
+                }
             }
             break;
         }
@@ -249,6 +278,20 @@ fn calculate_allocation_for_time(
     result
 }
 
+/// Checks whether one more facility can go to the material at `idx`.
+fn fits_with_one_more(
+    result: &[(String, u32, u32)],
+    active_materials: &[(usize, u32, f64)],
+    idx: usize,
+    eligible: &[u32],
+) -> bool {
+    let allocations: Vec<(usize, u32)> = active_materials
+        .iter()
+        .map(|(i, _, _)| (*i, result[*i].2 + u32::from(*i == idx)))
+        .collect();
+    fits_eligible(&allocations, eligible)
+}
+
 /// Distribute facilities proportionally when there aren't enough
 fn distribute_proportionally(
     materials: &[(String, u32, f64)],
@@ -306,13 +349,14 @@ struct ProductionRequirements {
 /// 
 /// This handles both simple raw materials and processed items that may
 /// require other processed items as ingredients (e.g., caramel_nut_chips requires nuts).
+#[allow(clippy::too_many_arguments)]
 fn calculate_item_requirements(
     item_name: &str,
     required_amount: f64,
     item_map: &HashMap<String, &ProductionItem>,
     facility_counts: &FacilityCounts,
     module_levels: &ModuleLevels,
-    fertilizer_time_per_unit: f64,
+    fertilizer_wait: f64,
     nimbus_bed_count: f64,
     visited: &mut HashSet<String>, // Prevent infinite recursion
 ) -> ProductionRequirements {
@@ -427,7 +471,7 @@ fn calculate_item_requirements(
     
     let result = if let Some(ref raw_mats) = item.raw_materials {
         // This is a processed item - recursively calculate requirements for each ingredient
-        let required_amounts = item.required_amount.as_ref().map(|v| v.as_slice()).unwrap_or(&[]);
+        let required_amounts = item.required_amount.as_deref().unwrap_or(&[]);
         
         let mut max_ingredient_time = 0.0;
         let mut total_ingredient_energy: Option<f64> = None;
@@ -453,7 +497,7 @@ fn calculate_item_requirements(
                 item_map,
                 facility_counts,
                 module_levels,
-                fertilizer_time_per_unit,
+                fertilizer_wait,
                 nimbus_bed_count,
                 visited,
             );
@@ -519,11 +563,11 @@ fn calculate_item_requirements(
         }
         
         // Add processing time for this item
-        let processing_facility_count = facility_counts.get_count(&item.facility) as f64;
+        let processing_facility_count = facility_counts.count_for(item) as f64;
         let processing_time = item.production_time * (batches_needed / processing_facility_count).ceil();
         
         // Add processing energy
-        let total_energy = match (total_ingredient_energy, item.energy) {
+        let total_energy = match (total_ingredient_energy, item.energy_cost) {
             (Some(ie), Some(pe)) => Some(ie + pe * batches_needed),
             (Some(ie), None) => Some(ie),
             (None, Some(pe)) => Some(pe * batches_needed),
@@ -542,22 +586,22 @@ fn calculate_item_requirements(
         }
     } else {
         // This is a base raw material
-        let facility_count = facility_counts.get_count(&item.facility) as f64;
+        let facility_count = facility_counts.count_for(item) as f64;
         let batches_needed = (required_amount / item.yield_amount as f64).ceil();
         
         // Calculate time with parallel facilities
         let time_per_batch = item.production_time;
         let parallel_batches = (batches_needed / facility_count).ceil();
         
-        // Add fertilizer time if required
+        // Wait for the first fertilizer batch if required
         let fertilizer_time = if item.requires_fertilizer {
-            fertilizer_time_per_unit * batches_needed
+            fertilizer_wait
         } else {
             0.0
         };
         
         let total_time = time_per_batch * parallel_batches + fertilizer_time;
-        let total_energy = item.energy.map(|e| e * batches_needed);
+        let total_energy = item.energy_cost.map(|e| e * batches_needed);
         let total_cost = item.cost.unwrap_or(0.0) * batches_needed;
         
         let mut all_facilities = HashSet::new();
@@ -579,6 +623,843 @@ fn calculate_item_requirements(
     result
 }
 
+/// Picks the variant of an item that will actually be produced: the
+/// high-speed variant when its module and facility level allow, otherwise
+/// the base item.
+pub(crate) fn resolve_item_variant<'a>(
+    item_name: &str,
+    item_map: &HashMap<String, &'a ProductionItem>,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+) -> Option<&'a ProductionItem> {
+    let high_speed_name = format!("high_speed_{}", item_name);
+    item_map
+        .get(&high_speed_name)
+        .filter(|hs| {
+            let can_use = if let Some((ref m, l)) = hs.module_requirement {
+                module_levels.can_use(m, l)
+            } else {
+                true
+            };
+            can_use && facility_counts.can_produce(&hs.facility, hs.facility_level)
+        })
+        .or_else(|| item_map.get(item_name))
+        .copied()
+}
+
+/// Builds the flattened ingredient tree of an item (parents before children).
+///
+/// Returns an empty list for raw materials. Ingredients that can't be
+/// resolved are left out; [`calculate_efficiencies`] has already filtered
+/// out items whose chains are invalid.
+pub(crate) fn build_material_tree(
+    item: &ProductionItem,
+    item_map: &HashMap<String, &ProductionItem>,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+) -> Vec<MaterialRequirement> {
+    fn visit(
+        item: &ProductionItem,
+        parent: Option<usize>,
+        item_map: &HashMap<String, &ProductionItem>,
+        facility_counts: &FacilityCounts,
+        module_levels: &ModuleLevels,
+        depth: usize,
+        out: &mut Vec<MaterialRequirement>,
+    ) {
+        // Recipes are shallow; the depth guard only protects against cycles in the data
+        if depth > 8 {
+            return;
+        }
+        let Some(ref raw_mats) = item.raw_materials else {
+            return;
+        };
+        let required_amounts = item.required_amount.as_deref().unwrap_or(&[]);
+
+        for (i, raw_mat) in raw_mats.iter().enumerate() {
+            let Some(mat) = resolve_item_variant(raw_mat, item_map, facility_counts, module_levels) else {
+                continue;
+            };
+            out.push(MaterialRequirement {
+                name: raw_mat.clone(),
+                variant: mat.name.clone(),
+                facility: mat.facility.clone(),
+                facility_level: mat.facility_level,
+                parent,
+                amount_per_parent: required_amounts.get(i).copied().unwrap_or(1),
+                yield_amount: mat.yield_amount.max(1),
+                production_time: mat.production_time,
+                is_raw: mat.raw_materials.is_none(),
+                requires_fertilizer: mat.requires_fertilizer,
+                cost: mat.cost.unwrap_or(0.0),
+                sell_value: mat.sell_value,
+                sell_currency: mat.sell_currency.clone(),
+            });
+            let index = out.len() - 1;
+            visit(mat, Some(index), item_map, facility_counts, module_levels, depth + 1, out);
+        }
+    }
+
+    let mut materials = Vec::new();
+    visit(item, None, item_map, facility_counts, module_levels, 0, &mut materials);
+    materials
+}
+
+/// Expands a material tree for a number of final batches, drawing each
+/// ingredient from the inventory before producing it.
+///
+/// Intermediates are netted before their own ingredients, so holding 5 nuts
+/// also reduces the walnut and chestnut needed. Taken stock is removed from
+/// `inventory`.
+///
+/// Returns an error if an ingredient amount doesn't fit in a `u32`.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::{Inventory, MaterialRequirement};
+/// use aniimax::optimizer::expand_materials;
+///
+/// // dried_strawberry: 3 strawberries per batch, strawberries yield 5 per batch
+/// let materials = vec![MaterialRequirement {
+///     name: "strawberry".to_string(),
+///     variant: "strawberry".to_string(),
+///     facility: "Farmland".to_string(),
+///     facility_level: 1,
+///     parent: None,
+///     amount_per_parent: 3,
+///     yield_amount: 5,
+///     production_time: 3600.0,
+///     is_raw: true,
+///     requires_fertilizer: false,
+///     cost: 26.0,
+///     sell_value: 9.0,
+///     sell_currency: "coins".to_string(),
+/// }];
+/// let mut inventory = Inventory::parse("strawberry=12").unwrap();
+///
+/// let usage = expand_materials(&materials, 10, &mut inventory).unwrap();
+/// assert_eq!(usage[0].required, 30);
+/// assert_eq!(usage[0].from_stock, 12);
+/// assert_eq!(usage[0].to_produce, 18);
+/// assert_eq!(usage[0].batches, 4);
+/// ```
+pub fn expand_materials(
+    materials: &[MaterialRequirement],
+    batches: u32,
+    inventory: &mut Inventory,
+) -> Result<Vec<MaterialUsage>, String> {
+    let mut usage: Vec<MaterialUsage> = Vec::with_capacity(materials.len());
+
+    for material in materials {
+        let parent_batches = match material.parent {
+            Some(p) => usage.get(p).map(|u| u.batches).unwrap_or(0),
+            None => batches,
+        };
+        let required = material
+            .amount_per_parent
+            .checked_mul(parent_batches)
+            .ok_or_else(|| format!("Too many units of '{}' required", material.name))?;
+        let from_stock = inventory.take(&material.name, required);
+        let to_produce = required - from_stock;
+
+        usage.push(MaterialUsage {
+            name: material.name.clone(),
+            variant: material.variant.clone(),
+            facility: material.facility.clone(),
+            is_raw: material.is_raw,
+            required,
+            from_stock,
+            to_produce,
+            batches: to_produce.div_ceil(material.yield_amount.max(1)),
+        });
+    }
+
+    Ok(usage)
+}
+
+/// Input side of a production plan after netting the inventory.
+struct PlannedInputs {
+    /// Fertilizer, raw material and intermediate steps, in display order
+    steps: Vec<ProductionStep>,
+    /// Whether the ingredients of the first batch are already on hand
+    first_batch_on_hand: bool,
+    /// Amount of every ingredient used, produced and taken from stock
+    usage: Vec<MaterialUsage>,
+    /// Fertilizer the plan consumes
+    fertilizer_required: u32,
+    /// Fertilizer that still has to be made on the Nimbus Beds
+    fertilizer_to_make: u32,
+}
+
+impl PlannedInputs {
+    /// Share of fertilizer that still has to be made (1.0 with an empty
+    /// inventory).
+    fn fertilizer_fraction(&self) -> f64 {
+        if self.fertilizer_required == 0 {
+            1.0
+        } else {
+            self.fertilizer_to_make as f64 / self.fertilizer_required as f64
+        }
+    }
+
+    /// Time the fertilized crops wait for the first fertilizer batch: none
+    /// if nothing is left to make or the stock covers `first_round` of
+    /// fertilizer.
+    fn fertilizer_wait(&self, eff: &ProductionEfficiency, first_round: u32) -> f64 {
+        let on_hand = self.fertilizer_required - self.fertilizer_to_make;
+        match eff.fertilizer_batch {
+            Some((time, _)) if self.fertilizer_to_make > 0 && on_hand < first_round => time,
+            _ => 0.0,
+        }
+    }
+
+    /// Material balance of the chain once `batches` of `eff` are made and
+    /// sold (or eaten): whole batches of every ingredient, of which the
+    /// units the recipes don't take are left over.
+    fn balance(&self, eff: &ProductionEfficiency, batches: u32) -> Vec<MaterialBalance> {
+        let mut balances = Vec::new();
+        if self.fertilizer_required > 0 {
+            // Whole batches on the beds, when they can make it
+            let produced = match eff.fertilizer_batch {
+                Some((_, yield_amount)) if yield_amount > 0 => {
+                    self.fertilizer_to_make.div_ceil(yield_amount) * yield_amount
+                }
+                _ => self.fertilizer_to_make,
+            };
+            add_balance(
+                &mut balances,
+                MaterialBalance {
+                    name: "fertilizer".to_string(),
+                    from_stock: self.fertilizer_required - self.fertilizer_to_make,
+                    produced,
+                    consumed: self.fertilizer_required,
+                    surplus_sold: 0,
+                    left_over: produced - self.fertilizer_to_make,
+                },
+            );
+        }
+        for (material, u) in eff.materials.iter().zip(&self.usage) {
+            let produced = u.batches * material.yield_amount;
+            add_balance(
+                &mut balances,
+                MaterialBalance {
+                    name: u.name.clone(),
+                    from_stock: u.from_stock,
+                    produced,
+                    consumed: u.required,
+                    surplus_sold: 0,
+                    left_over: u.from_stock + produced - u.required,
+                },
+            );
+        }
+        let made = batches * eff.item.yield_amount;
+        add_balance(
+            &mut balances,
+            MaterialBalance {
+                name: eff.item.name.clone(),
+                produced: made,
+                consumed: made,
+                ..Default::default()
+            },
+        );
+        balances
+    }
+
+    /// Adds the fertilizer and ingredients the chain takes from the starting
+    /// inventory to `stock`.
+    fn add_stock_used(&self, stock: &mut HashMap<String, u32>) {
+        let fertilizer = self.fertilizer_required - self.fertilizer_to_make;
+        if fertilizer > 0 {
+            *stock.entry("fertilizer".to_string()).or_insert(0) += fertilizer;
+        }
+        for u in self.usage.iter().filter(|u| u.from_stock > 0) {
+            *stock.entry(u.name.clone()).or_insert(0) += u.from_stock;
+        }
+    }
+
+    /// Records the chain's running time on the raw material and intermediate
+    /// steps (fertilizer time is part of the total).
+    fn set_step_time(&mut self, step_time: f64) {
+        for step in self.steps.iter_mut().filter(|s| s.item_name != "fertilizer") {
+            step.time = step_time;
+        }
+    }
+}
+
+/// Builds the fertilizer, raw material and intermediate steps for `batches`
+/// of an item, drawing from the inventory first.
+///
+/// Step times are left at 0; the parallel planner fills them in with
+/// [`PlannedInputs::set_step_time`]. Returns `None` if the ingredient
+/// amounts overflow (see [`expand_materials`]).
+fn plan_inputs(
+    eff: &ProductionEfficiency,
+    batches: u32,
+    facility_counts: &FacilityCounts,
+    inventory: &mut Inventory,
+    chain_id: Option<u32>,
+) -> Option<PlannedInputs> {
+    let first_batch_on_hand = !eff.materials.is_empty()
+        && eff
+            .materials
+            .iter()
+            .filter(|m| m.parent.is_none())
+            .all(|m| inventory.get(&m.name) >= m.amount_per_parent);
+
+    let usage = expand_materials(&eff.materials, batches, inventory).ok()?;
+    let mut steps = Vec::new();
+
+    // Fertilizer: one per batch of every fertilizer-requiring raw material
+    let nimbus_bed_count = facility_counts.get_count("Nimbus Bed");
+    let fertilizer_needed: u32 = if eff.materials.is_empty() {
+        eff.fertilizer_per_batch.checked_mul(batches)?
+    } else {
+        eff.materials
+            .iter()
+            .zip(&usage)
+            .filter(|(m, _)| m.requires_fertilizer)
+            .map(|(_, u)| u.batches)
+            .sum()
+    };
+    let fertilizer_required = fertilizer_needed;
+    let fertilizer_needed = fertilizer_needed - inventory.take("fertilizer", fertilizer_needed);
+    if fertilizer_needed > 0 && nimbus_bed_count > 0 {
+        steps.push(ProductionStep {
+            item_name: "fertilizer".to_string(),
+            facility: format!("Nimbus Bed (x{})", nimbus_bed_count),
+            quantity: fertilizer_needed,
+            time: 0.0, // Time is included in total
+            energy: None,
+            energy_gained: None,
+            profit_contribution: 0.0,
+            chain_id,
+            facility_allocation: None,
+        });
+    }
+
+    let raw_to_produce: u32 = usage.iter().filter(|u| u.is_raw).map(|u| u.to_produce).sum();
+
+    if let Some(ref raw_name) = eff.requires_raw {
+        if raw_to_produce > 0 {
+            let raw_facility = eff.raw_facility.as_deref().unwrap_or("Unknown");
+            let raw_facility_count = facility_counts.get_count(raw_facility);
+
+            // Calculate optimal facility allocation for multi-material production
+            let facility_allocation = if let Some(ref details) = eff.raw_material_details {
+                let materials_for_allocation: Vec<(String, u32, f64)> = details
+                    .iter()
+                    .map(|(name, _, time)| {
+                        let needed: u32 = usage
+                            .iter()
+                            .filter(|u| u.is_raw && &u.variant == name)
+                            .map(|u| u.to_produce)
+                            .sum();
+                        (name.clone(), needed, *time)
+                    })
+                    .collect();
+
+                let eligible: Vec<u32> = details
+                    .iter()
+                    .map(|(name, _, _)| {
+                        eff.materials
+                            .iter()
+                            .find(|m| &m.variant == name)
+                            .map_or(raw_facility_count, |m| facility_counts.count_at_level(raw_facility, m.facility_level))
+                    })
+                    .collect();
+                let allocation =
+                    calculate_optimal_facility_allocation(&materials_for_allocation, &eligible, raw_facility_count);
+                if allocation.len() > 1 {
+                    Some(allocation)
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            steps.push(ProductionStep {
+                item_name: raw_name.clone(),
+                facility: format!("{} (x{})", raw_facility, raw_facility_count),
+                quantity: raw_to_produce,
+                time: 0.0,
+                energy: None,
+                energy_gained: None,
+                profit_contribution: 0.0,
+                chain_id,
+                facility_allocation,
+            });
+        }
+
+        // Intermediate processing steps (e.g., nuts for caramel_nut_chips), deepest first
+        for u in usage.iter().rev().filter(|u| !u.is_raw && u.to_produce > 0) {
+            steps.push(ProductionStep {
+                item_name: u.variant.clone(),
+                facility: format!("{} (x{})", u.facility, facility_counts.get_count(&u.facility)),
+                quantity: u.to_produce,
+                time: 0.0,
+                energy: None,
+                energy_gained: None,
+                profit_contribution: 0.0,
+                chain_id,
+                facility_allocation: None,
+            });
+        }
+    }
+
+    Some(PlannedInputs {
+        steps,
+        first_batch_on_hand,
+        usage,
+        fertilizer_required,
+        fertilizer_to_make: fertilizer_needed,
+    })
+}
+
+/// Adds an item's balance to a plan's, merging it with the item's entry if
+/// there is one.
+fn add_balance(balances: &mut Vec<MaterialBalance>, balance: MaterialBalance) {
+    match balances.iter_mut().find(|b| b.name == balance.name) {
+        Some(existing) => existing.merge(&balance),
+        None => balances.push(balance),
+    }
+}
+
+/// Adds the stock levels of `other`, held from `offset` seconds on, to
+/// `series`, item by item.
+fn add_inventory(series: &mut Vec<InventorySeries>, other: &[InventorySeries], offset: f64) {
+    for item in other {
+        match series.iter_mut().find(|s| s.name == item.name) {
+            Some(existing) => existing.add(item, offset),
+            None => {
+                let mut shifted = InventorySeries {
+                    name: item.name.clone(),
+                    ..Default::default()
+                };
+                shifted.add(item, offset);
+                series.push(shifted);
+            }
+        }
+    }
+}
+
+/// Sells every unit left over in `balances` at its list price, booking the
+/// income in `ledger`. Prices come from the ingredients of `chains`; items
+/// that aren't among them are kept.
+fn sell_surplus(balances: &mut [MaterialBalance], chains: &[&ProductionEfficiency], ledger: &mut CurrencyLedger) {
+    for balance in balances.iter_mut().filter(|b| b.left_over > 0) {
+        let Some(material) = chains
+            .iter()
+            .flat_map(|eff| &eff.materials)
+            .find(|m| m.name == balance.name)
+        else {
+            continue;
+        };
+        ledger.earn(&material.sell_currency, balance.left_over as f64 * material.sell_value);
+        balance.surplus_sold += balance.left_over;
+        balance.left_over = 0;
+    }
+}
+
+/// Builds the batch-level jobs of one production chain: the fertilizer and
+/// every ingredient in `inputs` plus `batches` of the final item, which is
+/// sold as it finishes.
+fn chain_jobs(eff: &ProductionEfficiency, batches: u32, inputs: &PlannedInputs) -> Vec<BatchJob> {
+    let materials = &eff.materials;
+    let usage = &inputs.usage;
+    // Fertilizer is only tracked when the beds can make it
+    let fertilizer = eff.fertilizer_batch.filter(|_| inputs.fertilizer_required > 0);
+    let inputs_of = |parent: Option<usize>| -> Vec<(String, u32)> {
+        let mut needs: Vec<(String, u32)> = materials
+            .iter()
+            .filter(|m| m.parent == parent)
+            .map(|m| (m.name.clone(), m.amount_per_parent))
+            .collect();
+        let fertilizer_per_batch = match parent {
+            Some(i) if materials[i].requires_fertilizer => 1,
+            None if materials.is_empty() => eff.fertilizer_per_batch,
+            _ => 0,
+        };
+        if fertilizer.is_some() && fertilizer_per_batch > 0 {
+            needs.push(("fertilizer".to_string(), fertilizer_per_batch));
+        }
+        needs
+    };
+
+    // Processing still ahead of each ingredient once it is done
+    let mut tail_times: Vec<f64> = Vec::with_capacity(materials.len());
+    for material in materials {
+        let tail = match material.parent {
+            Some(p) => materials[p].production_time + tail_times[p],
+            None => eff.item.production_time,
+        };
+        tail_times.push(tail);
+    }
+
+    let mut jobs: Vec<BatchJob> = materials
+        .iter()
+        .zip(usage)
+        .enumerate()
+        .filter(|(_, (_, u))| u.batches > 0)
+        .map(|(i, (m, u))| BatchJob {
+            name: m.name.clone(),
+            variant: m.variant.clone(),
+            facility: m.facility.clone(),
+            production_time: m.production_time,
+            yield_amount: m.yield_amount,
+            inputs: inputs_of(Some(i)),
+            batches: u.batches,
+            sold: false,
+            seed_cost: m.cost,
+            sale_value: 0.0,
+            tail_time: tail_times[i],
+            level_stats: eff.level_stats.get(&m.variant).cloned().unwrap_or_default(),
+            timed_stats: Vec::new(),
+        })
+        .collect();
+    jobs.push(BatchJob {
+        name: eff.item.name.clone(),
+        variant: eff.item.name.clone(),
+        facility: eff.item.facility.clone(),
+        production_time: eff.item.production_time,
+        yield_amount: eff.item.yield_amount,
+        inputs: inputs_of(None),
+        batches,
+        sold: true,
+        seed_cost: eff.item.cost.unwrap_or(0.0),
+        sale_value: eff.ledger.earned("coins"),
+        tail_time: 0.0,
+        level_stats: eff.level_stats.get(&eff.item.name).cloned().unwrap_or_default(),
+        timed_stats: Vec::new(),
+    });
+
+    // Fertilizer, made ahead of the crops that need it
+    if let Some((production_time, yield_amount)) = fertilizer.filter(|_| inputs.fertilizer_to_make > 0) {
+        let tail_time = jobs
+            .iter()
+            .filter(|j| j.inputs.iter().any(|(n, _)| n == "fertilizer"))
+            .map(|j| j.production_time + j.tail_time)
+            .fold(0.0, f64::max);
+        jobs.insert(
+            0,
+            BatchJob {
+                name: "fertilizer".to_string(),
+                variant: "fertilizer".to_string(),
+                facility: "Nimbus Bed".to_string(),
+                production_time,
+                yield_amount,
+                inputs: Vec::new(),
+                batches: inputs.fertilizer_to_make.div_ceil(yield_amount.max(1)),
+                sold: false,
+                seed_cost: 0.0,
+                sale_value: 0.0,
+                tail_time,
+                level_stats: eff.level_stats.get("fertilizer").cloned().unwrap_or_default(),
+                timed_stats: Vec::new(),
+            },
+        );
+    }
+    jobs
+}
+
+/// How storage and the coin balance affect a set of chains running side by side.
+struct ScheduleEffects {
+    /// Most units held at once
+    peak_inventory: u32,
+    /// Extra time each chain needs to stay within the storage capacity and coin balance,
+    /// and to wait for facilities busy with batches in progress
+    delays: Vec<f64>,
+    /// Coin balance over time (empty without a starting balance)
+    balance: Vec<(f64, f64)>,
+    /// Stock of every item over time (empty if the chains couldn't be simulated)
+    stock_levels: Vec<InventorySeries>,
+    /// Jobs of the chains if they weren't simulated
+    unsimulated: Vec<PlannedJobs>,
+    /// When every chain finishes and when the last of them first sells, in
+    /// the simulated schedule (`None` if the chains weren't simulated)
+    timing: Option<(Vec<f64>, f64)>,
+    /// What each chain's sold batches fetch, relative to the planned yield
+    /// and price, with overrides changing them during the plan (1.0 otherwise)
+    sale_scales: Vec<f64>,
+}
+
+/// Whether plans have to be simulated batch by batch as they are made: to
+/// respect a storage limit, a starting balance or batches in progress, or
+/// overrides that start or end during the plan.
+fn needs_simulation(options: &PlanningOptions) -> bool {
+    !options.storage.is_unlimited()
+        || options.starting_coins.is_some()
+        || !options.in_progress.is_empty()
+        || options.overrides.is_timed()
+}
+
+/// Stats of a job from each time an override starts or ends on (see
+/// [`BatchJob::timed_stats`]), or none if the overrides never change them.
+///
+/// The job's own stats are those at the timeline's `planned_at`, facility
+/// levels and modules included; each override changes them by as much as it
+/// changes the item.
+pub(crate) fn timed_stats(job: &BatchJob, timeline: &OverrideTimeline) -> Vec<TimedStats> {
+    let Some(base) = timeline.item_at(&job.variant, timeline.planned_at) else {
+        return Vec::new();
+    };
+    let ratio = |now: f64, then: f64| if then > 0.0 { now / then } else { 1.0 };
+    let stats: Vec<TimedStats> = std::iter::once(0.0)
+        .chain(timeline.change_times())
+        .filter_map(|start| {
+            let item = timeline.item_at(&job.variant, start)?;
+            let speed = ratio(item.production_time, base.production_time);
+            let growth = ratio(item.yield_amount as f64, base.yield_amount as f64);
+            let yield_of = |amount: u32| ((amount as f64 * growth).round() as u32).max(1);
+            Some(TimedStats {
+                start,
+                production_time: job.production_time * speed,
+                yield_amount: yield_of(job.yield_amount),
+                level_stats: job
+                    .level_stats
+                    .iter()
+                    .map(|&(level, time, amount)| (level, time * speed, yield_of(amount)))
+                    .collect(),
+                price_factor: ratio(item.sell_value, base.sell_value),
+            })
+        })
+        .collect();
+    let unchanged = |s: &TimedStats| {
+        s.production_time == job.production_time && s.yield_amount == job.yield_amount && s.price_factor == 1.0
+    };
+    if stats.iter().all(unchanged) {
+        return Vec::new();
+    }
+    stats
+}
+
+/// Scales the sales in `currency` booked in a ledger by `scale` (see
+/// [`ScheduleEffects::sale_scales`]).
+fn scale_sales(ledger: &mut CurrencyLedger, currency: &str, scale: f64) {
+    if let Some(income) = ledger.income.get_mut(currency) {
+        *income *= scale;
+    }
+}
+
+/// Finds the plan reaching `target` when overrides during the plan change
+/// what its batches sell for: `plan` makes the plan for a target at the
+/// prices at the start, which is raised or lowered until the plan it gives
+/// just reaches `target`. Returns `None` if no plan does.
+fn size_to_target(target: f64, plan: impl Fn(f64) -> Option<ProductionPath>) -> Option<ProductionPath> {
+    let mut high = target;
+    let mut best = plan(high)?;
+    let mut doublings = 0;
+    while best.total_profit < target {
+        doublings += 1;
+        if doublings > 32 {
+            return None;
+        }
+        high *= 2.0;
+        best = plan(high)?;
+    }
+    // Smallest planned target still reaching it
+    let mut low = 0.0;
+    while high - low > 1.0 {
+        let mid = (low + high) / 2.0;
+        match plan(mid) {
+            Some(path) if path.total_profit >= target => {
+                high = mid;
+                best = path;
+            }
+            _ => low = mid,
+        }
+    }
+    Some(best)
+}
+
+/// With a storage limit, a starting balance or batches already in progress,
+/// simulates the chains batch by batch to find their peak inventory and how
+/// much each chain is slowed down by them.
+///
+/// Otherwise nothing is simulated: the chains' jobs are kept so the plan
+/// finally chosen can be (see [`simulate_stock_levels`]).
+///
+/// Returns `None` if the chains can't be produced within the storage limit
+/// or paid for with the coins available.
+fn schedule_effects(
+    chains: &[Vec<BatchJob>],
+    initial_stock: &HashMap<String, u32>,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
+) -> Option<ScheduleEffects> {
+    let timed = options.overrides.is_timed();
+    let chains: Vec<Vec<BatchJob>> = chains
+        .iter()
+        .map(|chain| {
+            chain
+                .iter()
+                .map(|job| BatchJob {
+                    timed_stats: if timed { timed_stats(job, &options.overrides) } else { Vec::new() },
+                    ..job.clone()
+                })
+                .collect()
+        })
+        .collect();
+    let jobs: Vec<BatchJob> = chains.iter().flatten().cloned().collect();
+    if !needs_simulation(options) {
+        return Some(ScheduleEffects {
+            peak_inventory: 0,
+            delays: vec![0.0; chains.len()],
+            balance: Vec::new(),
+            stock_levels: Vec::new(),
+            unsimulated: vec![PlannedJobs {
+                start: 0.0,
+                jobs,
+                initial_stock: initial_stock.clone(),
+            }],
+            timing: None,
+            sale_scales: vec![1.0; chains.len()],
+        });
+    }
+
+    // Completion time of every chain in a simulated schedule
+    let chain_times = |outcome: &ScheduleOutcome| -> Vec<f64> {
+        let mut start = 0;
+        chains
+            .iter()
+            .map(|chain| {
+                let end = start + chain.len();
+                let time = outcome.items[start..end]
+                    .iter()
+                    .map(|item| item.finish_time)
+                    .fold(0.0, f64::max);
+                start = end;
+                time
+            })
+            .collect()
+    };
+    // Time of every chain's first sale
+    let first_sales = |outcome: &ScheduleOutcome| -> f64 {
+        let mut start = 0;
+        chains
+            .iter()
+            .map(|chain| {
+                let items = &outcome.items[start..start + chain.len()];
+                start += chain.len();
+                chain
+                    .iter()
+                    .zip(items)
+                    .filter(|(job, _)| job.sold)
+                    .map(|(job, item)| item.start_time + job.stats_at(item.start_time).0)
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .filter(|time| time.is_finite())
+            .fold(0.0, f64::max)
+    };
+    // What every chain's sold batches fetch relative to the plan
+    let sale_scales = |outcome: &ScheduleOutcome| -> Vec<f64> {
+        let mut start = 0;
+        chains
+            .iter()
+            .map(|chain| {
+                let items = &outcome.items[start..start + chain.len()];
+                start += chain.len();
+                let (sales, batches) = chain
+                    .iter()
+                    .zip(items)
+                    .filter(|(job, _)| job.sold && timed)
+                    .fold((0.0, 0), |(sales, batches), (job, item)| (sales + item.sales, batches + job.batches));
+                if batches > 0 { sales / batches as f64 } else { 1.0 }
+            })
+            .collect()
+    };
+
+    let unlimited = simulate_batches(&jobs, initial_stock, facility_counts, &[], &StorageCapacity::default(), None);
+    let limited = simulate_batches(
+        &jobs,
+        initial_stock,
+        facility_counts,
+        &options.in_progress,
+        &options.storage,
+        options.starting_coins,
+    )
+    .ok()?;
+    let finish_times = chain_times(&limited);
+    let delays = match unlimited {
+        Ok(unlimited) => finish_times
+            .iter()
+            .zip(chain_times(&unlimited))
+            .map(|(capped, free)| (capped - free).max(0.0))
+            .collect(),
+        Err(_) => vec![0.0; chains.len()],
+    };
+    let startup = first_sales(&limited);
+    let sale_scales = sale_scales(&limited);
+    Some(ScheduleEffects {
+        peak_inventory: limited.peak_inventory,
+        delays,
+        balance: limited.balance,
+        stock_levels: limited.stock_levels,
+        unsimulated: Vec::new(),
+        timing: Some((finish_times, startup)),
+        sale_scales,
+    })
+}
+
+/// Simulates the parts of a plan not simulated while it was made, to fill
+/// in its peak inventory and the stock of every item over time.
+///
+/// The path finders only simulate plans as they make them when a storage
+/// limit, a starting balance or batches in progress need it, so without
+/// those this runs the simulation once, for the plan finally chosen. Parts
+/// of the plan that run one after the other are simulated separately, and
+/// the peak inventory is the highest of theirs.
+pub fn simulate_stock_levels(path: &mut ProductionPath, facility_counts: &FacilityCounts) {
+    for planned in std::mem::take(&mut path.unsimulated) {
+        let Ok(outcome) = simulate_batches(
+            &planned.jobs,
+            &planned.initial_stock,
+            facility_counts,
+            &[],
+            &StorageCapacity::default(),
+            None,
+        ) else {
+            continue;
+        };
+        path.peak_inventory = path.peak_inventory.max(outcome.peak_inventory);
+        add_inventory(&mut path.inventory_over_time, &outcome.stock_levels, planned.start);
+    }
+}
+
+/// Returns the items as produced at the configured facility levels (see
+/// [`ProductionItem::at_facility_level`]). When a facility type has mixed
+/// levels, each item is taken to be made on the lowest-level facility able
+/// to produce it.
+///
+/// The efficiency and order planners apply this themselves; it is exposed
+/// for callers that read item stats directly.
+pub fn items_at_levels(items: &[ProductionItem], facility_counts: &FacilityCounts) -> Vec<ProductionItem> {
+    items
+        .iter()
+        .map(|item| item.at_facility_level(facility_counts.producing_level(&item.facility, item.facility_level)))
+        .collect()
+}
+
+/// Batch time and yield of an item on the facilities of each level able to
+/// make it, highest first, as (level, time, yield), when its facility type
+/// has facilities at different levels; empty otherwise. `item` has the
+/// data's stats, before [`items_at_levels`].
+pub(crate) fn instance_level_stats(item: &ProductionItem, facility_counts: &FacilityCounts) -> Vec<(u32, f64, u32)> {
+    let Some(levels) = facility_counts.instance_levels.get(&item.facility) else {
+        return Vec::new();
+    };
+    let mut levels: Vec<u32> = levels.iter().copied().filter(|&l| l >= item.facility_level).collect();
+    levels.dedup();
+    levels
+        .into_iter()
+        .map(|level| {
+            let at_level = item.at_facility_level(level);
+            (level, at_level.production_time, at_level.yield_amount.max(1))
+        })
+        .collect()
+}
+
 /// Calculates efficiency metrics for all production items.
 ///
 /// This function evaluates each production item based on:
@@ -587,6 +1468,9 @@ fn calculate_item_requirements(
 /// - Total production time including raw material gathering
 /// - Parallel production capability based on facility counts
 ///
+/// Production times, yields and fertilizer needs follow the facility levels
+/// (see [`items_at_levels`]).
+///
 /// # Arguments
 ///
 /// * `items` - All available production items
@@ -625,6 +1509,7 @@ fn calculate_item_requirements(
 ///     dance_pad_polisher: (1, 1),
 ///     aniipod_maker: (1, 1),
 ///     nimbus_bed: (1, 1),      // 1 nimbus bed (for fertilizer)
+///     instance_levels: Default::default(),
 /// };
 /// let modules = ModuleLevels::default();
 ///
@@ -636,22 +1521,77 @@ pub fn calculate_efficiencies(
     facility_counts: &FacilityCounts,
     module_levels: &ModuleLevels,
 ) -> Vec<ProductionEfficiency> {
-    let item_map: HashMap<String, &ProductionItem> =
-        items.iter().map(|i| (i.name.clone(), i)).collect();
-
-    // Find fertilizer item for calculating fertilizer production time
-    let fertilizer_item = item_map.get("fertilizer");
-    let nimbus_bed_count = facility_counts.get_count("Nimbus Bed") as f64;
-
-    // Calculate time to produce one fertilizer (if Nimbus Bed is available)
-    // Fertilizer: 30 yield per 1800s, so each fertilizer takes 60s to produce
-    let fertilizer_time_per_unit = fertilizer_item
-        .map(|f| f.production_time / (f.yield_amount as f64 * nimbus_bed_count.max(1.0)))
-        .unwrap_or(0.0);
-
-    let mut efficiencies = Vec::new();
+    calculate_valued_efficiencies(
+        items,
+        &CurrencyValuation::new(target_currency),
+        facility_counts,
+        module_levels,
+    )
+}
 
-    for item in items {
+/// Calculates production efficiency for every item that sells for a currency
+/// the valuation counts, with profit in the valuation's base currency.
+///
+/// With exchange rates between currencies, coin and coupon items are ranked
+/// on one scale, and the path finders maximize the combined value. Each
+/// efficiency's `ledger` still records what is earned in which currency.
+///
+/// # Example
+///
+/// ```no_run
+/// use aniimax::optimizer::calculate_valued_efficiencies;
+/// use aniimax::models::{CurrencyValuation, FacilityCounts, ModuleLevels};
+/// use aniimax::data::load_all_data;
+/// use std::path::Path;
+///
+/// let items = load_all_data(Path::new("data")).unwrap();
+/// let counts = FacilityCounts {
+///     farmland: (4, 3),
+///     woodland: (2, 2),
+///     mineral_pile: (1, 1),
+///     carousel_mill: (2, 2),
+///     jukebox_dryer: (1, 1),
+///     crafting_table: (1, 1),
+///     dance_pad_polisher: (1, 1),
+///     aniipod_maker: (1, 1),
+///     nimbus_bed: (1, 1),
+///     instance_levels: Default::default(),
+/// };
+/// // 1 coupon is worth 20 coins to this player
+/// let valuation = CurrencyValuation::parse("coins", "coupons=20").unwrap();
+/// let efficiencies = calculate_valued_efficiencies(&items, &valuation, &counts, &ModuleLevels::default());
+/// ```
+pub fn calculate_valued_efficiencies(
+    items: &[ProductionItem],
+    valuation: &CurrencyValuation,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+) -> Vec<ProductionEfficiency> {
+    let data_items: HashMap<&str, &ProductionItem> = items.iter().map(|i| (i.name.as_str(), i)).collect();
+    let items = &items_at_levels(items, facility_counts);
+    let item_map: HashMap<String, &ProductionItem> =
+        items.iter().map(|i| (i.name.clone(), i)).collect();
+
+    // Find fertilizer item for calculating fertilizer production time
+    // (at the Nimbus Bed's level, so its level stats apply)
+    let fertilizer_item = item_map
+        .get("fertilizer")
+        .filter(|f| facility_counts.can_produce(&f.facility, f.facility_level));
+    let nimbus_bed_count = facility_counts.get_count("Nimbus Bed") as f64;
+
+    // Bed time to make one fertilizer on a single Nimbus Bed
+    // Fertilizer: 30 yield per 1800s, so each fertilizer takes 60s of bed time
+    let fertilizer_bed_time = fertilizer_item
+        .map(|f| f.production_time / f.yield_amount.max(1) as f64)
+        .unwrap_or(0.0);
+    // Time to produce one fertilizer with every Nimbus Bed on fertilizer
+    let fertilizer_time_per_unit = fertilizer_bed_time / nimbus_bed_count.max(1.0);
+    // Fertilizer comes in whole batches, so the first crops needing it wait for one
+    let fertilizer_wait = fertilizer_item.map_or(0.0, |f| f.production_time);
+
+    let mut efficiencies = Vec::new();
+
+    for item in items {
         // Filter by facility level (check if this facility can produce this item)
         if !facility_counts.can_produce(&item.facility, item.facility_level) {
             continue;
@@ -664,20 +1604,21 @@ pub fn calculate_efficiencies(
             }
         }
 
-        // Filter by target currency
-        if item.sell_currency != target_currency {
+        // Filter out items worth nothing to the valuation, sold or consumed
+        let Some((output_currency, value_per_unit)) = most_valuable_use(item, valuation) else {
             continue;
-        }
+        };
 
         // Filter out items that require fertilizer if no Nimbus Bed is available
         if item.requires_fertilizer && nimbus_bed_count == 0.0 {
             continue;
         }
 
+        let materials = build_material_tree(item, &item_map, facility_counts, module_levels);
         let (total_time, steady_state_time, total_energy, raw_cost, requires_raw, raw_facility, all_facilities, intermediate_steps, raw_material_details, fertilizer_per_batch) =
             if let Some(ref raw_mats) = item.raw_materials {
                 // This is a processed item - use recursive calculation to handle nested dependencies
-                let required_amounts = item.required_amount.as_ref().map(|v| v.as_slice()).unwrap_or(&[]);
+                let required_amounts = item.required_amount.as_deref().unwrap_or(&[]);
                 
                 // Track totals across all raw materials
                 let mut max_ingredient_time = 0.0;
@@ -706,7 +1647,7 @@ pub fn calculate_efficiencies(
                         &item_map,
                         facility_counts,
                         module_levels,
-                        fertilizer_time_per_unit,
+                        fertilizer_wait,
                         nimbus_bed_count,
                         &mut visited,
                     );
@@ -765,9 +1706,10 @@ pub fn calculate_efficiencies(
                     continue;
                 }
 
-                let processing_facility_count = facility_counts.get_count(&item.facility) as f64;
+                let processing_facility_count = facility_counts.count_for(item) as f64;
                 let processing_time_per_mill = item.production_time; // Time for 1 mill to process 1 batch
-                let processing_time = processing_time_per_mill / processing_facility_count;
+                // The first batch runs on a single facility, however many there are
+                let processing_time = processing_time_per_mill;
 
                 // For steady-state production, we need to find the bottleneck between:
                 // 1. Raw material production rate
@@ -812,7 +1754,7 @@ pub fn calculate_efficiencies(
                     };
                     
                     if let Some(raw) = raw_item {
-                        let raw_facility_count = facility_counts.get_count(&raw.facility) as f64;
+                        let raw_facility_count = facility_counts.count_for(raw) as f64;
                         // Units produced per second by all facilities for this raw material
                         let units_per_second = (raw.yield_amount as f64 * raw_facility_count) / raw.production_time;
                         // Convert to "processed batches worth" per second
@@ -839,6 +1781,14 @@ pub fn calculate_efficiencies(
                     }
                 }
                 
+                // Fertilizer rate: processed batches worth of fertilizer per second
+                // with every Nimbus Bed making fertilizer
+                if fertilizer_per_batch > 0 && fertilizer_time_per_unit > 0.0 {
+                    let fertilizer_batches_per_second =
+                        1.0 / (fertilizer_per_batch as f64 * fertilizer_time_per_unit);
+                    gathering_batches_per_second = gathering_batches_per_second.min(fertilizer_batches_per_second);
+                }
+
                 // Processing rate: processed batches per second
                 let processing_batches_per_second = processing_facility_count / processing_time_per_mill;
                 
@@ -849,10 +1799,13 @@ pub fn calculate_efficiencies(
                 } else { 
                     f64::INFINITY 
                 };
+                // Deeper ingredients (the soybeans in tofu for dried_bean_curd) and
+                // crops sharing a facility type can be slower still
+                let steady_state_time = steady_state_time.max(facility_load_time(item, &materials, facility_counts));
                 
                 // Total time for a single batch (used for display) is still sequential
                 let total_time = max_ingredient_time + processing_time;
-                let total_energy = match (total_ingredient_energy, item.energy) {
+                let total_energy = match (total_ingredient_energy, item.energy_cost) {
                     (Some(ie), Some(pe)) => Some(ie + pe),
                     (Some(ie), None) => Some(ie),
                     (None, Some(pe)) => Some(pe),
@@ -900,23 +1853,23 @@ pub fn calculate_efficiencies(
                 )
             } else {
                 // This is a raw material - direct production
-                let facility_count = facility_counts.get_count(&item.facility) as f64;
+                let facility_count = facility_counts.count_for(item) as f64;
                 let time_per_batch = item.production_time;
                 
-                // Add fertilizer time if required (1 fertilizer per batch)
+                // Fertilizer time if required (1 fertilizer per batch, made by every Nimbus Bed)
                 let fertilizer_time = if item.requires_fertilizer {
                     fertilizer_time_per_unit
                 } else {
                     0.0
                 };
                 
+                // For raw materials, steady-state time equals batch time / facility count,
+                // unless the Nimbus Beds can't keep up with the fertilizer
+                let steady_state_time = (time_per_batch / facility_count).max(fertilizer_time);
                 // For display purposes, time_per_unit is how long to produce one unit
-                let effective_time_per_yield =
-                    (time_per_batch + fertilizer_time) / (item.yield_amount as f64 * facility_count);
-                // For raw materials, steady-state time equals batch time / facility count
-                let steady_state_time = (time_per_batch + fertilizer_time) / facility_count;
+                let effective_time_per_yield = steady_state_time / item.yield_amount as f64;
                 // Energy per batch (not per unit) to match units_needed which counts batches
-                let energy_per_batch = item.energy;
+                let energy_per_batch = item.energy_cost;
                 let cost_per_batch = item.cost.unwrap_or(0.0);
                 
                 // Raw materials use just their own facility
@@ -929,7 +1882,12 @@ pub fn calculate_efficiencies(
                 (effective_time_per_yield, steady_state_time, energy_per_batch, cost_per_batch, None, None, raw_all_facilities, vec![], None, raw_fertilizer)
             };
 
-        let net_profit = item.sell_value * item.yield_amount as f64 - raw_cost;
+        // Seeds are always bought with coins, so they only count against
+        // profit as far as the valuation counts coins
+        let mut ledger = CurrencyLedger::default();
+        ledger.earn(output_currency, value_per_unit * item.yield_amount as f64);
+        ledger.spend("coins", raw_cost);
+        let net_profit = ledger.value(valuation);
         
         // For efficiency comparison, use steady-state time (bottleneck)
         let profit_per_second = if steady_state_time > 0.0 {
@@ -944,8 +1902,31 @@ pub fn calculate_efficiencies(
         let effective_profit_per_second = profit_per_second;
         
         // Startup time is the time to produce the first batch (before steady-state begins)
-        // This equals total_time for the first unit/batch
-        let startup_time = total_time;
+        // This equals total_time for the first batch of a processed item; a raw
+        // material takes one batch, after the first fertilizer batch if it needs one
+        let startup_time = if requires_raw.is_some() {
+            total_time
+        } else if item.requires_fertilizer {
+            fertilizer_wait + item.production_time
+        } else {
+            item.production_time
+        };
+
+        // Share of the Nimbus Beds that keeps up with this item's fertilizer demand
+        let nimbus_bed_load = if steady_state_time > 0.0 {
+            (fertilizer_per_batch as f64 * fertilizer_bed_time / steady_state_time).min(nimbus_bed_count)
+        } else {
+            0.0
+        };
+
+        // Facilities of one type at different levels make batches at different speeds
+        let level_stats = std::iter::once(item.name.as_str())
+            .chain(materials.iter().map(|m| m.variant.as_str()))
+            .chain(fertilizer_item.map(|f| f.name.as_str()))
+            .filter_map(|name| data_items.get(name))
+            .map(|data_item| (data_item.name.clone(), instance_level_stats(data_item, facility_counts)))
+            .filter(|(_, stats)| !stats.is_empty())
+            .collect();
 
         efficiencies.push(ProductionEfficiency {
             item: item.clone(),
@@ -955,19 +1936,72 @@ pub fn calculate_efficiencies(
             total_energy_per_unit: total_energy,
             requires_raw,
             raw_cost,
+            ledger,
+            valuation: valuation.clone(),
+            profit_per_batch: net_profit,
             raw_facility,
             all_facilities,
             intermediate_steps,
             startup_time,
+            steady_state_time,
             effective_profit_per_second,
             raw_material_details,
             fertilizer_per_batch,
+            nimbus_bed_load,
+            fertilizer_batch: fertilizer_item
+                .filter(|_| nimbus_bed_count > 0.0)
+                .map(|f| (f.production_time, f.yield_amount)),
+            materials,
+            level_stats,
         });
     }
 
     efficiencies
 }
 
+/// Long-run seconds per batch of a processed item set by the busiest
+/// facility type in its chain: every batch keeps each facility type busy
+/// for the batches of every ingredient it grows or processes (fractions of
+/// a batch included, as the units left over go into the next one), shared
+/// between the facilities able to do so.
+fn facility_load_time(item: &ProductionItem, materials: &[MaterialRequirement], facility_counts: &FacilityCounts) -> f64 {
+    // (facility, seconds of work per batch, facilities able to do it)
+    let mut loads: Vec<(&str, f64, u32)> =
+        vec![(item.facility.as_str(), item.production_time, facility_counts.count_for(item))];
+    let mut batches_per_batch: Vec<f64> = Vec::with_capacity(materials.len());
+    for material in materials {
+        let parent_batches = material.parent.map_or(1.0, |p| batches_per_batch[p]);
+        let batches = material.amount_per_parent as f64 * parent_batches / material.yield_amount as f64;
+        batches_per_batch.push(batches);
+
+        let eligible = facility_counts.count_at_level(&material.facility, material.facility_level);
+        match loads.iter_mut().find(|l| l.0 == material.facility) {
+            Some(load) => {
+                load.1 += batches * material.production_time;
+                load.2 = load.2.max(eligible);
+            }
+            None => loads.push((&material.facility, batches * material.production_time, eligible)),
+        }
+    }
+    loads
+        .iter()
+        .map(|&(_, work, facilities)| if facilities > 0 { work / facilities as f64 } else { f64::INFINITY })
+        .fold(0.0, f64::max)
+}
+
+/// Picks whether an item is worth more sold or consumed under a valuation.
+///
+/// Returns the currency (or consumable objective) the output is booked in
+/// with the amount per unit, or `None` if every use is worthless.
+fn most_valuable_use<'a>(item: &'a ProductionItem, valuation: &CurrencyValuation) -> Option<(&'a str, f64)> {
+    std::iter::once((item.sell_currency.as_str(), item.sell_value))
+        .chain(item.consumable_values.iter().map(|(o, &v)| (o.as_str(), v)))
+        .map(|(currency, amount)| (currency, amount, amount * valuation.rate(currency)))
+        .filter(|&(_, _, worth)| worth > 0.0)
+        .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(currency, amount, _)| (currency, amount))
+}
+
 /// Finds the optimal production path to achieve a target currency amount.
 ///
 /// This function selects the most efficient production option based on
@@ -988,7 +2022,9 @@ pub fn calculate_efficiencies(
 ///
 /// # Optimization Modes
 ///
-/// - **Time optimization** (default): Maximizes profit per second, considering energy costs
+/// - **Time optimization** (default): Finishes the target soonest, startup and
+///   whole batches included. With an energy cost, maximizes profit per second
+///   net of it instead.
 /// - **Energy optimization**: Maximizes profit per energy unit consumed
 ///
 /// # Example
@@ -1010,6 +2046,7 @@ pub fn calculate_efficiencies(
 ///     dance_pad_polisher: (1, 1),
 ///     aniipod_maker: (1, 1),
 ///     nimbus_bed: (1, 1),      // 1 nimbus bed (for fertilizer)
+///     instance_levels: Default::default(),
 /// };
 /// let modules = ModuleLevels::default();
 ///
@@ -1022,206 +2059,774 @@ pub fn find_best_production_path(
     optimize_energy: bool,
     energy_cost_per_min: f64,
     facility_counts: &FacilityCounts,
+) -> Option<ProductionPath> {
+    find_best_production_path_with_options(
+        efficiencies,
+        target_amount,
+        optimize_energy,
+        energy_cost_per_min,
+        facility_counts,
+        &PlanningOptions::default(),
+    )
+}
+
+/// Finds the optimal production path like [`find_best_production_path`], with
+/// additional planning inputs.
+///
+/// Ingredients, intermediates and fertilizer held in `options.inventory` are
+/// used before anything new is produced. This shrinks the input steps, the
+/// gathering part of the total time and, when the first batch's ingredients
+/// are already on hand, the startup time.
+///
+/// With a capacity in `options.storage`, the plan is simulated batch by batch
+/// so gathering interleaves with processing and stock never exceeds the
+/// capacity; any time lost waiting for space is added to the total. Items
+/// that can't be produced within the capacity are skipped. Every path
+/// reports its peak inventory.
+///
+/// # Example
+///
+/// ```no_run
+/// use aniimax::optimizer::{calculate_efficiencies, find_best_production_path_with_options};
+/// use aniimax::models::{FacilityCounts, Inventory, ModuleLevels, PlanningOptions};
+/// use aniimax::data::load_all_data;
+/// use std::path::Path;
+///
+/// let items = load_all_data(Path::new("data")).unwrap();
+/// let counts = FacilityCounts {
+///     farmland: (4, 3),
+///     woodland: (1, 2),
+///     mineral_pile: (1, 1),
+///     carousel_mill: (2, 2),
+///     jukebox_dryer: (1, 1),
+///     crafting_table: (1, 1),
+///     dance_pad_polisher: (1, 1),
+///     aniipod_maker: (1, 1),
+///     nimbus_bed: (1, 1),
+///     instance_levels: Default::default(),
+/// };
+/// let efficiencies = calculate_efficiencies(&items, "coins", &counts, &ModuleLevels::default());
+///
+/// let options = PlanningOptions {
+///     inventory: Inventory::parse("wheat=300,strawberry=12").unwrap(),
+///     ..Default::default()
+/// };
+/// let path = find_best_production_path_with_options(&efficiencies, 5000.0, false, 0.0, &counts, &options);
+/// ```
+pub fn find_best_production_path_with_options(
+    efficiencies: &[ProductionEfficiency],
+    target_amount: f64,
+    optimize_energy: bool,
+    energy_cost_per_min: f64,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
 ) -> Option<ProductionPath> {
     if efficiencies.is_empty() {
         return None;
     }
 
     // Sort by efficiency metric
+    let score = |e: &ProductionEfficiency| {
+        if optimize_energy {
+            e.profit_per_energy.unwrap_or(0.0)
+        } else {
+            // When optimizing for time, use effective profit per second (considers parallelization)
+            let energy_cost = e.total_energy_per_unit.unwrap_or(0.0) * energy_cost_per_min / 60.0;
+            e.effective_profit_per_second - (energy_cost / e.total_time_per_unit.max(1.0))
+        }
+    };
     let mut sorted = efficiencies.to_vec();
-    if optimize_energy {
-        sorted.sort_by(|a, b| {
-            let a_eff = a.profit_per_energy.unwrap_or(0.0);
-            let b_eff = b.profit_per_energy.unwrap_or(0.0);
-            b_eff
-                .partial_cmp(&a_eff)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-    } else {
-        // When optimizing for time, use effective profit per second (considers parallelization)
-        sorted.sort_by(|a, b| {
-            let a_energy_cost = a.total_energy_per_unit.unwrap_or(0.0) * energy_cost_per_min / 60.0;
-            let b_energy_cost = b.total_energy_per_unit.unwrap_or(0.0) * energy_cost_per_min / 60.0;
-            let a_net =
-                a.effective_profit_per_second - (a_energy_cost / a.total_time_per_unit.max(1.0));
-            let b_net =
-                b.effective_profit_per_second - (b_energy_cost / b.total_time_per_unit.max(1.0));
-            b_net
-                .partial_cmp(&a_net)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-    }
+    sorted.sort_by(|a, b| {
+        score(b)
+            .partial_cmp(&score(a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let scores: Vec<f64> = sorted.iter().map(score).collect();
 
-    // Get the best option
-    let best = &sorted[0];
+    // With a starting balance, waiting for coins slows items down by
+    // different amounts, so compare the finished plans instead of the rates
+    if options.starting_coins.is_some() {
+        return sorted
+            .iter()
+            .filter_map(|best| {
+                plan_single_path(best, target_amount, facility_counts, options)
+                    .or_else(|| plan_bootstrapped_path(best, &sorted, target_amount, facility_counts, options))
+            })
+            .min_by(|a, b| {
+                a.total_time
+                    .partial_cmp(&b.total_time)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+    }
 
-    // Calculate how many units we need to produce
-    let profit_per_unit = best.item.sell_value * best.item.yield_amount as f64 - best.raw_cost;
-    let units_needed = (target_amount / profit_per_unit).ceil() as u32;
+    // Take the best option whose plan fits in storage, moving on to the
+    // next one where sale limits make it pay less
+    let ranked = plan_diversified_path(&sorted, &scores, target_amount, facility_counts, options);
+    if optimize_energy || energy_cost_per_min > 0.0 {
+        return ranked;
+    }
 
-    let mut steps = Vec::new();
+    // The best steady-state rate doesn't always finish first: for a small
+    // target, an item with a quick first batch beats one with a slow start,
+    // so compare the finished plans
+    ranked
+        .into_iter()
+        .chain(
+            sorted
+                .iter()
+                .filter(|eff| facility_counts.count_for(&eff.item) > 0)
+                .filter_map(|eff| plan_single_path(eff, target_amount, facility_counts, options)),
+        )
+        .min_by(|a, b| {
+            a.total_time
+                .partial_cmp(&b.total_time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+}
 
-    // Get facility count for the main production
-    let main_facility_count = facility_counts.get_count(&best.item.facility);
-    let nimbus_bed_count = facility_counts.get_count("Nimbus Bed");
+/// Finds which item finishes a target soonest at every target size up to
+/// `max_target`, as (smallest target, item) for each range where the winner
+/// stays the same, smallest targets first.
+///
+/// Each item is timed by the same single-item plan
+/// [`find_best_production_path_with_options`] compares, with the same
+/// options, so a starting balance slows items down by the coins they wait
+/// for or the seed money they earn first. Changes are found by
+/// doubling the target from the smallest batch profit, then narrowed down
+/// to the nearest currency unit; a winner lasting less than a doubling may
+/// be missed.
+///
+/// # Example
+///
+/// ```no_run
+/// use aniimax::optimizer::{calculate_efficiencies, fastest_item_by_target};
+/// use aniimax::models::{FacilityCounts, ModuleLevels, PlanningOptions};
+/// use aniimax::data::load_all_data;
+/// use std::path::Path;
+///
+/// let items = load_all_data(Path::new("data")).unwrap();
+/// let counts = FacilityCounts {
+///     farmland: (4, 3),
+///     woodland: (1, 2),
+///     mineral_pile: (1, 1),
+///     carousel_mill: (2, 2),
+///     jukebox_dryer: (1, 1),
+///     crafting_table: (1, 1),
+///     dance_pad_polisher: (1, 1),
+///     aniipod_maker: (1, 1),
+///     nimbus_bed: (1, 1),
+///     instance_levels: Default::default(),
+/// };
+/// let efficiencies = calculate_efficiencies(&items, "coins", &counts, &ModuleLevels::default());
+///
+/// let options = PlanningOptions::default();
+///
+/// for (from, item) in fastest_item_by_target(&efficiencies, &counts, 100_000.0, &options) {
+///     println!("From {:.0} coins: {}", from, item);
+/// }
+/// ```
+pub fn fastest_item_by_target(
+    efficiencies: &[ProductionEfficiency],
+    facility_counts: &FacilityCounts,
+    max_target: f64,
+    options: &PlanningOptions,
+) -> Vec<(f64, String)> {
+    let candidates: Vec<&ProductionEfficiency> = efficiencies
+        .iter()
+        .filter(|eff| eff.profit_per_batch > 0.0 && eff.effective_profit_per_second > 0.0)
+        .filter(|eff| facility_counts.count_for(&eff.item) > 0)
+        .collect();
+    // Seed money for items the balance can't pay for is earned with the
+    // best-rated item it can, as the planner does
+    let mut sorted = efficiencies.to_vec();
+    sorted.sort_by(|a, b| {
+        b.effective_profit_per_second
+            .partial_cmp(&a.effective_profit_per_second)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let winner = |target: f64| -> Option<usize> {
+        candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, eff)| {
+                let path = plan_single_path(eff, target, facility_counts, options)
+                    .or_else(|| plan_bootstrapped_path(eff, &sorted, target, facility_counts, options))?;
+                Some((i, path.total_time))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| i)
+    };
 
-    // Add fertilizer production step if needed
-    if best.fertilizer_per_batch > 0 && nimbus_bed_count > 0 {
-        let total_fertilizer_needed = best.fertilizer_per_batch * units_needed;
-        
-        steps.push(ProductionStep {
-            item_name: "fertilizer".to_string(),
-            facility: format!("Nimbus Bed (x{})", nimbus_bed_count),
-            quantity: total_fertilizer_needed,
-            time: 0.0, // Time is included in total
-            energy: None,
-            profit_contribution: 0.0,
-            chain_id: None,
-            facility_allocation: None,
-        });
+    let mut target = candidates
+        .iter()
+        .map(|eff| eff.profit_per_batch)
+        .fold(f64::INFINITY, f64::min);
+    if !target.is_finite() {
+        return Vec::new();
+    }
+    let mut ranges: Vec<(f64, usize)> = Vec::new();
+    let mut previous: Option<(f64, usize)> = None;
+    while target <= max_target {
+        if let Some(current) = winner(target) {
+            match previous {
+                None => ranges.push((target, current)),
+                Some((below, last)) if last != current => {
+                    // Narrow down where the winner changes
+                    let (mut low, mut high) = (below, target);
+                    while high - low > 1.0 {
+                        let middle = (low + high) / 2.0;
+                        if winner(middle) == Some(last) {
+                            low = middle;
+                        } else {
+                            high = middle;
+                        }
+                    }
+                    ranges.push((high.ceil(), current));
+                }
+                _ => {}
+            }
+            previous = Some((target, current));
+        }
+        target *= 2.0;
     }
 
-    // Add raw material step if needed
-    if let Some(ref raw_name) = best.requires_raw {
-        // Sum all required amounts for display purposes
-        let raw_amount_needed = best.item.required_amount
-            .as_ref()
-            .map(|amounts| amounts.iter().sum::<u32>())
-            .unwrap_or(1) * units_needed;
-        let raw_facility = best.raw_facility.as_deref().unwrap_or("Unknown");
-        let raw_facility_count = facility_counts.get_count(raw_facility);
-        
-        // Calculate optimal facility allocation for multi-material production
-        let facility_allocation = if let Some(ref details) = best.raw_material_details {
-            // details is Vec<(name, amount_per_batch, time_per_batch)>
-            // We need to scale amounts by units_needed and calculate optimal facility split
-            let materials_for_allocation: Vec<(String, u32, f64)> = details.iter()
-                .map(|(name, amt_per_batch, time)| {
-                    (name.clone(), amt_per_batch * units_needed, *time)
-                })
-                .collect();
-            
-            let allocation = calculate_optimal_facility_allocation(&materials_for_allocation, raw_facility_count);
-            if allocation.len() > 1 {
-                Some(allocation)
-            } else {
-                None
+    ranges
+        .into_iter()
+        .map(|(from, i)| (from, candidates[i].item.name.clone()))
+        .collect()
+}
+
+/// Plans `target_amount` of profit from the items in `sorted` (best first,
+/// ranked by `scores`), moving on to the next item once sale limits drop an
+/// item's price far enough that the next one pays better. Items whose plan
+/// doesn't fit in storage are skipped.
+fn plan_diversified_path(
+    sorted: &[ProductionEfficiency],
+    scores: &[f64],
+    target_amount: f64,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
+) -> Option<ProductionPath> {
+    for (i, eff) in sorted.iter().enumerate() {
+        let Some(profit) = worthwhile_profit(eff, scores[i], scores.get(i + 1).copied(), target_amount) else {
+            // The price holds up for the whole target
+            match plan_single_path(eff, target_amount, facility_counts, options) {
+                Some(path) => return Some(path),
+                None => continue,
             }
-        } else {
-            None
         };
-        
-        steps.push(ProductionStep {
-            item_name: raw_name.clone(),
-            facility: format!("{} (x{})", raw_facility, raw_facility_count),
-            quantity: raw_amount_needed,
-            time: 0.0, // Time is included in total
-            energy: None,
-            profit_contribution: 0.0,
-            chain_id: None,
-            facility_allocation,
-        });
-        
-        // Add intermediate processing steps (e.g., nuts for caramel_nut_chips)
-        for (int_name, int_facility, int_amount_per_batch) in &best.intermediate_steps {
-            let int_qty = int_amount_per_batch * units_needed;
-            steps.push(ProductionStep {
-                item_name: int_name.clone(),
-                facility: format!("{} (x{})", int_facility, facility_counts.get_count(int_facility)),
-                quantity: int_qty,
-                time: 0.0,
-                energy: None,
-                profit_contribution: 0.0,
-                chain_id: None,
-                facility_allocation: None,
-            });
+        if profit <= 0.0 {
+            continue;
+        }
+        let Some(first) = plan_single_path(eff, profit, facility_counts, options) else {
+            continue;
+        };
+
+        // The rest starts with an empty warehouse, free facilities and whatever coins are left
+        let rest_options = PlanningOptions {
+            inventory: Inventory::default(),
+            in_progress: Vec::new(),
+            starting_coins: options
+                .starting_coins
+                .map(|coins| first.balance_over_time.last().map_or(coins, |&(_, c)| c)),
+            overrides: options.overrides.after(first.total_time),
+            ..options.clone()
+        };
+        let rest = plan_diversified_path(
+            &sorted[i + 1..],
+            &scores[i + 1..],
+            target_amount - first.total_profit,
+            facility_counts,
+            &rest_options,
+        );
+        match rest {
+            Some(rest) => return Some(run_in_sequence(first, rest)),
+            // Nothing else can make up the difference, so push on with this item
+            None => {
+                if let Some(path) = plan_single_path(eff, target_amount, facility_counts, options) {
+                    return Some(path);
+                }
+            }
         }
     }
+    None
+}
 
-    // Add production step
-    steps.push(ProductionStep {
-        item_name: best.item.name.clone(),
-        facility: format!("{} (x{})", best.item.facility, main_facility_count),
-        quantity: units_needed,
-        time: best.total_time_per_unit * units_needed as f64,
-        energy: best
-            .total_energy_per_unit
-            .map(|e| e * units_needed as f64),
-        profit_contribution: profit_per_unit * units_needed as f64,
-        chain_id: None,
-        facility_allocation: None,
-    });
+/// Profit worth making from `eff` before sale limits drop its marginal
+/// score below `next_score`, or `None` if that covers `target_amount` (or
+/// the item has no sale limits).
+fn worthwhile_profit(
+    eff: &ProductionEfficiency,
+    score: f64,
+    next_score: Option<f64>,
+    target_amount: f64,
+) -> Option<f64> {
+    eff.item.sale_curve.as_ref()?;
+    let days = sale_days(target_amount / eff.effective_profit_per_second);
+    let limit = batches_for_target(eff, target_amount, days).unwrap_or(u32::MAX);
 
-    // Calculate actual time with parallelization
-    // For processed items, use the steady-state calculation:
-    //   time = units_needed * profit_per_unit / effective_profit_per_second
-    // For raw materials, use direct batch calculation
-    let total_time = if best.requires_raw.is_some() {
-        // For processed items, effective_profit_per_second already accounts for bottleneck
-        // time = profit_needed / profit_per_second
-        units_needed as f64 * profit_per_unit / best.effective_profit_per_second
-    } else {
-        // For raw materials, units_needed is already the number of batches
-        best.item.production_time * (units_needed as f64 / main_facility_count as f64).ceil()
+    let mut batches = 0;
+    let mut current = 0.0;
+    while batches < limit {
+        let next = sold_value(eff, batches + 1, days);
+        let marginal = next - current;
+        if marginal <= 0.0 || next_score.is_some_and(|s| score * marginal / eff.profit_per_batch < s) {
+            return Some(current);
+        }
+        batches += 1;
+        current = next;
+    }
+    None
+}
+
+/// Days a plan of `seconds` spans, for daily sale limits.
+fn sale_days(seconds: f64) -> u32 {
+    ((seconds / 86_400.0).ceil() as u32).max(1)
+}
+
+/// Ledger of `batches` batches of `eff` sold over `days` days, with the
+/// average price per unit if the output is sold rather than consumed.
+fn sold_ledger(eff: &ProductionEfficiency, batches: u32, days: u32) -> (CurrencyLedger, Option<f64>) {
+    let mut ledger = eff.ledger.scaled(batches as f64);
+    let currency = &eff.item.sell_currency;
+    if eff.ledger.earned(currency) <= 0.0 {
+        return (ledger, None);
+    }
+    let units = batches * eff.item.yield_amount;
+    let factor = match &eff.item.sale_curve {
+        Some(curve) if units > 0 => curve.full_price_units(units, days) / units as f64,
+        _ => 1.0,
     };
+    if let Some(income) = ledger.income.get_mut(currency) {
+        *income *= factor;
+    }
+    (ledger, Some(eff.item.sell_value * factor))
+}
 
-    let total_energy = best
-        .total_energy_per_unit
-        .map(|e| e * units_needed as f64);
+/// Value of `batches` batches of `eff` sold over `days` days.
+fn sold_value(eff: &ProductionEfficiency, batches: u32, days: u32) -> f64 {
+    sold_ledger(eff, batches, days).0.value(&eff.valuation)
+}
 
-    Some(ProductionPath {
+/// Batches of `eff` needed to earn `target_amount` when sales are spread
+/// over `days` days, or `None` if its sale limits stop it getting there.
+fn batches_for_target(eff: &ProductionEfficiency, target_amount: f64, days: u32) -> Option<u32> {
+    let mut batches = (target_amount / eff.profit_per_batch).ceil() as u32;
+    if eff.item.sale_curve.is_none() {
+        return Some(batches);
+    }
+    let mut current = sold_value(eff, batches, days);
+    while current < target_amount {
+        let next = sold_value(eff, batches + 1, days);
+        if next <= current {
+            return None;
+        }
+        batches += 1;
+        current = next;
+    }
+    Some(batches)
+}
+
+/// Joins two plans run one after the other: the second starts once the
+/// first is finished.
+fn run_in_sequence(first: ProductionPath, second: ProductionPath) -> ProductionPath {
+    let offset = first.total_time;
+    let mut steps = first.steps;
+    steps.extend(second.steps);
+    let mut balance_over_time = first.balance_over_time;
+    balance_over_time.extend(second.balance_over_time.iter().skip(1).map(|&(t, c)| (t + offset, c)));
+    let mut ledger = first.ledger;
+    ledger.merge(&second.ledger);
+    let mut average_prices = first.average_prices;
+    average_prices.extend(second.average_prices);
+    let mut material_balance = first.material_balance;
+    for balance in second.material_balance {
+        add_balance(&mut material_balance, balance);
+    }
+    let mut inventory_over_time = first.inventory_over_time;
+    add_inventory(&mut inventory_over_time, &second.inventory_over_time, offset);
+    let mut unsimulated = first.unsimulated;
+    unsimulated.extend(second.unsimulated.into_iter().map(|planned| PlannedJobs {
+        start: planned.start + offset,
+        ..planned
+    }));
+
+    ProductionPath {
         steps,
-        total_time: total_time + best.startup_time, // Include startup delay
-        startup_time: best.startup_time,
-        total_energy,
-        total_profit: profit_per_unit * units_needed as f64,
-        currency: best.item.sell_currency.clone(),
-        items_produced: units_needed * best.item.yield_amount,
+        total_time: offset + second.total_time,
+        startup_time: offset + second.startup_time,
+        total_energy: match (first.total_energy, second.total_energy) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        },
+        total_profit: first.total_profit + second.total_profit,
+        currency: second.currency,
+        ledger,
+        items_produced: first.items_produced + second.items_produced,
         is_energy_self_sufficient: false,
         energy_items_produced: None,
         energy_item_name: None,
-    })
+        peak_inventory: first.peak_inventory.max(second.peak_inventory),
+        balance_over_time,
+        average_prices,
+        material_balance,
+        inventory_over_time,
+        unsimulated,
+    }
 }
 
-/// Finds the optimal production path using cross-facility parallelization.
-///
-/// This function finds all production chains that can run simultaneously without
-/// sharing any facilities. For example:
-/// - Farmland → Carousel Mill (super_wheatmeal)
-/// - Woodland → Crafting Table (wood_sculpture)  
-/// - Nimbus Bed (wool)
-/// All running in parallel since they use different facilities.
-///
-/// # Algorithm
-///
-/// Uses a greedy approach:
-/// 1. Sort all items by profit per second
-/// 2. Select the best item
-/// 3. Find the next best item that doesn't share any facilities with selected items
-/// 4. Repeat until no more non-conflicting items can be added
+/// Plans `target_amount` of profit from `best` for a player who can't yet
+/// pay for a round of its seeds: the missing coins are first earned with the
+/// first item in `sorted` that the starting balance does pay for, then the
+/// main plan runs with what that left.
 ///
-/// # Arguments
-///
-/// * `efficiencies` - Pre-calculated efficiency metrics for all items
-/// * `target_amount` - Target amount of currency to produce
-/// * `facility_counts` - Configuration for each facility (count and level)
-///
-/// # Returns
-///
-/// An `Option<ProductionPath>` containing the optimal parallel path, or `None` if no valid path exists.
-pub fn find_parallel_production_path(
-    efficiencies: &[ProductionEfficiency],
+/// Returns `None` without a starting balance, when a round is already
+/// affordable, or when no item can earn the difference.
+fn plan_bootstrapped_path(
+    best: &ProductionEfficiency,
+    sorted: &[ProductionEfficiency],
     target_amount: f64,
     facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
 ) -> Option<ProductionPath> {
-    if efficiencies.is_empty() {
+    let coins = options.starting_coins?;
+    let round_cost = best.raw_cost * facility_counts.count_for(&best.item).max(1) as f64;
+    let shortfall = round_cost - coins;
+    if shortfall <= 0.0 {
         return None;
     }
 
-    // Helper to get all facilities used by an item (including intermediate processing)
-    fn get_facilities_used(eff: &ProductionEfficiency) -> HashSet<String> {
-        // Use the pre-computed all_facilities set which tracks the entire chain
-        eff.all_facilities.clone()
+    // The stock is kept for the main plan
+    let bootstrap_options = PlanningOptions {
+        inventory: Inventory::default(),
+        ..options.clone()
+    };
+    let bootstrap = sorted
+        .iter()
+        .filter(|e| e.item.sell_currency == "coins" && e.raw_cost <= coins && e.raw_cost < best.raw_cost)
+        .find_map(|e| plan_single_path(e, shortfall, facility_counts, &bootstrap_options))?;
+    // Seed money only counts towards the target as far as coins are valued
+    let bootstrap_profit = bootstrap.ledger.value(&best.valuation);
+    if bootstrap_profit >= target_amount {
+        return None;
+    }
+
+    let main_options = PlanningOptions {
+        in_progress: Vec::new(),
+        starting_coins: Some(bootstrap.balance_over_time.last().map_or(coins, |&(_, c)| c)),
+        overrides: options.overrides.after(bootstrap.total_time),
+        ..options.clone()
+    };
+    let main = plan_single_path(best, target_amount - bootstrap_profit, facility_counts, &main_options)?;
+    let main_profit = main.total_profit;
+
+    let mut bootstrap = bootstrap;
+    if let Some(last) = bootstrap.steps.last_mut() {
+        last.item_name = format!("{} (for seed money)", last.item_name);
+    }
+    let mut path = run_in_sequence(bootstrap, main);
+    path.total_profit = bootstrap_profit + main_profit;
+    Some(path)
+}
+
+/// Batches of `eff` needed to earn `target_amount`, and the days their
+/// sales are spread over, or `None` if its sale limits stop it getting there.
+fn batches_for_target_over_days(eff: &ProductionEfficiency, target_amount: f64) -> Option<(u32, u32)> {
+    let mut days = 1;
+    let mut batches = batches_for_target(eff, target_amount, days)?;
+    // Daily sale limits depend on how many days the plan spans
+    if eff.item.sale_curve.is_some() {
+        for _ in 0..4 {
+            let needed = sale_days(batches as f64 * eff.steady_state_time);
+            if needed == days {
+                break;
+            }
+            days = needed;
+            batches = batches_for_target(eff, target_amount, days)?;
+        }
+    }
+    Some((batches, days))
+}
+
+/// Steady-state and startup time in seconds of `batches` of one item from
+/// idle facilities, before any wait for storage or coins.
+///
+/// Batches are whole: every stage of the chain takes as many rounds of its
+/// facilities as its batches need, so a last round that only fills some of
+/// them takes as long as a full one. `usage` gives the batches of every
+/// ingredient (see [`expand_materials`]), which already include the units
+/// left over when a yield doesn't divide what the recipe needs. Crops
+/// sharing a facility type get the split of facilities that finishes them
+/// soonest (see [`calculate_optimal_facility_allocation`]), and stages
+/// sharing a processing facility type run one after the other.
+///
+/// The stages overlap: processing starts once the first batch's
+/// ingredients are in, and gathering ends with the last batches still to
+/// be processed. Fertilizer is made in whole batches shared by the Nimbus
+/// Beds: crops needing it wait for the first batch unless the stock covers
+/// a round of them, and the last of them can only start once the beds have
+/// made all the fertilizer still needed. With the first batch's ingredients
+/// on hand only its processing delays the start.
+fn chain_time(
+    eff: &ProductionEfficiency,
+    batches: u32,
+    inputs: &PlannedInputs,
+    facility_counts: &FacilityCounts,
+) -> (f64, f64) {
+    let usage = &inputs.usage;
+    let rounds = |batches: u32, facilities: u32| batches.div_ceil(facilities.max(1)) as f64;
+    let final_facilities = facility_counts.count_for(&eff.item);
+    let final_time = eff.item.production_time * rounds(batches, final_facilities);
+
+    // The efficiency's startup assumes the first fertilizer batch is waited for
+    let first_round = if eff.materials.is_empty() {
+        batches.min(final_facilities) * eff.fertilizer_per_batch
+    } else {
+        eff.fertilizer_per_batch
+    };
+    let fertilizer_wait = inputs.fertilizer_wait(eff, first_round);
+    let assumed_wait = match eff.fertilizer_batch {
+        Some((time, _)) if eff.fertilizer_per_batch > 0 => time,
+        _ => 0.0,
+    };
+    let startup_time = if inputs.first_batch_on_hand {
+        eff.item.production_time
+    } else {
+        eff.startup_time - assumed_wait + fertilizer_wait
+    };
+    let fertilizer_time = match eff.fertilizer_batch {
+        Some((time, yield_amount)) if inputs.fertilizer_to_make > 0 => {
+            let fertilizer_batches = inputs.fertilizer_to_make.div_ceil(yield_amount.max(1));
+            time * rounds(fertilizer_batches, facility_counts.get_count("Nimbus Bed"))
+        }
+        _ => 0.0,
+    };
+    // The last fertilizer still goes through a batch of the chain
+    let fertilized_end = if fertilizer_time > 0.0 {
+        fertilizer_time + startup_time - fertilizer_wait
+    } else {
+        0.0
+    };
+    if eff.materials.is_empty() {
+        let total = (fertilizer_wait + final_time).max(fertilized_end);
+        return (total - startup_time, startup_time);
+    }
+
+    // Processing still ahead of each ingredient once it is done
+    let mut tail_times: Vec<f64> = Vec::with_capacity(eff.materials.len());
+    for material in &eff.materials {
+        let tail = match material.parent {
+            Some(p) => eff.materials[p].production_time + tail_times[p],
+            None => eff.item.production_time,
+        };
+        tail_times.push(tail);
+    }
+
+    /// Crops grown on one facility type
+    struct CropGroup<'a> {
+        facility: &'a str,
+        /// (variant, batches, time per batch)
+        crops: Vec<(String, u32, f64)>,
+        /// Facilities able to grow each crop
+        eligible: Vec<u32>,
+        /// Longest processing still ahead of any of them
+        tail: f64,
+        /// Time before the first of them can be planted
+        wait: f64,
+    }
+    let mut gathering: Vec<CropGroup> = Vec::new();
+    // (facility, time of its stages, processing time of one batch of each)
+    let mut processing: Vec<(&str, f64, f64)> = vec![(eff.item.facility.as_str(), final_time, eff.item.production_time)];
+    for ((material, u), tail) in eff.materials.iter().zip(usage).zip(&tail_times) {
+        if u.batches == 0 {
+            continue;
+        }
+        let eligible = facility_counts.count_at_level(&material.facility, material.facility_level);
+        if material.is_raw {
+            let crop = (material.variant.clone(), u.batches, material.production_time);
+            let wait = if material.requires_fertilizer { fertilizer_wait } else { 0.0 };
+            match gathering.iter_mut().find(|g| g.facility == material.facility) {
+                Some(group) => {
+                    group.crops.push(crop);
+                    group.eligible.push(eligible);
+                    group.tail = group.tail.max(*tail);
+                    group.wait = group.wait.max(wait);
+                }
+                None => gathering.push(CropGroup {
+                    facility: &material.facility,
+                    crops: vec![crop],
+                    eligible: vec![eligible],
+                    tail: *tail,
+                    wait,
+                }),
+            }
+        } else {
+            let time = material.production_time * rounds(u.batches, eligible);
+            match processing.iter_mut().find(|g| g.0 == material.facility) {
+                Some(group) => {
+                    group.1 += time;
+                    group.2 += material.production_time;
+                }
+                None => processing.push((&material.facility, time, material.production_time)),
+            }
+        }
+    }
+
+    let gathered = gathering
+        .iter()
+        .map(|CropGroup { facility, crops, eligible, tail, wait }| {
+            let total = facility_counts.get_count(facility);
+            let allocation = calculate_optimal_facility_allocation(crops, eligible, total);
+            let time = if allocation.iter().all(|&(_, batches, facilities)| batches == 0 || facilities > 0) {
+                allocation
+                    .iter()
+                    .zip(crops)
+                    .map(|(&(_, batches, facilities), (_, _, time))| time * rounds(batches, facilities))
+                    .fold(0.0, f64::max)
+            } else {
+                // Fewer facilities than crops: grow them in turn on every facility
+                crops
+                    .iter()
+                    .zip(eligible)
+                    .map(|((_, batches, time), &facilities)| time * rounds(*batches, facilities))
+                    .sum()
+            };
+            wait + time + tail
+        })
+        .fold(0.0, f64::max);
+    // A processing facility starts once the first batch reaches it
+    let processed = processing
+        .iter()
+        .map(|&(_, time, one_batch)| (startup_time - one_batch).max(0.0) + time)
+        .fold(0.0, f64::max);
+
+    let time = (gathered.max(processed).max(fertilized_end) - startup_time).max(0.0);
+    (time, startup_time)
+}
+
+/// Plans `target_amount` of profit from one item, or `None` if it can't be
+/// produced within the storage capacity.
+///
+/// With overrides starting or ending during the plan, its batches sell for
+/// what they fetch when they finish, and the plan is as large as it takes to
+/// reach the target at those prices.
+fn plan_single_path(
+    best: &ProductionEfficiency,
+    target_amount: f64,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
+) -> Option<ProductionPath> {
+    if !options.overrides.is_timed() {
+        return plan_single_batches(best, target_amount, facility_counts, options);
+    }
+    size_to_target(target_amount, |target| plan_single_batches(best, target, facility_counts, options))
+}
+
+/// Plans one item like [`plan_single_path`], with as many batches as
+/// `target_amount` takes at the prices at the start.
+fn plan_single_batches(
+    best: &ProductionEfficiency,
+    target_amount: f64,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
+) -> Option<ProductionPath> {
+    // Calculate how many units we need to produce
+    let (units_needed, sale_days_needed) = batches_for_target_over_days(best, target_amount)?;
+    let (mut ledger, mut average_price) = sold_ledger(best, units_needed, sale_days_needed);
+
+    // Get facility count for the main production
+    let main_facility_count = facility_counts.count_for(&best.item);
+
+    // Fertilizer, raw material and intermediate steps, netted against the inventory
+    let mut inventory = options.inventory.clone();
+    let inputs = plan_inputs(best, units_needed, facility_counts, &mut inventory, None)?;
+    let (total_time, startup_time) = chain_time(best, units_needed, &inputs, facility_counts);
+    let mut initial_stock = HashMap::new();
+    inputs.add_stock_used(&mut initial_stock);
+    let effects = schedule_effects(
+        &[chain_jobs(best, units_needed, &inputs)],
+        &initial_stock,
+        facility_counts,
+        options,
+    )?;
+    // Sold at the yields and prices in effect as the batches were made
+    let sale_scale = effects.sale_scales[0];
+    scale_sales(&mut ledger, &best.item.sell_currency, sale_scale);
+    average_price = average_price.map(|price| price * sale_scale);
+    let profit = ledger.value(&best.valuation);
+    let mut material_balance = inputs.balance(best, units_needed);
+    if options.sell_surplus {
+        sell_surplus(&mut material_balance, &[best], &mut ledger);
+    }
+    let mut steps = inputs.steps;
+
+    // Add production step
+    steps.push(ProductionStep {
+        item_name: best.item.name.clone(),
+        facility: format!("{} (x{})", best.item.facility, main_facility_count),
+        quantity: units_needed,
+        time: best.total_time_per_unit * units_needed as f64,
+        energy: best
+            .total_energy_per_unit
+            .map(|e| e * units_needed as f64),
+        energy_gained: None,
+        profit_contribution: profit,
+        chain_id: None,
+        facility_allocation: None,
+    });
+
+    // Waiting for storage space, for coins to pay for seeds and for busy
+    // facilities: the simulated schedule has the times, as for its cash flow
+    let (total_time, startup_time) = match &effects.timing {
+        Some((finish_times, first_sale)) => (finish_times[0], *first_sale),
+        None => (total_time + startup_time, startup_time),
+    };
+
+    let total_energy = best
+        .total_energy_per_unit
+        .map(|e| e * units_needed as f64);
+
+    Some(ProductionPath {
+        steps,
+        total_time,
+        startup_time,
+        total_energy,
+        total_profit: ledger.value(&best.valuation),
+        currency: best.valuation.base.clone(),
+        ledger,
+        items_produced: units_needed * best.item.yield_amount,
+        is_energy_self_sufficient: false,
+        energy_items_produced: None,
+        energy_item_name: None,
+        peak_inventory: effects.peak_inventory,
+        balance_over_time: effects.balance,
+        average_prices: average_price
+            .map(|price| vec![(best.item.name.clone(), price)])
+            .unwrap_or_default(),
+        material_balance,
+        inventory_over_time: effects.stock_levels,
+        unsimulated: effects.unsimulated,
+    })
+}
+
+/// Greedily selects production chains that can run simultaneously, each with
+/// the share of its facilities' time it gets (1.0 unless it shares them).
+///
+/// Items are taken in order of effective profit per second, skipping any item
+/// whose chain shares a facility with an already selected item.
+///
+/// Nimbus Beds are shared by time instead: chains needing fertilizer take the
+/// bed time their fertilizer needs, as long as it earns more than the best
+/// Nimbus Bed product (wool, petals) would in that time. The best Nimbus Bed
+/// product then runs on whatever bed time is left.
+pub(crate) fn select_parallel_chains<'a>(
+    efficiencies: &'a [ProductionEfficiency],
+    facility_counts: &FacilityCounts,
+) -> Vec<(&'a ProductionEfficiency, f64)> {
+    // Helper to get all facilities used by an item (including intermediate processing)
+    fn get_facilities_used(eff: &ProductionEfficiency) -> HashSet<String> {
+        // Use the pre-computed all_facilities set which tracks the entire chain
+        eff.all_facilities.clone()
     }
 
     // Sort efficiencies by profit per second (descending)
@@ -1232,18 +2837,31 @@ pub fn find_parallel_production_path(
             .unwrap_or(std::cmp::Ordering::Equal)
     });
 
+    let uses_beds = |eff: &ProductionEfficiency| eff.all_facilities.contains("Nimbus Bed");
+    let nimbus_bed_count = facility_counts.get_count("Nimbus Bed") as f64;
+    // Best Nimbus Bed product, which runs on the bed time fertilizer leaves free
+    let bed_product = sorted_effs
+        .iter()
+        .copied()
+        .find(|eff| uses_beds(eff) && eff.effective_profit_per_second > 0.0 && facility_counts.count_for(&eff.item) > 0);
+    let bed_rate = bed_product.map_or(0.0, |eff| eff.effective_profit_per_second / nimbus_bed_count.max(1.0));
+
     // Greedily select non-conflicting items
-    let mut selected_items: Vec<&ProductionEfficiency> = Vec::new();
+    let mut selected_items: Vec<(&ProductionEfficiency, f64)> = Vec::new();
     let mut occupied_facilities: HashSet<String> = HashSet::new();
+    let mut bed_load = 0.0;
 
-    for eff in &sorted_effs {
+    for eff in sorted_effs {
+        if uses_beds(eff) {
+            continue;
+        }
         // Skip items with no profit
         if eff.effective_profit_per_second <= 0.0 {
             continue;
         }
         
         // Skip items from facilities with 0 count
-        if facility_counts.get_count(&eff.item.facility) == 0 {
+        if facility_counts.count_for(&eff.item) == 0 {
             continue;
         }
         if let Some(ref raw_fac) = eff.raw_facility {
@@ -1257,13 +2875,130 @@ pub fn find_parallel_production_path(
         // Check if any facility is already occupied
         let has_conflict = facilities_needed.iter().any(|f| occupied_facilities.contains(f));
         
-        if !has_conflict {
+        // Fertilizer has to fit in the bed time left, and be worth more there than the bed product
+        let fits_beds = eff.nimbus_bed_load == 0.0
+            || (bed_load + eff.nimbus_bed_load <= nimbus_bed_count + 1e-9
+                && eff.effective_profit_per_second > eff.nimbus_bed_load * bed_rate);
+        
+        if !has_conflict && fits_beds {
             // Add this item to selected list
-            selected_items.push(eff);
+            selected_items.push((eff, 1.0));
             occupied_facilities.extend(facilities_needed);
+            bed_load += eff.nimbus_bed_load;
+        }
+    }
+
+    if let Some(eff) = bed_product {
+        let share = 1.0 - bed_load / nimbus_bed_count.max(1.0);
+        if share > 1e-9 && !eff.all_facilities.iter().any(|f| occupied_facilities.contains(f)) {
+            selected_items.push((eff, share));
         }
     }
 
+    selected_items
+}
+
+/// Returns the best steady-state profit per second achievable with the given
+/// efficiencies, taking the better of the single best item and the combined
+/// rate of all non-conflicting parallel chains.
+///
+/// Startup time is ignored, which makes this a good basis for comparing
+/// configurations over long horizons.
+pub fn best_profit_rate(
+    efficiencies: &[ProductionEfficiency],
+    facility_counts: &FacilityCounts,
+) -> f64 {
+    let single_rate = efficiencies
+        .iter()
+        .filter(|eff| facility_counts.count_for(&eff.item) > 0)
+        .map(|eff| eff.effective_profit_per_second)
+        .fold(0.0, f64::max);
+    let parallel_rate: f64 = select_parallel_chains(efficiencies, facility_counts)
+        .iter()
+        .map(|(eff, share)| eff.effective_profit_per_second * share)
+        .sum();
+    single_rate.max(parallel_rate)
+}
+
+/// Finds the optimal production path using cross-facility parallelization.
+///
+/// This function finds all production chains that can run simultaneously without
+/// sharing any facilities. For example:
+/// - Farmland → Carousel Mill (super_wheatmeal)
+/// - Woodland → Crafting Table (wood_sculpture)  
+/// - Nimbus Bed (wool)
+///
+/// All running in parallel since they use different facilities.
+///
+/// # Algorithm
+///
+/// Uses a greedy approach:
+/// 1. Sort all items by profit per second
+/// 2. Select the best item
+/// 3. Find the next best item that doesn't share any facilities with selected items
+/// 4. Repeat until no more non-conflicting items can be added
+///
+/// # Arguments
+///
+/// * `efficiencies` - Pre-calculated efficiency metrics for all items
+/// * `target_amount` - Target amount of currency to produce
+/// * `facility_counts` - Configuration for each facility (count and level)
+///
+/// # Returns
+///
+/// An `Option<ProductionPath>` containing the optimal parallel path, or `None` if no valid path exists.
+pub fn find_parallel_production_path(
+    efficiencies: &[ProductionEfficiency],
+    target_amount: f64,
+    facility_counts: &FacilityCounts,
+) -> Option<ProductionPath> {
+    find_parallel_production_path_with_options(
+        efficiencies,
+        target_amount,
+        facility_counts,
+        &PlanningOptions::default(),
+    )
+}
+
+/// Finds a parallel production path like [`find_parallel_production_path`],
+/// with additional planning inputs.
+///
+/// The inventory in `options` is shared by all chains: stock taken by an
+/// earlier (more profitable) chain is no longer available to later ones.
+/// So is the storage capacity; a chain slowed down by it earns less per
+/// second and gets fewer of the extra batches that top up the target.
+///
+/// With overrides starting or ending during the plan, batches sell for what
+/// they fetch when they finish, and the chains are as large as it takes to
+/// reach the target at those prices.
+pub fn find_parallel_production_path_with_options(
+    efficiencies: &[ProductionEfficiency],
+    target_amount: f64,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
+) -> Option<ProductionPath> {
+    if !options.overrides.is_timed() {
+        return plan_parallel_batches(efficiencies, target_amount, facility_counts, options);
+    }
+    size_to_target(target_amount, |target| {
+        plan_parallel_batches(efficiencies, target, facility_counts, options)
+    })
+}
+
+/// Plans parallel chains like [`find_parallel_production_path_with_options`],
+/// with as many batches as `target_amount` takes at the prices at the start.
+fn plan_parallel_batches(
+    efficiencies: &[ProductionEfficiency],
+    target_amount: f64,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
+) -> Option<ProductionPath> {
+    if efficiencies.is_empty() {
+        return None;
+    }
+
+    let selected_items = select_parallel_chains(efficiencies, facility_counts);
+
     // Need at least 2 items for parallel mode to be useful
     if selected_items.len() <= 1 {
         return None;
@@ -1272,31 +3007,43 @@ pub fn find_parallel_production_path(
     // Calculate combined profit rate
     let combined_profit_per_second: f64 = selected_items
         .iter()
-        .map(|eff| eff.effective_profit_per_second)
+        .map(|(eff, share)| eff.effective_profit_per_second * share)
         .sum();
 
-    // Calculate startup time: max first-batch time across all parallel chains
-    // This is the time before steady-state production begins
-    let startup_time: f64 = selected_items
-        .iter()
-        .map(|eff| eff.startup_time)
-        .fold(0.0, f64::max);
-
     // Calculate time needed (steady-state only, startup added separately)
     let theoretical_time = target_amount / combined_profit_per_second;
+    let days = sale_days(theoretical_time);
 
     // Build production steps
     let mut steps = Vec::new();
     let mut total_profit = 0.0;
     let mut total_energy: Option<f64> = None;
     let mut total_items = 0u32;
-    let mut chain_id: u32 = 0;
+    let mut inventory = options.inventory.clone();
+    // Batch-level jobs and ingredient usage of every chain, for the storage simulation
+    let mut chain_ids: Vec<u32> = Vec::new();
+    let mut chains: Vec<Vec<BatchJob>> = Vec::new();
+    let mut initial_stock: HashMap<String, u32> = HashMap::new();
+    // Index of each chain's final step with its item
+    let mut sold_steps: Vec<(usize, &ProductionEfficiency)> = Vec::new();
 
-    for eff in &selected_items {
-        let current_chain_id = chain_id;
-        chain_id += 1;
+    // Startup time: max first-batch time across all parallel chains
+    // This is the time before steady-state production begins; each chain's
+    // sold step includes its own
+    let mut startup_time: f64 = 0.0;
+    // Nimbus Beds busy making fertilizer; fertilizer in stock frees them up
+    let nimbus_bed_count = facility_counts.get_count("Nimbus Bed") as f64;
+    let mut bed_load = 0.0;
+
+    for (current_chain_id, &(eff, share)) in (0u32..).zip(selected_items.iter()) {
         
-        let profit_per_batch = eff.item.sell_value * eff.item.yield_amount as f64 - eff.raw_cost;
+        let profit_per_batch = eff.profit_per_batch;
+        // A Nimbus Bed product (selected last) gets the bed time fertilizer leaves free
+        let share = if share < 1.0 {
+            (1.0 - bed_load / nimbus_bed_count.max(1.0)).max(share)
+        } else {
+            share
+        };
         
         // Calculate batches based on steady-state time
         let batches = if eff.requires_raw.is_some() {
@@ -1304,7 +3051,7 @@ pub fn find_parallel_production_path(
             (theoretical_time * eff.effective_profit_per_second / profit_per_batch).ceil() as u32
         } else {
             // Raw item
-            let facility_count = facility_counts.get_count(&eff.item.facility) as f64;
+            let facility_count = facility_counts.count_for(&eff.item) as f64 * share;
             let time_per_effective_batch = eff.item.production_time / facility_count;
             (theoretical_time / time_per_effective_batch).ceil() as u32
         };
@@ -1313,17 +3060,24 @@ pub fn find_parallel_production_path(
             continue;
         }
 
-        let step_profit = profit_per_batch * batches as f64;
+        let step_profit = sold_value(eff, batches, days);
         total_profit += step_profit;
 
-        // Calculate actual time for this step
-        let step_time = if eff.requires_raw.is_some() {
-            // For processed items, time = batches * steady_state_time_per_batch
-            batches as f64 * (profit_per_batch / eff.effective_profit_per_second)
-        } else {
-            let facility_count = facility_counts.get_count(&eff.item.facility) as f64;
-            eff.item.production_time * (batches as f64 / facility_count).ceil()
-        };
+        // Fertilizer, raw material and intermediate steps, netted against the inventory
+        let mut inputs = plan_inputs(eff, batches, facility_counts, &mut inventory, Some(current_chain_id))?;
+
+        bed_load += eff.nimbus_bed_load * inputs.fertilizer_fraction();
+
+        // Calculate actual time for this step, in whole batches; a chain on
+        // part of the facilities' time takes that much longer
+        let (step_time, chain_startup) = chain_time(eff, batches, &inputs, facility_counts);
+        let step_time = step_time / share;
+        inputs.set_step_time(step_time);
+        chain_ids.push(current_chain_id);
+        chains.push(chain_jobs(eff, batches, &inputs));
+        inputs.add_stock_used(&mut initial_stock);
+
+        startup_time = startup_time.max(chain_startup);
 
         if let Some(energy) = eff.total_energy_per_unit {
             let step_energy = energy * batches as f64;
@@ -1331,120 +3085,127 @@ pub fn find_parallel_production_path(
         }
 
         total_items += batches * eff.item.yield_amount;
-        
-        let nimbus_bed_count = facility_counts.get_count("Nimbus Bed");
-        
-        // Add fertilizer production step if needed
-        if eff.fertilizer_per_batch > 0 && nimbus_bed_count > 0 {
-            let total_fertilizer_needed = eff.fertilizer_per_batch * batches;
-            
-            steps.push(ProductionStep {
-                item_name: "fertilizer".to_string(),
-                facility: format!("Nimbus Bed (x{})", nimbus_bed_count),
-                quantity: total_fertilizer_needed,
-                time: 0.0, // Time is included in total
-                energy: None,
-                profit_contribution: 0.0,
-                chain_id: Some(current_chain_id),
-                facility_allocation: None,
-            });
-        }
 
         // For processed items, show the full production chain
-        if let Some(ref requires) = eff.requires_raw {
-            // Step 1: Raw materials
-            let raw_facility = eff.raw_facility.as_ref().unwrap_or(&eff.item.facility);
-            let raw_qty = if let Some(ref amounts) = eff.item.required_amount {
-                amounts.iter().sum::<u32>() * batches
-            } else {
-                batches
-            };
-            
-            // Calculate optimal facility allocation for multi-material production
-            let raw_facility_count = facility_counts.get_count(raw_facility);
-            let facility_allocation = if let Some(ref details) = eff.raw_material_details {
-                let materials_for_allocation: Vec<(String, u32, f64)> = details.iter()
-                    .map(|(name, amt_per_batch, time)| {
-                        (name.clone(), amt_per_batch * batches, *time)
-                    })
-                    .collect();
-                
-                let allocation = calculate_optimal_facility_allocation(&materials_for_allocation, raw_facility_count);
-                if allocation.len() > 1 {
-                    Some(allocation)
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
-            
-            steps.push(ProductionStep {
-                item_name: requires.clone(),
-                facility: format!("{} (x{})", raw_facility, facility_counts.get_count(raw_facility)),
-                quantity: raw_qty,
-                time: step_time,
-                energy: None,
-                profit_contribution: 0.0,
-                chain_id: Some(current_chain_id),
-                facility_allocation,
-            });
-            
-            // Step 2: Intermediate processing steps (e.g., nuts for caramel_nut_chips)
-            for (int_name, int_facility, int_amount_per_batch) in &eff.intermediate_steps {
-                let int_qty = int_amount_per_batch * batches;
-                steps.push(ProductionStep {
-                    item_name: int_name.clone(),
-                    facility: format!("{} (x{})", int_facility, facility_counts.get_count(int_facility)),
-                    quantity: int_qty,
-                    time: step_time,
-                    energy: None,
-                    profit_contribution: 0.0,
-                    chain_id: Some(current_chain_id),
-                    facility_allocation: None,
-                });
-            }
-        }
+        steps.extend(inputs.steps);
 
-        // Step 3 (or 1 for raw items): Final product
+        // Final product
+        let facility = if share < 1.0 {
+            format!(
+                "{} (x{}, {:.0}% of the time)",
+                eff.item.facility,
+                facility_counts.count_for(&eff.item),
+                share * 100.0
+            )
+        } else {
+            format!("{} (x{})", eff.item.facility, facility_counts.count_for(&eff.item))
+        };
+        sold_steps.push((steps.len(), eff));
         steps.push(ProductionStep {
             item_name: eff.item.name.clone(),
-            facility: format!("{} (x{})", eff.item.facility, facility_counts.get_count(&eff.item.facility)),
+            facility,
             quantity: batches,
-            time: step_time,
+            time: chain_startup + step_time,
             energy: eff.total_energy_per_unit.map(|e| e * batches as f64),
+            energy_gained: None,
             profit_contribution: step_profit,
             chain_id: Some(current_chain_id),
             facility_allocation: None,
         });
     }
 
+    // Chains slowed down by the storage limit or the coin balance earn less
+    // per second, so the top-up below favours the others
+    let effects = schedule_effects(&chains, &initial_stock, facility_counts, options)?;
+    for (chain_id, delay) in chain_ids.iter().zip(&effects.delays) {
+        for step in steps.iter_mut().filter(|s| s.chain_id == Some(*chain_id)) {
+            step.time += delay;
+        }
+    }
+
     // Make sure we meet target by iteratively increasing if needed
     while total_profit < target_amount {
-        // Find the step with highest profit/sec and add one batch
-        let best_step_idx = steps
+        // Find the step with highest profit/sec whose next batch still sells
+        // for something, and add one batch
+        let best_step = sold_steps
             .iter()
-            .enumerate()
-            .filter(|(_, s)| s.profit_contribution > 0.0)
-            .max_by(|(_, a), (_, b)| {
-                let a_rate = a.profit_contribution / a.time;
-                let b_rate = b.profit_contribution / b.time;
+            .map(|&(idx, eff)| (idx, sold_value(eff, steps[idx].quantity + 1, days) - steps[idx].profit_contribution))
+            .filter(|&(idx, marginal)| steps[idx].profit_contribution > 0.0 && marginal > 0.0)
+            .max_by(|&(a, _), &(b, _)| {
+                let a_rate = steps[a].profit_contribution / steps[a].time;
+                let b_rate = steps[b].profit_contribution / steps[b].time;
                 a_rate.partial_cmp(&b_rate).unwrap_or(std::cmp::Ordering::Equal)
-            })
-            .map(|(i, _)| i);
+            });
 
-        if let Some(idx) = best_step_idx {
+        if let Some((idx, marginal)) = best_step {
             let step = &mut steps[idx];
-            let profit_per_batch = step.profit_contribution / step.quantity as f64;
             step.quantity += 1;
-            step.profit_contribution += profit_per_batch;
-            total_profit += profit_per_batch;
+            step.profit_contribution += marginal;
+            total_profit += marginal;
         } else {
             break;
         }
     }
 
-    // Recalculate actual total time (longest step since they run in parallel)
+    // Balance and schedule of the batches finally made, the top-up included
+    let mut material_balance = Vec::new();
+    let mut stock = options.inventory.clone();
+    chains.clear();
+    initial_stock.clear();
+    for &(idx, eff) in &sold_steps {
+        let inputs = plan_inputs(eff, steps[idx].quantity, facility_counts, &mut stock, None)?;
+        chains.push(chain_jobs(eff, steps[idx].quantity, &inputs));
+        inputs.add_stock_used(&mut initial_stock);
+        for balance in inputs.balance(eff, steps[idx].quantity) {
+            add_balance(&mut material_balance, balance);
+        }
+    }
+    let effects = schedule_effects(&chains, &initial_stock, facility_counts, options)?;
+
+    // Each chain sold at the yields and prices in effect as its batches were made
+    let mut ledger = CurrencyLedger::default();
+    let mut average_prices = Vec::new();
+    let timed = options.overrides.is_timed();
+    if timed {
+        total_profit = 0.0;
+    }
+    for (&(idx, eff), &sale_scale) in sold_steps.iter().zip(&effects.sale_scales) {
+        let (mut chain_ledger, average_price) = sold_ledger(eff, steps[idx].quantity, days);
+        scale_sales(&mut chain_ledger, &eff.item.sell_currency, sale_scale);
+        if timed {
+            steps[idx].profit_contribution = chain_ledger.value(&eff.valuation);
+            total_profit += steps[idx].profit_contribution;
+        }
+        ledger.merge(&chain_ledger);
+        if let Some(price) = average_price {
+            average_prices.push((eff.item.name.clone(), price * sale_scale));
+        }
+    }
+    if let Some((finish_times, first_sale)) = &effects.timing {
+        // Chains take as long as in the simulated schedule, as for its cash flow
+        startup_time = *first_sale;
+        for (chain_id, finish) in chain_ids.iter().zip(finish_times) {
+            let longest = steps
+                .iter()
+                .filter(|s| s.chain_id == Some(*chain_id))
+                .map(|s| s.time)
+                .fold(0.0, f64::max);
+            let shift = finish - longest;
+            for step in steps.iter_mut().filter(|s| s.chain_id == Some(*chain_id)) {
+                step.time = (step.time + shift).max(0.0);
+            }
+        }
+    }
+    if options.sell_surplus {
+        let chains: Vec<&ProductionEfficiency> = sold_steps.iter().map(|&(_, eff)| eff).collect();
+        let valuation = &selected_items[0].0.valuation;
+        let before = ledger.value(valuation);
+        sell_surplus(&mut material_balance, &chains, &mut ledger);
+        total_profit += ledger.value(valuation) - before;
+    }
+
+    // Chains run in parallel, each from its own startup: the plan takes as
+    // long as the chain whose startup and production together take longest
     let actual_total_time = steps.iter().map(|s| s.time).fold(0.0, f64::max);
 
     // Only return if we have multiple independent productions
@@ -1455,66 +3216,532 @@ pub fn find_parallel_production_path(
 
     Some(ProductionPath {
         steps,
-        total_time: actual_total_time + startup_time, // Include startup delay
+        total_time: actual_total_time,
         startup_time,
         total_energy,
         total_profit,
-        currency: selected_items[0].item.sell_currency.clone(),
+        currency: selected_items[0].0.valuation.base.clone(),
+        ledger,
         items_produced: total_items,
         is_energy_self_sufficient: false,
         energy_items_produced: None,
         energy_item_name: None,
+        peak_inventory: effects.peak_inventory,
+        balance_over_time: effects.balance,
+        average_prices,
+        material_balance,
+        inventory_over_time: effects.stock_levels,
+        unsimulated: effects.unsimulated,
     })
 }
 
-/// Calculates efficiency metrics for items that can be consumed for energy.
-///
-/// Only items with a non-None energy field can be consumed for energy.
-/// This is used for energy self-sufficient mode.
-pub fn calculate_energy_efficiencies(
-    items: &[ProductionItem],
-    facility_counts: &FacilityCounts,
-    module_levels: &ModuleLevels,
-) -> Vec<EnergyItemEfficiency> {
-    let mut efficiencies = Vec::new();
+/// Cost of one batch of `eff`, its whole chain included, under `objective`.
+fn cost_per_batch(eff: &ProductionEfficiency, objective: CostObjective) -> f64 {
+    match objective {
+        CostObjective::Energy => eff.total_energy_per_unit.unwrap_or(0.0),
+        CostObjective::SeedCoins => eff.raw_cost,
+        CostObjective::Interactions => {
+            // Batches of each ingredient per batch of the item, parents first
+            let mut batches: Vec<f64> = Vec::with_capacity(eff.materials.len());
+            for material in &eff.materials {
+                let parent_batches = material.parent.map_or(1.0, |p| batches[p]);
+                batches.push(parent_batches * material.amount_per_parent as f64 / material.yield_amount.max(1) as f64);
+            }
+            1.0 + batches.iter().sum::<f64>() + eff.fertilizer_per_batch as f64
+        }
+    }
+}
 
-    for item in items {
-        // Only raw materials (no raw_materials field) can be efficiently produced for energy
-        // Processed items require raw materials which have opportunity cost
-        if item.raw_materials.is_some() {
+/// Cost of a path under `objective`, from the batches of every item it sells.
+fn path_cost(path: &ProductionPath, efficiencies: &[ProductionEfficiency], objective: CostObjective) -> f64 {
+    path.steps
+        .iter()
+        .filter(|step| step.profit_contribution > 0.0)
+        .filter_map(|step| {
+            let name = step.item_name.split(" (").next().unwrap_or(&step.item_name);
+            let eff = efficiencies.iter().find(|e| e.item.name == name)?;
+            Some(cost_per_batch(eff, objective) * step.quantity as f64)
+        })
+        .sum()
+}
+
+/// Joins plans run side by side on separate facilities, each as its own chain.
+fn run_in_parallel(paths: Vec<ProductionPath>) -> Option<ProductionPath> {
+    let currency = paths.first()?.currency.clone();
+    let mut steps = Vec::new();
+    let mut ledger = CurrencyLedger::default();
+    let mut average_prices = Vec::new();
+    let mut total_time: f64 = 0.0;
+    let mut startup_time: f64 = 0.0;
+    let mut total_energy: Option<f64> = None;
+    let mut total_profit = 0.0;
+    let mut items_produced = 0;
+    let mut peak_inventory = 0;
+    let mut material_balance = Vec::new();
+    let mut inventory_over_time = Vec::new();
+    let mut unsimulated: Vec<PlannedJobs> = Vec::new();
+
+    for (chain_id, path) in (0u32..).zip(paths) {
+        // The item sold is what takes the chain's whole time
+        steps.extend(path.steps.into_iter().map(|step| ProductionStep {
+            chain_id: Some(chain_id),
+            time: if step.profit_contribution > 0.0 { path.total_time } else { step.time },
+            ..step
+        }));
+        ledger.merge(&path.ledger);
+        average_prices.extend(path.average_prices);
+        total_time = total_time.max(path.total_time);
+        startup_time = startup_time.max(path.startup_time);
+        if let Some(energy) = path.total_energy {
+            total_energy = Some(total_energy.unwrap_or(0.0) + energy);
+        }
+        total_profit += path.total_profit;
+        items_produced += path.items_produced;
+        // Every chain holds its own stock at the same time
+        peak_inventory += path.peak_inventory;
+        for balance in path.material_balance {
+            add_balance(&mut material_balance, balance);
+        }
+        add_inventory(&mut inventory_over_time, &path.inventory_over_time, 0.0);
+        // Jobs starting together are simulated together
+        for planned in path.unsimulated {
+            match unsimulated.iter_mut().find(|p| p.start == planned.start) {
+                Some(existing) => {
+                    existing.jobs.extend(planned.jobs);
+                    for (name, units) in planned.initial_stock {
+                        *existing.initial_stock.entry(name).or_insert(0) += units;
+                    }
+                }
+                None => unsimulated.push(planned),
+            }
+        }
+    }
+
+    Some(ProductionPath {
+        steps,
+        total_time,
+        startup_time,
+        total_energy,
+        total_profit,
+        currency,
+        ledger,
+        items_produced,
+        is_energy_self_sufficient: false,
+        energy_items_produced: None,
+        energy_item_name: None,
+        peak_inventory,
+        balance_over_time: Vec::new(),
+        average_prices,
+        material_balance,
+        inventory_over_time,
+        unsimulated,
+    })
+}
+
+/// Plans `target_amount` from the chains that cost least per unit of
+/// profit, run side by side: each chain makes as much as it can before the
+/// deadline, and the next cheapest chain on free facilities makes up the rest.
+///
+/// Chains needing fertilizer are left out, as they would share the Nimbus
+/// Beds. If the plan runs late (batches are whole), every chain is given a
+/// little less time and the plan is tried again.
+fn plan_cheapest_mix(
+    efficiencies: &[ProductionEfficiency],
+    target_amount: f64,
+    deadline: f64,
+    objective: CostObjective,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
+) -> Option<ProductionPath> {
+    let mut sorted: Vec<&ProductionEfficiency> = efficiencies
+        .iter()
+        .filter(|e| e.effective_profit_per_second > 0.0 && e.profit_per_batch > 0.0)
+        .filter(|e| e.nimbus_bed_load == 0.0 && facility_counts.count_for(&e.item) > 0)
+        .collect();
+    let cost_per_profit = |e: &ProductionEfficiency| cost_per_batch(e, objective) / e.profit_per_batch;
+    sorted.sort_by(|a, b| {
+        cost_per_profit(a)
+            .partial_cmp(&cost_per_profit(b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    for time_share in [1.0, 0.9, 0.8, 0.7] {
+        let mut occupied: HashSet<&String> = HashSet::new();
+        let mut remaining = target_amount;
+        let mut paths = Vec::new();
+        for eff in &sorted {
+            if remaining <= 0.0 {
+                break;
+            }
+            if eff.all_facilities.iter().any(|f| occupied.contains(f)) {
+                continue;
+            }
+            let capacity = eff.effective_profit_per_second * (deadline * time_share - eff.startup_time);
+            if capacity <= 0.0 {
+                continue;
+            }
+            let Some(path) = plan_single_path(eff, remaining.min(capacity), facility_counts, options) else {
+                continue;
+            };
+            remaining -= path.total_profit;
+            occupied.extend(eff.all_facilities.iter());
+            paths.push(path);
+        }
+        // Giving the chains less time won't make them earn more
+        if remaining > 0.0 {
+            return None;
+        }
+        let path = run_in_parallel(paths)?;
+        if path.total_time <= deadline {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Finds the cheapest plan that reaches `target_amount` within `deadline`
+/// seconds, where cost is the energy, seed coins or interactions given by
+/// `objective` (see [`CostObjective`]).
+///
+/// The candidates are every single-item plan, the fastest single-item and
+/// parallel plans, and a mix of the chains that cost least per unit of
+/// profit, each running as long as the deadline allows. Costs come from
+/// `total_energy_per_unit` and `raw_cost` of each item sold, or for
+/// interactions, from the batches its chain needs. Ties go to the faster plan.
+///
+/// If no candidate finishes in time, the returned plan is the fastest one,
+/// with `meets_deadline` false. With a starting balance in `options`, chains
+/// aren't mixed, since each would count on the same coins.
+///
+/// # Example
+///
+/// ```no_run
+/// use aniimax::optimizer::{calculate_efficiencies, find_deadline_path};
+/// use aniimax::models::{CostObjective, FacilityCounts, ModuleLevels, PlanningOptions};
+/// use aniimax::data::load_all_data;
+/// use std::path::Path;
+///
+/// let items = load_all_data(Path::new("data")).unwrap();
+/// let counts = FacilityCounts {
+///     farmland: (4, 3),
+///     woodland: (2, 2),
+///     mineral_pile: (1, 1),
+///     carousel_mill: (2, 2),
+///     jukebox_dryer: (1, 1),
+///     crafting_table: (1, 1),
+///     dance_pad_polisher: (1, 1),
+///     aniipod_maker: (1, 1),
+///     nimbus_bed: (1, 1),
+///     instance_levels: Default::default(),
+/// };
+/// let efficiencies = calculate_efficiencies(&items, "coins", &counts, &ModuleLevels::default());
+///
+/// // 30k coins within 12 hours, spending as little energy as possible
+/// let plan = find_deadline_path(
+///     &efficiencies,
+///     30000.0,
+///     12.0 * 3600.0,
+///     CostObjective::Energy,
+///     &counts,
+///     &PlanningOptions::default(),
+/// );
+/// if !plan.meets_deadline {
+///     println!("Can't make it; the fastest plan needs {:?}", plan.path.map(|p| p.total_time));
+/// }
+/// ```
+pub fn find_deadline_path(
+    efficiencies: &[ProductionEfficiency],
+    target_amount: f64,
+    deadline: f64,
+    objective: CostObjective,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
+) -> DeadlinePlan {
+    let mut candidates: Vec<ProductionPath> = efficiencies
+        .iter()
+        .filter(|eff| facility_counts.count_for(&eff.item) > 0)
+        .filter_map(|eff| plan_single_path(eff, target_amount, facility_counts, options))
+        .collect();
+    candidates.extend(find_best_production_path_with_options(
+        efficiencies,
+        target_amount,
+        false,
+        0.0,
+        facility_counts,
+        options,
+    ));
+    candidates.extend(find_parallel_production_path_with_options(
+        efficiencies,
+        target_amount,
+        facility_counts,
+        options,
+    ));
+    if options.starting_coins.is_none() {
+        candidates.extend(plan_cheapest_mix(
+            efficiencies,
+            target_amount,
+            deadline,
+            objective,
+            facility_counts,
+            options,
+        ));
+    }
+
+    let mut costed: Vec<(f64, ProductionPath)> = candidates
+        .into_iter()
+        .map(|path| (path_cost(&path, efficiencies, objective), path))
+        .collect();
+    let by_time = |a: &ProductionPath, b: &ProductionPath| {
+        a.total_time
+            .partial_cmp(&b.total_time)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    };
+    let meets_deadline = costed.iter().any(|(_, path)| path.total_time <= deadline);
+    if meets_deadline {
+        costed.retain(|(_, path)| path.total_time <= deadline);
+        costed.sort_by(|(a_cost, a), (b_cost, b)| {
+            a_cost
+                .partial_cmp(b_cost)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| by_time(a, b))
+        });
+    } else {
+        costed.sort_by(|(_, a), (_, b)| by_time(a, b));
+    }
+
+    let (cost, path) = match costed.into_iter().next() {
+        Some((cost, path)) => (cost, Some(path)),
+        None => (0.0, None),
+    };
+    DeadlinePlan {
+        path,
+        meets_deadline,
+        deadline,
+        objective,
+        cost,
+    }
+}
+
+/// Most points a step's energy is spent at; larger steps spend theirs in
+/// groups of batches.
+const ENERGY_SPEND_POINTS: u32 = 1024;
+
+/// Energy a path spends, as (time in seconds, energy) points in time order.
+///
+/// A step's energy (that of its whole chain) is spent batch by batch as its
+/// batches start, spread evenly over the step's time within the plan's
+/// steady state. Steps with many batches spend them in groups, each at the
+/// start of its first batch.
+pub fn energy_spends(path: &ProductionPath) -> Vec<(f64, f64)> {
+    let steady = (path.total_time - path.startup_time).max(0.0);
+    let mut spends = Vec::new();
+    for step in &path.steps {
+        let Some(energy) = step.energy.filter(|&e| e > 0.0) else {
+            continue;
+        };
+        if step.quantity == 0 {
             continue;
         }
+        let batches = step.quantity as u64;
+        let span = step.time.min(steady);
+        let groups = step.quantity.min(ENERGY_SPEND_POINTS) as u64;
+        for group in 0..groups {
+            let first = batches * group / groups;
+            let next = batches * (group + 1) / groups;
+            spends.push((
+                span * first as f64 / batches as f64,
+                energy * (next - first) as f64 / batches as f64,
+            ));
+        }
+    }
+    spends.sort_by(|a, b| a.0.total_cmp(&b.0));
+    spends
+}
+
+/// Stretches a path to wait for regenerating energy wherever a spend finds
+/// the pool short, or returns `None` if it can't be paid for within
+/// `time_budget` seconds.
+fn fit_energy_budget(mut path: ProductionPath, budget: &EnergyBudget, time_budget: f64) -> Option<ProductionPath> {
+    let (wait, _) = budget.spend(&energy_spends(&path), path.total_time)?;
+    path.total_time += wait;
+    (path.total_time <= time_budget).then_some(path)
+}
 
-        // Must have energy value to be consumable
-        let energy_per_batch = match item.energy {
-            Some(e) if e > 0.0 => e,
-            _ => continue,
+/// Finds the most profitable single-item plan that fits an energy budget.
+///
+/// Instead of a target amount, the plan is limited by the energy the budget
+/// provides and by `time_budget` (in seconds): energy that regenerates can
+/// only be spent once it has come in, so a plan may run longer than its
+/// production time to wait for it. For every item the largest affordable
+/// number of batches is found, and the item with the highest total profit
+/// wins.
+///
+/// # Example
+///
+/// ```no_run
+/// use aniimax::optimizer::{calculate_efficiencies, find_energy_budget_path};
+/// use aniimax::models::{EnergyBudget, FacilityCounts, ModuleLevels, PlanningOptions};
+/// use aniimax::data::load_all_data;
+/// use std::path::Path;
+///
+/// let items = load_all_data(Path::new("data")).unwrap();
+/// let counts = FacilityCounts {
+///     farmland: (4, 3),
+///     woodland: (1, 2),
+///     mineral_pile: (1, 1),
+///     carousel_mill: (2, 2),
+///     jukebox_dryer: (1, 1),
+///     crafting_table: (1, 1),
+///     dance_pad_polisher: (1, 1),
+///     aniipod_maker: (1, 1),
+///     nimbus_bed: (1, 1),
+///     instance_levels: Default::default(),
+/// };
+/// let efficiencies = calculate_efficiencies(&items, "coins", &counts, &ModuleLevels::default());
+///
+/// let budget = EnergyBudget { pool: 500_000.0, regen_per_min: 100.0, cap: None };
+/// let path = find_energy_budget_path(&efficiencies, &budget, 24.0 * 3600.0, &counts, &PlanningOptions::default());
+/// ```
+pub fn find_energy_budget_path(
+    efficiencies: &[ProductionEfficiency],
+    budget: &EnergyBudget,
+    time_budget: f64,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
+) -> Option<ProductionPath> {
+    let mut best: Option<ProductionPath> = None;
+
+    for eff in efficiencies {
+        let profit_per_batch = eff.profit_per_batch;
+        let facility_count = facility_counts.count_for(&eff.item);
+        if profit_per_batch <= 0.0 || facility_count == 0 {
+            continue;
+        }
+        // Aim just below a whole number of batches so rounding up lands on it
+        let plan = |batches: u32| {
+            plan_single_batches(eff, (batches as f64 - 0.5) * profit_per_batch, facility_counts, options)
+                .and_then(|path| fit_energy_budget(path, budget, time_budget))
         };
 
-        // Filter by facility level
-        if !facility_counts.can_produce(&item.facility, item.facility_level) {
+        // Upper bound on the batches that fit in the time and energy available,
+        // with slack for stock on hand
+        let mut max_batches = 2.0 * eff.effective_profit_per_second * time_budget / profit_per_batch + 1.0;
+        // (energy spent as it comes in is never lost to the cap)
+        if let Some(energy) = eff.total_energy_per_unit.filter(|&e| e > 0.0) {
+            let income = budget.starting_energy() + budget.regen_per_min * time_budget / 60.0;
+            max_batches = max_batches.min((income / energy).ceil());
+        }
+        let mut high = max_batches as u32 + 1;
+
+        // Largest batch count that still fits
+        let Some(mut path) = plan(1) else {
             continue;
+        };
+        let mut low = 1;
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            match plan(mid) {
+                Some(p) => {
+                    low = mid;
+                    path = p;
+                }
+                None => high = mid,
+            }
         }
 
-        // Filter by module requirement
-        if let Some((ref module_name, required_level)) = item.module_requirement {
-            if !module_levels.can_use(module_name, required_level) {
-                continue;
+        let better = best.as_ref().is_none_or(|b| {
+            path.total_profit > b.total_profit
+                || (path.total_profit == b.total_profit && path.total_time < b.total_time)
+        });
+        if better {
+            best = Some(path);
+        }
+    }
+
+    best
+}
+
+/// Finds the most profitable parallel plan that fits an energy budget, like
+/// [`find_energy_budget_path`] for [`find_parallel_production_path`].
+///
+/// The chains are the same as in parallel mode; only their combined size is
+/// limited by the energy budget and `time_budget` (in seconds).
+pub fn find_parallel_energy_budget_path(
+    efficiencies: &[ProductionEfficiency],
+    budget: &EnergyBudget,
+    time_budget: f64,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
+) -> Option<ProductionPath> {
+    let plan = |target: f64| {
+        plan_parallel_batches(efficiencies, target, facility_counts, options)
+            .and_then(|path| fit_energy_budget(path, budget, time_budget))
+    };
+
+    // No plan can earn more than every item running for the whole budget
+    let mut high: f64 = efficiencies
+        .iter()
+        .map(|e| e.effective_profit_per_second * time_budget)
+        .sum();
+    let mut low = 0.0;
+    let mut best = None;
+    for _ in 0..40 {
+        let mid = (low + high) / 2.0;
+        match plan(mid) {
+            Some(path) => {
+                low = mid;
+                best = Some(path);
             }
+            None => high = mid,
         }
+        if high - low < 1.0 {
+            break;
+        }
+    }
 
-        let facility_count = facility_counts.get_count(&item.facility) as f64;
-        let time_per_batch = item.production_time / facility_count;
-        let energy_per_second = energy_per_batch / time_per_batch;
-        let cost_per_batch = item.cost.unwrap_or(0.0);
+    best
+}
 
-        efficiencies.push(EnergyItemEfficiency {
-            item: item.clone(),
-            energy_per_second,
-            time_per_batch,
-            energy_per_batch,
-            cost_per_batch,
-        });
+/// Calculates efficiency metrics for items that can be consumed for energy.
+///
+/// Every producible item with an energy value qualifies, processed foods
+/// (e.g. herbs) included: their rate is the steady-state rate of the whole
+/// chain and their cost includes the seeds of their ingredients. This is
+/// used for energy self-sufficient mode.
+pub fn calculate_energy_efficiencies(
+    items: &[ProductionItem],
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+) -> Vec<EnergyItemEfficiency> {
+    // Chains are calculated per sell currency; for eating, the currency doesn't matter
+    let mut currencies: Vec<&str> = items.iter().map(|i| i.sell_currency.as_str()).collect();
+    currencies.sort();
+    currencies.dedup();
+
+    let mut efficiencies = Vec::new();
+
+    for currency in currencies {
+        for production in calculate_efficiencies(items, currency, facility_counts, module_levels) {
+            // Must have energy value to be consumable
+            let energy_per_batch = match production.item.energy_value {
+                Some(e) if e > 0.0 => e,
+                _ => continue,
+            };
+
+            let time_per_batch = production.steady_state_time;
+            if !(time_per_batch > 0.0 && time_per_batch.is_finite()) {
+                continue;
+            }
+
+            efficiencies.push(EnergyItemEfficiency {
+                item: production.item.clone(),
+                energy_per_second: energy_per_batch / time_per_batch,
+                time_per_batch,
+                energy_per_batch,
+                cost_per_batch: production.raw_cost,
+                production,
+            });
+        }
     }
 
     // Sort by energy per second (best first)
@@ -1531,8 +3758,8 @@ pub fn calculate_energy_efficiencies(
 ///
 /// This function calculates a production plan where:
 /// - Some facilities produce items for profit (to sell)
-/// - Some facilities produce items for energy (to consume)
-/// - Total energy from consumed items >= energy consumed during production
+/// - Other facilities produce items for energy (to consume) at the same time
+/// - Energy from consumed items always covers the energy spent so far
 ///
 /// # Arguments
 ///
@@ -1551,6 +3778,40 @@ pub fn find_self_sufficient_path(
     target_amount: f64,
     energy_cost_per_min: f64,
     facility_counts: &FacilityCounts,
+) -> Option<ProductionPath> {
+    find_self_sufficient_path_with_options(
+        profit_efficiencies,
+        energy_efficiencies,
+        target_amount,
+        energy_cost_per_min,
+        facility_counts,
+        &PlanningOptions::default(),
+    )
+}
+
+/// Finds an energy self-sufficient path like [`find_self_sufficient_path`],
+/// with additional planning inputs.
+///
+/// Energy and profit chains run at the same time on separate facilities.
+/// Every combination of energy items (raw or processed, at most one per set
+/// of facilities) that produces energy at least as fast as it is spent is
+/// tried; the remaining facilities run the fastest single-item or parallel
+/// profit plan, and the combination that reaches the target (after the
+/// energy items' seed costs) soonest wins.
+///
+/// Energy items are eaten as each batch finishes. The profit chains wait
+/// for the first meals if needed, so the energy eaten so far always covers
+/// the energy spent so far; the wait counts towards the startup time.
+///
+/// Inputs of the profit items held in `options.inventory` are used before
+/// anything new is produced, and their chains respect `options.storage`.
+pub fn find_self_sufficient_path_with_options(
+    profit_efficiencies: &[ProductionEfficiency],
+    energy_efficiencies: &[EnergyItemEfficiency],
+    target_amount: f64,
+    energy_cost_per_min: f64,
+    facility_counts: &FacilityCounts,
+    options: &PlanningOptions,
 ) -> Option<ProductionPath> {
     if profit_efficiencies.is_empty() {
         return None;
@@ -1558,240 +3819,314 @@ pub fn find_self_sufficient_path(
 
     // If no energy cost, just use the simple path
     if energy_cost_per_min <= 0.0 {
-        return find_best_production_path(
+        return find_best_production_path_with_options(
             profit_efficiencies,
             target_amount,
             false,
             0.0,
             facility_counts,
+            options,
         );
     }
 
-    // If no energy items available, can't be self-sufficient
-    if energy_efficiencies.is_empty() {
-        return None;
-    }
+    // Energy spent per second
+    let drain = energy_cost_per_min / 60.0;
 
-    // Sort profit items by profit per second
-    let mut sorted_profit = profit_efficiencies.to_vec();
-    sorted_profit.sort_by(|a, b| {
-        b.effective_profit_per_second
-            .partial_cmp(&a.effective_profit_per_second)
+    // The fastest energy item for every set of facilities
+    let mut sorted_energy: Vec<&EnergyItemEfficiency> = energy_efficiencies.iter().collect();
+    sorted_energy.sort_by(|a, b| {
+        b.energy_per_second
+            .partial_cmp(&a.energy_per_second)
             .unwrap_or(std::cmp::Ordering::Equal)
     });
+    let mut candidates: Vec<&EnergyItemEfficiency> = Vec::new();
+    for eff in sorted_energy {
+        if !candidates
+            .iter()
+            .any(|c| c.production.all_facilities == eff.production.all_facilities)
+        {
+            candidates.push(eff);
+        }
+    }
 
-    let best_profit = &sorted_profit[0];
-    let best_energy = &energy_efficiencies[0]; // Already sorted
-
-    // Calculate profit per batch for the profit item
-    let profit_per_batch = best_profit.item.sell_value * best_profit.item.yield_amount as f64
-        - best_profit.raw_cost;
+    // Fastest profit plan on the given items, single-item or parallel
+    let fastest_profit_path = |efficiencies: &[ProductionEfficiency], goal: f64| {
+        let single =
+            find_best_production_path_with_options(efficiencies, goal, false, 0.0, facility_counts, options);
+        let parallel = find_parallel_production_path_with_options(efficiencies, goal, facility_counts, options);
+        match (parallel, single) {
+            (Some(p), Some(s)) => Some(if p.total_time <= s.total_time { p } else { s }),
+            (p, s) => p.or(s),
+        }
+    };
 
-    // Get facility counts
-    let profit_facility_count = facility_counts.get_count(&best_profit.item.facility) as f64;
-    let energy_facility_count = facility_counts.get_count(&best_energy.item.facility) as f64;
+    let mut best: Option<(ProductionPath, Vec<&EnergyItemEfficiency>, Vec<EnergyChainUse>, f64)> = None;
 
-    // Energy rate (per second)
-    let energy_rate = energy_cost_per_min / 60.0;
+    for energy_set in energy_item_sets(&candidates, drain) {
+        let profit_items: Vec<ProductionEfficiency> = profit_efficiencies
+            .iter()
+            .filter(|e| {
+                energy_set
+                    .iter()
+                    .all(|c| c.production.all_facilities.is_disjoint(&e.all_facilities))
+            })
+            .cloned()
+            .collect();
+        if profit_items.is_empty() {
+            continue;
+        }
 
-    // Energy production rate from the best energy item (with all facilities)
-    let energy_production_rate = best_energy.energy_per_second * energy_facility_count;
+        // Energy seeds are paid in coins, so when coins are valued they eat into
+        // the profit; aim higher until the net profit reaches the target
+        let mut goal = target_amount;
+        for _ in 0..8 {
+            let Some(profit_path) = fastest_profit_path(&profit_items, goal) else {
+                break;
+            };
+            let Some((uses, wait)) =
+                schedule_energy_chains(&energy_set, drain, profit_path.total_time, facility_counts)
+            else {
+                break;
+            };
+            let seed_cost: f64 = energy_set
+                .iter()
+                .zip(&uses)
+                .map(|(eff, u)| eff.cost_per_batch * u.batches as f64)
+                .sum();
 
-    // Check if we can even be self-sufficient
-    // We need: energy_production_rate > energy_rate (otherwise we can never catch up)
-    if energy_production_rate <= energy_rate {
-        // Can't be self-sufficient with current setup
-        return None;
+            let mut ledger = profit_path.ledger.clone();
+            ledger.spend("coins", seed_cost);
+            if ledger.value(&profit_items[0].valuation) >= target_amount {
+                if best
+                    .as_ref()
+                    .is_none_or(|(path, _, _, best_wait)| profit_path.total_time + wait < path.total_time + best_wait)
+                {
+                    best = Some((profit_path, energy_set.clone(), uses, wait));
+                }
+                break;
+            }
+            goal = target_amount + seed_cost;
+        }
     }
 
-    // Calculate the optimal split
-    // Let T_profit = time producing profit items
-    // Let T_energy = time producing energy items
-    // Let T_total = T_profit + T_energy
-    //
-    // Constraints:
-    // 1. Profit >= target: profit_rate * T_profit >= target
-    // 2. Energy balance: energy_produced >= energy_consumed
-    //    best_energy.energy_per_second * energy_facility_count * T_energy >= energy_rate * T_total
-    //
-    // From constraint 2:
-    // E * T_energy >= R * (T_profit + T_energy)
-    // E * T_energy >= R * T_profit + R * T_energy
-    // T_energy * (E - R) >= R * T_profit
-    // T_energy >= T_profit * R / (E - R)
-
-    // Calculate time needed for profit production
-    let batches_for_profit = (target_amount / profit_per_batch).ceil();
-
-    // Time to produce profit items (with parallelization)
-    let time_for_profit = if best_profit.requires_raw.is_some() {
-        best_profit.total_time_per_unit * batches_for_profit / profit_facility_count
-    } else {
-        best_profit.item.production_time * (batches_for_profit / profit_facility_count).ceil()
-    };
+    let (profit_path, energy_set, uses, wait) = best?;
+    let valuation = &profit_efficiencies.first()?.valuation;
 
-    // Calculate energy batches needed using the formula:
-    // Energy needed = (T_profit + T_energy) * R
-    // Energy produced = B * E  (where B = batches, E = energy per batch)
-    // T_energy = production_time * ceil(B / facility_count)
-    //
-    // For self-sufficiency: B * E >= (T_profit + T_energy) * R
-    // 
-    // Let's solve iteratively since ceiling makes it non-linear
-    let production_time_per_batch = best_energy.item.production_time;
-    
-    // Start with minimum batches and increase until we have enough energy
-    let mut energy_batches = 1u32;
-    loop {
-        let rounds = (energy_batches as f64 / energy_facility_count).ceil();
-        let actual_energy_time = production_time_per_batch * rounds;
-        let total_time = time_for_profit + actual_energy_time;
-        let energy_needed = total_time * energy_rate;
-        let energy_produced = energy_batches as f64 * best_energy.energy_per_batch;
-        
-        if energy_produced >= energy_needed {
-            break;
-        }
-        
-        energy_batches += 1;
-        
-        // Safety check to prevent infinite loop
-        if energy_batches > 10000 {
-            return None;
-        }
+    // Every chain runs at the same time; a single-item profit plan becomes chain 0
+    let mut steps = profit_path.steps;
+    for step in &mut steps {
+        step.chain_id.get_or_insert(0);
     }
+    let mut chain_id = steps.iter().filter_map(|s| s.chain_id).max().map_or(0, |id| id + 1);
 
-    // Calculate actual times with the determined batch counts
-    let energy_rounds = (energy_batches as f64 / energy_facility_count).ceil();
-    let actual_energy_production_time = production_time_per_batch * energy_rounds;
-    let total_time = time_for_profit + actual_energy_production_time;
-    let total_energy_needed = total_time * energy_rate;
+    let mut energy_inventory = Inventory::default();
+    let mut ledger = profit_path.ledger.clone();
+    let mut material_balance = profit_path.material_balance;
+    let mut energy_balance = Vec::new();
+    let mut energy_items_produced = 0;
+    let mut energy_item_names = Vec::new();
+    let mut energy_jobs = Vec::new();
+    let mut energy_stock = HashMap::new();
 
-    // Build the production steps
-    let mut steps = Vec::new();
+    for (eff, energy_use) in energy_set.iter().zip(&uses) {
+        if energy_use.batches == 0 {
+            continue;
+        }
+        let batches = energy_use.batches;
 
-    // Add energy production step
-    steps.push(ProductionStep {
-        item_name: format!("{} (for energy)", best_energy.item.name),
-        facility: format!(
-            "{} (x{})",
-            best_energy.item.facility,
-            facility_counts.get_count(&best_energy.item.facility)
-        ),
-        quantity: energy_batches,
-        time: actual_energy_production_time,
-        energy: Some(energy_batches as f64 * best_energy.energy_per_batch),
-        profit_contribution: -(energy_batches as f64 * best_energy.cost_per_batch), // Cost of seeds
-        chain_id: None,
-        facility_allocation: None,
-    });
+        // Ingredients (and fertilizer) of the energy item
+        let mut inputs = plan_inputs(&eff.production, batches, facility_counts, &mut energy_inventory, Some(chain_id))?;
+        inputs.set_step_time(energy_use.time);
+        for balance in inputs.balance(&eff.production, batches) {
+            add_balance(&mut energy_balance, balance);
+        }
+        energy_jobs.extend(chain_jobs(&eff.production, batches, &inputs));
+        inputs.add_stock_used(&mut energy_stock);
+        steps.extend(inputs.steps);
 
-    let nimbus_bed_count = facility_counts.get_count("Nimbus Bed");
-    
-    // Add fertilizer production step if needed for profit item
-    if best_profit.fertilizer_per_batch > 0 && nimbus_bed_count > 0 {
-        let total_fertilizer_needed = best_profit.fertilizer_per_batch * batches_for_profit as u32;
-        
         steps.push(ProductionStep {
-            item_name: "fertilizer".to_string(),
-            facility: format!("Nimbus Bed (x{})", nimbus_bed_count),
-            quantity: total_fertilizer_needed,
-            time: 0.0, // Time is included in total
-            energy: None,
-            profit_contribution: 0.0,
-            chain_id: None,
+            item_name: format!("{} (for energy)", eff.item.name),
+            facility: format!(
+                "{} (x{})",
+                eff.item.facility,
+                facility_counts.count_for(&eff.item)
+            ),
+            quantity: batches,
+            time: energy_use.time,
+            energy: eff.item.energy_cost.map(|e| batches as f64 * e),
+            energy_gained: Some(batches as f64 * eff.energy_per_batch),
+            profit_contribution: -(batches as f64 * eff.cost_per_batch), // Cost of seeds
+            chain_id: Some(chain_id),
             facility_allocation: None,
         });
-    }
 
-    // Add raw material step for profit item if needed
-    if let Some(ref raw_name) = best_profit.requires_raw {
-        let raw_amount_needed = best_profit.item.required_amount
-            .as_ref()
-            .map(|amounts| amounts.iter().sum::<u32>())
-            .unwrap_or(1) * batches_for_profit as u32;
-        let raw_facility = best_profit.raw_facility.as_deref().unwrap_or("Unknown");
-        let raw_facility_count = facility_counts.get_count(raw_facility);
-        
-        // Calculate optimal facility allocation for multi-material production
-        let facility_allocation = if let Some(ref details) = best_profit.raw_material_details {
-            let materials_for_allocation: Vec<(String, u32, f64)> = details.iter()
-                .map(|(name, amt_per_batch, time)| {
-                    (name.clone(), amt_per_batch * batches_for_profit as u32, *time)
-                })
-                .collect();
-            
-            let allocation = calculate_optimal_facility_allocation(&materials_for_allocation, raw_facility_count);
-            if allocation.len() > 1 {
-                Some(allocation)
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-        
-        steps.push(ProductionStep {
-            item_name: raw_name.clone(),
-            facility: format!("{} (x{})", raw_facility, raw_facility_count),
-            quantity: raw_amount_needed,
-            time: 0.0,
-            energy: None,
-            profit_contribution: 0.0,
-            chain_id: None,
-            facility_allocation,
-        });
-        
-        // Add intermediate processing steps (e.g., nuts for caramel_nut_chips)
-        for (int_name, int_facility, int_amount_per_batch) in &best_profit.intermediate_steps {
-            let int_qty = int_amount_per_batch * batches_for_profit as u32;
-            steps.push(ProductionStep {
-                item_name: int_name.clone(),
-                facility: format!("{} (x{})", int_facility, facility_counts.get_count(int_facility)),
-                quantity: int_qty,
-                time: 0.0,
-                energy: None,
-                profit_contribution: 0.0,
-                chain_id: None,
-                facility_allocation: None,
-            });
-        }
+        ledger.spend("coins", batches as f64 * eff.cost_per_batch);
+        energy_items_produced += batches * eff.item.yield_amount;
+        energy_item_names.push(eff.item.name.clone());
+        chain_id += 1;
     }
 
-    // Add profit production step
-    steps.push(ProductionStep {
-        item_name: format!("{} (for profit)", best_profit.item.name),
-        facility: format!(
-            "{} (x{})",
-            best_profit.item.facility,
-            facility_counts.get_count(&best_profit.item.facility)
-        ),
-        quantity: batches_for_profit as u32,
-        time: time_for_profit,
-        energy: None,
-        profit_contribution: profit_per_batch * batches_for_profit,
-        chain_id: None,
-        facility_allocation: None,
-    });
+    // The profit plan already sold its own surplus
+    if options.sell_surplus {
+        let chains: Vec<&ProductionEfficiency> = energy_set.iter().map(|eff| &eff.production).collect();
+        sell_surplus(&mut energy_balance, &chains, &mut ledger);
+    }
+    for balance in energy_balance {
+        add_balance(&mut material_balance, balance);
+    }
 
-    // Calculate actual profit (minus seed costs for energy items)
-    let energy_seed_cost = energy_batches as f64 * best_energy.cost_per_batch;
-    let gross_profit = profit_per_batch * batches_for_profit;
-    let net_profit = gross_profit - energy_seed_cost;
-    
-    // For energy self-sufficient mode, startup time is the longer of the two chains
-    let startup_time = best_profit.startup_time.max(best_energy.item.production_time);
+    // Energy chains start right away; the profit plan once enough energy is in
+    let mut inventory_over_time = Vec::new();
+    add_inventory(&mut inventory_over_time, &profit_path.inventory_over_time, wait);
+    let mut unsimulated = vec![PlannedJobs {
+        start: 0.0,
+        jobs: energy_jobs,
+        initial_stock: energy_stock,
+    }];
+    unsimulated.extend(profit_path.unsimulated.into_iter().map(|planned| PlannedJobs {
+        start: planned.start + wait,
+        ..planned
+    }));
 
     Some(ProductionPath {
         steps,
-        total_time: total_time + startup_time,
-        startup_time,
-        total_energy: Some(total_energy_needed),
-        total_profit: net_profit,
-        currency: best_profit.item.sell_currency.clone(),
-        items_produced: batches_for_profit as u32 * best_profit.item.yield_amount,
+        total_time: wait + profit_path.total_time,
+        startup_time: wait + profit_path.startup_time,
+        total_energy: Some(profit_path.total_time * drain),
+        total_profit: ledger.value(valuation),
+        currency: profit_path.currency,
+        ledger,
+        items_produced: profit_path.items_produced,
         is_energy_self_sufficient: true,
-        energy_items_produced: Some(energy_batches * best_energy.item.yield_amount),
-        energy_item_name: Some(best_energy.item.name.clone()),
+        energy_items_produced: Some(energy_items_produced),
+        energy_item_name: Some(energy_item_names.join(" + ")),
+        peak_inventory: profit_path.peak_inventory,
+        balance_over_time: profit_path.balance_over_time,
+        average_prices: profit_path.average_prices,
+        material_balance,
+        inventory_over_time,
+        unsimulated,
     })
 }
+
+/// Batches of one energy item eaten during a self-sufficient plan.
+struct EnergyChainUse {
+    /// Batches produced and eaten
+    batches: u32,
+    /// When the last of them is eaten
+    time: f64,
+}
+
+/// Combinations of energy items on separate facilities that together
+/// produce at least `drain` energy per second. Items are only added while
+/// the combination is still too slow, so no combination holds an item it
+/// doesn't need.
+fn energy_item_sets<'a>(candidates: &[&'a EnergyItemEfficiency], drain: f64) -> Vec<Vec<&'a EnergyItemEfficiency>> {
+    fn extend<'a>(
+        candidates: &[&'a EnergyItemEfficiency],
+        start: usize,
+        drain: f64,
+        chosen: &mut Vec<&'a EnergyItemEfficiency>,
+        sets: &mut Vec<Vec<&'a EnergyItemEfficiency>>,
+    ) {
+        let rate: f64 = chosen.iter().map(|c| c.energy_per_second).sum();
+        if rate >= drain {
+            sets.push(chosen.clone());
+            return;
+        }
+        for (i, candidate) in candidates.iter().enumerate().skip(start) {
+            let overlaps = chosen
+                .iter()
+                .any(|c| !c.production.all_facilities.is_disjoint(&candidate.production.all_facilities));
+            if overlaps {
+                continue;
+            }
+            chosen.push(candidate);
+            extend(candidates, i + 1, drain, chosen, sets);
+            chosen.pop();
+        }
+    }
+
+    let mut sets = Vec::new();
+    extend(candidates, 0, drain, &mut Vec::new(), &mut sets);
+    sets
+}
+
+/// Works out how many batches of each energy item are eaten over profit
+/// chains that run for `duration` seconds and spend `drain` energy per second,
+/// and how long the profit chains have to wait for energy before starting.
+///
+/// Energy chains start right away and batches are eaten as they finish: a raw
+/// item's plots all finish at the end of each round, a processed item's
+/// batches come in as its chain turns them out in whole batches (see
+/// [`chain_time`]). The profit
+/// chains start late enough that the energy eaten so far covers the energy
+/// spent before every meal, so the balance never goes negative.
+fn schedule_energy_chains(
+    energy_set: &[&EnergyItemEfficiency],
+    drain: f64,
+    duration: f64,
+    facility_counts: &FacilityCounts,
+) -> Option<(Vec<EnergyChainUse>, f64)> {
+    // (time between meals of a raw item, energy per meal, batches per meal)
+    let meals: Vec<(Option<f64>, f64, u32)> = energy_set
+        .iter()
+        .map(|eff| {
+            if eff.production.requires_raw.is_none() {
+                let count = facility_counts.count_for(&eff.item);
+                let round = eff.time_per_batch * count as f64;
+                (Some(round), eff.energy_per_batch * count as f64, count)
+            } else {
+                (None, eff.energy_per_batch, 1)
+            }
+        })
+        .collect();
+    if energy_set.iter().any(|eff| eff.time_per_batch <= 0.0) {
+        return None;
+    }
+    // When the `n`th meal (from 1) of a chain is ready
+    let meal_time = |index: usize, n: u32| -> f64 {
+        match meals[index].0 {
+            Some(round) => round * n as f64,
+            None => {
+                let production = &energy_set[index].production;
+                match plan_inputs(production, n, facility_counts, &mut Inventory::default(), None) {
+                    Some(inputs) => {
+                        let (time, startup) = chain_time(production, n, &inputs, facility_counts);
+                        startup + time
+                    }
+                    None => f64::INFINITY,
+                }
+            }
+        }
+    };
+
+    let mut uses: Vec<EnergyChainUse> = energy_set
+        .iter()
+        .map(|_| EnergyChainUse { batches: 0, time: 0.0 })
+        .collect();
+    let mut next_meal: Vec<f64> = (0..meals.len()).map(|i| meal_time(i, 1)).collect();
+    let mut meals_eaten: Vec<u32> = vec![0; meals.len()];
+    let needed = drain * duration;
+    let mut eaten = 0.0;
+    let mut wait: f64 = 0.0;
+
+    while eaten < needed {
+        let (index, &time) = next_meal
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))?;
+        let (_, energy, batches) = meals[index];
+
+        // Energy spent up to this meal can't exceed what was eaten before it
+        wait = wait.max(time - eaten / drain);
+
+        eaten += energy;
+        uses[index].batches += batches;
+        uses[index].time = time;
+        meals_eaten[index] += 1;
+        next_meal[index] = meal_time(index, meals_eaten[index] + 1);
+    }
+
+    Some((uses, wait))
+}
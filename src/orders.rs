@@ -0,0 +1,591 @@
+//! Produce-to-order planning for Aniimax.
+//!
+//! Quests and gifts ask for specific goods rather than currency. This module
+//! expands an order (e.g. 30 dried_flowers and 10 sf_medium_exp_gem) into an
+//! exact bill of materials and schedules the batches across the available
+//! facilities.
+
+use std::collections::HashMap;
+
+use crate::models::{
+    BatchJob, BoardOrder, CurrencyLedger, CurrencyValuation, FacilityCounts, Inventory, MaterialUsage, ModuleLevels,
+    OrderBoardPlan, OrderEvaluation, OrderLine, OrderPlan, PlanningOptions, ProductionEfficiency, ProductionItem,
+};
+use crate::optimizer::{
+    best_profit_rate, calculate_valued_efficiencies, instance_level_stats, items_at_levels, resolve_item_variant,
+    timed_stats,
+};
+use crate::schedule::simulate_batches;
+
+/// Recipes are shallow; deeper chains mean a cycle in the data.
+const MAX_RECIPE_DEPTH: usize = 8;
+
+/// Boards with more candidate orders than this are filled greedily instead
+/// of trying every subset.
+const EXHAUSTIVE_BOARD_LIMIT: usize = 12;
+
+/// Orders keeping a facility type busy for longer than this (one year, in
+/// seconds) aren't simulated; the simulation takes time per batch.
+const ORDER_HORIZON: f64 = 365.0 * 24.0 * 3600.0;
+
+/// Parses an order such as `dried_flowers=30,sf_medium_exp_gem=10`.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::orders::parse_order;
+///
+/// let order = parse_order("dried_flowers=30, sf_medium_exp_gem=10").unwrap();
+/// assert_eq!(order.len(), 2);
+/// assert_eq!(order[0].item, "dried_flowers");
+/// assert_eq!(order[0].quantity, 30);
+/// ```
+pub fn parse_order(spec: &str) -> Result<Vec<OrderLine>, String> {
+    let mut order = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (item, quantity) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("Expected ITEM=QUANTITY in order entry '{}'", entry))?;
+        let quantity: u32 = quantity
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid quantity in order entry '{}'", entry))?;
+        order.push(OrderLine {
+            item: item.trim().to_string(),
+            quantity,
+        });
+    }
+    if order.is_empty() {
+        return Err("Order is empty".to_string());
+    }
+    Ok(order)
+}
+
+/// Ingredients consumed by one batch of an item, fertilizer included.
+fn batch_inputs(item: &ProductionItem) -> Vec<(String, u32)> {
+    let mut inputs: Vec<(String, u32)> = match item.raw_materials {
+        Some(ref raw_mats) => raw_mats
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let amount = item
+                    .required_amount
+                    .as_ref()
+                    .and_then(|a| a.get(i).copied())
+                    .unwrap_or(1);
+                (name.clone(), amount)
+            })
+            .collect(),
+        None => vec![],
+    };
+    if item.requires_fertilizer {
+        inputs.push(("fertilizer".to_string(), 1));
+    }
+    inputs
+}
+
+/// Checks that the current facilities and modules can produce an item.
+fn check_producible(
+    item: &ProductionItem,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+) -> Result<(), String> {
+    if facility_counts.get_count(&item.facility) == 0 {
+        return Err(format!("No {} available to produce {}", item.facility, item.name));
+    }
+    if !facility_counts.can_produce(&item.facility, item.facility_level) {
+        return Err(format!(
+            "{} needs {} level {}",
+            item.name, item.facility, item.facility_level
+        ));
+    }
+    if let Some((ref module, level)) = item.module_requirement {
+        if !module_levels.can_use(module, level) {
+            return Err(format!("{} needs {} level {}", item.name, module, level));
+        }
+    }
+    Ok(())
+}
+
+/// An item of the bill of materials with its recipe data.
+struct BomEntry<'a> {
+    usage: MaterialUsage,
+    item: &'a ProductionItem,
+    /// Longest processing time still ahead of this item before an ordered item is done
+    tail_time: f64,
+}
+
+/// Expands an order into exact counts of every item it needs.
+///
+/// Items are processed ordered-items-first so that an ingredient's demand is
+/// complete before its batches are rounded up; shared ingredients (e.g. wheat
+/// for two different products) are therefore only rounded once.
+fn expand_order<'a>(
+    order: &[OrderLine],
+    item_map: &HashMap<String, &'a ProductionItem>,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+    inventory: &mut Inventory,
+) -> Result<Vec<BomEntry<'a>>, String> {
+    // Longest distance of every item from an ordered item
+    fn visit<'a>(
+        name: &str,
+        depth: usize,
+        item_map: &HashMap<String, &'a ProductionItem>,
+        facility_counts: &FacilityCounts,
+        module_levels: &ModuleLevels,
+        depths: &mut Vec<(String, usize, &'a ProductionItem)>,
+    ) -> Result<(), String> {
+        if depth > MAX_RECIPE_DEPTH {
+            return Err(format!("Recipe for '{}' is too deep or circular", name));
+        }
+        let item = resolve_item_variant(name, item_map, facility_counts, module_levels)
+            .ok_or_else(|| format!("Unknown item '{}'", name))?;
+        match depths.iter_mut().find(|(n, _, _)| n == name) {
+            Some(entry) if entry.1 >= depth => return Ok(()),
+            Some(entry) => entry.1 = depth,
+            None => depths.push((name.to_string(), depth, item)),
+        }
+        for (input, _) in batch_inputs(item) {
+            visit(&input, depth + 1, item_map, facility_counts, module_levels, depths)?;
+        }
+        Ok(())
+    }
+
+    let mut depths = Vec::new();
+    let mut demand: HashMap<String, u32> = HashMap::new();
+    for line in order {
+        visit(&line.item, 0, item_map, facility_counts, module_levels, &mut depths)?;
+        let ordered = demand.entry(line.item.clone()).or_insert(0);
+        *ordered = ordered
+            .checked_add(line.quantity)
+            .ok_or_else(|| format!("Could not schedule {}: the order needs too many units of it", line.item))?;
+    }
+    // Stable sort keeps first-seen order within a depth
+    depths.sort_by_key(|(_, depth, _)| *depth);
+
+    let mut tail_times: HashMap<String, f64> = HashMap::new();
+    let mut entries = Vec::new();
+    for (name, _, item) in depths {
+        let required = demand.get(&name).copied().unwrap_or(0);
+        if required == 0 {
+            continue;
+        }
+        let from_stock = inventory.take(&name, required);
+        let to_produce = required - from_stock;
+        let batches = to_produce.div_ceil(item.yield_amount.max(1));
+        if batches > 0 {
+            check_producible(item, facility_counts, module_levels)?;
+        }
+        let tail_time = tail_times.get(&name).copied().unwrap_or(0.0);
+
+        for (input, amount) in batch_inputs(item) {
+            let needed = demand.entry(input.clone()).or_insert(0);
+            *needed = amount
+                .checked_mul(batches)
+                .and_then(|units| needed.checked_add(units))
+                .ok_or_else(|| format!("Could not schedule {}: the order needs too many units of it", input))?;
+            let input_tail = tail_times.entry(input).or_insert(0.0);
+            *input_tail = input_tail.max(tail_time + item.production_time);
+        }
+
+        entries.push(BomEntry {
+            usage: MaterialUsage {
+                name,
+                variant: item.name.clone(),
+                facility: item.facility.clone(),
+                is_raw: item.raw_materials.is_none(),
+                required,
+                from_stock,
+                to_produce,
+                batches,
+            },
+            item,
+            tail_time,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Builds the bill of materials for an order: every ordered item,
+/// intermediate, raw material and fertilizer, with exact unit and batch
+/// counts after drawing on the inventory.
+///
+/// Taken stock is removed from `inventory`.
+///
+/// # Errors
+///
+/// Returns an error if an item is unknown or can't be produced with the
+/// current facilities and modules.
+pub fn bill_of_materials(
+    items: &[ProductionItem],
+    order: &[OrderLine],
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+    inventory: &mut Inventory,
+) -> Result<Vec<MaterialUsage>, String> {
+    let items = items_at_levels(items, facility_counts);
+    let item_map: HashMap<String, &ProductionItem> =
+        items.iter().map(|i| (i.name.clone(), i)).collect();
+    let entries = expand_order(order, &item_map, facility_counts, module_levels, inventory)?;
+    Ok(entries.into_iter().map(|e| e.usage).collect())
+}
+
+/// Returns the first job whose facility type can't work through its batches
+/// within [`ORDER_HORIZON`], even with every facility running its fastest
+/// batches back to back.
+fn beyond_horizon<'a>(jobs: &'a [BatchJob], facility_counts: &FacilityCounts) -> Option<&'a BatchJob> {
+    let mut busy: HashMap<&str, f64> = HashMap::new();
+    for job in jobs {
+        let timed = job
+            .timed_stats
+            .iter()
+            .flat_map(|t| std::iter::once(t.production_time).chain(t.level_stats.iter().map(|s| s.1)));
+        let fastest = job
+            .level_stats
+            .iter()
+            .map(|s| s.1)
+            .chain(timed)
+            .fold(job.production_time, f64::min);
+        let time = busy.entry(job.facility.as_str()).or_insert(0.0);
+        *time += job.batches as f64 * fastest / facility_counts.get_count(&job.facility).max(1) as f64;
+        if *time > ORDER_HORIZON {
+            return Some(job);
+        }
+    }
+    None
+}
+
+/// Plans production of specific item quantities.
+///
+/// The order is expanded into an exact bill of materials (raw crops,
+/// intermediates and fertilizer), netted against `options.inventory`, and
+/// scheduled batch by batch across the available facilities to finish as
+/// early as possible without exceeding `options.storage`.
+///
+/// # Errors
+///
+/// Returns an error if an item is unknown or can't be produced with the
+/// current facilities and modules.
+///
+/// # Example
+///
+/// ```no_run
+/// use aniimax::data::load_all_data;
+/// use aniimax::models::{FacilityCounts, ModuleLevels, PlanningOptions};
+/// use aniimax::orders::{parse_order, plan_order};
+/// use std::path::Path;
+///
+/// let items = load_all_data(Path::new("data")).unwrap();
+/// let counts = FacilityCounts {
+///     farmland: (4, 3),
+///     woodland: (1, 2),
+///     mineral_pile: (1, 1),
+///     carousel_mill: (2, 2),
+///     jukebox_dryer: (1, 1),
+///     crafting_table: (1, 1),
+///     dance_pad_polisher: (1, 1),
+///     aniipod_maker: (1, 1),
+///     nimbus_bed: (1, 1),
+///     instance_levels: Default::default(),
+/// };
+/// let order = parse_order("dried_flowers=30").unwrap();
+///
+/// let plan = plan_order(&items, &order, &counts, &ModuleLevels::default(), &PlanningOptions::default()).unwrap();
+/// println!("Done in {}s", plan.completion_time);
+/// ```
+pub fn plan_order(
+    items: &[ProductionItem],
+    order: &[OrderLine],
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+    options: &PlanningOptions,
+) -> Result<OrderPlan, String> {
+    let data_items: HashMap<&str, &ProductionItem> = items.iter().map(|i| (i.name.as_str(), i)).collect();
+    let items = items_at_levels(items, facility_counts);
+    let item_map: HashMap<String, &ProductionItem> =
+        items.iter().map(|i| (i.name.clone(), i)).collect();
+    let mut inventory = options.inventory.clone();
+    let entries = expand_order(order, &item_map, facility_counts, module_levels, &mut inventory)?;
+
+    let mut initial_stock: HashMap<String, u32> = HashMap::new();
+    for entry in &entries {
+        *initial_stock.entry(entry.usage.name.clone()).or_insert(0) += entry.usage.from_stock;
+    }
+    let mut jobs: Vec<BatchJob> = entries
+        .iter()
+        .filter(|e| e.usage.batches > 0)
+        .map(|e| BatchJob {
+            name: e.usage.name.clone(),
+            variant: e.item.name.clone(),
+            facility: e.item.facility.clone(),
+            production_time: e.item.production_time,
+            yield_amount: e.item.yield_amount,
+            inputs: batch_inputs(e.item),
+            batches: e.usage.batches,
+            sold: false,
+            seed_cost: e.item.cost.unwrap_or(0.0),
+            sale_value: 0.0, // Orders are paid for on delivery
+            tail_time: e.tail_time,
+            level_stats: data_items
+                .get(e.item.name.as_str())
+                .map(|item| instance_level_stats(item, facility_counts))
+                .unwrap_or_default(),
+            timed_stats: Vec::new(),
+        })
+        .collect();
+    // Overrides starting or ending while the order is made change its batches
+    if options.overrides.is_timed() {
+        for job in &mut jobs {
+            job.timed_stats = timed_stats(job, &options.overrides);
+        }
+    }
+    if let Some(job) = beyond_horizon(&jobs, facility_counts) {
+        return Err(format!(
+            "Could not schedule {}: its batches need more than a year on {}",
+            job.variant, job.facility
+        ));
+    }
+    let outcome = simulate_batches(
+        &jobs,
+        &initial_stock,
+        facility_counts,
+        &options.in_progress,
+        &options.storage,
+        options.starting_coins,
+    )?;
+
+    let mut schedule = outcome.items;
+    schedule.sort_by(|a, b| {
+        a.start_time
+            .partial_cmp(&b.start_time)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.finish_time.partial_cmp(&b.finish_time).unwrap_or(std::cmp::Ordering::Equal))
+    });
+
+    let seed_cost = entries
+        .iter()
+        .map(|e| e.item.cost.unwrap_or(0.0) * e.usage.batches as f64)
+        .sum();
+    let energy = entries
+        .iter()
+        .filter_map(|e| e.item.energy_cost.map(|energy| energy * e.usage.batches as f64))
+        .fold(None, |total: Option<f64>, energy| Some(total.unwrap_or(0.0) + energy));
+
+    Ok(OrderPlan {
+        order: order.to_vec(),
+        materials: entries.into_iter().map(|e| e.usage).collect(),
+        schedule,
+        completion_time: outcome.completion_time,
+        seed_cost,
+        energy,
+        peak_inventory: outcome.peak_inventory,
+    })
+}
+
+/// Plans the combined goods of several board orders.
+fn plan_orders(
+    items: &[ProductionItem],
+    orders: &[&BoardOrder],
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+    options: &PlanningOptions,
+) -> Result<OrderPlan, String> {
+    let lines: Vec<OrderLine> = orders.iter().flat_map(|o| o.lines.iter().cloned()).collect();
+    plan_order(items, &lines, facility_counts, module_levels, options)
+}
+
+/// Chooses which orders of an order board to fill within a time budget.
+///
+/// Every subset of the board is planned with [`plan_order`] (greedily for
+/// large boards). A subset is worth its rewards, less seed costs, plus what
+/// selling produce earns: on the facilities the orders leave free while they
+/// are made, and on every facility in the time left over. The best subset is
+/// compared against spending the whole budget selling produce at
+/// `sell_value`, so an order is only taken when it beats the market. Rewards
+/// and seeds in another currency count at the valuation's rates. Overrides
+/// starting or ending within the budget change the market from then on.
+///
+/// # Arguments
+///
+/// * `items` - All available production items
+/// * `orders` - The orders on the board
+/// * `valuation` - Currency to maximize, with the worth of any others
+/// * `facility_counts` - Configuration for each facility (count and level)
+/// * `module_levels` - Configuration for each item upgrade module level
+/// * `time_budget` - Available time in seconds
+/// * `options` - Additional planning inputs (the inventory is shared by all orders)
+pub fn optimize_order_board(
+    items: &[ProductionItem],
+    orders: &[BoardOrder],
+    valuation: &CurrencyValuation,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+    time_budget: f64,
+    options: &PlanningOptions,
+) -> OrderBoardPlan {
+    // The market from each time an override starts or ends on, with its best selling rate
+    let mut starts = vec![0.0];
+    if options.overrides.is_timed() {
+        starts.extend(options.overrides.change_times().into_iter().filter(|&t| t < time_budget));
+    }
+    let markets: Vec<(f64, Vec<ProductionEfficiency>, f64)> = starts
+        .into_iter()
+        .map(|start| {
+            let items = options.overrides.items_at(items, start);
+            let efficiencies = calculate_valued_efficiencies(&items, valuation, facility_counts, module_levels);
+            let sell_rate = best_profit_rate(&efficiencies, facility_counts);
+            (start, efficiencies, sell_rate)
+        })
+        .collect();
+    // What selling at `rate` (given a market's efficiencies and best rate) earns between two times
+    let sales_between = |from: f64, to: f64, rate: &dyn Fn(&[ProductionEfficiency], f64) -> f64| -> f64 {
+        markets
+            .iter()
+            .enumerate()
+            .map(|(i, (start, efficiencies, sell_rate))| {
+                let end = markets.get(i + 1).map_or(f64::INFINITY, |next| next.0);
+                let overlap = end.min(to) - start.max(from);
+                if overlap > 0.0 { rate(efficiencies, *sell_rate) * overlap } else { 0.0 }
+            })
+            .sum()
+    };
+    let market_rate = |_: &[ProductionEfficiency], sell_rate: f64| sell_rate;
+    let reward_in = |order: &BoardOrder| order.reward * valuation.rate(&order.reward_currency);
+    // Facilities a plan doesn't use keep selling produce while it runs
+    let free_rate = |plan: &OrderPlan, efficiencies: &[ProductionEfficiency], sell_rate: f64| -> f64 {
+        let busy = |facility: &str| plan.schedule.iter().any(|s| s.facility == facility);
+        let free: Vec<ProductionEfficiency> = efficiencies
+            .iter()
+            .filter(|e| !e.all_facilities.iter().any(|f| busy(f)))
+            .filter(|e| e.nimbus_bed_load == 0.0 || !busy("Nimbus Bed"))
+            .cloned()
+            .collect();
+        best_profit_rate(&free, facility_counts).min(sell_rate)
+    };
+
+    let mut evaluations: Vec<OrderEvaluation> = orders
+        .iter()
+        .map(|order| {
+            let market_value = order
+                .lines
+                .iter()
+                .filter_map(|line| {
+                    items
+                        .iter()
+                        .find(|i| i.name == line.item && i.sell_currency == order.reward_currency)
+                        .map(|i| i.sell_value * line.quantity as f64)
+                })
+                .fold(0.0, |total, value| total + value);
+            let completion_time = plan_orders(items, &[order], facility_counts, module_levels, options)
+                .ok()
+                .map(|plan| plan.completion_time);
+            OrderEvaluation {
+                order: order.clone(),
+                market_value,
+                completion_time,
+                selected: false,
+            }
+        })
+        .collect();
+
+    // Only orders that pay in this currency and fit the budget on their own can help
+    let candidates: Vec<usize> = evaluations
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| reward_in(&e.order) > 0.0 && e.completion_time.is_some_and(|t| t <= time_budget))
+        .map(|(i, _)| i)
+        .collect();
+
+    let sell_only_value = sales_between(0.0, time_budget, &market_rate);
+    // Value of a subset, its plan and its sales while the plan runs
+    let subset_value = |chosen: &[usize]| -> Option<(f64, OrderPlan, f64)> {
+        let selected: Vec<&BoardOrder> = chosen.iter().map(|&i| &orders[i]).collect();
+        let plan = plan_orders(items, &selected, facility_counts, module_levels, options).ok()?;
+        if plan.completion_time > time_budget {
+            return None;
+        }
+        let reward: f64 = selected.iter().map(|o| reward_in(o)).sum();
+        // Seeds are bought with coins
+        let seed_cost = plan.seed_cost * valuation.rate("coins");
+        let sales_during = sales_between(0.0, plan.completion_time, &|efficiencies, sell_rate| {
+            free_rate(&plan, efficiencies, sell_rate)
+        });
+        let value = reward - seed_cost + sales_during + sales_between(plan.completion_time, time_budget, &market_rate);
+        Some((value, plan, sales_during))
+    };
+
+    let mut best: Option<(Vec<usize>, OrderPlan, f64)> = None;
+    let mut best_value = sell_only_value;
+
+    if candidates.len() <= EXHAUSTIVE_BOARD_LIMIT {
+        // Subsets that overrun the budget; their supersets overrun it too
+        let mut infeasible: Vec<u32> = Vec::new();
+        for mask in 1u32..(1u32 << candidates.len()) {
+            if infeasible.iter().any(|m| m & !mask == 0) {
+                continue;
+            }
+            let chosen: Vec<usize> = (0..candidates.len())
+                .filter(|bit| mask & (1 << bit) != 0)
+                .map(|bit| candidates[bit])
+                .collect();
+            let upper_bound: f64 = chosen.iter().map(|&i| reward_in(&orders[i])).sum::<f64>() + sell_only_value;
+            if upper_bound <= best_value {
+                continue;
+            }
+            match subset_value(&chosen) {
+                Some((value, plan, sales_during)) if value > best_value => {
+                    best_value = value;
+                    best = Some((chosen, plan, sales_during));
+                }
+                Some(_) => {}
+                None => infeasible.push(mask),
+            }
+        }
+    } else {
+        // Highest reward per hour of standalone production first
+        let mut ranked = candidates.clone();
+        ranked.sort_by(|&a, &b| {
+            let rate = |i: usize| reward_in(&orders[i]) / evaluations[i].completion_time.unwrap_or(f64::MAX).max(1.0);
+            rate(b).partial_cmp(&rate(a)).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let mut chosen: Vec<usize> = Vec::new();
+        for index in ranked {
+            chosen.push(index);
+            match subset_value(&chosen) {
+                Some((value, plan, sales_during)) if value > best_value => {
+                    best_value = value;
+                    best = Some((chosen.clone(), plan, sales_during));
+                }
+                _ => {
+                    chosen.pop();
+                }
+            }
+        }
+    }
+
+    let mut rewards = CurrencyLedger::default();
+    let (plan, sales_during) = match best {
+        Some((chosen, plan, sales_during)) => {
+            for &i in &chosen {
+                evaluations[i].selected = true;
+                rewards.earn(&orders[i].reward_currency, orders[i].reward);
+            }
+            (Some(plan), sales_during)
+        }
+        None => (None, 0.0),
+    };
+    let completion_time = plan.as_ref().map(|p| p.completion_time).unwrap_or(0.0);
+
+    OrderBoardPlan {
+        evaluations,
+        plan,
+        valuation: valuation.clone(),
+        time_budget,
+        rewards,
+        sales_during,
+        sale_value: sales_between(completion_time, time_budget, &market_rate),
+        sell_only_value,
+    }
+}
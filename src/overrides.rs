@@ -0,0 +1,120 @@
+//! Price and timing overrides for Aniimax.
+//!
+//! Seasonal events and boosts change sell prices, production times and yields
+//! for a while. This module turns override rows into [`ItemOverride`]s and
+//! applies the ones in effect at a given time to the loaded production items,
+//! so efficiencies can be calculated with the event in place.
+
+use crate::models::{
+    FacilityCounts, ItemOverride, OverrideField, OverrideOperation, OverrideRow, OverrideScope, ProductionItem,
+};
+
+/// Builds an override from a CSV row (or an equivalent API input).
+///
+/// Facility names are matched case-insensitively with `_`, `-` or spaces
+/// between words. Start and end times are given in hours and stored in seconds.
+///
+/// # Errors
+///
+/// Returns an error for an unknown scope, facility, field or operation, a
+/// missing target, a negative or non-finite value, a production time of zero,
+/// or a window that ends before it starts.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::{OverrideRow, OverrideScope};
+/// use aniimax::overrides::parse_override_row;
+///
+/// let row = OverrideRow {
+///     scope: "facility".to_string(),
+///     target: "carousel-mill".to_string(),
+///     field: "production_time".to_string(),
+///     operation: "multiply".to_string(),
+///     value: 0.5,
+///     start_hours: None,
+///     end_hours: Some(12.0),
+/// };
+/// let rule = parse_override_row(&row).unwrap();
+/// assert_eq!(rule.scope, OverrideScope::Facility("Carousel Mill".to_string()));
+/// assert_eq!(rule.end, Some(12.0 * 3600.0));
+/// ```
+pub fn parse_override_row(row: &OverrideRow) -> Result<ItemOverride, String> {
+    let target = row.target.trim();
+    let scope = match row.scope.trim() {
+        "global" => OverrideScope::Global,
+        "facility" => {
+            let facility = FacilityCounts::find_facility(target)
+                .ok_or_else(|| format!("Unknown facility '{}' in override", target))?;
+            OverrideScope::Facility(facility.to_string())
+        }
+        "item" if !target.is_empty() => OverrideScope::Item(target.to_string()),
+        "item" => return Err("Item override is missing its item name".to_string()),
+        other => return Err(format!("Unknown override scope '{}'", other)),
+    };
+    let field =
+        OverrideField::parse(&row.field).ok_or_else(|| format!("Unknown override field '{}'", row.field.trim()))?;
+
+    if !row.value.is_finite() || row.value < 0.0 {
+        return Err(format!("Invalid override value {} for {}", row.value, field.name()));
+    }
+    if field == OverrideField::ProductionTime && row.value == 0.0 {
+        return Err("Production time can't be overridden to zero".to_string());
+    }
+    let operation = match row.operation.trim() {
+        "multiply" => OverrideOperation::Multiply(row.value),
+        "set" => OverrideOperation::Set(row.value),
+        other => return Err(format!("Unknown override operation '{}'", other)),
+    };
+
+    let start = row.start_hours.map(|h| h * 3600.0);
+    let end = row.end_hours.map(|h| h * 3600.0);
+    if start.into_iter().chain(end).any(|t| !t.is_finite()) {
+        return Err("Override times must be finite".to_string());
+    }
+    if let (Some(start), Some(end)) = (start, end) {
+        if end <= start {
+            return Err(format!("Override for {} ends before it starts", field.name()));
+        }
+    }
+
+    Ok(ItemOverride {
+        scope,
+        field,
+        operation,
+        start,
+        end,
+    })
+}
+
+/// Checks that every item override names an item that exists.
+///
+/// Returns an error naming the first override whose item doesn't exist.
+pub fn check_override_targets(items: &[ProductionItem], overrides: &[ItemOverride]) -> Result<(), String> {
+    for rule in overrides {
+        if let OverrideScope::Item(ref name) = rule.scope {
+            if !items.iter().any(|i| i.name == *name) {
+                return Err(format!("Override given for unknown item '{}'", name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the items as they are at `time` (seconds from plan start), with
+/// every override in effect at that time applied in order.
+///
+/// Returns modified copies; the inputs are left untouched.
+pub fn apply_overrides(items: &[ProductionItem], overrides: &[ItemOverride], time: f64) -> Vec<ProductionItem> {
+    let active: Vec<&ItemOverride> = overrides.iter().filter(|o| o.is_active_at(time)).collect();
+    items
+        .iter()
+        .map(|item| {
+            let mut changed = item.clone();
+            for rule in active.iter().filter(|o| o.applies_to(item)) {
+                rule.apply(&mut changed);
+            }
+            changed
+        })
+        .collect()
+}
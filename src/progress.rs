@@ -0,0 +1,175 @@
+//! Batches already in progress for Aniimax.
+//!
+//! Plans normally start from idle facilities. When some plots are still
+//! mid-growth, this module describes those batches so the planners can keep
+//! their facilities busy until they finish and count what they yield.
+//!
+//! The batch-level schedule ([`crate::schedule::simulate_batches`]) holds the
+//! facilities and delays the output; the path finders pick that up as extra
+//! time, the same way they account for storage limits.
+
+use std::collections::HashMap;
+
+use crate::models::{CurrencyValuation, FacilityCounts, InProgressBatch, Inventory, ProductionItem};
+use crate::optimizer::items_at_levels;
+
+/// Parses a duration such as `1h30m`, `45m`, `90s` or `1.5` (hours) into
+/// seconds.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::progress::parse_duration;
+///
+/// assert_eq!(parse_duration("1h30m").unwrap(), 5400.0);
+/// assert_eq!(parse_duration("45m").unwrap(), 2700.0);
+/// assert_eq!(parse_duration("1.5").unwrap(), 5400.0);
+/// assert!(parse_duration("soon").is_err());
+/// ```
+pub fn parse_duration(text: &str) -> Result<f64, String> {
+    let text = text.trim();
+    let invalid = || format!("Invalid duration '{}' (e.g. \"1h30m\", \"45m\" or \"1.5\" hours)", text);
+    if let Ok(hours) = text.parse::<f64>() {
+        return if hours >= 0.0 { Ok(hours * 3600.0) } else { Err(invalid()) };
+    }
+
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    for c in text.chars() {
+        let unit = match c {
+            'h' => 3600.0,
+            'm' => 60.0,
+            's' => 1.0,
+            _ => {
+                number.push(c);
+                continue;
+            }
+        };
+        let amount: f64 = number.trim().parse().map_err(|_| invalid())?;
+        if amount < 0.0 {
+            return Err(invalid());
+        }
+        seconds += amount * unit;
+        number.clear();
+    }
+    if text.is_empty() || !number.trim().is_empty() {
+        return Err(invalid());
+    }
+    Ok(seconds)
+}
+
+/// Parses in-progress batches such as `wheat*4=45m,wooden_sculpture=2h`.
+///
+/// Each entry names the item being produced, optionally how many facilities
+/// are running a batch of it (`*4`, default 1), and the time until those
+/// batches finish (see [`parse_duration`]). The entries are then resolved
+/// with [`in_progress_batches`].
+///
+/// # Errors
+///
+/// Returns an error if an entry is malformed or can't be resolved.
+pub fn parse_in_progress(
+    spec: &str,
+    items: &[ProductionItem],
+    facility_counts: &FacilityCounts,
+) -> Result<Vec<InProgressBatch>, String> {
+    let entries = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|entry| {
+            let (item, time) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected ITEM=TIME or ITEM*COUNT=TIME in in-progress entry '{}'", entry))?;
+            let (item, facilities) = match item.split_once('*') {
+                Some((item, count)) => {
+                    let count: u32 = count
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid facility count in in-progress entry '{}'", entry))?;
+                    (item.trim(), count)
+                }
+                None => (item.trim(), 1),
+            };
+            Ok((item.to_string(), facilities, parse_duration(time)?))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    in_progress_batches(&entries, items, facility_counts)
+}
+
+/// Resolves in-progress entries given as (item, facilities running a batch,
+/// seconds until they finish).
+///
+/// Yields are taken at the level of the facilities that would produce the
+/// item (see [`items_at_levels`]). Output that some recipe uses, or
+/// fertilizer, is stocked; anything else is sold.
+///
+/// # Errors
+///
+/// Returns an error if an item is unknown or more facilities are running
+/// batches than exist.
+pub fn in_progress_batches(
+    entries: &[(String, u32, f64)],
+    items: &[ProductionItem],
+    facility_counts: &FacilityCounts,
+) -> Result<Vec<InProgressBatch>, String> {
+    let leveled = items_at_levels(items, facility_counts);
+    let mut batches = Vec::new();
+    let mut busy: HashMap<String, u32> = HashMap::new();
+
+    for (item, facilities, remaining) in entries {
+        let (item, facilities) = (item.as_str(), *facilities);
+        if facilities == 0 {
+            continue;
+        }
+        let produced = leveled
+            .iter()
+            .find(|i| i.name == item)
+            .ok_or_else(|| format!("Unknown in-progress item '{}'", item))?;
+
+        let running = busy.entry(produced.facility.clone()).or_insert(0);
+        *running += facilities;
+        let available = facility_counts.get_count(&produced.facility);
+        if *running > available {
+            return Err(format!(
+                "{} {} are running batches, but only {} exist",
+                running, produced.facility, available
+            ));
+        }
+
+        let name = item.strip_prefix("high_speed_").unwrap_or(item).to_string();
+        let used = name == "fertilizer"
+            || items
+                .iter()
+                .any(|i| i.raw_materials.as_ref().is_some_and(|raw| raw.contains(&name)));
+        batches.push(InProgressBatch {
+            item: item.to_string(),
+            name,
+            facility: produced.facility.clone(),
+            facilities,
+            remaining: remaining.max(0.0),
+            units: facilities * produced.yield_amount,
+            sold: !used,
+        });
+    }
+    Ok(batches)
+}
+
+/// Adds the stocked output of in-progress batches to the inventory and
+/// returns what the sold output is worth, in the valuation's base currency.
+pub fn credit_in_progress(
+    batches: &[InProgressBatch],
+    items: &[ProductionItem],
+    valuation: &CurrencyValuation,
+    inventory: &mut Inventory,
+) -> f64 {
+    let mut value = 0.0;
+    for batch in batches {
+        if !batch.sold {
+            inventory.add(&batch.name, batch.units);
+        } else if let Some(item) = items.iter().find(|i| i.name == batch.item) {
+            value += batch.units as f64 * item.sell_value * valuation.rate(&item.sell_currency);
+        }
+    }
+    value
+}
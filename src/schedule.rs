@@ -0,0 +1,494 @@
+//! Batch-level production scheduling for Aniimax.
+//!
+//! The path finders estimate times from steady-state rates. This module
+//! instead simulates production one batch at a time on the available
+//! facilities, which gives exact completion times for orders and lets plans
+//! respect a warehouse capacity and a coin balance.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::models::{
+    BatchJob, FacilityCounts, InProgressBatch, InventorySeries, ScheduleOutcome, ScheduledItem, StorageCapacity,
+};
+
+/// Units held in storage, in total and per category.
+#[derive(Default)]
+struct StorageLevels {
+    total: u32,
+    per_category: HashMap<&'static str, u32>,
+}
+
+impl StorageLevels {
+    fn add(&mut self, category: &'static str, amount: u32) {
+        self.total += amount;
+        *self.per_category.entry(category).or_insert(0) += amount;
+    }
+
+    fn remove(&mut self, category: &'static str, amount: u32) {
+        self.total = self.total.saturating_sub(amount);
+        if let Some(held) = self.per_category.get_mut(category) {
+            *held = held.saturating_sub(amount);
+        }
+    }
+
+    /// Whether `amount` more units of a category fit once `freed` units
+    /// (of which `freed_in_category` are in the same category) have left.
+    fn fits(
+        &self,
+        storage: &StorageCapacity,
+        category: &'static str,
+        amount: u32,
+        freed: u32,
+        freed_in_category: u32,
+    ) -> bool {
+        let total_fits = storage
+            .total
+            .is_none_or(|cap| self.total.saturating_sub(freed) + amount <= cap);
+        let category_fits = storage.limit_for(category).is_none_or(|cap| {
+            let held = self.per_category.get(category).copied().unwrap_or(0);
+            held.saturating_sub(freed_in_category) + amount <= cap
+        });
+        total_fits && category_fits
+    }
+}
+
+/// Units of every item held after each change, in order of first mention.
+#[derive(Default)]
+struct LevelLog {
+    series: Vec<(String, Vec<(f64, u32)>)>,
+}
+
+impl LevelLog {
+    /// Records the units of an item held at the start, unless it is
+    /// tracked already.
+    fn start(&mut self, name: &str, units: u32) {
+        if !self.series.iter().any(|(n, _)| n == name) {
+            self.series.push((name.to_string(), vec![(0.0, units)]));
+        }
+    }
+
+    /// Records that `units` of an item are held from `time` on; several
+    /// changes at the same time keep only the last, after the starting
+    /// level.
+    fn record(&mut self, name: &str, time: f64, units: u32) {
+        self.start(name, 0);
+        let Some((_, points)) = self.series.iter_mut().find(|(n, _)| n == name) else {
+            return;
+        };
+        let changed_now = points.len() > 1;
+        match points.last_mut() {
+            Some(last) if changed_now && (last.0 - time).abs() < 1e-9 => last.1 = units,
+            _ => points.push((time, units)),
+        }
+    }
+
+    fn into_series(self) -> Vec<InventorySeries> {
+        self.series
+            .into_iter()
+            .map(|(name, points)| InventorySeries::from_points(&name, points))
+            .collect()
+    }
+}
+
+/// Batches finishing together: (time, job, batches, level group they run on,
+/// yield of each). Bit patterns of non-negative float times sort like the
+/// floats; jobs past the simulated ones are batches already in progress.
+type Completion = (u64, usize, u32, usize, u32);
+
+/// A job being simulated.
+struct JobState<'a> {
+    job: &'a BatchJob,
+    category: &'static str,
+    remaining: u32,
+    start_time: Option<f64>,
+    finish_time: f64,
+    sales: f64,
+}
+
+impl JobState<'_> {
+    /// Priority when several jobs compete for a facility: the work left on
+    /// this item plus the processing that still has to follow it.
+    fn priority(&self) -> f64 {
+        self.remaining as f64 * self.job.production_time + self.job.tail_time
+    }
+
+    /// Batch time and yield of a batch started at `time` on a facility of
+    /// `level`, or `None` if it can't make the item.
+    fn stats_at(&self, level: u32, time: f64) -> Option<(f64, u32)> {
+        let (production_time, yield_amount, level_stats) = self.job.stats_at(time);
+        if level_stats.is_empty() {
+            return Some((production_time, yield_amount));
+        }
+        level_stats
+            .iter()
+            .find(|s| s.0 == level)
+            .map(|&(_, time, amount)| (time, amount))
+    }
+
+    /// The highest of the idle (level, count) groups of facilities that can
+    /// make a batch started at `time`, with its batch time and yield there.
+    fn group_for(&self, groups: &[(u32, u32)], time: f64) -> Option<(usize, f64, u32)> {
+        groups
+            .iter()
+            .enumerate()
+            .filter(|(_, g)| g.1 > 0)
+            .find_map(|(i, g)| self.stats_at(g.0, time).map(|(time, amount)| (i, time, amount)))
+    }
+}
+
+/// Simulates a set of jobs batch by batch.
+///
+/// Whenever a facility is idle it starts the highest-priority batch whose
+/// ingredients are in stock, so processing starts as soon as the first
+/// ingredients arrive and competing crops share the plots. Ingredients are
+/// taken from storage when a batch starts and its output is stored when it
+/// finishes (unless the job is sold).
+///
+/// With a storage limit, a batch only starts if its output is sure to fit
+/// (space is reserved until it finishes), and an ingredient is not gathered
+/// beyond an even share of the capacity (or a full round of its consumers,
+/// if that's more). Gathering and processing therefore interleave so stock
+/// never exceeds capacity and no ingredient crowds out the others.
+///
+/// With `starting_coins`, a batch only starts once its seeds can be paid
+/// for, and sold batches add their sale value when they finish, so early
+/// sales pay for later plantings. The balance after every change is
+/// recorded in the outcome.
+///
+/// The outcome also tracks the stock of every item over time: ingredients
+/// and fertilizer as they are stored and used, and sold items as the units
+/// made so far.
+///
+/// Batches of a job started together on several facilities finish together,
+/// so they are tracked as one event, which keeps large plans on many
+/// facilities quick to simulate.
+///
+/// When a facility type has facilities at different levels, each batch is
+/// made at the time and yield of the facility it runs on (see
+/// [`BatchJob::level_stats`]), the highest idle level able to make it first.
+/// Where overrides change a job's stats (see [`BatchJob::timed_stats`]), a
+/// batch takes the time and yield in effect when it starts and sells at the
+/// price in effect when it finishes.
+///
+/// Facilities running `in_progress` batches stay busy until those finish.
+/// Their stocked output is expected in `initial_stock` (as it is in the
+/// planning inventory), but only arrives when they finish.
+///
+/// # Errors
+///
+/// Returns an error if some batches can never start, e.g. because storage is
+/// too small to hold the inputs of a single batch or the coins run out. Batches are started
+/// greedily, so a capacity with very little slack may be rejected even
+/// though a more careful order would fit.
+pub fn simulate_batches(
+    jobs: &[BatchJob],
+    initial_stock: &HashMap<String, u32>,
+    facility_counts: &FacilityCounts,
+    in_progress: &[InProgressBatch],
+    storage: &StorageCapacity,
+    starting_coins: Option<f64>,
+) -> Result<ScheduleOutcome, String> {
+    let mut states: Vec<JobState> = jobs
+        .iter()
+        .map(|job| JobState {
+            job,
+            category: StorageCapacity::category(&job.facility),
+            remaining: job.batches,
+            start_time: None,
+            finish_time: 0.0,
+            sales: 0.0,
+        })
+        .collect();
+
+    let category_of = |name: &str| {
+        jobs.iter()
+            .find(|j| j.name == name)
+            .map(|j| StorageCapacity::category(&j.facility))
+            .unwrap_or("goods")
+    };
+
+    let mut stock: HashMap<String, u32> = HashMap::new();
+    // Stock plus the output of running batches, which is counted against capacity until it arrives
+    let mut committed = StorageLevels::default();
+    let mut held_total: u32 = 0;
+    for (name, &amount) in initial_stock {
+        *stock.entry(name.clone()).or_insert(0) += amount;
+        committed.add(category_of(name), amount);
+        held_total += amount;
+    }
+
+    // Stock still being produced arrives later; its space is taken already
+    let mut incoming: HashMap<String, u32> = HashMap::new();
+    let mut arrivals: Vec<u32> = Vec::with_capacity(in_progress.len());
+    for batch in in_progress {
+        let arriving = if batch.sold {
+            0
+        } else {
+            let held = stock.entry(batch.name.clone()).or_insert(0);
+            let arriving = batch.units.min(*held);
+            *held -= arriving;
+            held_total -= arriving;
+            *incoming.entry(batch.name.clone()).or_insert(0) += arriving;
+            arriving
+        };
+        arrivals.push(arriving);
+    }
+    let mut peak_inventory = held_total;
+
+    let mut levels = LevelLog::default();
+    for job in jobs.iter().filter(|j| j.batches > 0) {
+        levels.start(&job.name, stock.get(&job.name).copied().unwrap_or(0));
+    }
+    let mut stocked: Vec<(&String, &u32)> = stock.iter().collect();
+    stocked.sort();
+    for (name, &units) in stocked {
+        levels.start(name, units);
+    }
+    let mut made: HashMap<&str, u32> = HashMap::new();
+    let mut coins = starting_coins.unwrap_or(0.0);
+    let mut balance: Vec<(f64, f64)> = starting_coins.map(|c| vec![(0.0, c)]).unwrap_or_default();
+
+    // Units consumed by one round of every facility that uses an ingredient
+    let mut round_demand: HashMap<&str, u32> = HashMap::new();
+    for job in jobs {
+        for (name, amount) in &job.inputs {
+            *round_demand.entry(name.as_str()).or_insert(0) +=
+                amount * facility_counts.get_count(&job.facility).max(1);
+        }
+    }
+
+    // Each stored item may take an even share of its limits, which leaves room for the others
+    let mut stored: Vec<&str> = jobs.iter().filter(|j| !j.sold).map(|j| j.name.as_str()).collect();
+    stored.sort();
+    stored.dedup();
+    let share_of = |name: &str| -> u32 {
+        let category = category_of(name);
+        let in_category = stored.iter().filter(|n| category_of(n) == category).count() as u32;
+        let total_share = storage.total.map(|cap| cap / (stored.len() as u32).max(1));
+        let category_share = storage.limit_for(category).map(|cap| cap / in_category.max(1));
+        match (total_share, category_share) {
+            (Some(a), Some(b)) => a.min(b),
+            (a, b) => a.or(b).unwrap_or(u32::MAX),
+        }
+    };
+
+    let mut facilities: Vec<&str> = jobs.iter().filter(|j| j.batches > 0).map(|j| j.facility.as_str()).collect();
+    facilities.sort();
+    facilities.dedup();
+    // Idle facilities of each type as (level, count), highest level first
+    let mut idle: HashMap<&str, Vec<(u32, u32)>> = facilities
+        .iter()
+        .map(|&f| {
+            let mut groups: Vec<(u32, u32)> = Vec::new();
+            for level in facility_counts.instance_levels(f) {
+                match groups.last_mut() {
+                    Some((l, count)) if *l == level => *count += 1,
+                    _ => groups.push((level, 1)),
+                }
+            }
+            (f, groups)
+        })
+        .collect();
+
+    let mut events: BinaryHeap<Reverse<Completion>> = BinaryHeap::new();
+    let mut held_back: Vec<Vec<(usize, u32)>> = Vec::with_capacity(in_progress.len());
+    for (i, batch) in in_progress.iter().enumerate() {
+        let mut busy = Vec::new();
+        if let Some(groups) = idle.get_mut(batch.facility.as_str()) {
+            let mut left = batch.facilities;
+            for (group, (_, count)) in groups.iter_mut().enumerate() {
+                let taken = (*count).min(left);
+                if taken > 0 {
+                    *count -= taken;
+                    left -= taken;
+                    busy.push((group, taken));
+                }
+            }
+        }
+        held_back.push(busy);
+        events.push(Reverse((batch.remaining.max(0.0).to_bits(), states.len() + i, 1, 0, 0)));
+    }
+    let mut now = 0.0;
+    let mut completion_time: f64 = 0.0;
+
+    loop {
+        for &facility in &facilities {
+            // Batches of one job started in a row on one level, finishing together
+            let mut wave: Option<(usize, usize, u32, f64, u32)> = None;
+            while idle[facility].iter().any(|g| g.1 > 0) {
+                let groups = &idle[facility];
+                let next = states
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, s)| s.remaining > 0 && s.job.facility == facility)
+                    .filter_map(|(i, s)| s.group_for(groups, now).map(|group| (i, s, group)))
+                    .filter(|(_, s, _)| {
+                        s.job
+                            .inputs
+                            .iter()
+                            .all(|(n, a)| stock.get(n).copied().unwrap_or(0) >= *a)
+                    })
+                    .filter(|(_, s, _)| starting_coins.is_none() || s.job.seed_cost <= coins + 1e-9)
+                    .filter(|&(_, s, (_, _, amount))| {
+                        if s.job.sold || storage.is_unlimited() {
+                            return true;
+                        }
+                        // Don't stock an ingredient beyond its share, or a round of its consumers if that's more
+                        let ahead = stock.get(&s.job.name).copied().unwrap_or(0)
+                            + incoming.get(&s.job.name).copied().unwrap_or(0);
+                        let demand = round_demand.get(s.job.name.as_str()).copied().unwrap_or(0);
+                        if demand > 0 && ahead >= demand.max(share_of(&s.job.name)) {
+                            return false;
+                        }
+                        // Inputs leave storage as the batch starts
+                        let freed: u32 = s.job.inputs.iter().map(|(_, a)| a).sum();
+                        let freed_in_category: u32 = s
+                            .job
+                            .inputs
+                            .iter()
+                            .filter(|(n, _)| category_of(n) == s.category)
+                            .map(|(_, a)| a)
+                            .sum();
+                        committed.fits(storage, s.category, amount, freed, freed_in_category)
+                    })
+                    .max_by(|(_, a, _), (_, b, _)| {
+                        a.priority()
+                            .partial_cmp(&b.priority())
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    })
+                    .map(|(i, _, group)| (i, group));
+                let Some((index, (group, time, amount))) = next else {
+                    break;
+                };
+
+                let state = &mut states[index];
+                for (name, amount) in &state.job.inputs {
+                    if let Some(units) = stock.get_mut(name) {
+                        *units -= amount;
+                        committed.remove(category_of(name), *amount);
+                        held_total -= amount;
+                        levels.record(name, now, *units);
+                    }
+                }
+                if !state.job.sold {
+                    committed.add(state.category, amount);
+                    *incoming.entry(state.job.name.clone()).or_insert(0) += amount;
+                }
+                if starting_coins.is_some() && state.job.seed_cost > 0.0 {
+                    coins -= state.job.seed_cost;
+                    let started_now = balance.len() > 1 && balance.last().is_some_and(|last| last.0 == now);
+                    if started_now {
+                        balance.pop();
+                    }
+                    balance.push((now, coins));
+                }
+                state.remaining -= 1;
+                state.start_time.get_or_insert(now);
+                wave = match wave {
+                    Some((same, on, batches, _, _)) if same == index && on == group => {
+                        Some((index, group, batches + 1, time, amount))
+                    }
+                    other => {
+                        if let Some((started, on, batches, time, amount)) = other {
+                            events.push(Reverse(((now + time).to_bits(), started, batches, on, amount)));
+                        }
+                        Some((index, group, 1, time, amount))
+                    }
+                };
+                if let Some(groups) = idle.get_mut(facility) {
+                    groups[group].1 -= 1;
+                }
+            }
+            if let Some((started, on, batches, time, amount)) = wave {
+                events.push(Reverse(((now + time).to_bits(), started, batches, on, amount)));
+            }
+        }
+
+        let Some(Reverse((time_bits, index, batches, group, per_batch))) = events.pop() else {
+            break;
+        };
+        now = f64::from_bits(time_bits);
+        if index >= states.len() {
+            let batch = &in_progress[index - states.len()];
+            let arriving = arrivals[index - states.len()];
+            if let Some(units) = incoming.get_mut(&batch.name) {
+                *units -= arriving;
+            }
+            let units = stock.entry(batch.name.clone()).or_insert(0);
+            *units += arriving;
+            if arriving > 0 {
+                levels.record(&batch.name, now, *units);
+            }
+            held_total += arriving;
+            peak_inventory = peak_inventory.max(held_total);
+            if let Some(groups) = idle.get_mut(batch.facility.as_str()) {
+                for &(held, count) in &held_back[index - states.len()] {
+                    groups[held].1 += count;
+                }
+            }
+            continue;
+        }
+        completion_time = now;
+        let state = &mut states[index];
+        state.finish_time = now;
+        let Some(groups) = idle.get_mut(state.job.facility.as_str()) else {
+            continue;
+        };
+        groups[group].1 += batches;
+        if !state.job.sold {
+            let amount = per_batch * batches;
+            if let Some(units) = incoming.get_mut(&state.job.name) {
+                *units -= amount;
+            }
+            let units = stock.entry(state.job.name.clone()).or_insert(0);
+            *units += amount;
+            levels.record(&state.job.name, now, *units);
+            held_total += amount;
+            peak_inventory = peak_inventory.max(held_total);
+        } else {
+            let units = made.entry(state.job.name.as_str()).or_insert(0);
+            *units += per_batch * batches;
+            levels.record(&state.job.name, now, *units);
+            // Facilities of other levels, and overrides, change what a batch yields and sells for
+            let scale = per_batch as f64 / state.job.yield_amount.max(1) as f64 * state.job.price_factor_at(now);
+            state.sales += scale * batches as f64;
+            if starting_coins.is_some() && state.job.sale_value > 0.0 {
+                coins += state.job.sale_value * scale * batches as f64;
+                balance.push((now, coins));
+            }
+        }
+    }
+
+    if let Some(unaffordable) = states
+        .iter()
+        .find(|s| s.remaining > 0 && starting_coins.is_some() && s.job.seed_cost > coins)
+    {
+        return Err(format!("Not enough coins to plant {}", unaffordable.job.variant));
+    }
+    if let Some(stuck) = states.iter().find(|s| s.remaining > 0) {
+        return Err(if storage.is_unlimited() {
+            format!("Could not schedule {}", stuck.job.variant)
+        } else {
+            format!("Storage capacity is too small to produce {}", stuck.job.variant)
+        });
+    }
+
+    Ok(ScheduleOutcome {
+        items: states
+            .iter()
+            .map(|s| ScheduledItem {
+                name: s.job.variant.clone(),
+                facility: s.job.facility.clone(),
+                batches: s.job.batches,
+                start_time: s.start_time.unwrap_or(0.0),
+                finish_time: s.finish_time,
+                sales: s.sales,
+            })
+            .collect(),
+        completion_time,
+        peak_inventory,
+        balance,
+        stock_levels: levels.into_series(),
+    })
+}
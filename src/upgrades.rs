@@ -0,0 +1,732 @@
+//! Upgrade planning for Aniimax.
+//!
+//! This module evaluates facility levels, extra facility slots and module
+//! levels as purchases: it applies upgrades to a configuration and works out
+//! how long each one takes to pay for itself, and plans multi-step
+//! earn-and-upgrade progressions.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::models::{
+    CurrencyBalance, FacilityCounts, ItemOverride, ModuleLevels, ProductionEfficiency, ProductionItem, ProductionPath,
+    ProgressionGoal, ProgressionMilestone, ProgressionPlan, UpgradeCost, UpgradeKind, UpgradeRoi,
+};
+use crate::optimizer::{
+    best_profit_rate, calculate_efficiencies, find_best_production_path,
+    find_parallel_production_path,
+};
+use crate::overrides::apply_overrides;
+
+/// Applies an upgrade to a facility and module configuration.
+///
+/// Returns the upgraded copies; the inputs are left untouched.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::{FacilityCounts, ModuleLevels, UpgradeCost, UpgradeKind};
+/// use aniimax::upgrades::apply_upgrade;
+///
+/// let counts = FacilityCounts {
+///     farmland: (4, 3),
+///     woodland: (1, 1),
+///     mineral_pile: (1, 1),
+///     carousel_mill: (1, 1),
+///     jukebox_dryer: (1, 1),
+///     crafting_table: (1, 1),
+///     dance_pad_polisher: (1, 1),
+///     aniipod_maker: (1, 1),
+///     nimbus_bed: (0, 1),
+///     instance_levels: Default::default(),
+/// };
+/// let upgrade = UpgradeCost {
+///     kind: UpgradeKind::FacilitySlot,
+///     target: "Farmland".to_string(),
+///     level: 5,
+///     coins: 2000.0,
+///     coupons: 0.0,
+/// };
+///
+/// let (upgraded, _) = apply_upgrade(&upgrade, &counts, &ModuleLevels::default());
+/// assert_eq!(upgraded.get_count("Farmland"), 5);
+/// ```
+pub fn apply_upgrade(
+    upgrade: &UpgradeCost,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+) -> (FacilityCounts, ModuleLevels) {
+    let mut counts = facility_counts.clone();
+    let mut modules = module_levels.clone();
+
+    match upgrade.kind {
+        UpgradeKind::FacilityLevel => counts.set_level(&upgrade.target, upgrade.level),
+        UpgradeKind::FacilitySlot => counts.set_count(&upgrade.target, upgrade.level),
+        UpgradeKind::Module => modules.set_level(&upgrade.target, upgrade.level),
+    }
+
+    (counts, modules)
+}
+
+/// Returns the upgrades that can be bought next from the current configuration.
+///
+/// An upgrade is "next" when it raises its facility level, facility count or
+/// module level by exactly one step.
+pub fn next_upgrades<'a>(
+    upgrades: &'a [UpgradeCost],
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+) -> Vec<&'a UpgradeCost> {
+    upgrades
+        .iter()
+        .filter(|u| {
+            let current = match u.kind {
+                UpgradeKind::FacilityLevel => facility_counts.get_level(&u.target),
+                UpgradeKind::FacilitySlot => facility_counts.get_count(&u.target),
+                UpgradeKind::Module => module_levels.get_level(&u.target),
+            };
+            u.level == current + 1
+        })
+        .collect()
+}
+
+/// Returns the next steps that have no row in the upgrade price list.
+///
+/// Every facility level, facility slot and module is checked, so targets the
+/// price list doesn't cover can be reported rather than silently left out of
+/// the rankings. The returned upgrades carry zero prices.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::{FacilityCounts, ModuleLevels, UpgradeCost, UpgradeKind};
+/// use aniimax::upgrades::unpriced_upgrades;
+///
+/// let counts = FacilityCounts {
+///     farmland: (4, 3),
+///     woodland: (1, 1),
+///     mineral_pile: (1, 1),
+///     carousel_mill: (1, 1),
+///     jukebox_dryer: (1, 1),
+///     crafting_table: (1, 1),
+///     dance_pad_polisher: (1, 1),
+///     aniipod_maker: (1, 1),
+///     nimbus_bed: (1, 1),
+///     instance_levels: Default::default(),
+/// };
+/// let prices = [UpgradeCost {
+///     kind: UpgradeKind::FacilityLevel,
+///     target: "Farmland".to_string(),
+///     level: 4,
+///     coins: 18000.0,
+///     coupons: 0.0,
+/// }];
+///
+/// let missing: Vec<String> = unpriced_upgrades(&prices, &counts, &ModuleLevels::default())
+///     .iter()
+///     .map(|u| u.describe())
+///     .collect();
+/// assert!(missing.contains(&"Nimbus Bed Lv.2".to_string()));
+/// assert!(!missing.contains(&"Farmland Lv.4".to_string()));
+/// ```
+pub fn unpriced_upgrades(
+    upgrades: &[UpgradeCost],
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+) -> Vec<UpgradeCost> {
+    let facility_steps = FacilityCounts::FACILITY_NAMES.iter().flat_map(|&facility| {
+        [
+            (UpgradeKind::FacilityLevel, facility, facility_counts.get_level(facility)),
+            (UpgradeKind::FacilitySlot, facility, facility_counts.get_count(facility)),
+        ]
+    });
+    let module_steps = ModuleLevels::MODULE_NAMES
+        .iter()
+        .map(|&module| (UpgradeKind::Module, module, module_levels.get_level(module)));
+
+    facility_steps
+        .chain(module_steps)
+        .filter(|(kind, target, current)| {
+            !upgrades
+                .iter()
+                .any(|u| u.kind == *kind && u.target == *target && u.level == current + 1)
+        })
+        .map(|(kind, target, current)| UpgradeCost {
+            kind,
+            target: target.to_string(),
+            level: current + 1,
+            coins: 0.0,
+            coupons: 0.0,
+        })
+        .collect()
+}
+
+/// Calculates the return on investment of every next-step upgrade.
+///
+/// For each upgrade returned by [`next_upgrades`], the best steady-state profit
+/// rate (see [`best_profit_rate`]) is computed for the current and the upgraded
+/// configuration. The payback time is the upgrade's price in the target
+/// currency divided by the gain in profit per second. Prices in other
+/// currencies can't be repaid by that profit and are left to the caller to
+/// weigh (they remain available on [`UpgradeRoi::upgrade`]).
+///
+/// # Arguments
+///
+/// * `items` - All available production items
+/// * `target_currency` - The currency profit is measured in ("coins" or "coupons")
+/// * `facility_counts` - Current facility configuration
+/// * `module_levels` - Current module levels
+/// * `upgrades` - Upgrade price list
+/// * `balance` - Optional balance used to flag affordable upgrades
+///
+/// # Returns
+///
+/// A vector of [`UpgradeRoi`] sorted by payback time (fastest first), then
+/// upgrades priced only in another currency (no payback time) by the profit
+/// they add; upgrades that don't increase profit are listed last.
+pub fn calculate_upgrade_roi(
+    items: &[ProductionItem],
+    target_currency: &str,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+    upgrades: &[UpgradeCost],
+    balance: Option<&CurrencyBalance>,
+) -> Vec<UpgradeRoi> {
+    let current_effs = calculate_efficiencies(items, target_currency, facility_counts, module_levels);
+    let current_rate = best_profit_rate(&current_effs, facility_counts);
+
+    let mut results: Vec<UpgradeRoi> = next_upgrades(upgrades, facility_counts, module_levels)
+        .into_iter()
+        .map(|upgrade| {
+            let (counts, modules) = apply_upgrade(upgrade, facility_counts, module_levels);
+            let upgraded_effs = calculate_efficiencies(items, target_currency, &counts, &modules);
+            let upgraded_rate = best_profit_rate(&upgraded_effs, &counts);
+
+            // Profit in the target currency can't repay a price in the other one
+            let gain = upgraded_rate - current_rate;
+            let price = upgrade.cost_in(target_currency);
+            let free = upgrade.coins + upgrade.coupons <= 0.0;
+            let payback_time = if gain > 1e-9 && (price > 0.0 || free) {
+                Some(price / gain)
+            } else {
+                None
+            };
+
+            UpgradeRoi {
+                upgrade: upgrade.clone(),
+                current_profit_per_second: current_rate,
+                upgraded_profit_per_second: upgraded_rate,
+                payback_time,
+                affordable: balance.map(|b| b.can_afford(upgrade)).unwrap_or(true),
+            }
+        })
+        .collect();
+
+    // Upgrades that pay for themselves first, then those priced in the other
+    // currency by the profit they add, then those adding nothing
+    let gain = |roi: &UpgradeRoi| roi.upgraded_profit_per_second - roi.current_profit_per_second;
+    results.sort_by(|a, b| match (a.payback_time, b.payback_time) {
+        (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => gain(b).partial_cmp(&gain(a)).unwrap_or(std::cmp::Ordering::Equal),
+    });
+
+    results
+}
+
+/// Upper bound on purchases in a plan without a configuration goal or horizon.
+const MAX_MILESTONES: usize = 100;
+
+/// Goals needing at most this many purchases have every ordering searched;
+/// longer goals fall back to the greedy choice.
+const EXHAUSTIVE_SEARCH_LIMIT: usize = 8;
+
+/// Currencies upgrades can be priced in.
+const CURRENCIES: [&str; 2] = ["coins", "coupons"];
+
+/// Finds the fastest way to earn an amount of currency with a configuration.
+///
+/// Compares the single-item and cross-facility parallel planners and returns
+/// whichever finishes first, or `None` if the currency can't be produced.
+pub fn find_fastest_path(
+    items: &[ProductionItem],
+    currency: &str,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+    target_amount: f64,
+) -> Option<ProductionPath> {
+    let efficiencies = calculate_efficiencies(items, currency, facility_counts, module_levels);
+    fastest_path_from(&efficiencies, facility_counts, target_amount)
+}
+
+/// [`find_fastest_path`] with the efficiencies already calculated.
+fn fastest_path_from(
+    efficiencies: &[ProductionEfficiency],
+    facility_counts: &FacilityCounts,
+    target_amount: f64,
+) -> Option<ProductionPath> {
+    let single = find_best_production_path(efficiencies, target_amount, false, 0.0, facility_counts);
+    let parallel = find_parallel_production_path(efficiencies, target_amount, facility_counts);
+
+    match (parallel, single) {
+        (Some(p), Some(s)) => Some(if p.total_time <= s.total_time { p } else { s }),
+        (p, s) => p.or(s),
+    }
+}
+
+/// Production items and the overrides that change them over time.
+///
+/// The ordering search reaches the same configurations many times, so the
+/// efficiencies of every configuration are calculated once per set of
+/// overrides in effect.
+struct Catalog<'a> {
+    items: &'a [ProductionItem],
+    overrides: &'a [ItemOverride],
+    efficiencies: RefCell<HashMap<String, Rc<Vec<ProductionEfficiency>>>>,
+}
+
+impl<'a> Catalog<'a> {
+    fn new(items: &'a [ProductionItem], overrides: &'a [ItemOverride]) -> Self {
+        Catalog {
+            items,
+            overrides,
+            efficiencies: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Efficiencies of a configuration in `currency`, with the items as
+    /// they are at `time` (seconds from plan start).
+    fn efficiencies(&self, currency: &str, state: &PlannerState, time: f64) -> Rc<Vec<ProductionEfficiency>> {
+        let active: String = self
+            .overrides
+            .iter()
+            .map(|o| if o.is_active_at(time) { '1' } else { '0' })
+            .collect();
+        let key = format!("{}|{:?}|{:?}|{}", currency, state.counts, state.modules, active);
+        if let Some(cached) = self.efficiencies.borrow().get(&key) {
+            return Rc::clone(cached);
+        }
+        let items = apply_overrides(self.items, self.overrides, time);
+        let efficiencies = Rc::new(calculate_efficiencies(&items, currency, &state.counts, &state.modules));
+        self.efficiencies.borrow_mut().insert(key, Rc::clone(&efficiencies));
+        efficiencies
+    }
+
+    /// Fastest way to earn `amount` of `currency` with a configuration,
+    /// starting at `time`.
+    fn fastest_path(&self, currency: &str, state: &PlannerState, time: f64, amount: f64) -> Option<ProductionPath> {
+        fastest_path_from(&self.efficiencies(currency, state, time), &state.counts, amount)
+    }
+}
+
+/// Planner state between purchases.
+#[derive(Debug, Clone)]
+struct PlannerState {
+    counts: FacilityCounts,
+    modules: ModuleLevels,
+    balance: CurrencyBalance,
+    time: f64,
+}
+
+/// Facility types a plan produces on.
+fn facilities_used(path: &ProductionPath) -> Vec<&str> {
+    path.steps
+        .iter()
+        .map(|step| step.facility.split(" (").next().unwrap_or(&step.facility))
+        .collect()
+}
+
+/// Earns whatever is missing for an upgrade, then buys it.
+///
+/// Each earning run uses the items as they are when it starts. When both
+/// currencies are short, coupons are earned first and the coin shortfall
+/// includes their seeds. The coins are earned either afterwards, or at the
+/// same time on the facilities the coupon plan leaves free, whichever
+/// finishes first.
+///
+/// Returns `None` if a currency the upgrade is priced in can't be produced.
+fn buy_upgrade(
+    catalog: &Catalog,
+    state: &PlannerState,
+    upgrade: &UpgradeCost,
+) -> Option<(PlannerState, ProgressionMilestone)> {
+    let mut balance = state.balance;
+    let mut production = Vec::new();
+    let shortfall = |balance: &CurrencyBalance, currency: &str| upgrade.cost_in(currency) - balance.get(currency);
+
+    // Coupon items are planted with coins, so the coin shortfall includes their seeds
+    let mut coupon_time = 0.0;
+    if shortfall(&balance, "coupons") > 0.0 {
+        let path = catalog.fastest_path("coupons", state, state.time, shortfall(&balance, "coupons"))?;
+        coupon_time = path.total_time;
+        for c in CURRENCIES {
+            balance.add(c, path.ledger.net(c));
+        }
+        production.push(path);
+    }
+
+    let mut time = state.time + coupon_time;
+    let coins_missing = shortfall(&balance, "coins");
+    if coins_missing > 0.0 {
+        let after = catalog.fastest_path("coins", state, time, coins_missing);
+        let alongside = production.first().and_then(|coupon_path| {
+            let mut free = state.clone();
+            for facility in facilities_used(coupon_path) {
+                free.counts.set_count(facility, 0);
+            }
+            catalog.fastest_path("coins", &free, state.time, coins_missing)
+        });
+        let mut choices = Vec::new();
+        if let Some(path) = after {
+            choices.push((time + path.total_time, path));
+        }
+        if let Some(path) = alongside {
+            choices.push((state.time + path.total_time.max(coupon_time), path));
+        }
+        let (end, path) = choices
+            .into_iter()
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))?;
+        time = end;
+        for c in CURRENCIES {
+            balance.add(c, path.ledger.net(c));
+        }
+        production.push(path);
+    }
+    for currency in CURRENCIES {
+        balance.add(currency, -upgrade.cost_in(currency));
+    }
+
+    let (counts, modules) = apply_upgrade(upgrade, &state.counts, &state.modules);
+    let milestone = ProgressionMilestone {
+        upgrade: upgrade.clone(),
+        production,
+        start_time: state.time,
+        purchase_time: time,
+        balance_after: balance,
+        facility_counts: counts.clone(),
+        module_levels: modules.clone(),
+    };
+    let next = PlannerState {
+        counts,
+        modules,
+        balance,
+        time,
+    };
+    Some((next, milestone))
+}
+
+/// Returns `true` if the goal sets a facility or module configuration.
+fn has_configuration_goal(goal: &ProgressionGoal) -> bool {
+    goal.facility_counts.is_some() || goal.module_levels.is_some()
+}
+
+/// Returns `true` if the upgrade moves towards (and not past) the goal configuration.
+fn is_towards_goal(upgrade: &UpgradeCost, goal: &ProgressionGoal) -> bool {
+    match upgrade.kind {
+        UpgradeKind::FacilityLevel => goal
+            .facility_counts
+            .as_ref()
+            .is_some_and(|g| upgrade.level <= g.get_level(&upgrade.target)),
+        UpgradeKind::FacilitySlot => goal
+            .facility_counts
+            .as_ref()
+            .is_some_and(|g| upgrade.level <= g.get_count(&upgrade.target)),
+        UpgradeKind::Module => goal
+            .module_levels
+            .as_ref()
+            .is_some_and(|g| upgrade.level <= g.get_level(&upgrade.target)),
+    }
+}
+
+/// Counts the purchases still needed to reach the goal configuration.
+fn remaining_goal_steps(state: &PlannerState, goal: &ProgressionGoal) -> usize {
+    let mut steps = 0;
+    if let Some(ref g) = goal.facility_counts {
+        for facility in FacilityCounts::FACILITY_NAMES {
+            steps += g.get_level(facility).saturating_sub(state.counts.get_level(facility)) as usize;
+            steps += g.get_count(facility).saturating_sub(state.counts.get_count(facility)) as usize;
+        }
+    }
+    if let Some(ref g) = goal.module_levels {
+        for module in ModuleLevels::MODULE_NAMES {
+            steps += g.get_level(module).saturating_sub(state.modules.get_level(module)) as usize;
+        }
+    }
+    steps
+}
+
+/// Returns the upgrades worth considering from the current state.
+fn candidate_upgrades<'a>(
+    upgrades: &'a [UpgradeCost],
+    state: &PlannerState,
+    goal: &ProgressionGoal,
+) -> Vec<&'a UpgradeCost> {
+    let candidates = next_upgrades(upgrades, &state.counts, &state.modules);
+    if has_configuration_goal(goal) {
+        candidates
+            .into_iter()
+            .filter(|u| is_towards_goal(u, goal))
+            .collect()
+    } else {
+        candidates
+    }
+}
+
+/// Best steady-state profit rate of a configuration in the given currency,
+/// with the items as they are at `time`.
+fn profit_rate(catalog: &Catalog, currency: &str, state: &PlannerState, time: f64) -> f64 {
+    best_profit_rate(&catalog.efficiencies(currency, state, time), &state.counts)
+}
+
+/// Picks the next purchase greedily: the upgrade with the largest profit rate
+/// gain per second spent earning it. Without a configuration goal, upgrades
+/// that don't increase profit are never picked.
+fn choose_greedy(
+    catalog: &Catalog,
+    currency: &str,
+    upgrades: &[UpgradeCost],
+    state: &PlannerState,
+    goal: &ProgressionGoal,
+) -> Option<(PlannerState, ProgressionMilestone)> {
+    let require_gain = !has_configuration_goal(goal);
+
+    let mut best: Option<(f64, f64, PlannerState, ProgressionMilestone)> = None;
+    for upgrade in candidate_upgrades(upgrades, state, goal) {
+        let Some((next, milestone)) = buy_upgrade(catalog, state, upgrade) else {
+            continue;
+        };
+        // Compare both configurations at the purchase time, when the overrides may differ
+        let gain = profit_rate(catalog, currency, &next, next.time) - profit_rate(catalog, currency, state, next.time);
+        if require_gain && gain <= 1e-9 {
+            continue;
+        }
+        let wait = (next.time - state.time).max(1.0);
+        let score = gain / wait;
+
+        let is_better = match best {
+            None => true,
+            Some((best_score, best_wait, _, _)) => {
+                score > best_score + 1e-12 || ((score - best_score).abs() <= 1e-12 && wait < best_wait)
+            }
+        };
+        if is_better {
+            best = Some((score, wait, next, milestone));
+        }
+    }
+
+    best.map(|(_, _, next, milestone)| (next, milestone))
+}
+
+/// Searches every ordering of the remaining goal purchases for the one that
+/// reaches the goal soonest (branch and bound on elapsed time).
+fn search_goal_orderings(
+    catalog: &Catalog,
+    upgrades: &[UpgradeCost],
+    state: &PlannerState,
+    goal: &ProgressionGoal,
+    current: &mut Vec<ProgressionMilestone>,
+    best: &mut Option<(f64, Vec<ProgressionMilestone>)>,
+) {
+    if let Some((best_time, _)) = best {
+        if state.time >= *best_time {
+            return;
+        }
+    }
+
+    if remaining_goal_steps(state, goal) == 0 {
+        *best = Some((state.time, current.clone()));
+        return;
+    }
+
+    for upgrade in candidate_upgrades(upgrades, state, goal) {
+        if let Some((next, milestone)) = buy_upgrade(catalog, state, upgrade) {
+            current.push(milestone);
+            search_goal_orderings(catalog, upgrades, &next, goal, current, best);
+            current.pop();
+        }
+    }
+}
+
+/// Plans a multi-step progression: what to produce to afford each upgrade,
+/// in which order to buy them, and the configuration after every purchase.
+///
+/// Each purchase chains the existing path finders (see [`find_fastest_path`])
+/// to earn whatever the current balance is missing, then applies the upgrade.
+///
+/// - With a goal configuration, only upgrades towards it are bought. Goals that
+///   need only a few purchases have every ordering searched for the fastest
+///   one; larger goals pick greedily.
+/// - Without one, the upgrade with the best profit rate gain per second of
+///   earning is bought next, for as long as upgrades increase profit.
+///
+/// Purchases that would happen after `goal.time_horizon` are dropped.
+///
+/// Overrides are applied as the plan goes: each earning run and each
+/// comparison of upgrades uses the overrides in effect at that point, so a
+/// price event that starts or ends mid-plan changes what is produced and
+/// bought from then on.
+///
+/// # Arguments
+///
+/// * `items` - All available production items, without overrides applied
+/// * `overrides` - Price and timing overrides, with windows relative to plan start
+/// * `currency` - Currency used to measure profit rate ("coins" or "coupons")
+/// * `facility_counts` - Starting facility configuration
+/// * `module_levels` - Starting module levels
+/// * `upgrades` - Upgrade price list
+/// * `balance` - Starting balance
+/// * `goal` - Where the plan should stop
+#[allow(clippy::too_many_arguments)]
+pub fn plan_progression(
+    items: &[ProductionItem],
+    overrides: &[ItemOverride],
+    currency: &str,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+    upgrades: &[UpgradeCost],
+    balance: &CurrencyBalance,
+    goal: &ProgressionGoal,
+) -> ProgressionPlan {
+    let catalog = Catalog::new(items, overrides);
+    let start = PlannerState {
+        counts: facility_counts.clone(),
+        modules: module_levels.clone(),
+        balance: *balance,
+        time: 0.0,
+    };
+
+    let mut milestones: Vec<ProgressionMilestone> = Vec::new();
+    let steps = remaining_goal_steps(&start, goal);
+
+    if has_configuration_goal(goal) && steps <= EXHAUSTIVE_SEARCH_LIMIT {
+        let mut best = None;
+        search_goal_orderings(&catalog, upgrades, &start, goal, &mut Vec::new(), &mut best);
+        if let Some((_, found)) = best {
+            milestones = found;
+        }
+    }
+
+    if milestones.is_empty() {
+        let mut state = start;
+        while milestones.len() < MAX_MILESTONES {
+            if has_configuration_goal(goal) && remaining_goal_steps(&state, goal) == 0 {
+                break;
+            }
+            if goal.time_horizon.is_some_and(|h| state.time >= h) {
+                break;
+            }
+            match choose_greedy(&catalog, currency, upgrades, &state, goal) {
+                Some((next, milestone)) => {
+                    milestones.push(milestone);
+                    state = next;
+                }
+                None => break,
+            }
+        }
+    }
+
+    if let Some(horizon) = goal.time_horizon {
+        milestones.retain(|m| m.purchase_time <= horizon);
+    }
+
+    let goal_reached = has_configuration_goal(goal)
+        && milestones.last().map_or(steps == 0, |m| {
+            let end = PlannerState {
+                counts: m.facility_counts.clone(),
+                modules: m.module_levels.clone(),
+                balance: m.balance_after,
+                time: m.purchase_time,
+            };
+            remaining_goal_steps(&end, goal) == 0
+        });
+
+    ProgressionPlan {
+        total_time: milestones.last().map_or(0.0, |m| m.purchase_time),
+        milestones,
+        goal_reached,
+    }
+}
+
+/// Parses a goal configuration such as `farmland=10x5,kitchen_module=2`.
+///
+/// Facilities take `<count>x<level>`; modules take a level. Facility names are
+/// matched case-insensitively with `_`, `-` or spaces between words. Anything
+/// not mentioned keeps its current value.
+///
+/// # Example
+///
+/// ```
+/// use aniimax::models::{FacilityCounts, ModuleLevels};
+/// use aniimax::upgrades::parse_goal_spec;
+///
+/// let counts = FacilityCounts {
+///     farmland: (4, 3),
+///     woodland: (1, 1),
+///     mineral_pile: (1, 1),
+///     carousel_mill: (1, 1),
+///     jukebox_dryer: (1, 1),
+///     crafting_table: (1, 1),
+///     dance_pad_polisher: (1, 1),
+///     aniipod_maker: (1, 1),
+///     nimbus_bed: (0, 1),
+///     instance_levels: Default::default(),
+/// };
+///
+/// let (goal_counts, goal_modules) =
+///     parse_goal_spec("farmland=10x5, carousel-mill=2x2, kitchen_module=2", &counts, &ModuleLevels::default())
+///         .unwrap();
+/// assert_eq!(goal_counts.farmland, (10, 5));
+/// assert_eq!(goal_counts.carousel_mill, (2, 2));
+/// assert_eq!(goal_modules.kitchen_module, 2);
+/// ```
+pub fn parse_goal_spec(
+    spec: &str,
+    facility_counts: &FacilityCounts,
+    module_levels: &ModuleLevels,
+) -> Result<(FacilityCounts, ModuleLevels), String> {
+    fn normalize(name: &str) -> String {
+        name.trim().to_lowercase().replace(['-', ' '], "_")
+    }
+
+    let mut counts = facility_counts.clone();
+    let mut modules = module_levels.clone();
+
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, value) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("Expected NAME=VALUE in goal entry '{}'", entry))?;
+        let key = normalize(name);
+
+        if let Some(facility) = FacilityCounts::FACILITY_NAMES
+            .iter()
+            .find(|f| normalize(f) == key)
+        {
+            let (count, level) = value
+                .trim()
+                .split_once('x')
+                .ok_or_else(|| format!("Expected COUNTxLEVEL for '{}'", name.trim()))?;
+            let count: u32 = count
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid count in goal entry '{}'", entry))?;
+            let level: u32 = level
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid level in goal entry '{}'", entry))?;
+            counts.set_count(facility, count);
+            counts.set_level(facility, level);
+        } else if ModuleLevels::MODULE_NAMES.contains(&key.as_str()) {
+            let level: u32 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid level in goal entry '{}'", entry))?;
+            modules.set_level(&key, level);
+        } else {
+            return Err(format!("Unknown facility or module '{}'", name.trim()));
+        }
+    }
+
+    Ok((counts, modules))
+}
@@ -3,13 +3,22 @@
 //! This module provides JavaScript-accessible functions for the production optimizer.
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
-use crate::models::{FacilityCounts, ModuleLevels, ProductionEfficiency, ProductionItem};
+use crate::models::{
+    BoardOrder, CostObjective, CurrencyLedger, CurrencyValuation, EnergyBudget, FacilityCounts, ForbiddenRule, InProgressBatch, Inventory, ModuleLevels, OrderLine, OverrideRow, OverrideTimeline,
+    PinnedItem, PlanConstraints, PlanningOptions, ProductionEfficiency, ProductionItem, StorageCapacity,
+};
+use crate::constraints::{apply_constraints, parse_forbidden, parse_pins};
 use crate::optimizer::{
-    calculate_efficiencies, calculate_energy_efficiencies, find_best_production_path,
-    find_parallel_production_path, find_self_sufficient_path,
+    calculate_energy_efficiencies, calculate_valued_efficiencies, fastest_item_by_target,
+    find_best_production_path_with_options, find_deadline_path, find_energy_budget_path, find_parallel_energy_budget_path,
+    find_parallel_production_path_with_options, find_self_sufficient_path_with_options, simulate_stock_levels,
 };
+use crate::orders::{optimize_order_board, plan_order};
+use crate::overrides::{apply_overrides, check_override_targets, parse_override_row};
+use crate::progress::{credit_in_progress, in_progress_batches};
 
 /// JavaScript-friendly facility configuration.
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -18,6 +27,35 @@ pub struct JsFacilityConfig {
     pub count: u32,
     #[serde(default = "default_level")]
     pub level: u32,
+    /// Level of every facility when they differ (e.g. [5, 5, 3]); replaces
+    /// `count` and `level` when given
+    #[serde(default)]
+    pub levels: Vec<u32>,
+}
+
+/// Builds facility counts from one configuration per facility type, in the
+/// order of [`FacilityCounts::FACILITY_NAMES`].
+fn facility_counts(configs: [&JsFacilityConfig; 9]) -> FacilityCounts {
+    let [farmland, woodland, mineral_pile, carousel_mill, jukebox_dryer, crafting_table, dance_pad_polisher, aniipod_maker, nimbus_bed] =
+        configs.map(|c| (c.count, c.level));
+    let mut counts = FacilityCounts {
+        farmland,
+        woodland,
+        mineral_pile,
+        carousel_mill,
+        jukebox_dryer,
+        crafting_table,
+        dance_pad_polisher,
+        aniipod_maker,
+        nimbus_bed,
+        instance_levels: Default::default(),
+    };
+    for (facility, config) in FacilityCounts::FACILITY_NAMES.iter().zip(configs) {
+        if !config.levels.is_empty() {
+            counts.set_instance_levels(facility, config.levels.clone());
+        }
+    }
+    counts
 }
 
 fn default_level() -> u32 {
@@ -40,14 +78,44 @@ pub struct JsModuleLevels {
 /// JavaScript-friendly input for optimization.
 #[derive(Debug, Clone, Deserialize)]
 pub struct JsOptimizeInput {
+    #[serde(default)]
     pub target_amount: f64,
     pub currency: String,
     pub energy_self_sufficient: bool,
     pub energy_cost_per_min: f64,
     #[serde(default)]
     pub parallel: bool,
+    /// Same as forbidding `wheat` (kept for older front ends)
     #[serde(default)]
     pub exclude_wheat: bool,
+    /// Facilities to keep free (facility name -> count)
+    #[serde(default)]
+    pub reserve: HashMap<String, u32>,
+    /// Items to always produce (item name, or `item@facility`, -> fewest batches)
+    #[serde(default)]
+    pub pins: HashMap<String, u32>,
+    /// Items or whole facilities never to use
+    #[serde(default)]
+    pub forbid: Vec<String>,
+    /// Rank items by profit per energy instead of profit per second
+    #[serde(default)]
+    pub optimize_energy: bool,
+    /// Energy available; when set, plan the most profit it pays for instead of `target_amount`
+    #[serde(default)]
+    pub energy_budget: Option<f64>,
+    #[serde(default)]
+    pub energy_regen_per_min: f64,
+    #[serde(default)]
+    pub energy_cap: Option<f64>,
+    /// Time budget for `energy_budget`, in hours
+    #[serde(default = "default_time_budget_hours")]
+    pub time_budget_hours: f64,
+    /// Reach `target_amount` within this many hours as cheaply as possible
+    #[serde(default)]
+    pub deadline_hours: Option<f64>,
+    /// Cost to keep lowest with `deadline_hours`: "energy", "seed_coins" or "interactions"
+    #[serde(default)]
+    pub minimize: Option<String>,
     pub farmland: JsFacilityConfig,
     pub woodland: JsFacilityConfig,
     pub mineral_pile: JsFacilityConfig,
@@ -60,6 +128,37 @@ pub struct JsOptimizeInput {
     pub nimbus_bed: JsFacilityConfig,
     #[serde(default)]
     pub modules: JsModuleLevels,
+    /// Items already in storage (item name -> units)
+    #[serde(default)]
+    pub inventory: HashMap<String, u32>,
+    /// Storage capacity ("total" or a category -> units)
+    #[serde(default)]
+    pub storage: HashMap<String, u32>,
+    /// Coins on hand; seeds are only planted once they can be paid for
+    #[serde(default)]
+    pub starting_coins: Option<f64>,
+    /// Worth of other currencies in `currency` (e.g. {"coupons": 20}), to
+    /// earn any mix of them
+    #[serde(default)]
+    pub valuation: HashMap<String, f64>,
+    /// Price and timing overrides for events and boosts
+    #[serde(default)]
+    pub overrides: Vec<JsOverride>,
+    /// Batches already running when the plan starts
+    #[serde(default)]
+    pub in_progress: Vec<JsInProgressBatch>,
+    /// Sell the units whole batches make beyond what the plan uses
+    #[serde(default)]
+    pub sell_surplus: bool,
+    /// Simulate the plan batch by batch for `inventory_over_time` and
+    /// `peak_inventory` (done anyway with `storage`, `starting_coins` or
+    /// `in_progress`)
+    #[serde(default)]
+    pub stock_levels: bool,
+}
+
+fn default_time_budget_hours() -> f64 {
+    24.0
 }
 
 /// JavaScript-friendly production step output.
@@ -70,11 +169,36 @@ pub struct JsProductionStep {
     pub quantity: u32,
     pub time_seconds: f64,
     pub energy: Option<f64>,
+    /// Energy gained by eating the step's output (steps producing for energy)
+    pub energy_gained: Option<f64>,
     pub chain_id: Option<u32>,
     /// Optimal facility allocation: Vec<(material_name, batches_needed, facilities_to_allocate)>
     pub facility_allocation: Option<Vec<(String, u32, u32)>>,
 }
 
+/// JavaScript-friendly income and expenses in one currency.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsLedgerEntry {
+    pub currency: String,
+    pub income: f64,
+    pub expenses: f64,
+    pub net: f64,
+}
+
+/// Lists the currencies a ledger touches, in display order.
+fn ledger_entries(ledger: &CurrencyLedger) -> Vec<JsLedgerEntry> {
+    ledger
+        .currencies()
+        .into_iter()
+        .map(|c| JsLedgerEntry {
+            currency: c.to_string(),
+            income: ledger.earned(c),
+            expenses: ledger.spent(c),
+            net: ledger.net(c),
+        })
+        .collect()
+}
+
 /// JavaScript-friendly efficiency output.
 #[derive(Debug, Clone, Serialize)]
 pub struct JsEfficiency {
@@ -88,10 +212,12 @@ pub struct JsEfficiency {
     pub sell_value: f64,
     pub yield_amount: u32,
     pub requires_raw: Option<String>,
+    /// Income and expenses of one batch, by currency
+    pub ledger: Vec<JsLedgerEntry>,
 }
 
 /// JavaScript-friendly optimization result.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Default)]
 pub struct JsOptimizeResult {
     pub success: bool,
     pub error: Option<String>,
@@ -100,12 +226,334 @@ pub struct JsOptimizeResult {
     pub total_time_formatted: String,
     pub total_energy: Option<f64>,
     pub total_profit: f64,
+    /// Income and expenses of the whole plan, by currency
+    pub ledger: Vec<JsLedgerEntry>,
     pub items_produced: u32,
     pub currency: String,
     pub all_efficiencies: Vec<JsEfficiency>,
     pub is_energy_self_sufficient: bool,
     pub energy_items_produced: Option<u32>,
     pub energy_item_name: Option<String>,
+    /// Most units held at once (0 unless the plan was simulated, see `stock_levels`)
+    pub peak_inventory: u32,
+    /// Coin balance over the plan as (seconds, coins); empty without `starting_coins`
+    pub balance_over_time: Vec<(f64, f64)>,
+    /// Average price per unit of each item sold, after sale limits
+    pub average_prices: Vec<(String, f64)>,
+    /// Schedule of the pinned items, run alongside `steps` on their own facilities
+    pub pinned_schedule: Vec<JsScheduledItem>,
+    /// Time until the pinned items are done
+    pub pinned_completion_time_seconds: f64,
+    /// What the pinned items sell for, counted towards `target_amount`
+    pub pinned_value: f64,
+    /// What the sold output of in-progress batches is worth, counted towards `target_amount`
+    pub in_progress_value: f64,
+    /// With `deadline_hours`: whether the plan finishes in time (if not, it's the fastest plan)
+    pub deadline_met: Option<bool>,
+    /// With `deadline_hours`: the plan's cost under `minimize`
+    pub deadline_cost: Option<f64>,
+    /// In plain time optimization, the item finishing soonest at target sizes
+    /// up to ten times `target_amount`, as (smallest target, item)
+    pub fastest_item_by_target: Vec<(f64, String)>,
+    /// Units of every item taken from stock, produced, used and left over
+    pub material_balance: Vec<JsMaterialBalance>,
+    /// What is held once the plan is done, to pass as the next `inventory`
+    pub leftover_inventory: HashMap<String, u32>,
+    /// Stock of every item over the plan, with peak and lowest levels (empty
+    /// unless the plan was simulated, see `stock_levels`)
+    pub inventory_over_time: Vec<JsInventorySeries>,
+}
+
+/// JavaScript-friendly facility configuration for all facilities.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct JsFacilities {
+    #[serde(default)]
+    pub farmland: JsFacilityConfig,
+    #[serde(default)]
+    pub woodland: JsFacilityConfig,
+    #[serde(default)]
+    pub mineral_pile: JsFacilityConfig,
+    #[serde(default)]
+    pub carousel_mill: JsFacilityConfig,
+    #[serde(default)]
+    pub jukebox_dryer: JsFacilityConfig,
+    #[serde(default)]
+    pub crafting_table: JsFacilityConfig,
+    #[serde(default)]
+    pub dance_pad_polisher: JsFacilityConfig,
+    #[serde(default)]
+    pub aniipod_maker: JsFacilityConfig,
+    #[serde(default)]
+    pub nimbus_bed: JsFacilityConfig,
+}
+
+impl From<&JsFacilities> for FacilityCounts {
+    fn from(f: &JsFacilities) -> Self {
+        facility_counts([
+            &f.farmland,
+            &f.woodland,
+            &f.mineral_pile,
+            &f.carousel_mill,
+            &f.jukebox_dryer,
+            &f.crafting_table,
+            &f.dance_pad_polisher,
+            &f.aniipod_maker,
+            &f.nimbus_bed,
+        ])
+    }
+}
+
+impl From<&JsModuleLevels> for ModuleLevels {
+    fn from(m: &JsModuleLevels) -> Self {
+        ModuleLevels {
+            ecological_module: m.ecological_module,
+            kitchen_module: m.kitchen_module,
+            mineral_detector: m.mineral_detector,
+            crafting_module: m.crafting_module,
+        }
+    }
+}
+
+/// Converts a JavaScript storage map (`"total"` or a category -> units)
+/// into a [`StorageCapacity`].
+fn storage_capacity(limits: &HashMap<String, u32>) -> Result<StorageCapacity, String> {
+    let spec: Vec<String> = limits
+        .iter()
+        .map(|(name, amount)| format!("{}={}", name, amount))
+        .collect();
+    StorageCapacity::parse(&spec.join(","))
+}
+
+/// Builds the valuation of `currency` with a JavaScript map of the worth of
+/// other currencies in it.
+fn valuation_of(currency: &str, rates: &HashMap<String, f64>) -> CurrencyValuation {
+    let mut valuation = CurrencyValuation::new(currency);
+    valuation.rates = rates
+        .iter()
+        .filter(|(other, _)| *other != currency)
+        .map(|(other, &rate)| (other.clone(), rate))
+        .collect();
+    valuation
+}
+
+/// JavaScript-friendly price or timing override (see [`OverrideRow`]).
+#[derive(Debug, Clone, Deserialize)]
+pub struct JsOverride {
+    /// `global`, `facility` or `item`
+    pub scope: String,
+    /// Facility or item name (empty for global overrides)
+    #[serde(default)]
+    pub target: String,
+    /// `sell_value`, `production_time` or `yield`
+    pub field: String,
+    /// `multiply` or `set`
+    pub operation: String,
+    pub value: f64,
+    /// Hours from plan start when the override begins
+    #[serde(default)]
+    pub start_hours: Option<f64>,
+    /// Hours from plan start when the override ends
+    #[serde(default)]
+    pub end_hours: Option<f64>,
+}
+
+/// Applies JavaScript overrides to the items as they are at plan start.
+fn overridden_items(
+    items: &[ProductionItem],
+    overrides: &[JsOverride],
+) -> Result<(Vec<ProductionItem>, OverrideTimeline), String> {
+    let rules = overrides
+        .iter()
+        .map(|o| {
+            parse_override_row(&OverrideRow {
+                scope: o.scope.clone(),
+                target: o.target.clone(),
+                field: o.field.clone(),
+                operation: o.operation.clone(),
+                value: o.value,
+                start_hours: o.start_hours,
+                end_hours: o.end_hours,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    check_override_targets(items, &rules)?;
+    Ok((apply_overrides(items, &rules, 0.0), OverrideTimeline::new(items, &rules)))
+}
+
+/// JavaScript-friendly batches already in progress.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JsInProgressBatch {
+    pub item: String,
+    /// Facilities running a batch each
+    #[serde(default = "default_facilities")]
+    pub facilities: u32,
+    pub remaining_seconds: f64,
+}
+
+fn default_facilities() -> u32 {
+    1
+}
+
+/// Resolves the JavaScript in-progress batches (see [`in_progress_batches`]).
+fn in_progress(
+    batches: &[JsInProgressBatch],
+    items: &[ProductionItem],
+    facility_counts: &FacilityCounts,
+) -> Result<Vec<InProgressBatch>, String> {
+    let entries: Vec<(String, u32, f64)> = batches
+        .iter()
+        .map(|b| (b.item.clone(), b.facilities, b.remaining_seconds))
+        .collect();
+    in_progress_batches(&entries, items, facility_counts)
+}
+
+/// Builds typed planning constraints from the JavaScript reserve, pin and
+/// forbid inputs (`exclude_wheat` forbids wheat).
+fn plan_constraints(input: &JsOptimizeInput) -> Result<PlanConstraints, String> {
+    let mut reservations = Vec::new();
+    for (name, &count) in &input.reserve {
+        let facility = FacilityCounts::find_facility(name).ok_or_else(|| format!("Unknown facility '{}'", name))?;
+        reservations.push((facility.to_string(), count));
+    }
+    reservations.sort();
+    let pin_spec: Vec<String> = input.pins.iter().map(|(item, batches)| format!("{}={}", item, batches)).collect();
+    let mut pins: Vec<PinnedItem> = parse_pins(&pin_spec.join(","))?;
+    pins.sort_by(|a, b| a.item.cmp(&b.item));
+    let mut forbidden = parse_forbidden(&input.forbid.join(","));
+    if input.exclude_wheat && !forbidden.contains(&ForbiddenRule::Item("wheat".to_string())) {
+        forbidden.push(ForbiddenRule::Item("wheat".to_string()));
+    }
+    Ok(PlanConstraints {
+        reservations,
+        pins,
+        forbidden,
+    })
+}
+
+/// JavaScript-friendly item quantity.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JsOrderLine {
+    pub item: String,
+    pub quantity: u32,
+}
+
+/// JavaScript-friendly order board entry.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JsBoardOrder {
+    pub name: String,
+    pub items: Vec<JsOrderLine>,
+    pub reward: f64,
+    pub currency: String,
+}
+
+/// JavaScript-friendly input for the order board optimizer.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JsOrderBoardInput {
+    pub orders: Vec<JsBoardOrder>,
+    pub time_budget_hours: f64,
+    pub currency: String,
+    /// Worth of other currencies in `currency` (e.g. {"coupons": 20}), so
+    /// rewards paid in them count
+    #[serde(default)]
+    pub valuation: HashMap<String, f64>,
+    #[serde(flatten)]
+    pub facilities: JsFacilities,
+    #[serde(default)]
+    pub modules: JsModuleLevels,
+    /// Items already in storage (item name -> units)
+    #[serde(default)]
+    pub inventory: HashMap<String, u32>,
+    /// Storage capacity ("total" or a category -> units)
+    #[serde(default)]
+    pub storage: HashMap<String, u32>,
+    /// Coins on hand; seeds are only planted once they can be paid for
+    #[serde(default)]
+    pub starting_coins: Option<f64>,
+    /// Price and timing overrides for events and boosts
+    #[serde(default)]
+    pub overrides: Vec<JsOverride>,
+    /// Batches already running when the plan starts
+    #[serde(default)]
+    pub in_progress: Vec<JsInProgressBatch>,
+}
+
+/// JavaScript-friendly evaluation of one board order.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsOrderEvaluation {
+    pub name: String,
+    pub reward: f64,
+    pub reward_currency: String,
+    pub market_value: f64,
+    pub completion_time_seconds: Option<f64>,
+    pub selected: bool,
+}
+
+/// JavaScript-friendly bill of materials entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsMaterialUsage {
+    pub name: String,
+    pub variant: String,
+    pub facility: String,
+    pub required: u32,
+    pub from_stock: u32,
+    pub batches: u32,
+}
+
+/// JavaScript-friendly material balance entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsMaterialBalance {
+    pub name: String,
+    pub from_stock: u32,
+    pub produced: u32,
+    pub consumed: u32,
+    pub surplus_sold: u32,
+    pub left_over: u32,
+}
+
+/// JavaScript-friendly stock levels of one item.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsInventorySeries {
+    pub name: String,
+    /// Units held after every change as (time in seconds, units)
+    pub points: Vec<(f64, u32)>,
+    pub peak: u32,
+    pub peak_time_seconds: f64,
+    pub minimum: u32,
+    pub minimum_time_seconds: f64,
+}
+
+/// JavaScript-friendly schedule entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsScheduledItem {
+    pub name: String,
+    pub facility: String,
+    pub batches: u32,
+    pub start_time_seconds: f64,
+    pub finish_time_seconds: f64,
+}
+
+/// JavaScript-friendly order board result.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct JsOrderBoardResult {
+    pub success: bool,
+    pub error: Option<String>,
+    pub orders: Vec<JsOrderEvaluation>,
+    pub materials: Vec<JsMaterialUsage>,
+    pub schedule: Vec<JsScheduledItem>,
+    pub completion_time_seconds: f64,
+    pub completion_time_formatted: String,
+    pub currency: String,
+    /// Worth of the selected rewards in `currency`
+    pub reward: f64,
+    /// Rewards of the selected orders, by currency
+    pub rewards: HashMap<String, f64>,
+    pub seed_cost: f64,
+    /// Sales on the facilities the orders leave free while they are made
+    pub sales_during: f64,
+    pub sale_value: f64,
+    pub total_value: f64,
+    pub sell_only_value: f64,
+    pub peak_inventory: u32,
 }
 
 impl From<&ProductionEfficiency> for JsEfficiency {
@@ -121,6 +569,7 @@ impl From<&ProductionEfficiency> for JsEfficiency {
             sell_value: eff.item.sell_value,
             yield_amount: eff.item.yield_amount,
             requires_raw: eff.requires_raw.clone(),
+            ledger: ledger_entries(&eff.ledger),
         }
     }
 }
@@ -185,24 +634,26 @@ fn get_embedded_items() -> Vec<ProductionItem> {
     let mut rdr = ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_reader(farmland_data.as_bytes());
-    for result in rdr.deserialize::<crate::models::FarmlandRow>() {
-        if let Ok(row) = result {
-            items.push(ProductionItem {
-                name: row.name,
-                facility: "Farmland".to_string(),
-                raw_materials: None,
-                required_amount: None,
-                cost: Some(row.cost),
-                sell_currency: "coins".to_string(),
-                sell_value: row.sell_value,
-                production_time: row.production_time,
-                yield_amount: row.yield_amount,
-                energy: row.energy,
-                facility_level: row.facility_level,
-                module_requirement: parse_module_requirement(&row.module_requirement),
-                requires_fertilizer: row.facility_level >= 4,
-            });
-        }
+    for row in rdr.deserialize::<crate::models::FarmlandRow>().flatten() {
+        items.push(ProductionItem {
+            name: row.name,
+            facility: "Farmland".to_string(),
+            raw_materials: None,
+            required_amount: None,
+            cost: Some(row.cost),
+            sell_currency: "coins".to_string(),
+            sell_value: row.sell_value,
+            production_time: row.production_time,
+            yield_amount: row.yield_amount,
+            energy_cost: row.energy_cost,
+            energy_value: row.energy_value,
+            facility_level: row.facility_level,
+            module_requirement: parse_module_requirement(&row.module_requirement),
+            requires_fertilizer: row.facility_level >= 4,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
+        });
     }
 
     // Woodland items
@@ -210,27 +661,32 @@ fn get_embedded_items() -> Vec<ProductionItem> {
     let mut rdr = ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_reader(woodland_data.as_bytes());
-    for result in rdr.deserialize::<crate::models::WoodlandRow>() {
-        if let Ok(row) = result {
-            let energy = row.energy.and_then(|e| {
-                if e == "NULL" { None } else { e.parse().ok() }
-            });
-            items.push(ProductionItem {
-                name: row.name,
-                facility: "Woodland".to_string(),
-                raw_materials: None,
-                required_amount: None,
-                cost: Some(row.cost),
-                sell_currency: row.sell_currency,
-                sell_value: row.sell_value,
-                production_time: row.production_time,
-                yield_amount: row.yield_amount,
-                energy,
-                facility_level: row.facility_level,
-                module_requirement: parse_module_requirement(&row.module_requirement),
-                requires_fertilizer: row.facility_level >= 3,
-            });
-        }
+    for row in rdr.deserialize::<crate::models::WoodlandRow>().flatten() {
+        let energy_cost = row.energy_cost.and_then(|e| {
+            if e == "NULL" { None } else { e.parse().ok() }
+        });
+        let energy_value = row.energy_value.and_then(|e| {
+            if e == "NULL" { None } else { e.parse().ok() }
+        });
+        items.push(ProductionItem {
+            name: row.name,
+            facility: "Woodland".to_string(),
+            raw_materials: None,
+            required_amount: None,
+            cost: Some(row.cost),
+            sell_currency: row.sell_currency,
+            sell_value: row.sell_value,
+            production_time: row.production_time,
+            yield_amount: row.yield_amount,
+            energy_cost,
+            energy_value,
+            facility_level: row.facility_level,
+            module_requirement: parse_module_requirement(&row.module_requirement),
+            requires_fertilizer: row.facility_level >= 3,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
+        });
     }
 
     // Mineral Pile items
@@ -238,24 +694,26 @@ fn get_embedded_items() -> Vec<ProductionItem> {
     let mut rdr = ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_reader(mineral_data.as_bytes());
-    for result in rdr.deserialize::<crate::models::MineralRow>() {
-        if let Ok(row) = result {
-            items.push(ProductionItem {
-                name: row.name,
-                facility: "Mineral Pile".to_string(),
-                raw_materials: None,
-                required_amount: None,
-                cost: None,
-                sell_currency: row.sell_currency,
-                sell_value: row.sell_value,
-                production_time: row.production_time,
-                yield_amount: row.yield_amount,
-                energy: None,
-                facility_level: row.facility_level,
-                module_requirement: parse_module_requirement(&row.module_requirement),
-                requires_fertilizer: false,
-            });
-        }
+    for row in rdr.deserialize::<crate::models::MineralRow>().flatten() {
+        items.push(ProductionItem {
+            name: row.name,
+            facility: "Mineral Pile".to_string(),
+            raw_materials: None,
+            required_amount: None,
+            cost: None,
+            sell_currency: row.sell_currency,
+            sell_value: row.sell_value,
+            production_time: row.production_time,
+            yield_amount: row.yield_amount,
+            energy_cost: None,
+            energy_value: None,
+            facility_level: row.facility_level,
+            module_requirement: parse_module_requirement(&row.module_requirement),
+            requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
+        });
     }
 
     // Carousel Mill items
@@ -263,26 +721,28 @@ fn get_embedded_items() -> Vec<ProductionItem> {
     let mut rdr = ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_reader(carousel_data.as_bytes());
-    for result in rdr.deserialize::<crate::models::ProcessingRowWithEnergy>() {
-        if let Ok(row) = result {
-            let raw_mats = parse_raw_materials(&row.raw_materials);
-            let req_amounts = parse_required_amounts(&row.required_amount);
-            items.push(ProductionItem {
-                name: row.name,
-                facility: "Carousel Mill".to_string(),
-                raw_materials: Some(raw_mats),
-                required_amount: Some(req_amounts),
-                cost: None,
-                sell_currency: "coins".to_string(),
-                sell_value: row.sell_value,
-                production_time: row.production_time,
-                yield_amount: 1,
-                energy: row.energy,
-                facility_level: row.facility_level,
-                module_requirement: parse_module_requirement(&row.module_requirement),
-                requires_fertilizer: false,
-            });
-        }
+    for row in rdr.deserialize::<crate::models::ProcessingRowWithEnergy>().flatten() {
+        let raw_mats = parse_raw_materials(&row.raw_materials);
+        let req_amounts = parse_required_amounts(&row.required_amount);
+        items.push(ProductionItem {
+            name: row.name,
+            facility: "Carousel Mill".to_string(),
+            raw_materials: Some(raw_mats),
+            required_amount: Some(req_amounts),
+            cost: None,
+            sell_currency: "coins".to_string(),
+            sell_value: row.sell_value,
+            production_time: row.production_time,
+            yield_amount: 1,
+            energy_cost: row.energy_cost,
+            energy_value: row.energy_value,
+            facility_level: row.facility_level,
+            module_requirement: parse_module_requirement(&row.module_requirement),
+            requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
+        });
     }
 
     // Jukebox Dryer items
@@ -290,26 +750,28 @@ fn get_embedded_items() -> Vec<ProductionItem> {
     let mut rdr = ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_reader(jukebox_data.as_bytes());
-    for result in rdr.deserialize::<crate::models::ProcessingRowWithEnergy>() {
-        if let Ok(row) = result {
-            let raw_mats = parse_raw_materials(&row.raw_materials);
-            let req_amounts = parse_required_amounts(&row.required_amount);
-            items.push(ProductionItem {
-                name: row.name,
-                facility: "Jukebox Dryer".to_string(),
-                raw_materials: Some(raw_mats),
-                required_amount: Some(req_amounts),
-                cost: None,
-                sell_currency: "coins".to_string(),
-                sell_value: row.sell_value,
-                production_time: row.production_time,
-                yield_amount: 1,
-                energy: row.energy,
-                facility_level: row.facility_level,
-                module_requirement: parse_module_requirement(&row.module_requirement),
-                requires_fertilizer: false,
-            });
-        }
+    for row in rdr.deserialize::<crate::models::ProcessingRowWithEnergy>().flatten() {
+        let raw_mats = parse_raw_materials(&row.raw_materials);
+        let req_amounts = parse_required_amounts(&row.required_amount);
+        items.push(ProductionItem {
+            name: row.name,
+            facility: "Jukebox Dryer".to_string(),
+            raw_materials: Some(raw_mats),
+            required_amount: Some(req_amounts),
+            cost: None,
+            sell_currency: "coins".to_string(),
+            sell_value: row.sell_value,
+            production_time: row.production_time,
+            yield_amount: 1,
+            energy_cost: row.energy_cost,
+            energy_value: row.energy_value,
+            facility_level: row.facility_level,
+            module_requirement: parse_module_requirement(&row.module_requirement),
+            requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
+        });
     }
 
     // Crafting Table items
@@ -317,26 +779,28 @@ fn get_embedded_items() -> Vec<ProductionItem> {
     let mut rdr = ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_reader(crafting_data.as_bytes());
-    for result in rdr.deserialize::<crate::models::ProcessingRowNoEnergy>() {
-        if let Ok(row) = result {
-            let raw_mats = parse_raw_materials(&row.raw_materials);
-            let req_amounts = parse_required_amounts(&row.required_amount);
-            items.push(ProductionItem {
-                name: row.name,
-                facility: "Crafting Table".to_string(),
-                raw_materials: Some(raw_mats),
-                required_amount: Some(req_amounts),
-                cost: None,
-                sell_currency: "coupons".to_string(),
-                sell_value: row.sell_value,
-                production_time: row.production_time,
-                yield_amount: 1,
-                energy: None,
-                facility_level: row.facility_level,
-                module_requirement: parse_module_requirement(&row.module_requirement),
-                requires_fertilizer: false,
-            });
-        }
+    for row in rdr.deserialize::<crate::models::ProcessingRowNoEnergy>().flatten() {
+        let raw_mats = parse_raw_materials(&row.raw_materials);
+        let req_amounts = parse_required_amounts(&row.required_amount);
+        items.push(ProductionItem {
+            name: row.name,
+            facility: "Crafting Table".to_string(),
+            raw_materials: Some(raw_mats),
+            required_amount: Some(req_amounts),
+            cost: None,
+            sell_currency: "coupons".to_string(),
+            sell_value: row.sell_value,
+            production_time: row.production_time,
+            yield_amount: 1,
+            energy_cost: None,
+            energy_value: None,
+            facility_level: row.facility_level,
+            module_requirement: parse_module_requirement(&row.module_requirement),
+            requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
+        });
     }
 
     // Dance Pad Polisher items
@@ -344,26 +808,28 @@ fn get_embedded_items() -> Vec<ProductionItem> {
     let mut rdr = ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_reader(dance_data.as_bytes());
-    for result in rdr.deserialize::<crate::models::ProcessingRowNoEnergy>() {
-        if let Ok(row) = result {
-            let raw_mats = parse_raw_materials(&row.raw_materials);
-            let req_amounts = parse_required_amounts(&row.required_amount);
-            items.push(ProductionItem {
-                name: row.name,
-                facility: "Dance Pad Polisher".to_string(),
-                raw_materials: Some(raw_mats),
-                required_amount: Some(req_amounts),
-                cost: None,
-                sell_currency: "coupons".to_string(),
-                sell_value: row.sell_value,
-                production_time: row.production_time,
-                yield_amount: 1,
-                energy: None,
-                facility_level: row.facility_level,
-                module_requirement: parse_module_requirement(&row.module_requirement),
-                requires_fertilizer: false,
-            });
-        }
+    for row in rdr.deserialize::<crate::models::ProcessingRowNoEnergy>().flatten() {
+        let raw_mats = parse_raw_materials(&row.raw_materials);
+        let req_amounts = parse_required_amounts(&row.required_amount);
+        items.push(ProductionItem {
+            name: row.name,
+            facility: "Dance Pad Polisher".to_string(),
+            raw_materials: Some(raw_mats),
+            required_amount: Some(req_amounts),
+            cost: None,
+            sell_currency: "coupons".to_string(),
+            sell_value: row.sell_value,
+            production_time: row.production_time,
+            yield_amount: 1,
+            energy_cost: None,
+            energy_value: None,
+            facility_level: row.facility_level,
+            module_requirement: parse_module_requirement(&row.module_requirement),
+            requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
+        });
     }
 
     // Aniipod Maker items
@@ -371,26 +837,28 @@ fn get_embedded_items() -> Vec<ProductionItem> {
     let mut rdr = ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_reader(aniipod_data.as_bytes());
-    for result in rdr.deserialize::<crate::models::ProcessingRowNoEnergy>() {
-        if let Ok(row) = result {
-            let raw_mats = parse_raw_materials(&row.raw_materials);
-            let req_amounts = parse_required_amounts(&row.required_amount);
-            items.push(ProductionItem {
-                name: row.name,
-                facility: "Aniipod Maker".to_string(),
-                raw_materials: Some(raw_mats),
-                required_amount: Some(req_amounts),
-                cost: None,
-                sell_currency: "coins".to_string(),
-                sell_value: row.sell_value,
-                production_time: row.production_time,
-                yield_amount: 1,
-                energy: None,
-                facility_level: row.facility_level,
-                module_requirement: parse_module_requirement(&row.module_requirement),
-                requires_fertilizer: false,
-            });
-        }
+    for row in rdr.deserialize::<crate::models::ProcessingRowNoEnergy>().flatten() {
+        let raw_mats = parse_raw_materials(&row.raw_materials);
+        let req_amounts = parse_required_amounts(&row.required_amount);
+        items.push(ProductionItem {
+            name: row.name,
+            facility: "Aniipod Maker".to_string(),
+            raw_materials: Some(raw_mats),
+            required_amount: Some(req_amounts),
+            cost: None,
+            sell_currency: "coins".to_string(),
+            sell_value: row.sell_value,
+            production_time: row.production_time,
+            yield_amount: 1,
+            energy_cost: None,
+            energy_value: None,
+            facility_level: row.facility_level,
+            module_requirement: parse_module_requirement(&row.module_requirement),
+            requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
+        });
     }
 
     // Nimbus Bed items (produces fertilizer, wool, petals)
@@ -398,26 +866,53 @@ fn get_embedded_items() -> Vec<ProductionItem> {
     let mut rdr = ReaderBuilder::new()
         .trim(csv::Trim::All)
         .from_reader(nimbus_data.as_bytes());
-    for result in rdr.deserialize::<crate::models::NimbusBedRow>() {
-        if let Ok(row) = result {
-            items.push(ProductionItem {
-                name: row.name,
-                facility: "Nimbus Bed".to_string(),
-                raw_materials: None,
-                required_amount: None,
-                cost: None,
-                sell_currency: "coins".to_string(),
-                sell_value: row.sell_value,
-                production_time: row.production_time,
-                yield_amount: row.yield_amount,
-                energy: None,
-                facility_level: 1,
-                module_requirement: None,
-                requires_fertilizer: false,
-            });
-        }
+    for row in rdr.deserialize::<crate::models::NimbusBedRow>().flatten() {
+        items.push(ProductionItem {
+            name: row.name,
+            facility: "Nimbus Bed".to_string(),
+            raw_materials: None,
+            required_amount: None,
+            cost: None,
+            sell_currency: "coins".to_string(),
+            sell_value: row.sell_value,
+            production_time: row.production_time,
+            yield_amount: row.yield_amount,
+            energy_cost: None,
+            energy_value: None,
+            facility_level: 1,
+            module_requirement: None,
+            requires_fertilizer: false,
+            consumable_values: HashMap::new(),
+            sale_curve: None,
+            level_stats: Vec::new(),
+        });
     }
 
+    // Facility level stats (speed, yield and fertilizer by level)
+    let facility_levels_data = include_str!("../data/facility_levels.csv");
+    let mut rdr = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(facility_levels_data.as_bytes());
+    let facility_levels: Vec<crate::models::FacilityLevelRow> = rdr.deserialize().flatten().collect();
+    let _ = crate::data::apply_facility_levels(&mut items, &facility_levels);
+
+    // Values gained by consuming items (e.g., experience gems)
+    let consumables_data = include_str!("../data/consumables.csv");
+    let mut rdr = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(consumables_data.as_bytes());
+    let consumables: Vec<crate::models::ConsumableRow> = rdr.deserialize().flatten().collect();
+    // The bundled file only names bundled items
+    let _ = crate::data::apply_consumables(&mut items, &consumables);
+
+    // Sale limit tiers
+    let sale_limits_data = include_str!("../data/sale_limits.csv");
+    let mut rdr = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(sale_limits_data.as_bytes());
+    let sale_limits: Vec<crate::models::SaleLimitRow> = rdr.deserialize().flatten().collect();
+    let _ = crate::data::apply_sale_limits(&mut items, &sale_limits);
+
     items
 }
 
@@ -437,28 +932,33 @@ pub fn optimize(input_json: &str) -> String {
                 total_time_formatted: "0s".to_string(),
                 total_energy: None,
                 total_profit: 0.0,
+                ledger: vec![],
                 items_produced: 0,
                 currency: String::new(),
                 all_efficiencies: vec![],
                 is_energy_self_sufficient: false,
                 energy_items_produced: None,
                 energy_item_name: None,
+                peak_inventory: 0,
+                balance_over_time: vec![],
+                average_prices: vec![],
+                ..Default::default()
             })
             .unwrap_or_default();
         }
     };
 
-    let facility_counts = FacilityCounts {
-        farmland: (input.farmland.count, input.farmland.level),
-        woodland: (input.woodland.count, input.woodland.level),
-        mineral_pile: (input.mineral_pile.count, input.mineral_pile.level),
-        carousel_mill: (input.carousel_mill.count, input.carousel_mill.level),
-        jukebox_dryer: (input.jukebox_dryer.count, input.jukebox_dryer.level),
-        crafting_table: (input.crafting_table.count, input.crafting_table.level),
-        dance_pad_polisher: (input.dance_pad_polisher.count, input.dance_pad_polisher.level),
-        aniipod_maker: (input.aniipod_maker.count, input.aniipod_maker.level),
-        nimbus_bed: (input.nimbus_bed.count, input.nimbus_bed.level),
-    };
+    let facility_counts = facility_counts([
+        &input.farmland,
+        &input.woodland,
+        &input.mineral_pile,
+        &input.carousel_mill,
+        &input.jukebox_dryer,
+        &input.crafting_table,
+        &input.dance_pad_polisher,
+        &input.aniipod_maker,
+        &input.nimbus_bed,
+    ]);
 
     let module_levels = ModuleLevels {
         ecological_module: input.modules.ecological_module,
@@ -467,18 +967,131 @@ pub fn optimize(input_json: &str) -> String {
         crafting_module: input.modules.crafting_module,
     };
 
-    let mut items = get_embedded_items();
+    let storage = match storage_capacity(&input.storage) {
+        Ok(storage) => storage,
+        Err(e) => {
+            return serde_json::to_string(&JsOptimizeResult {
+                success: false,
+                error: Some(e),
+                total_time_formatted: "0s".to_string(),
+                currency: input.currency,
+                ..Default::default()
+            })
+            .unwrap_or_default();
+        }
+    };
+    let mut planning_options = PlanningOptions {
+        inventory: Inventory {
+            items: input.inventory.clone(),
+        },
+        storage,
+        starting_coins: input.starting_coins,
+        in_progress: Vec::new(),
+        sell_surplus: input.sell_surplus,
+        overrides: OverrideTimeline::default(),
+    };
+
+    let items = match overridden_items(&get_embedded_items(), &input.overrides) {
+        Ok((items, timeline)) => {
+            planning_options.overrides = timeline;
+            items
+        }
+        Err(e) => {
+            return serde_json::to_string(&JsOptimizeResult {
+                success: false,
+                error: Some(e),
+                total_time_formatted: "0s".to_string(),
+                currency: input.currency,
+                ..Default::default()
+            })
+            .unwrap_or_default();
+        }
+    };
     
-    // Filter out wheat-related items if requested
-    if input.exclude_wheat {
-        items.retain(|item| {
-            let name = item.name.to_lowercase();
-            // Exclude wheat, high_speed_wheat, wheatmeal, super_wheatmeal
-            !name.contains("wheat")
-        });
-    }
+    // Reservations, pins and forbidden items (wheat too, with exclude_wheat)
+    let setup = match plan_constraints(&input)
+        .and_then(|constraints| apply_constraints(&items, &facility_counts, &module_levels, &constraints))
+    {
+        Ok(setup) => setup,
+        Err(e) => {
+            return serde_json::to_string(&JsOptimizeResult {
+                success: false,
+                error: Some(e),
+                total_time_formatted: "0s".to_string(),
+                currency: input.currency,
+                ..Default::default()
+            })
+            .unwrap_or_default();
+        }
+    };
     
-    let efficiencies = calculate_efficiencies(&items, &input.currency, &facility_counts, &module_levels);
+    let valuation = valuation_of(&input.currency, &input.valuation);
+
+    // Batches in progress keep their facilities busy; their output is stocked or sold
+    planning_options.in_progress = match in_progress(&input.in_progress, &items, &facility_counts) {
+        Ok(batches) => batches,
+        Err(e) => {
+            return serde_json::to_string(&JsOptimizeResult {
+                success: false,
+                error: Some(e),
+                total_time_formatted: "0s".to_string(),
+                currency: input.currency,
+                ..Default::default()
+            })
+            .unwrap_or_default();
+        }
+    };
+    let in_progress_value = credit_in_progress(
+        &planning_options.in_progress,
+        &items,
+        &valuation,
+        &mut planning_options.inventory,
+    );
+
+    // Pinned items run first; the rest of the plan gets their facilities once they are done
+    let mut pinned_schedule = Vec::new();
+    let mut pinned_completion_time_seconds = 0.0;
+    let mut pinned_value = 0.0;
+    if !setup.pins.is_empty() {
+        match plan_order(&setup.items, &setup.pins, &setup.facility_counts, &module_levels, &planning_options) {
+            Ok(plan) => {
+                for usage in &plan.materials {
+                    planning_options.inventory.take(&usage.name, usage.from_stock);
+                }
+                planning_options.in_progress.extend(setup.pinned_batches(&plan));
+                pinned_schedule = plan
+                    .schedule
+                    .iter()
+                    .map(|s| JsScheduledItem {
+                        name: s.name.clone(),
+                        facility: s.facility.clone(),
+                        batches: s.batches,
+                        start_time_seconds: s.start_time,
+                        finish_time_seconds: s.finish_time,
+                    })
+                    .collect();
+                pinned_completion_time_seconds = plan.completion_time;
+                pinned_value = setup.pinned_value(&valuation);
+            }
+            Err(e) => {
+                return serde_json::to_string(&JsOptimizeResult {
+                    success: false,
+                    error: Some(e),
+                    total_time_formatted: "0s".to_string(),
+                    currency: input.currency,
+                    ..Default::default()
+                })
+                .unwrap_or_default();
+            }
+        }
+    }
+    let items = setup.items.clone();
+    let facility_counts = setup.facility_counts.clone();
+    let target_amount = input.target_amount - pinned_value - in_progress_value;
+    let credits_reach_target =
+        pinned_value + in_progress_value > 0.0 && input.energy_budget.is_none() && target_amount <= 0.0;
+
+    let efficiencies = calculate_valued_efficiencies(&items, &valuation, &facility_counts, &module_levels);
 
     if efficiencies.is_empty() {
         return serde_json::to_string(&JsOptimizeResult {
@@ -492,43 +1105,108 @@ pub fn optimize(input_json: &str) -> String {
             total_time_formatted: "0s".to_string(),
             total_energy: None,
             total_profit: 0.0,
+            ledger: vec![],
             items_produced: 0,
             currency: input.currency,
             all_efficiencies: vec![],
             is_energy_self_sufficient: false,
             energy_items_produced: None,
             energy_item_name: None,
+            peak_inventory: 0,
+            balance_over_time: vec![],
+            average_prices: vec![],
+            ..Default::default()
         })
         .unwrap_or_default();
     }
 
     let all_efficiencies: Vec<JsEfficiency> = efficiencies.iter().map(JsEfficiency::from).collect();
 
+    let objective = match input.minimize.as_deref().map(CostObjective::parse).transpose() {
+        Ok(objective) => objective.unwrap_or_default(),
+        Err(e) => {
+            return serde_json::to_string(&JsOptimizeResult {
+                success: false,
+                error: Some(e),
+                total_time_formatted: "0s".to_string(),
+                currency: input.currency,
+                ..Default::default()
+            })
+            .unwrap_or_default();
+        }
+    };
+    let deadline_plan = match input.deadline_hours {
+        Some(hours) if !credits_reach_target && input.energy_budget.is_none() => Some(find_deadline_path(
+            &efficiencies,
+            target_amount,
+            hours * 3600.0,
+            objective,
+            &facility_counts,
+            &planning_options,
+        )),
+        _ => None,
+    };
+
     // Choose optimization mode
-    let path_result = if input.energy_self_sufficient && input.energy_cost_per_min > 0.0 {
+    let path_result = if credits_reach_target {
+        None
+    } else if let Some(ref plan) = deadline_plan {
+        // Deadline mode: the cheapest plan in time, or the fastest if none is
+        plan.path.clone()
+    } else if let Some(pool) = input.energy_budget {
+        // Energy budget mode: the most profit the energy pays for, single or parallel
+        let budget = EnergyBudget {
+            pool,
+            regen_per_min: input.energy_regen_per_min,
+            cap: input.energy_cap,
+        };
+        let time_budget = input.time_budget_hours * 3600.0;
+        let single_path =
+            find_energy_budget_path(&efficiencies, &budget, time_budget, &facility_counts, &planning_options);
+        let parallel_path = if input.parallel {
+            find_parallel_energy_budget_path(&efficiencies, &budget, time_budget, &facility_counts, &planning_options)
+        } else {
+            None
+        };
+
+        match (parallel_path, single_path) {
+            (Some(p), Some(s)) => {
+                // Use the more profitable approach
+                if p.total_profit >= s.total_profit {
+                    Some(p)
+                } else {
+                    Some(s)
+                }
+            }
+            (p, s) => p.or(s),
+        }
+    } else if input.energy_self_sufficient && input.energy_cost_per_min > 0.0 {
         // Energy self-sufficient mode
         let energy_efficiencies = calculate_energy_efficiencies(&items, &facility_counts, &module_levels);
-        find_self_sufficient_path(
+        find_self_sufficient_path_with_options(
             &efficiencies,
             &energy_efficiencies,
-            input.target_amount,
+            target_amount,
             input.energy_cost_per_min,
             &facility_counts,
+            &planning_options,
         )
-    } else if input.parallel {
+    } else if input.parallel && !input.optimize_energy {
         // Cross-facility parallel production mode
         // Compare parallel vs single-facility approach, use whichever is faster
-        let parallel_path = find_parallel_production_path(
+        let parallel_path = find_parallel_production_path_with_options(
             &efficiencies,
-            input.target_amount,
+            target_amount,
             &facility_counts,
+            &planning_options,
         );
-        let single_path = find_best_production_path(
+        let single_path = find_best_production_path_with_options(
             &efficiencies,
-            input.target_amount,
+            target_amount,
             false,
             0.0,
             &facility_counts,
+            &planning_options,
         );
         
         match (parallel_path, single_path) {
@@ -545,18 +1223,22 @@ pub fn optimize(input_json: &str) -> String {
             (None, None) => None,
         }
     } else {
-        // Simple time optimization (ignore energy)
-        find_best_production_path(
+        // Time optimization, or energy optimization when requested
+        find_best_production_path_with_options(
             &efficiencies,
-            input.target_amount,
-            false,
+            target_amount,
+            input.optimize_energy,
             0.0,
             &facility_counts,
+            &planning_options,
         )
     };
 
     match path_result {
-        Some(path) => {
+        Some(mut path) => {
+            if input.stock_levels {
+                simulate_stock_levels(&mut path, &facility_counts);
+            }
             let steps: Vec<JsProductionStep> = path
                 .steps
                 .iter()
@@ -566,10 +1248,50 @@ pub fn optimize(input_json: &str) -> String {
                     quantity: s.quantity,
                     time_seconds: s.time,
                     energy: s.energy,
+                    energy_gained: s.energy_gained,
                     chain_id: s.chain_id,
                     facility_allocation: s.facility_allocation.clone(),
                 })
                 .collect();
+            let fastest_item_by_target = if deadline_plan.is_none()
+                && input.energy_budget.is_none()
+                && !input.optimize_energy
+                && input.energy_cost_per_min == 0.0
+            {
+                fastest_item_by_target(&efficiencies, &facility_counts, target_amount * 10.0, &planning_options)
+            } else {
+                vec![]
+            };
+            let leftover_inventory = path
+                .leftover_inventory(&planning_options.inventory)
+                .items
+                .into_iter()
+                .filter(|&(_, amount)| amount > 0)
+                .collect();
+            let material_balance = path
+                .material_balance
+                .iter()
+                .map(|b| JsMaterialBalance {
+                    name: b.name.clone(),
+                    from_stock: b.from_stock,
+                    produced: b.produced,
+                    consumed: b.consumed,
+                    surplus_sold: b.surplus_sold,
+                    left_over: b.left_over,
+                })
+                .collect();
+            let inventory_over_time = path
+                .inventory_over_time
+                .iter()
+                .map(|series| JsInventorySeries {
+                    name: series.name.clone(),
+                    points: series.points.clone(),
+                    peak: series.peak,
+                    peak_time_seconds: series.peak_time,
+                    minimum: series.minimum,
+                    minimum_time_seconds: series.minimum_time,
+                })
+                .collect();
 
             serde_json::to_string(&JsOptimizeResult {
                 success: true,
@@ -579,17 +1301,47 @@ pub fn optimize(input_json: &str) -> String {
                 total_time_formatted: format_time(path.total_time),
                 total_energy: path.total_energy,
                 total_profit: path.total_profit,
+                ledger: ledger_entries(&path.ledger),
                 items_produced: path.items_produced,
                 currency: path.currency,
                 all_efficiencies,
                 is_energy_self_sufficient: path.is_energy_self_sufficient,
                 energy_items_produced: path.energy_items_produced,
                 energy_item_name: path.energy_item_name,
+                peak_inventory: path.peak_inventory,
+                balance_over_time: path.balance_over_time,
+                average_prices: path.average_prices,
+                pinned_schedule,
+                pinned_completion_time_seconds,
+                pinned_value,
+                in_progress_value,
+                deadline_met: deadline_plan.as_ref().map(|plan| plan.meets_deadline),
+                deadline_cost: deadline_plan.as_ref().map(|plan| plan.cost),
+                fastest_item_by_target,
+                material_balance,
+                leftover_inventory,
+                inventory_over_time,
             })
             .unwrap_or_default()
         }
+        None if credits_reach_target => serde_json::to_string(&JsOptimizeResult {
+            success: true,
+            error: None,
+            total_time_seconds: pinned_completion_time_seconds,
+            total_time_formatted: format_time(pinned_completion_time_seconds),
+            currency: input.currency,
+            all_efficiencies,
+            pinned_schedule,
+            pinned_completion_time_seconds,
+            pinned_value,
+            in_progress_value,
+            ..Default::default()
+        })
+        .unwrap_or_default(),
         None => {
-            let error_msg = if input.energy_self_sufficient {
+            let error_msg = if input.energy_budget.is_some() {
+                "The energy budget doesn't pay for a single batch of anything."
+            } else if input.energy_self_sufficient {
                 "Cannot achieve energy self-sufficiency with current setup. Try increasing facility counts or reducing energy cost."
             } else {
                 "Could not find a valid production path."
@@ -602,18 +1354,180 @@ pub fn optimize(input_json: &str) -> String {
                 total_time_formatted: "0s".to_string(),
                 total_energy: None,
                 total_profit: 0.0,
+                ledger: vec![],
                 items_produced: 0,
                 currency: input.currency,
                 all_efficiencies,
                 is_energy_self_sufficient: false,
                 energy_items_produced: None,
                 energy_item_name: None,
+                peak_inventory: 0,
+                balance_over_time: vec![],
+                average_prices: vec![],
+                ..Default::default()
             })
             .unwrap_or_default()
         }
     }
 }
 
+/// Choose which orders of an order board to fill within a time budget.
+///
+/// Takes a JSON string input and returns a JSON string result.
+#[wasm_bindgen]
+pub fn optimize_orders(input_json: &str) -> String {
+    let input: JsOrderBoardInput = match serde_json::from_str(input_json) {
+        Ok(i) => i,
+        Err(e) => {
+            return serde_json::to_string(&JsOrderBoardResult {
+                success: false,
+                error: Some(format!("Invalid input: {}", e)),
+                ..Default::default()
+            })
+            .unwrap_or_default();
+        }
+    };
+
+    let facility_counts = FacilityCounts::from(&input.facilities);
+    let module_levels = ModuleLevels::from(&input.modules);
+    let storage = match storage_capacity(&input.storage) {
+        Ok(storage) => storage,
+        Err(e) => {
+            return serde_json::to_string(&JsOrderBoardResult {
+                success: false,
+                error: Some(e),
+                ..Default::default()
+            })
+            .unwrap_or_default();
+        }
+    };
+    let mut planning_options = PlanningOptions {
+        inventory: Inventory {
+            items: input.inventory.clone(),
+        },
+        storage,
+        starting_coins: input.starting_coins,
+        in_progress: Vec::new(),
+        sell_surplus: false,
+        overrides: OverrideTimeline::default(),
+    };
+    let orders: Vec<BoardOrder> = input
+        .orders
+        .iter()
+        .map(|o| BoardOrder {
+            name: o.name.clone(),
+            lines: o
+                .items
+                .iter()
+                .map(|l| OrderLine {
+                    item: l.item.clone(),
+                    quantity: l.quantity,
+                })
+                .collect(),
+            reward: o.reward,
+            reward_currency: o.currency.clone(),
+        })
+        .collect();
+
+    let items = match overridden_items(&get_embedded_items(), &input.overrides) {
+        Ok((items, timeline)) => {
+            planning_options.overrides = timeline;
+            items
+        }
+        Err(e) => {
+            return serde_json::to_string(&JsOrderBoardResult {
+                success: false,
+                error: Some(e),
+                ..Default::default()
+            })
+            .unwrap_or_default();
+        }
+    };
+    // Stocked output of in-progress batches can fill orders once it arrives
+    planning_options.in_progress = match in_progress(&input.in_progress, &items, &facility_counts) {
+        Ok(batches) => batches,
+        Err(e) => {
+            return serde_json::to_string(&JsOrderBoardResult {
+                success: false,
+                error: Some(e),
+                ..Default::default()
+            })
+            .unwrap_or_default();
+        }
+    };
+    let valuation = valuation_of(&input.currency, &input.valuation);
+    credit_in_progress(
+        &planning_options.in_progress,
+        &items,
+        &valuation,
+        &mut planning_options.inventory,
+    );
+    let board = optimize_order_board(
+        &items,
+        &orders,
+        &valuation,
+        &facility_counts,
+        &module_levels,
+        input.time_budget_hours * 3600.0,
+        &planning_options,
+    );
+
+    let completion_time = board.plan.as_ref().map(|p| p.completion_time).unwrap_or(0.0);
+    serde_json::to_string(&JsOrderBoardResult {
+        success: true,
+        error: None,
+        orders: board
+            .evaluations
+            .iter()
+            .map(|e| JsOrderEvaluation {
+                name: e.order.name.clone(),
+                reward: e.order.reward,
+                reward_currency: e.order.reward_currency.clone(),
+                market_value: e.market_value,
+                completion_time_seconds: e.completion_time,
+                selected: e.selected,
+            })
+            .collect(),
+        materials: board
+            .plan
+            .iter()
+            .flat_map(|p| p.materials.iter())
+            .map(|m| JsMaterialUsage {
+                name: m.name.clone(),
+                variant: m.variant.clone(),
+                facility: m.facility.clone(),
+                required: m.required,
+                from_stock: m.from_stock,
+                batches: m.batches,
+            })
+            .collect(),
+        schedule: board
+            .plan
+            .iter()
+            .flat_map(|p| p.schedule.iter())
+            .map(|s| JsScheduledItem {
+                name: s.name.clone(),
+                facility: s.facility.clone(),
+                batches: s.batches,
+                start_time_seconds: s.start_time,
+                finish_time_seconds: s.finish_time,
+            })
+            .collect(),
+        completion_time_seconds: completion_time,
+        completion_time_formatted: format_time(completion_time),
+        currency: board.valuation.base.clone(),
+        reward: board.reward(),
+        rewards: board.rewards.income.clone(),
+        seed_cost: board.seed_cost(),
+        sales_during: board.sales_during,
+        sale_value: board.sale_value,
+        total_value: board.total_value(),
+        sell_only_value: board.sell_only_value,
+        peak_inventory: board.plan.as_ref().map(|p| p.peak_inventory).unwrap_or(0),
+    })
+    .unwrap_or_default()
+}
+
 /// Get the version of the optimizer.
 #[wasm_bindgen]
 pub fn get_version() -> String {
@@ -634,17 +1548,17 @@ pub fn get_available_items(input_json: &str) -> String {
 
     let input: Result<JsOptimizeInput, _> = serde_json::from_str(input_json);
     let facility_counts = match input {
-        Ok(i) => FacilityCounts {
-            farmland: (i.farmland.count, i.farmland.level),
-            woodland: (i.woodland.count, i.woodland.level),
-            mineral_pile: (i.mineral_pile.count, i.mineral_pile.level),
-            carousel_mill: (i.carousel_mill.count, i.carousel_mill.level),
-            jukebox_dryer: (i.jukebox_dryer.count, i.jukebox_dryer.level),
-            crafting_table: (i.crafting_table.count, i.crafting_table.level),
-            dance_pad_polisher: (i.dance_pad_polisher.count, i.dance_pad_polisher.level),
-            aniipod_maker: (i.aniipod_maker.count, i.aniipod_maker.level),
-            nimbus_bed: (i.nimbus_bed.count, i.nimbus_bed.level),
-        },
+        Ok(i) => facility_counts([
+            &i.farmland,
+            &i.woodland,
+            &i.mineral_pile,
+            &i.carousel_mill,
+            &i.jukebox_dryer,
+            &i.crafting_table,
+            &i.dance_pad_polisher,
+            &i.aniipod_maker,
+            &i.nimbus_bed,
+        ]),
         Err(_) => FacilityCounts {
             farmland: (1, 99),
             woodland: (1, 99),
@@ -655,6 +1569,7 @@ pub fn get_available_items(input_json: &str) -> String {
             dance_pad_polisher: (1, 99),
             aniipod_maker: (1, 99),
             nimbus_bed: (1, 99),
+            instance_levels: Default::default(),
         },
     };
 
@@ -0,0 +1,145 @@
+//! Tests for facility reservations, pinned items and forbidden items.
+
+use aniimax::constraints::{apply_constraints, parse_forbidden, parse_pins, parse_reservations};
+use aniimax::data::load_all_data;
+use aniimax::models::{CurrencyValuation, FacilityCounts, ForbiddenRule, ModuleLevels, PlanConstraints, PlanningOptions};
+use aniimax::optimizer::calculate_efficiencies;
+use aniimax::orders::plan_order;
+use std::path::Path;
+
+fn default_facility_counts() -> FacilityCounts {
+    FacilityCounts {
+        farmland: (4, 3),
+        woodland: (2, 2),
+        mineral_pile: (1, 1),
+        carousel_mill: (1, 1),
+        jukebox_dryer: (1, 1),
+        crafting_table: (2, 3),
+        dance_pad_polisher: (1, 1),
+        aniipod_maker: (1, 1),
+        nimbus_bed: (1, 1),
+        instance_levels: Default::default(),
+    }
+}
+
+#[test]
+fn test_parse_constraints() {
+    let reserved = parse_reservations("farmland=2,crafting table=1").unwrap();
+    assert_eq!(reserved[1], ("Crafting Table".to_string(), 1));
+    assert!(parse_reservations("orchard=1").is_err());
+    assert!(parse_reservations("farmland").is_err());
+
+    let pins = parse_pins("advanced_wood_sculpture=5").unwrap();
+    assert_eq!(pins[0].min_batches, 5);
+    assert_eq!(pins[0].facility, None);
+    assert!(parse_pins("advanced_wood_sculpture=0").is_err());
+    let pins = parse_pins("advanced_wood_sculpture@crafting-table=2").unwrap();
+    assert_eq!(pins[0].item, "advanced_wood_sculpture");
+    assert_eq!(pins[0].facility.as_deref(), Some("Crafting Table"));
+    assert!(parse_pins("advanced_wood_sculpture@orchard=2").is_err());
+
+    assert_eq!(
+        parse_forbidden("wheat,Mineral Pile"),
+        vec![
+            ForbiddenRule::Item("wheat".to_string()),
+            ForbiddenRule::Facility("Mineral Pile".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_constraints_limit_items_and_facilities() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels {
+        crafting_module: 1,
+        ..Default::default()
+    };
+    let constraints = PlanConstraints {
+        reservations: parse_reservations("farmland=1").unwrap(),
+        pins: parse_pins("advanced_wood_sculpture=3").unwrap(),
+        forbidden: parse_forbidden("wheat,mineral_pile"),
+    };
+    let setup = apply_constraints(&items, &counts, &modules, &constraints).unwrap();
+
+    // Forbidding wheat drops every recipe made from it
+    assert_eq!(setup.facility_counts.get_count("Farmland"), 3);
+    assert_eq!(setup.facility_counts.get_count("Mineral Pile"), 0);
+    assert!(setup.items.iter().all(|i| i.name != "wheat" && i.facility != "Mineral Pile"));
+    let efficiencies = calculate_efficiencies(&setup.items, "coins", &setup.facility_counts, &modules);
+    assert!(efficiencies.iter().all(|e| e.item.name != "wheatmeal"));
+
+    // The pinned chain keeps only the facilities its batches run on, until they are done
+    assert_eq!(setup.pins[0].quantity, 3);
+    let pinned = plan_order(&setup.items, &setup.pins, &setup.facility_counts, &modules, &PlanningOptions::default()).unwrap();
+    let busy = setup.pinned_batches(&pinned);
+    let table = busy.iter().find(|b| b.facility == "Crafting Table").unwrap();
+    assert_eq!(table.facilities, 2);
+    assert!(busy.iter().all(|b| b.units == 0 && b.remaining <= pinned.completion_time));
+    assert!(busy.iter().any(|b| b.facility == "Woodland"));
+    assert!(busy.iter().all(|b| b.facility != "Farmland"));
+    assert_eq!(setup.pinned_value(&CurrencyValuation::new("coins")), 3.0 * 431.0);
+
+    // Pinning to a facility that doesn't make the item is an error
+    let wrong_facility = PlanConstraints {
+        pins: parse_pins("advanced_wood_sculpture@woodland=3").unwrap(),
+        ..Default::default()
+    };
+    assert!(apply_constraints(&items, &counts, &modules, &wrong_facility).is_err());
+
+    // Reserving more than exists, or pinning a forbidden item, is an error
+    let too_many = PlanConstraints {
+        reservations: parse_reservations("farmland=5").unwrap(),
+        ..Default::default()
+    };
+    assert!(apply_constraints(&items, &counts, &modules, &too_many).is_err());
+    let pinned_forbidden = PlanConstraints {
+        pins: parse_pins("wheatmeal=1").unwrap(),
+        forbidden: parse_forbidden("wheatmeal"),
+        ..Default::default()
+    };
+    assert!(apply_constraints(&items, &counts, &modules, &pinned_forbidden).is_err());
+}
+
+#[test]
+fn test_pinned_batches_leave_the_rest_of_a_facility_to_the_plan() {
+    use aniimax::optimizer::{calculate_valued_efficiencies, find_best_production_path_with_options};
+
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let mut counts = default_facility_counts();
+    counts.farmland = (10, 3);
+    let modules = ModuleLevels::default();
+    let valuation = CurrencyValuation::new("coins");
+    let plan_with = |pins: &str| {
+        let constraints = PlanConstraints {
+            pins: parse_pins(pins).unwrap(),
+            ..Default::default()
+        };
+        let setup = apply_constraints(&items, &counts, &modules, &constraints).unwrap();
+        let mut options = PlanningOptions::default();
+        let mut target = 20000.0;
+        if !setup.pins.is_empty() {
+            let pinned = plan_order(&setup.items, &setup.pins, &setup.facility_counts, &modules, &options).unwrap();
+            options.in_progress = setup.pinned_batches(&pinned);
+            target -= setup.pinned_value(&valuation);
+        }
+        let efficiencies = calculate_valued_efficiencies(&setup.items, &valuation, &setup.facility_counts, &modules);
+        find_best_production_path_with_options(&efficiencies, target, false, 0.0, &setup.facility_counts, &options)
+            .expect("Should find a path")
+    };
+
+    // One pinned strawberry batch holds one plot for an hour, not every plot
+    let free = plan_with("");
+    let pinned = plan_with("strawberry@farmland=1");
+    assert!(pinned.total_time <= free.total_time + 3600.0);
+}
@@ -1,6 +1,10 @@
 //! Tests for data loading functionality.
 
-use aniimax::data::load_all_data;
+use aniimax::data::{
+    apply_consumables, apply_facility_levels, apply_sale_limits, load_all_data, load_consumables, load_facility_levels,
+    load_farmland, load_overrides, load_processing_with_energy, load_sale_limits, load_woodland,
+};
+use aniimax::overrides::{apply_overrides, check_override_targets};
 use std::path::Path;
 
 #[test]
@@ -54,3 +58,191 @@ fn test_currency_types() {
         );
     }
 }
+
+#[test]
+fn test_energy_cost_and_value_load_separately() {
+    let dir = std::env::temp_dir().join(format!("aniimax_energy_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let farmland = dir.join("farmland.csv");
+    std::fs::write(
+        &farmland,
+        "name, cost, sell_value, production_time, yield, energy_cost, energy_value, facility_level, module_requirement\n\
+         wheat, 0, 1, 90, 10, 100, 809, 1,\n\
+         cotton, 20, 67, 2700, 6, 50, , 3,\n",
+    )
+    .unwrap();
+    let woodland = dir.join("woodland.csv");
+    std::fs::write(
+        &woodland,
+        "name, cost, sell_currency, sell_value, production_time, yield, energy_cost, energy_value, facility_level, module_requirement\n\
+         willow, 1, coupons, 53, 1350, 3, 40, NULL, 1,\n",
+    )
+    .unwrap();
+
+    let crops = load_farmland(&farmland).unwrap();
+    assert_eq!(crops[0].energy_cost, Some(100.0));
+    assert_eq!(crops[0].energy_value, Some(809.0));
+    assert_eq!(crops[1].energy_cost, Some(50.0));
+    assert_eq!(crops[1].energy_value, None);
+
+    let trees = load_woodland(&woodland).unwrap();
+    assert_eq!(trees[0].energy_cost, Some(40.0));
+    assert_eq!(trees[0].energy_value, None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_energy_cost_column_is_optional() {
+    let data_dir = Path::new("data");
+    if data_dir.exists() {
+        // The bundled data records both what producing an item costs and what eating it gives
+        let items = load_all_data(data_dir).expect("Failed to load data");
+        assert!(items.iter().any(|i| i.energy_cost.is_some()));
+        assert!(items.iter().any(|i| i.energy_value.is_some()));
+    }
+
+    let dir = std::env::temp_dir().join(format!("aniimax_no_energy_cost_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mill = dir.join("carousel_mill.csv");
+    std::fs::write(
+        &mill,
+        "name, raw_materials, required_amount, sell_value, production_time, energy_value, facility_level, module_requirement\n\
+         wheatmeal, wheat, 120, 150, 60, 8360, 1,\n",
+    )
+    .unwrap();
+
+    let recipes = load_processing_with_energy(&mill, "Carousel Mill").unwrap();
+    assert_eq!(recipes[0].energy_cost, None);
+    assert_eq!(recipes[0].energy_value, Some(8360.0));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_consumable_values_sale_limits_and_level_stats_attach_to_items() {
+    let dir = std::env::temp_dir().join(format!("aniimax_consumables_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let farmland = dir.join("farmland.csv");
+    std::fs::write(
+        &farmland,
+        "name, cost, sell_value, production_time, yield, energy_cost, energy_value, facility_level, module_requirement\n\
+         wheat, 0, 1, 90, 10, 100, 809, 1,\n",
+    )
+    .unwrap();
+    let consumables = dir.join("consumables.csv");
+    std::fs::write(&consumables, "name, objective, value\nwheat, experience, 2.5\n").unwrap();
+    let unknown = dir.join("unknown.csv");
+    std::fs::write(&unknown, "name, objective, value\ngem, experience, 100\n").unwrap();
+    let sale_limits = dir.join("sale_limits.csv");
+    std::fs::write(&sale_limits, "name, from_units, price_factor\nwheat, 500, 0\nwheat, 200, 0.5\n").unwrap();
+    let facility_levels = dir.join("facility_levels.csv");
+    std::fs::write(
+        &facility_levels,
+        "facility, level, speed_multiplier, yield_bonus, requires_fertilizer\n\
+         Farmland, 4, 1.5, 0.2, true\n\
+         Farmland, 1, 1.0, 0, false\n\
+         Farmland, 5, 2.0, 0, \n\
+         Woodland, 3, 1.0, 0, true\n",
+    )
+    .unwrap();
+    let broken_levels = dir.join("broken_levels.csv");
+    std::fs::write(
+        &broken_levels,
+        "facility, level, speed_multiplier, yield_bonus, requires_fertilizer\nFarmland, 2, 0, 0, false\n",
+    )
+    .unwrap();
+
+    let mut items = load_farmland(&farmland).unwrap();
+    assert!(items[0].consumable_values.is_empty());
+
+    apply_consumables(&mut items, &load_consumables(&consumables).unwrap()).unwrap();
+    assert_eq!(items[0].consumable_values.get("experience"), Some(&2.5));
+    assert!(apply_consumables(&mut items, &load_consumables(&unknown).unwrap()).is_err());
+
+    // Tiers are kept in order of units sold
+    apply_sale_limits(&mut items, &load_sale_limits(&sale_limits).unwrap()).unwrap();
+    let curve = items[0].sale_curve.as_ref().unwrap();
+    assert_eq!(curve.tiers, vec![(200, 0.5), (500, 0.0)]);
+    assert_eq!(curve.daily_limit(), Some(500));
+
+    // Level stats only reach items of their facility, in level order
+    apply_facility_levels(&mut items, &load_facility_levels(&facility_levels).unwrap()).unwrap();
+    let levels: Vec<u32> = items[0].level_stats.iter().map(|s| s.level).collect();
+    assert_eq!(levels, vec![1, 4, 5]);
+    assert!(!items[0].at_facility_level(3).requires_fertilizer);
+    assert!(items[0].at_facility_level(4).requires_fertilizer);
+    assert_eq!(items[0].at_facility_level(4).production_time, 60.0);
+    // A level that doesn't say keeps the crop's own need
+    assert!(!items[0].at_facility_level(5).requires_fertilizer);
+    assert_eq!(items[0].at_facility_level(5).production_time, 45.0);
+    assert!(apply_facility_levels(&mut items, &load_facility_levels(&broken_levels).unwrap()).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_overrides_apply_within_their_windows() {
+    let dir = std::env::temp_dir().join(format!("aniimax_overrides_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let farmland = dir.join("farmland.csv");
+    std::fs::write(
+        &farmland,
+        "name, cost, sell_value, production_time, yield, energy_cost, energy_value, facility_level, module_requirement\n\
+         wheat, 0, 1, 90, 10, 100, 809, 1,\n\
+         cotton, 20, 67, 2700, 6, 50, , 3,\n",
+    )
+    .unwrap();
+    let overrides = dir.join("overrides.csv");
+    std::fs::write(
+        &overrides,
+        "scope, target, field, operation, value, start_hours, end_hours\n\
+         item, wheat, sell_value, multiply, 1.5, 2, 26\n\
+         facility, farmland, production_time, multiply, 0.5, , 12\n\
+         global, , yield, set, 7.6, 12,\n",
+    )
+    .unwrap();
+    let backwards = dir.join("backwards.csv");
+    std::fs::write(
+        &backwards,
+        "scope, target, field, operation, value, start_hours, end_hours\n\
+         item, wheat, sell_value, multiply, 2, 5, 3\n",
+    )
+    .unwrap();
+    let unknown = dir.join("unknown.csv");
+    std::fs::write(
+        &unknown,
+        "scope, target, field, operation, value, start_hours, end_hours\n\
+         item, gem, sell_value, set, 5, ,\n",
+    )
+    .unwrap();
+
+    let items = load_farmland(&farmland).unwrap();
+    let rules = load_overrides(&overrides).unwrap();
+    check_override_targets(&items, &rules).unwrap();
+
+    // At the start only the faster Farmland is in effect
+    let start = apply_overrides(&items, &rules, 0.0);
+    assert_eq!(start[0].sell_value, 1.0);
+    assert_eq!(start[0].production_time, 45.0);
+    assert_eq!(start[1].production_time, 1350.0);
+
+    let event = apply_overrides(&items, &rules, 3.0 * 3600.0);
+    assert_eq!(event[0].sell_value, 1.5);
+    assert_eq!(event[1].sell_value, 67.0);
+
+    // Yields are rounded, and the originals are untouched
+    let later = apply_overrides(&items, &rules, 30.0 * 3600.0);
+    assert_eq!(later[0].sell_value, 1.0);
+    assert_eq!(later[0].production_time, 90.0);
+    assert_eq!(later[1].yield_amount, 8);
+    assert_eq!(items[1].yield_amount, 6);
+
+    assert!(load_overrides(&backwards).is_err());
+    assert!(check_override_targets(&items, &load_overrides(&unknown).unwrap()).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,6 +1,10 @@
 //! Tests for data models and structures.
 
-use aniimax::models::{FacilityCounts, ProductionItem};
+use aniimax::models::{
+    CurrencyLedger, CurrencyValuation, EnergyBudget, FacilityCounts, Inventory, InventorySeries, ProductionItem,
+    StorageCapacity,
+};
+use std::collections::HashMap;
 
 fn default_facility_counts() -> FacilityCounts {
     FacilityCounts {
@@ -13,6 +17,7 @@ fn default_facility_counts() -> FacilityCounts {
         dance_pad_polisher: (1, 1),
         aniipod_maker: (1, 1),
         nimbus_bed: (1, 1),
+        instance_levels: Default::default(),
     }
 }
 
@@ -69,10 +74,14 @@ fn test_production_item_creation() {
         sell_value: 1.0,
         production_time: 90.0,
         yield_amount: 10,
-        energy: Some(809.0),
+        energy_cost: Some(809.0),
+        energy_value: Some(809.0),
         facility_level: 1,
         module_requirement: None,
         requires_fertilizer: false,
+        consumable_values: HashMap::new(),
+        sale_curve: None,
+        level_stats: Vec::new(),
     };
 
     assert_eq!(item.name, "wheat");
@@ -94,13 +103,150 @@ fn test_processed_item_creation() {
         sell_value: 25.0,
         production_time: 300.0,
         yield_amount: 1,
-        energy: Some(3000.0),
+        energy_cost: Some(3000.0),
+        energy_value: Some(3000.0),
         facility_level: 1,
         module_requirement: None,
         requires_fertilizer: false,
+        consumable_values: HashMap::new(),
+        sale_curve: None,
+        level_stats: Vec::new(),
     };
 
     assert_eq!(item.name, "wheatmeal");
     assert_eq!(item.raw_materials, Some(vec!["wheat".to_string()]));
     assert_eq!(item.required_amount, Some(vec![2]));
 }
+
+#[test]
+fn test_inventory_parse() {
+    let inventory = Inventory::parse("wheat=300, strawberry=12,wheat=5").unwrap();
+    assert_eq!(inventory.get("wheat"), 305);
+    assert_eq!(inventory.get("strawberry"), 12);
+    assert_eq!(inventory.get("fertilizer"), 0);
+
+    assert!(Inventory::parse("").unwrap().is_empty());
+    assert!(Inventory::parse("wheat").is_err());
+    assert!(Inventory::parse("wheat=lots").is_err());
+    assert!(Inventory::parse("wheat=4294967295,wheat=5").is_err());
+
+    let mut full = Inventory::parse("wheat=4294967295").unwrap();
+    full.add("wheat", 5);
+    assert_eq!(full.get("wheat"), u32::MAX);
+}
+
+#[test]
+fn test_storage_capacity_parse() {
+    let storage = StorageCapacity::parse("2000, crops=800,goods=300").unwrap();
+    assert_eq!(storage.total, Some(2000));
+    assert_eq!(storage.limit_for("crops"), Some(800));
+    assert_eq!(storage.limit_for("goods"), Some(300));
+    assert_eq!(storage.limit_for("timber"), None);
+
+    assert_eq!(StorageCapacity::parse("minerals=50").unwrap().total, None);
+    assert!(StorageCapacity::parse("").unwrap().is_unlimited());
+    assert!(StorageCapacity::parse("attic=10").is_err());
+    assert!(StorageCapacity::parse("crops=lots").is_err());
+
+    assert_eq!(StorageCapacity::category("Farmland"), "crops");
+    assert_eq!(StorageCapacity::category("Nimbus Bed"), "materials");
+    assert_eq!(StorageCapacity::category("Jukebox Dryer"), "goods");
+}
+
+#[test]
+fn test_energy_budget() {
+    let budget = EnergyBudget {
+        pool: 1000.0,
+        regen_per_min: 6.0,
+        cap: Some(600.0),
+    };
+    assert_eq!(budget.starting_energy(), 600.0);
+    // Nothing regenerates past the cap
+    assert_eq!(budget.available(600.0), 600.0);
+    assert_eq!(budget.time_to_afford(500.0), Some(0.0));
+    assert_eq!(budget.time_to_afford(660.0), None);
+    // Spending everything at the end leaves 60 short: the regeneration
+    // while full was lost, so the second spend waits another 600s
+    assert_eq!(budget.spend(&[(600.0, 600.0), (600.0, 60.0)], 600.0), Some((600.0, 0.0)));
+    // Spending early keeps the pool below the cap, so nothing is lost
+    assert_eq!(budget.spend(&[(0.0, 600.0), (600.0, 60.0)], 600.0), Some((0.0, 0.0)));
+    assert_eq!(budget.spend(&[(0.0, 600.0)], 1200.0), Some((0.0, 120.0)));
+    assert_eq!(budget.spend(&[(0.0, 700.0)], 0.0), None);
+
+    let fixed = EnergyBudget {
+        pool: 1000.0,
+        ..Default::default()
+    };
+    assert_eq!(fixed.available(3600.0), 1000.0);
+    assert_eq!(fixed.time_to_afford(1001.0), None);
+    assert_eq!(fixed.spend(&[(0.0, 600.0), (60.0, 401.0)], 60.0), None);
+}
+
+#[test]
+fn test_currency_valuation() {
+    let valuation = CurrencyValuation::parse("coins", "coupons=20").unwrap();
+    assert!(valuation.is_combined());
+    assert_eq!(valuation.rate("coins"), 1.0);
+    assert_eq!(valuation.rate("coupons"), 20.0);
+    assert_eq!(valuation.rate("gems"), 0.0);
+
+    assert!(!CurrencyValuation::new("coupons").is_combined());
+    assert!(CurrencyValuation::parse("coins", "coupons").is_err());
+    assert!(CurrencyValuation::parse("coins", "coupons=-1").is_err());
+    assert!(CurrencyValuation::parse("coins", "coins=2").is_err());
+
+    // 10 coupons earned for 5 coins of seeds
+    let mut ledger = CurrencyLedger::default();
+    ledger.earn("coupons", 10.0);
+    ledger.spend("coins", 5.0);
+    assert_eq!(ledger.value(&valuation), 195.0);
+    assert_eq!(ledger.value(&CurrencyValuation::new("coupons")), 10.0);
+    assert_eq!(ledger.value(&CurrencyValuation::new("coins")), -5.0);
+    assert_eq!(ledger.scaled(2.0).net("coins"), -10.0);
+}
+
+#[test]
+fn test_facility_counts_mixed_levels() {
+    let mut counts = default_facility_counts();
+    counts.set_levels_spec("farmland=6x5+4x3, carousel-mill=2x2").unwrap();
+
+    assert_eq!(counts.get_count("Farmland"), 10);
+    assert_eq!(counts.get_level("Farmland"), 5);
+    assert_eq!(counts.count_at_level("Farmland", 4), 6);
+    assert_eq!(counts.count_at_level("Farmland", 3), 10);
+    assert_eq!(counts.producing_level("Farmland", 1), 3);
+    assert_eq!(counts.producing_level("Farmland", 4), 5);
+    assert_eq!(counts.describe_levels("Farmland"), "6 x Lv.5 + 4 x Lv.3");
+    // Uniform levels keep the plain (count, level) form
+    assert!(!counts.instance_levels.contains_key("Carousel Mill"));
+    assert_eq!(counts.carousel_mill, (2, 2));
+
+    // New plots join at the lowest level; raising the level lifts the rest
+    counts.set_count("Farmland", 12);
+    assert_eq!(counts.describe_levels("Farmland"), "6 x Lv.5 + 6 x Lv.3");
+    counts.set_level("Farmland", 5);
+    assert_eq!(counts.describe_levels("Farmland"), "12 x Lv.5");
+    assert!(!counts.instance_levels.contains_key("Farmland"));
+
+    assert!(counts.set_levels_spec("farmland=6").is_err());
+    assert!(counts.set_levels_spec("orchard=1x1").is_err());
+}
+
+#[test]
+fn test_inventory_series_add() {
+    let mut first = InventorySeries::from_points("nuts", vec![(0.0, 2), (100.0, 8), (200.0, 0)]);
+    let second = InventorySeries::from_points("nuts", vec![(0.0, 0), (50.0, 6), (120.0, 1)]);
+
+    // Held side by side, the levels add up at every change
+    first.add(&second, 0.0);
+    assert_eq!(first.points, vec![(0.0, 2), (50.0, 8), (100.0, 14), (120.0, 9), (200.0, 1)]);
+    assert_eq!((first.peak, first.peak_time), (14, 100.0));
+    assert_eq!((first.minimum, first.minimum_time), (2, 0.0));
+
+    // Run later, the other plan's stock only counts from its start
+    let mut later = InventorySeries::from_points("nuts", vec![(0.0, 3), (40.0, 0)]);
+    later.add(&second, 40.0);
+    assert_eq!(later.level_at(39.0), 3);
+    assert_eq!(later.level_at(90.0), 6);
+    assert_eq!(later.level_at(500.0), 1);
+}
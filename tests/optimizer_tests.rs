@@ -1,8 +1,18 @@
 //! Tests for production optimization algorithms.
 
-use aniimax::data::load_all_data;
-use aniimax::models::{FacilityCounts, ModuleLevels};
-use aniimax::optimizer::{calculate_efficiencies, find_best_production_path};
+use aniimax::data::{apply_facility_levels, apply_sale_limits, load_all_data};
+use aniimax::models::{
+    CostObjective, CurrencyValuation, EnergyBudget, FacilityLevelRow, SaleLimitRow, FacilityCounts, Inventory, MaterialRequirement,
+    ItemOverride, ModuleLevels, OverrideField, OverrideOperation, OverrideScope, OverrideTimeline, PlanningOptions,
+    StorageCapacity,
+};
+use aniimax::overrides::apply_overrides;
+use aniimax::optimizer::{
+    calculate_efficiencies, calculate_energy_efficiencies, calculate_valued_efficiencies, energy_spends, expand_materials, fastest_item_by_target,
+    find_best_production_path,
+    find_best_production_path_with_options, find_deadline_path, find_energy_budget_path, find_parallel_energy_budget_path,
+    find_parallel_production_path_with_options, find_self_sufficient_path, simulate_stock_levels,
+};
 use std::path::Path;
 
 fn default_facility_counts() -> FacilityCounts {
@@ -16,6 +26,7 @@ fn default_facility_counts() -> FacilityCounts {
         dance_pad_polisher: (1, 3),
         aniipod_maker: (1, 3),
         nimbus_bed: (1, 1),
+        instance_levels: Default::default(),
     }
 }
 
@@ -84,6 +95,7 @@ fn test_calculate_efficiencies_filters_by_level() {
         dance_pad_polisher: (1, 1),
         aniipod_maker: (1, 1),
         nimbus_bed: (0, 1),
+        instance_levels: Default::default(),
     };
 
     // Level 3 for all
@@ -97,6 +109,7 @@ fn test_calculate_efficiencies_filters_by_level() {
         dance_pad_polisher: (1, 3),
         aniipod_maker: (1, 3),
         nimbus_bed: (1, 1),
+        instance_levels: Default::default(),
     };
 
     let eff_level_1 = calculate_efficiencies(&items, "coins", &counts_level_1, &modules);
@@ -198,6 +211,7 @@ fn test_parallel_production_increases_efficiency() {
         dance_pad_polisher: (1, 3),
         aniipod_maker: (1, 3),
         nimbus_bed: (1, 1),
+        instance_levels: Default::default(),
     };
 
     // Multiple facilities
@@ -211,6 +225,7 @@ fn test_parallel_production_increases_efficiency() {
         dance_pad_polisher: (2, 3),
         aniipod_maker: (2, 3),
         nimbus_bed: (1, 1),
+        instance_levels: Default::default(),
     };
 
     let eff_single = calculate_efficiencies(&items, "coins", &counts_single, &modules);
@@ -229,3 +244,982 @@ fn test_parallel_production_increases_efficiency() {
         "Multiple facilities should be faster or equal"
     );
 }
+
+#[test]
+fn test_expand_materials_rejects_overflow() {
+    let materials = vec![MaterialRequirement {
+        name: "wheat".to_string(),
+        variant: "wheat".to_string(),
+        facility: "Farmland".to_string(),
+        facility_level: 1,
+        parent: None,
+        amount_per_parent: 2,
+        yield_amount: 5,
+        production_time: 60.0,
+        is_raw: true,
+        requires_fertilizer: false,
+        cost: 1.0,
+        sell_value: 1.0,
+        sell_currency: "coins".to_string(),
+    }];
+
+    assert!(expand_materials(&materials, u32::MAX, &mut Inventory::default()).is_err());
+    assert_eq!(expand_materials(&materials, 10, &mut Inventory::default()).unwrap()[0].required, 20);
+}
+
+#[test]
+fn test_inventory_shrinks_raw_step() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = default_module_levels();
+
+    let efficiencies: Vec<_> = calculate_efficiencies(&items, "coins", &counts, &modules)
+        .into_iter()
+        .filter(|e| e.item.name == "rice_processed")
+        .collect();
+    if efficiencies.is_empty() {
+        return;
+    }
+
+    let empty = find_best_production_path(&efficiencies, 2000.0, false, 0.0, &counts)
+        .expect("Should find a path");
+    let options = PlanningOptions {
+        inventory: Inventory::parse("rice=100").unwrap(),
+        ..Default::default()
+    };
+    let stocked = find_best_production_path_with_options(&efficiencies, 2000.0, false, 0.0, &counts, &options)
+        .expect("Should find a path");
+
+    let raw_qty = |path: &aniimax::models::ProductionPath| {
+        path.steps.iter().find(|s| s.item_name == "rice").map(|s| s.quantity).unwrap_or(0)
+    };
+    assert_eq!(raw_qty(&stocked) + 100, raw_qty(&empty));
+    assert!(stocked.total_time < empty.total_time, "Stock should shorten the plan");
+    assert!(stocked.startup_time < empty.startup_time, "First batch is already on hand");
+    assert_eq!(stocked.total_profit, empty.total_profit);
+}
+
+
+#[test]
+fn test_storage_capacity_caps_peak_inventory() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let mut counts = default_facility_counts();
+    counts.farmland = (6, 3);
+    let modules = default_module_levels();
+
+    let efficiencies: Vec<_> = calculate_efficiencies(&items, "coins", &counts, &modules)
+        .into_iter()
+        .filter(|e| e.item.name == "rice_processed")
+        .collect();
+    if efficiencies.is_empty() {
+        return;
+    }
+
+    let mut free = find_best_production_path(&efficiencies, 20000.0, false, 0.0, &counts)
+        .expect("Should find a path");
+    // Without a limit the plan is only simulated on request
+    assert!(!free.unsimulated.is_empty());
+    simulate_stock_levels(&mut free, &counts);
+    assert!(free.unsimulated.is_empty());
+    assert!(free.peak_inventory > 0, "Gathering outpaces processing");
+
+    // A capacity above the peak changes nothing
+    let roomy = PlanningOptions {
+        storage: StorageCapacity::parse(&(free.peak_inventory + 1).to_string()).unwrap(),
+        ..Default::default()
+    };
+    let path = find_best_production_path_with_options(&efficiencies, 20000.0, false, 0.0, &counts, &roomy)
+        .expect("Should find a path");
+    assert_eq!(path.total_time, free.total_time);
+
+    // A tight one keeps stock down, at the cost of time if anything
+    let cap = (free.peak_inventory / 2).max(1);
+    let tight = PlanningOptions {
+        storage: StorageCapacity::parse(&format!("crops={}", cap)).unwrap(),
+        ..Default::default()
+    };
+    if let Some(path) = find_best_production_path_with_options(&efficiencies, 20000.0, false, 0.0, &counts, &tight) {
+        assert!(path.peak_inventory <= cap);
+        assert!(path.total_time >= free.total_time);
+        assert_eq!(path.total_profit, free.total_profit);
+    }
+
+    // Too small to hold a single batch of ingredients
+    let cramped = PlanningOptions {
+        storage: StorageCapacity::parse("crops=1").unwrap(),
+        ..Default::default()
+    };
+    assert!(find_best_production_path_with_options(&efficiencies, 20000.0, false, 0.0, &counts, &cramped).is_none());
+}
+
+#[test]
+fn test_energy_budget_limits_plan() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = default_module_levels();
+    let options = PlanningOptions::default();
+    let time_budget = 12.0 * 3600.0;
+
+    // Only items that cost energy, so the pool is what limits the plan
+    let efficiencies: Vec<_> = calculate_efficiencies(&items, "coins", &counts, &modules)
+        .into_iter()
+        .filter(|e| e.total_energy_per_unit.is_some_and(|energy| energy > 0.0))
+        .collect();
+    if efficiencies.is_empty() {
+        return;
+    }
+
+    let small = EnergyBudget {
+        pool: 200_000.0,
+        ..Default::default()
+    };
+    let path = find_energy_budget_path(&efficiencies, &small, time_budget, &counts, &options)
+        .expect("Should afford some batches");
+    assert!(path.total_energy.unwrap() <= small.pool);
+    assert!(path.total_time <= time_budget);
+
+    // More energy never earns less
+    let large = EnergyBudget {
+        pool: 2_000_000.0,
+        ..Default::default()
+    };
+    let richer = find_energy_budget_path(&efficiencies, &large, time_budget, &counts, &options).unwrap();
+    assert!(richer.total_profit >= path.total_profit);
+
+    // Regeneration pays for more than the capped starting pool alone
+    let regen = EnergyBudget {
+        pool: 1_000_000.0,
+        regen_per_min: 2000.0,
+        cap: Some(small.pool),
+    };
+    let regen_path = find_energy_budget_path(&efficiencies, &regen, time_budget, &counts, &options).unwrap();
+    assert!(regen.spend(&energy_spends(&regen_path), regen_path.total_time).is_some());
+    assert!(
+        regen_path.total_energy.unwrap()
+            <= regen.starting_energy() + regen.regen_per_min * regen_path.total_time / 60.0 + 1e-6
+    );
+    assert!(regen_path.total_profit >= path.total_profit);
+
+    if let Some(parallel) = find_parallel_energy_budget_path(&efficiencies, &large, time_budget, &counts, &options) {
+        assert!(parallel.total_energy.unwrap_or(0.0) <= large.pool);
+        assert!(parallel.total_time <= time_budget);
+    }
+
+    // Not even one batch is affordable
+    let empty = EnergyBudget::default();
+    assert!(find_energy_budget_path(&efficiencies, &empty, time_budget, &counts, &options).is_none());
+}
+
+#[test]
+fn test_energy_efficiencies_include_processed_foods() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let energy = calculate_energy_efficiencies(&items, &counts, &default_module_levels());
+
+    assert!(energy.iter().any(|e| e.production.requires_raw.is_some()));
+    for eff in &energy {
+        assert!(eff.energy_per_second > 0.0);
+        assert!((eff.time_per_batch - eff.production.steady_state_time).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_self_sufficient_path_covers_energy() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = default_module_levels();
+    let profit = calculate_efficiencies(&items, "coins", &counts, &modules);
+    let energy = calculate_energy_efficiencies(&items, &counts, &modules);
+    let energy_cost = 20.0;
+
+    let path = find_self_sufficient_path(&profit, &energy, 5000.0, energy_cost, &counts)
+        .expect("Should find a self-sufficient path");
+    assert!(path.is_energy_self_sufficient);
+    assert!(path.total_profit >= 5000.0);
+
+    // Energy and profit chains run side by side
+    assert!(path.steps.iter().all(|s| s.chain_id.is_some()));
+    let eaten: f64 = path
+        .steps
+        .iter()
+        .filter_map(|s| s.energy_gained)
+        .sum();
+    assert!(eaten >= path.total_energy.unwrap());
+    assert!(path.total_energy.unwrap() <= energy_cost / 60.0 * path.total_time + 1e-6);
+}
+
+#[test]
+fn test_self_sufficient_path_mixes_energy_items() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = FacilityCounts {
+        farmland: (4, 3),
+        woodland: (4, 3),
+        ..default_facility_counts()
+    };
+    let modules = default_module_levels();
+    let profit = calculate_efficiencies(&items, "coins", &counts, &modules);
+    let energy = calculate_energy_efficiencies(&items, &counts, &modules);
+
+    // Spend energy a little faster than the best item alone produces it
+    let energy_cost = energy[0].energy_per_second * 60.0 * 1.1;
+    let path = find_self_sufficient_path(&profit, &energy, 5000.0, energy_cost, &counts)
+        .expect("Should mix energy items");
+
+    let energy_chains: Vec<_> = path
+        .steps
+        .iter()
+        .filter(|s| s.item_name.ends_with("(for energy)"))
+        .collect();
+    assert!(energy_chains.len() >= 2);
+    assert!(path.energy_item_name.unwrap().contains(" + "));
+}
+
+#[test]
+fn test_starting_coins_gate_plantings() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = FacilityCounts {
+        farmland: (20, 5),
+        ..default_facility_counts()
+    };
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &default_module_levels());
+    let target = 100_000.0;
+
+    let free = find_best_production_path_with_options(&efficiencies, target, false, 0.0, &counts, &PlanningOptions::default())
+        .unwrap();
+    assert!(free.balance_over_time.is_empty());
+
+    // A balance that never runs out only adds the series
+    let rich = PlanningOptions {
+        starting_coins: Some(1e12),
+        ..Default::default()
+    };
+    let rich = find_best_production_path_with_options(&efficiencies, target, false, 0.0, &counts, &rich).unwrap();
+    assert!(!rich.balance_over_time.is_empty());
+
+    for coins in [100.0, 0.0] {
+        let options = PlanningOptions {
+            starting_coins: Some(coins),
+            ..Default::default()
+        };
+        let path = find_best_production_path_with_options(&efficiencies, target, false, 0.0, &counts, &options)
+            .expect("Should bootstrap from a small balance");
+
+        assert!(path.total_profit >= target);
+        assert!(path.total_time >= rich.total_time - 1e-6);
+        assert_eq!(path.balance_over_time[0], (0.0, coins));
+        assert!(path.balance_over_time.iter().all(|&(_, balance)| balance >= -1e-6));
+        assert!(path.balance_over_time.windows(2).all(|w| w[0].0 <= w[1].0));
+        // The times come from the same schedule as the cash flow, which ends with the last sale
+        let (last_sale, _) = *path.balance_over_time.last().unwrap();
+        assert!((last_sale - path.total_time).abs() < 1e-6);
+        assert!(path.startup_time <= path.total_time);
+
+        let parallel = find_parallel_production_path_with_options(&efficiencies, target, &counts, &options).unwrap();
+        let (last_sale, _) = *parallel.balance_over_time.last().unwrap();
+        assert!((last_sale - parallel.total_time).abs() < 1e-6);
+    }
+
+    // Without a coin, the seeds are paid for by a free crop first
+    let broke = PlanningOptions {
+        starting_coins: Some(0.0),
+        ..Default::default()
+    };
+    let path = find_best_production_path_with_options(&efficiencies, target, false, 0.0, &counts, &broke).unwrap();
+    if free.steps.iter().any(|s| s.profit_contribution > 0.0 && s.item_name != "wheat") {
+        assert!(path.steps.iter().any(|s| s.item_name.ends_with("(for seed money)")));
+    }
+}
+
+#[test]
+fn test_coupon_ledger_keeps_seed_costs_in_coins() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let efficiencies = calculate_efficiencies(&items, "coupons", &counts, &default_module_levels());
+
+    // Willow seeds cost coins but the wood sells for coupons
+    let willow = efficiencies.iter().find(|e| e.item.name == "willow").unwrap();
+    let sale = willow.item.sell_value * willow.item.yield_amount as f64;
+    assert!(willow.raw_cost > 0.0);
+    assert_eq!(willow.ledger.net("coupons"), sale);
+    assert_eq!(willow.ledger.spent("coins"), willow.raw_cost);
+    assert!((willow.profit_per_second - sale / willow.steady_state_time).abs() < 1e-9);
+
+    let path = find_best_production_path(std::slice::from_ref(willow), 1000.0, false, 0.0, &counts).unwrap();
+    assert_eq!(path.total_profit, path.ledger.net("coupons"));
+    assert!(path.ledger.earned("coupons") >= 1000.0);
+    assert!(path.ledger.spent("coins") > 0.0);
+    assert_eq!(path.ledger.earned("coins"), 0.0);
+}
+
+#[test]
+fn test_valued_efficiencies_combine_currencies() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = default_module_levels();
+    let valuation = CurrencyValuation::parse("coins", "coupons=20").unwrap();
+    let valued = calculate_valued_efficiencies(&items, &valuation, &counts, &modules);
+    let coins = calculate_efficiencies(&items, "coins", &counts, &modules);
+    let coupons = calculate_efficiencies(&items, "coupons", &counts, &modules);
+
+    // Every coin and coupon item is ranked on the same scale
+    assert_eq!(valued.len(), coins.len() + coupons.len());
+    for eff in &valued {
+        let sale = eff.item.sell_value * eff.item.yield_amount as f64;
+        let rate = valuation.rate(&eff.item.sell_currency);
+        assert!((eff.profit_per_batch - (sale * rate - eff.raw_cost)).abs() < 1e-9);
+    }
+
+    // The plan is measured in coins but still reports what it earns in coupons
+    let willow = valued.iter().find(|e| e.item.name == "willow").unwrap();
+    let path = find_best_production_path(std::slice::from_ref(willow), 5000.0, false, 0.0, &counts).unwrap();
+    assert_eq!(path.currency, "coins");
+    assert!(path.total_profit >= 5000.0);
+    assert!((path.total_profit - path.ledger.value(&valuation)).abs() < 1e-9);
+    assert!(path.ledger.earned("coupons") > 0.0);
+    assert_eq!(path.ledger.earned("coins"), 0.0);
+}
+
+#[test]
+fn test_experience_objective_consumes_gems() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = FacilityCounts {
+        farmland: (4, 3),
+        dance_pad_polisher: (2, 2),
+        ..default_facility_counts()
+    };
+    let modules = default_module_levels();
+
+    // Only items consumed for experience count towards an experience target
+    let efficiencies = calculate_efficiencies(&items, "experience", &counts, &modules);
+    assert!(!efficiencies.is_empty());
+    for eff in &efficiencies {
+        let experience = eff.item.consumable_values["experience"];
+        assert_eq!(eff.ledger.earned("experience"), experience * eff.item.yield_amount as f64);
+        assert_eq!(eff.ledger.earned("coins"), 0.0);
+    }
+
+    let path = find_best_production_path(&efficiencies, 1000.0, false, 0.0, &counts).unwrap();
+    assert_eq!(path.currency, "experience");
+    assert!(path.total_profit >= 1000.0);
+
+    // Valuing coins lets sold items compete with consumed ones
+    let valuation = CurrencyValuation::parse("experience", "coins=0.2").unwrap();
+    let valued = calculate_valued_efficiencies(&items, &valuation, &counts, &modules);
+    assert!(valued.iter().any(|e| e.ledger.earned("coins") > 0.0));
+    assert!(valued.iter().any(|e| e.ledger.earned("experience") > 0.0));
+    assert!(valued.len() > efficiencies.len());
+}
+
+#[test]
+fn test_sale_limits_spread_production_across_items() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let mut items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = default_module_levels();
+    let target = 20_000.0;
+
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &modules);
+    let free = find_best_production_path(&efficiencies, target, false, 0.0, &counts).unwrap();
+    let top = free.steps.last().unwrap().item_name.clone();
+    let top_units = free.items_produced;
+    assert!(free.average_prices.iter().all(|(_, p)| *p > 0.0));
+
+    // Halve the price after a quarter of the units and stop at half of them
+    let rows = [
+        SaleLimitRow {
+            name: top.clone(),
+            from_units: top_units / 4,
+            price_factor: 0.5,
+        },
+        SaleLimitRow {
+            name: top.clone(),
+            from_units: top_units / 2,
+            price_factor: 0.0,
+        },
+    ];
+    apply_sale_limits(&mut items, &rows).unwrap();
+    // Against a much slower item, the full-price units are still worth making first
+    let efficiencies: Vec<_> = calculate_efficiencies(&items, "coins", &counts, &modules)
+        .into_iter()
+        .filter(|e| e.item.name == top || e.item.name == "wheat")
+        .collect();
+    let path = find_best_production_path(&efficiencies, target, false, 0.0, &counts).unwrap();
+
+    assert!(path.total_profit >= target);
+    let sold: Vec<&String> = path.average_prices.iter().map(|(name, _)| name).collect();
+    assert!(sold.len() > 1, "Should move on to other items, sold {:?}", sold);
+    assert_eq!(sold[0], &top);
+
+    let list_price = items.iter().find(|i| i.name == top).unwrap().sell_value;
+    let top_price = path.average_prices[0].1;
+    assert!(top_price < list_price);
+    assert!(top_price >= list_price * 0.5);
+}
+
+#[test]
+fn test_facility_levels_change_speed_yield_and_fertilizer() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let mut items = load_all_data(data_dir).expect("Failed to load data");
+    let modules = default_module_levels();
+    let wheat_at = |items: &[aniimax::models::ProductionItem], counts: &FacilityCounts| {
+        calculate_efficiencies(items, "coins", counts, &modules)
+            .into_iter()
+            .find(|e| e.item.name == "wheat")
+    };
+
+    // Level 5 Farmland grows twice as fast with 50% more per batch
+    apply_facility_levels(
+        &mut items,
+        &[FacilityLevelRow {
+            facility: "Farmland".to_string(),
+            level: 5,
+            speed_multiplier: 2.0,
+            yield_bonus: 0.5,
+            requires_fertilizer: Some(true),
+        }],
+    )
+    .unwrap();
+
+    let low = FacilityCounts {
+        farmland: (1, 3),
+        ..default_facility_counts()
+    };
+    let high = FacilityCounts {
+        farmland: (1, 5),
+        ..default_facility_counts()
+    };
+    let slow = wheat_at(&items, &low).unwrap();
+    let fast = wheat_at(&items, &high).unwrap();
+    assert!(!slow.item.requires_fertilizer);
+    assert!(fast.item.requires_fertilizer);
+    assert_eq!(fast.item.production_time, slow.item.production_time / 2.0);
+    assert_eq!(fast.item.yield_amount, slow.item.yield_amount * 3 / 2);
+
+    // Fertilizer follows the facility's level, not the crop's
+    let no_bed = FacilityCounts {
+        farmland: (1, 5),
+        nimbus_bed: (0, 1),
+        ..default_facility_counts()
+    };
+    assert!(wheat_at(&items, &no_bed).is_none());
+    let no_bed_low = FacilityCounts {
+        nimbus_bed: (0, 1),
+        ..low
+    };
+    assert!(wheat_at(&items, &no_bed_low).is_some());
+}
+
+#[test]
+fn test_mixed_facility_levels_restrict_high_level_crops() {
+    use aniimax::models::ProductionItem;
+    use std::collections::HashMap;
+
+    let item = |name: &str, facility: &str, level: u32, raw: Option<(Vec<&str>, Vec<u32>)>, value: f64| ProductionItem {
+        name: name.to_string(),
+        facility: facility.to_string(),
+        required_amount: raw.as_ref().map(|(_, amounts)| amounts.clone()),
+        raw_materials: raw.map(|(names, _)| names.iter().map(|n| n.to_string()).collect()),
+        cost: None,
+        sell_currency: "coins".to_string(),
+        sell_value: value,
+        production_time: 100.0,
+        yield_amount: 1,
+        energy_cost: None,
+        energy_value: None,
+        facility_level: level,
+        module_requirement: None,
+        requires_fertilizer: false,
+        consumable_values: HashMap::new(),
+        sale_curve: None,
+        level_stats: Vec::new(),
+    };
+    let items = vec![
+        item("basic", "Farmland", 1, None, 0.0),
+        item("rare", "Farmland", 5, None, 0.0),
+        item("mix", "Jukebox Dryer", 1, Some((vec!["basic", "rare"], vec![10, 10])), 1000.0),
+    ];
+    let modules = default_module_levels();
+
+    // Only 2 of the 10 plots can grow the level 5 crop
+    let mut counts = default_facility_counts();
+    counts.set_levels_spec("farmland=2x5+8x1").unwrap();
+    assert_eq!(counts.count_at_level("Farmland", 5), 2);
+
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &modules);
+    let path = find_best_production_path(&efficiencies, 10_000.0, false, 0.0, &counts).unwrap();
+    let allocation = path
+        .steps
+        .iter()
+        .find_map(|s| s.facility_allocation.clone())
+        .expect("Both crops share Farmland, so they should be allocated");
+    let plots = |name: &str| allocation.iter().find(|(n, _, _)| n == name).unwrap().2;
+    assert_eq!(plots("rare"), 2);
+    assert_eq!(plots("basic"), 8);
+
+    // The level 5 crop can't be grown on level 1 plots at all
+    counts.set_levels_spec("farmland=10x1").unwrap();
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &modules);
+    assert!(efficiencies.iter().all(|e| e.item.name != "mix"));
+}
+
+#[test]
+fn test_parallel_total_time_is_the_slowest_chain() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = FacilityCounts {
+        farmland: (6, 5),
+        woodland: (3, 3),
+        nimbus_bed: (2, 1),
+        ..default_facility_counts()
+    };
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &default_module_levels());
+    let path = find_parallel_production_path_with_options(&efficiencies, 50_000.0, &counts, &PlanningOptions::default())
+        .expect("Should find a parallel path");
+
+    // Each chain's time already counts its own startup, so the slowest chain
+    // sets the total instead of one chain's startup plus another's production
+    let slowest = path.steps.iter().map(|s| s.time).fold(0.0, f64::max);
+    assert!((path.total_time - slowest).abs() < 1e-6);
+}
+
+#[test]
+fn test_fertilizer_and_wool_share_nimbus_beds() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    // Level 5 Farmland needs fertilizer for every crop
+    let counts = FacilityCounts {
+        farmland: (10, 5),
+        nimbus_bed: (2, 1),
+        ..default_facility_counts()
+    };
+    let efficiencies: Vec<_> = calculate_efficiencies(&items, "coins", &counts, &default_module_levels())
+        .into_iter()
+        .filter(|e| e.item.name == "ginseng" || e.item.name == "wool")
+        .collect();
+    let ginseng = efficiencies.iter().find(|e| e.item.name == "ginseng").unwrap();
+    assert!(ginseng.nimbus_bed_load > 0.0 && ginseng.nimbus_bed_load < 2.0);
+
+    let wool_step = |path: &aniimax::models::ProductionPath| {
+        path.steps.iter().find(|s| s.item_name == "wool").cloned().expect("Wool should use the free bed time")
+    };
+    let path = find_parallel_production_path_with_options(&efficiencies, 200_000.0, &counts, &PlanningOptions::default())
+        .expect("Should find a parallel path");
+    assert!(path.steps.iter().any(|s| s.item_name == "fertilizer" && s.quantity > 0));
+    let shared = wool_step(&path);
+    assert!(shared.facility.contains("% of the time"));
+
+    // Fertilizer in stock leaves the beds to wool
+    let options = PlanningOptions {
+        inventory: Inventory::parse("fertilizer=1000").unwrap(),
+        ..Default::default()
+    };
+    let stocked = find_parallel_production_path_with_options(&efficiencies, 200_000.0, &counts, &options)
+        .expect("Should find a parallel path");
+    assert!(stocked.steps.iter().all(|s| s.item_name != "fertilizer"));
+    assert!(wool_step(&stocked).quantity > shared.quantity);
+}
+
+#[test]
+fn test_deadline_path_minimizes_cost() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = FacilityCounts {
+        farmland: (4, 3),
+        woodland: (2, 2),
+        carousel_mill: (2, 3),
+        ..default_facility_counts()
+    };
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &default_module_levels());
+    let options = PlanningOptions::default();
+    let target = 30000.0;
+    let fastest = find_best_production_path(&efficiencies, target, false, 0.0, &counts).expect("Should find a path");
+
+    // With time to spare, the plan spends no more than the fastest one
+    let deadline = fastest.total_time * 2.0;
+    let plan = find_deadline_path(&efficiencies, target, deadline, CostObjective::Energy, &counts, &options);
+    assert!(plan.meets_deadline);
+    let path = plan.path.expect("Should find a path");
+    assert!(path.total_time <= deadline);
+    assert!(path.total_profit >= target);
+    assert!(path.total_energy.unwrap_or(0.0) <= fastest.total_energy.unwrap_or(0.0));
+    assert_eq!(plan.cost, path.total_energy.unwrap_or(0.0));
+
+    let seeds = find_deadline_path(&efficiencies, target, deadline, CostObjective::SeedCoins, &counts, &options);
+    let seeds_path = seeds.path.expect("Should find a path");
+    assert!(seeds_path.ledger.spent("coins") <= fastest.ledger.spent("coins") + 1e-6);
+
+    // An impossible deadline reports the fastest plan instead
+    let missed = find_deadline_path(&efficiencies, target, 60.0, CostObjective::Interactions, &counts, &options);
+    assert!(!missed.meets_deadline);
+    assert!(missed.path.expect("Should find a path").total_time <= fastest.total_time);
+}
+
+#[test]
+fn test_small_target_picks_item_that_finishes_first() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &default_module_levels());
+    let target = 100.0;
+    let path = find_best_production_path(&efficiencies, target, false, 0.0, &counts).expect("Should find a path");
+
+    // No single item gets there sooner, whatever its steady-state rate
+    for eff in efficiencies.iter().filter(|e| e.profit_per_batch > 0.0) {
+        let single: Vec<_> = efficiencies.iter().filter(|e| e.item.name == eff.item.name).cloned().collect();
+        if let Some(other) = find_best_production_path(&single, target, false, 0.0, &counts) {
+            assert!(path.total_time <= other.total_time + 1e-6, "{} finishes sooner", eff.item.name);
+        }
+    }
+
+    // The winner changes as the target grows, and the ranges are in order
+    let ranges = fastest_item_by_target(&efficiencies, &counts, 100_000.0, &PlanningOptions::default());
+    assert!(ranges.len() >= 2);
+    assert!(ranges.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 != w[1].1));
+    let (_, small) = ranges.iter().rev().find(|(from, _)| *from <= target).expect("Should cover the target");
+    assert_eq!(&path.steps.last().unwrap().item_name, small);
+}
+
+#[test]
+fn test_fastest_item_by_target_counts_the_starting_balance() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = FacilityCounts {
+        farmland: (6, 5),
+        woodland: (3, 3),
+        nimbus_bed: (2, 1),
+        ..default_facility_counts()
+    };
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &default_module_levels());
+    let target = 50_000.0;
+
+    // Without coins, seed money comes first, which changes which item is fastest
+    let options = PlanningOptions {
+        starting_coins: Some(0.0),
+        ..Default::default()
+    };
+    let path = find_best_production_path_with_options(&efficiencies, target, false, 0.0, &counts, &options)
+        .expect("Should find a path");
+    let ranges = fastest_item_by_target(&efficiencies, &counts, target, &options);
+    let (_, fastest) = ranges.iter().rev().find(|(from, _)| *from <= target).expect("Should cover the target");
+    assert_eq!(&path.steps.last().unwrap().item_name, fastest);
+}
+
+#[test]
+fn test_whole_batches_round_up_per_facility() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = FacilityCounts {
+        farmland: (4, 3),
+        ..default_facility_counts()
+    };
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &default_module_levels());
+    let only = |name: &str| -> Vec<_> { efficiencies.iter().filter(|e| e.item.name == name).cloned().collect() };
+
+    // 5 and 8 batches of wheat both take two rounds of the four plots, 9 take three
+    let wheat = only("wheat");
+    let per_batch = wheat[0].profit_per_batch;
+    let time_for = |batches: f64| {
+        find_best_production_path(&wheat, per_batch * batches, false, 0.0, &counts)
+            .expect("Should find a path")
+            .total_time
+    };
+    assert_eq!(time_for(5.0), time_for(8.0));
+    assert!(time_for(9.0) > time_for(8.0));
+    // The first round is the startup, counted once
+    assert_eq!(time_for(8.0), 2.0 * wheat[0].item.production_time);
+
+    // Dried bean curd waits on the soybeans in its tofu, not just on the dryer
+    let curd = only("dried_bean_curd");
+    if let Some(first) = curd.first() {
+        let soybean = items.iter().find(|i| i.name == "soybean").unwrap();
+        let soybean_time = 5.0 / soybean.yield_amount as f64 * soybean.production_time / 4.0;
+        assert!(first.steady_state_time >= soybean_time - 1e-6);
+
+        let path = find_best_production_path(&curd, first.profit_per_batch * 10.0, false, 0.0, &counts)
+            .expect("Should find a path");
+        // 50 soybeans are 8 batches (6 left over), two rounds of the plots
+        assert!(path.total_time >= 2.0 * soybean.production_time);
+    }
+}
+
+#[test]
+fn test_fertilized_crops_wait_for_first_fertilizer_batch() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = FacilityCounts {
+        farmland: (20, 5),
+        nimbus_bed: (2, 1),
+        ..default_facility_counts()
+    };
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &default_module_levels());
+    let agave: Vec<_> = efficiencies.iter().filter(|e| e.item.name == "agave").cloned().collect();
+    let Some(first) = agave.first() else {
+        return;
+    };
+    let (fertilizer_time, _) = first.fertilizer_batch.expect("Level 5 Farmland needs fertilizer");
+
+    // 17 batches are one round of the plots, planted once the first fertilizer is in
+    let mut path = find_best_production_path(&agave, first.profit_per_batch * 17.0, false, 0.0, &counts)
+        .expect("Should find a path");
+    simulate_stock_levels(&mut path, &counts);
+    assert_eq!(path.total_time, fertilizer_time + first.item.production_time);
+    assert_eq!(path.startup_time, path.total_time);
+    let simulated_end = path
+        .inventory_over_time
+        .iter()
+        .flat_map(|s| s.points.iter().map(|p| p.0))
+        .fold(0.0, f64::max);
+    assert_eq!(simulated_end, path.total_time);
+
+    // Fertilizer in stock lets them start straight away
+    let options = PlanningOptions {
+        inventory: Inventory::parse("fertilizer=30").unwrap(),
+        ..Default::default()
+    };
+    let stocked = find_best_production_path_with_options(&agave, first.profit_per_batch * 17.0, false, 0.0, &counts, &options)
+        .expect("Should find a path");
+    assert_eq!(stocked.total_time, first.item.production_time);
+}
+
+#[test]
+fn test_material_balance_tracks_leftovers() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &default_module_levels());
+    let aniipod: Vec<_> = efficiencies.iter().filter(|e| e.item.name == "sf_aniipod").cloned().collect();
+    if aniipod.is_empty() {
+        return;
+    }
+    let target = aniipod[0].profit_per_batch * 30.0;
+
+    // 5 rocks in stock leave 115 to mine, which rounds up past a whole batch
+    let options = PlanningOptions {
+        inventory: Inventory::parse("rock=5").unwrap(),
+        ..Default::default()
+    };
+    let path = find_best_production_path_with_options(&aniipod, target, false, 0.0, &counts, &options)
+        .expect("Should find a path");
+    for b in &path.material_balance {
+        assert_eq!(b.from_stock + b.produced, b.consumed + b.surplus_sold + b.left_over, "{}", b.name);
+    }
+    let rock = path.material_balance.iter().find(|b| b.name == "rock").expect("Should track rock");
+    assert_eq!(rock.from_stock, 5);
+    assert!(rock.left_over > 0);
+    assert_eq!(rock.surplus_sold, 0);
+
+    // The leftovers feed the next plan
+    let next = path.leftover_inventory(&options.inventory);
+    assert_eq!(next.get("rock"), rock.left_over);
+    assert_eq!(Inventory::parse(&next.to_spec()).unwrap().to_spec(), next.to_spec());
+
+    // Selling the surplus clears it and books the income
+    let selling = PlanningOptions {
+        sell_surplus: true,
+        ..options.clone()
+    };
+    let sold = find_best_production_path_with_options(&aniipod, target, false, 0.0, &counts, &selling)
+        .expect("Should find a path");
+    let sold_rock = sold.material_balance.iter().find(|b| b.name == "rock").unwrap();
+    assert_eq!(sold_rock.left_over, 0);
+    assert_eq!(sold_rock.surplus_sold, rock.left_over);
+    let currency = &items.iter().find(|i| i.name == "rock").unwrap().sell_currency;
+    assert!(sold.ledger.earned(currency) > path.ledger.earned(currency));
+}
+
+#[test]
+fn test_inventory_over_time_follows_material_balance() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = FacilityCounts {
+        farmland: (4, 3),
+        carousel_mill: (2, 3),
+        ..default_facility_counts()
+    };
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &default_module_levels());
+    let options = PlanningOptions {
+        inventory: Inventory::parse("rice=10").unwrap(),
+        ..Default::default()
+    };
+    let mut path = find_best_production_path_with_options(&efficiencies, 20000.0, false, 0.0, &counts, &options)
+        .expect("Should find a path");
+    assert!(path.inventory_over_time.is_empty());
+    simulate_stock_levels(&mut path, &counts);
+    assert!(!path.inventory_over_time.is_empty());
+
+    let sold: Vec<&str> = path
+        .steps
+        .iter()
+        .filter(|s| s.profit_contribution > 0.0)
+        .map(|s| s.item_name.as_str())
+        .collect();
+    for series in &path.inventory_over_time {
+        assert!(series.points.windows(2).all(|w| w[0].0 <= w[1].0), "{}", series.name);
+        assert!(series.points.iter().all(|p| p.1 <= series.peak));
+        assert!(series.minimum <= series.peak);
+
+        // Stock starts at what the plan takes from the inventory and ends at
+        // what it leaves over; sold items end at the units made
+        let balance = path.material_balance.iter().find(|b| b.name == series.name).unwrap();
+        let end = series.points.last().unwrap().1;
+        if sold.contains(&series.name.as_str()) {
+            assert_eq!(end, balance.produced);
+        } else {
+            assert_eq!(series.points[0].1, balance.from_stock, "{}", series.name);
+            assert_eq!(end, balance.left_over, "{}", series.name);
+            assert!(series.peak <= path.peak_inventory);
+        }
+    }
+}
+
+#[test]
+fn test_timed_overrides_apply_to_the_batches_they_cover() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let base_items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    // Aniipods sell for three times as much from an hour in
+    let event = ItemOverride {
+        scope: OverrideScope::Item("sf_aniipod".to_string()),
+        field: OverrideField::SellValue,
+        operation: OverrideOperation::Multiply(3.0),
+        start: Some(3600.0),
+        end: Some(24.0 * 3600.0),
+    };
+    let items = apply_overrides(&base_items, std::slice::from_ref(&event), 0.0);
+    let efficiencies = calculate_efficiencies(&items, "coins", &counts, &default_module_levels());
+    let timed = PlanningOptions {
+        overrides: OverrideTimeline::new(&base_items, std::slice::from_ref(&event)),
+        ..Default::default()
+    };
+
+    let target = 5000.0;
+    let plain = find_best_production_path_with_options(&efficiencies, target, false, 0.0, &counts, &PlanningOptions::default())
+        .expect("Should find a path");
+    let path = find_best_production_path_with_options(&efficiencies, target, false, 0.0, &counts, &timed)
+        .expect("Should find a path");
+    assert!(path.steps.iter().any(|s| s.item_name == "sf_aniipod"));
+    assert!(path.total_profit >= target);
+    assert!(path.total_profit < plain.total_profit * 1.5);
+    assert!(path.total_time < plain.total_time * 0.75);
+    assert!((path.ledger.value(&CurrencyValuation::new("coins")) - path.total_profit).abs() < 1e-6);
+
+    // The parallel planner prices its aniipods the same way
+    let plain = find_parallel_production_path_with_options(&efficiencies, target, &counts, &PlanningOptions::default())
+        .expect("Should find a parallel path");
+    let path = find_parallel_production_path_with_options(&efficiencies, target, &counts, &timed)
+        .expect("Should find a parallel path");
+    assert!(path.total_profit >= target);
+    assert!(path.total_time <= plain.total_time);
+    let sold: f64 = path.steps.iter().map(|s| s.profit_contribution).sum();
+    assert!((sold - path.total_profit).abs() < 1e-6);
+
+    // An event that ended before the plan starts changes nothing
+    let over = PlanningOptions {
+        overrides: timed.overrides.after(30.0 * 3600.0),
+        ..Default::default()
+    };
+    let late = find_best_production_path_with_options(&efficiencies, target, false, 0.0, &counts, &over)
+        .expect("Should find a path");
+    let plain = find_best_production_path_with_options(&efficiencies, target, false, 0.0, &counts, &PlanningOptions::default())
+        .expect("Should find a path");
+    assert_eq!(late.total_profit, plain.total_profit);
+    assert_eq!(late.total_time, plain.total_time);
+}
@@ -0,0 +1,406 @@
+//! Tests for produce-to-order planning.
+
+use aniimax::data::{load_all_data, load_orders};
+use aniimax::models::{BoardOrder, CurrencyValuation, FacilityCounts, Inventory, ModuleLevels, PlanningOptions, StorageCapacity};
+use aniimax::orders::{bill_of_materials, optimize_order_board, parse_order, plan_order};
+use std::path::Path;
+
+fn default_facility_counts() -> FacilityCounts {
+    FacilityCounts {
+        farmland: (4, 5),
+        woodland: (1, 3),
+        mineral_pile: (1, 3),
+        carousel_mill: (1, 3),
+        jukebox_dryer: (2, 3),
+        crafting_table: (1, 3),
+        dance_pad_polisher: (1, 3),
+        aniipod_maker: (1, 3),
+        nimbus_bed: (1, 1),
+        instance_levels: Default::default(),
+    }
+}
+
+#[test]
+fn test_parse_order() {
+    let order = parse_order("dried_flowers=30,sf_medium_exp_gem=10").unwrap();
+    assert_eq!(order.len(), 2);
+    assert_eq!(order[1].item, "sf_medium_exp_gem");
+    assert_eq!(order[1].quantity, 10);
+
+    assert!(parse_order("").is_err());
+    assert!(parse_order("dried_flowers").is_err());
+    assert!(parse_order("dried_flowers=many").is_err());
+}
+
+#[test]
+fn test_bill_of_materials_exact_counts() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let order = parse_order("dried_flowers=30").unwrap();
+
+    let materials = bill_of_materials(
+        &items,
+        &order,
+        &counts,
+        &ModuleLevels::default(),
+        &mut Inventory::default(),
+    )
+    .expect("Should expand the order");
+
+    let find = |name: &str| materials.iter().find(|m| m.name == name).unwrap();
+    let flowers = find("dried_flowers");
+    assert_eq!(flowers.batches, 30);
+
+    // 3 lavender and 3 rose per batch
+    let lavender = find("lavender");
+    assert_eq!(lavender.required, 90);
+    assert_eq!(lavender.batches, 90u32.div_ceil(items.iter().find(|i| i.name == "lavender").unwrap().yield_amount));
+    assert_eq!(find("rose").required, 90);
+
+    // One fertilizer per batch of a fertilizer-requiring crop
+    let fertilizer_batches: u32 = materials
+        .iter()
+        .filter(|m| items.iter().any(|i| i.name == m.variant && i.requires_fertilizer))
+        .map(|m| m.batches)
+        .sum();
+    if fertilizer_batches > 0 {
+        assert_eq!(find("fertilizer").required, fertilizer_batches);
+    }
+}
+
+#[test]
+fn test_plan_order_uses_inventory() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let order = parse_order("dried_flowers=30").unwrap();
+
+    let empty = plan_order(&items, &order, &counts, &modules, &PlanningOptions::default()).unwrap();
+    let options = PlanningOptions {
+        inventory: Inventory::parse("dried_flowers=10,rose=30").unwrap(),
+        ..Default::default()
+    };
+    let stocked = plan_order(&items, &order, &counts, &modules, &options).unwrap();
+
+    let flowers = stocked.materials.iter().find(|m| m.name == "dried_flowers").unwrap();
+    assert_eq!(flowers.from_stock, 10);
+    assert_eq!(flowers.batches, 20);
+    let rose = stocked.materials.iter().find(|m| m.name == "rose").unwrap();
+    assert_eq!(rose.required, 60);
+    assert_eq!(rose.from_stock, 30);
+
+    assert!(stocked.completion_time < empty.completion_time);
+    assert!(stocked.seed_cost < empty.seed_cost);
+
+    // The schedule covers every batch and ends with the last finished item
+    for usage in empty.materials.iter().filter(|m| m.batches > 0) {
+        let scheduled = empty.schedule.iter().find(|s| s.name == usage.variant).unwrap();
+        assert_eq!(scheduled.batches, usage.batches);
+        assert!(scheduled.finish_time <= empty.completion_time);
+    }
+}
+
+#[test]
+fn test_plan_order_respects_storage() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let order = parse_order("dried_flowers=30").unwrap();
+
+    let free = plan_order(&items, &order, &counts, &modules, &PlanningOptions::default()).unwrap();
+    let cap = free.peak_inventory - 10;
+    let options = PlanningOptions {
+        storage: StorageCapacity::parse(&cap.to_string()).unwrap(),
+        ..Default::default()
+    };
+    let capped = plan_order(&items, &order, &counts, &modules, &options).unwrap();
+    assert!(capped.peak_inventory <= cap);
+    assert!(capped.completion_time >= free.completion_time);
+    assert_eq!(capped.materials.len(), free.materials.len());
+
+    // No room for the crops of a single dried_flowers batch
+    let options = PlanningOptions {
+        storage: StorageCapacity::parse("crops=4").unwrap(),
+        ..Default::default()
+    };
+    assert!(plan_order(&items, &order, &counts, &modules, &options).is_err());
+}
+
+#[test]
+fn test_plan_order_rejects_unavailable_items() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let modules = ModuleLevels::default();
+    let options = PlanningOptions::default();
+
+    let order = parse_order("no_such_item=1").unwrap();
+    assert!(plan_order(&items, &order, &default_facility_counts(), &modules, &options).is_err());
+
+    let mut counts = default_facility_counts();
+    counts.jukebox_dryer = (0, 3);
+    let order = parse_order("dried_flowers=1").unwrap();
+    assert!(plan_order(&items, &order, &counts, &modules, &options).is_err());
+}
+
+#[test]
+fn test_plan_order_rejects_orders_beyond_the_horizon() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let options = PlanningOptions::default();
+
+    // Years of milling are turned down before any batch is simulated
+    for spec in ["wheatmeal=10000000", "wheatmeal=100000000"] {
+        let order = parse_order(spec).unwrap();
+        let err = plan_order(&items, &order, &default_facility_counts(), &ModuleLevels::default(), &options)
+            .expect_err("An order this large can't be scheduled");
+        assert!(err.starts_with("Could not schedule"), "{}", err);
+    }
+}
+
+#[test]
+fn test_load_orders() {
+    let path = Path::new("data/orders_example.csv");
+    if !path.exists() {
+        return;
+    }
+
+    let orders = load_orders(path).expect("Failed to load orders");
+    assert!(!orders.is_empty());
+    for order in &orders {
+        assert!(!order.lines.is_empty());
+        assert!(order.reward > 0.0);
+    }
+    let bakery = orders.iter().find(|o| o.name == "bakery").unwrap();
+    assert_eq!(bakery.lines.len(), 2);
+    assert_eq!(bakery.lines[1].quantity, 20);
+}
+
+#[test]
+fn test_order_board_picks_orders_that_beat_selling() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let order = |name: &str, spec: &str, reward: f64| BoardOrder {
+        name: name.to_string(),
+        lines: parse_order(spec).unwrap(),
+        reward,
+        reward_currency: "coins".to_string(),
+    };
+    let orders = vec![
+        order("generous", "strawberry=20", 1_000_000.0),
+        order("stingy", "strawberry=20", 1.0),
+        order("too_slow", "dried_flowers=500", 10_000_000.0),
+    ];
+
+    let board = optimize_order_board(
+        &items,
+        &orders,
+        &CurrencyValuation::new("coins"),
+        &counts,
+        &modules,
+        6.0 * 3600.0,
+        &PlanningOptions::default(),
+    );
+
+    let picked: Vec<&str> = board
+        .evaluations
+        .iter()
+        .filter(|e| e.selected)
+        .map(|e| e.order.name.as_str())
+        .collect();
+    assert_eq!(picked, vec!["generous"]);
+    assert!(board.total_value() > board.sell_only_value);
+    assert!(board.plan.as_ref().unwrap().completion_time <= board.time_budget);
+
+    // Nothing worth more than the market: sell instead
+    let board = optimize_order_board(
+        &items,
+        &orders[1..2],
+        &CurrencyValuation::new("coins"),
+        &counts,
+        &modules,
+        6.0 * 3600.0,
+        &PlanningOptions::default(),
+    );
+    assert!(board.plan.is_none());
+    assert_eq!(board.total_value(), board.sell_only_value);
+}
+
+#[test]
+fn test_order_board_combines_orders_in_both_currencies() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let order = |name: &str, spec: &str, reward: f64, currency: &str| BoardOrder {
+        name: name.to_string(),
+        lines: parse_order(spec).unwrap(),
+        reward,
+        reward_currency: currency.to_string(),
+    };
+    let orders = vec![
+        order("crops", "strawberry=20", 200_000.0, "coins"),
+        order("ore", "rock=20", 20_000.0, "coupons"),
+    ];
+    let picked = |board: &aniimax::models::OrderBoardPlan| -> Vec<String> {
+        board
+            .evaluations
+            .iter()
+            .filter(|e| e.selected)
+            .map(|e| e.order.name.clone())
+            .collect()
+    };
+    let budget = 6.0 * 3600.0;
+
+    // The orders use different facilities, so filling both beats either alone
+    let valuation = CurrencyValuation::parse("coins", "coupons=20").unwrap();
+    let board = optimize_order_board(&items, &orders, &valuation, &counts, &modules, budget, &PlanningOptions::default());
+    assert_eq!(picked(&board), vec!["crops", "ore"]);
+    assert_eq!(board.rewards.earned("coins"), 200_000.0);
+    assert_eq!(board.rewards.earned("coupons"), 20_000.0);
+    assert_eq!(board.reward(), 600_000.0);
+    assert!(board.total_value() > board.sell_only_value);
+
+    // Coupons are worth nothing to a plain coin valuation
+    let board = optimize_order_board(
+        &items,
+        &orders,
+        &CurrencyValuation::new("coins"),
+        &counts,
+        &modules,
+        budget,
+        &PlanningOptions::default(),
+    );
+    assert_eq!(picked(&board), vec!["crops"]);
+    // Facilities the crops leave free keep selling while they grow
+    assert!(board.sales_during > 0.0);
+    assert!(board.sales_during + board.sale_value <= board.sell_only_value);
+}
+
+#[test]
+fn test_order_runs_each_facility_at_its_level() {
+    use aniimax::models::{FacilityLevelStats, ProductionItem};
+    use std::collections::HashMap;
+
+    // Level 5 plots grow crops twice as fast
+    let stats = |level: u32, speed_multiplier: f64| FacilityLevelStats {
+        level,
+        speed_multiplier,
+        yield_bonus: 0.0,
+        requires_fertilizer: None,
+    };
+    let crop = |name: &str, level: u32| ProductionItem {
+        name: name.to_string(),
+        facility: "Farmland".to_string(),
+        raw_materials: None,
+        required_amount: None,
+        cost: Some(0.0),
+        sell_currency: "coins".to_string(),
+        sell_value: 1.0,
+        production_time: 100.0,
+        yield_amount: 10,
+        energy_cost: None,
+        energy_value: None,
+        facility_level: level,
+        module_requirement: None,
+        requires_fertilizer: false,
+        consumable_values: HashMap::new(),
+        sale_curve: None,
+        level_stats: vec![stats(1, 1.0), stats(5, 2.0)],
+    };
+    let items = vec![crop("basic", 1), crop("rare", 5)];
+    let mut counts = default_facility_counts();
+    counts.set_levels_spec("farmland=1x5+1x1").unwrap();
+    let modules = ModuleLevels::default();
+    let options = PlanningOptions::default();
+
+    // The level 5 plot grows three batches while the level 1 plot grows one
+    let plan = plan_order(&items, &parse_order("basic=40").unwrap(), &counts, &modules, &options).unwrap();
+    assert_eq!(plan.completion_time, 150.0);
+
+    // The level 1 plot can't grow the level 5 crop at all
+    let plan = plan_order(&items, &parse_order("rare=20").unwrap(), &counts, &modules, &options).unwrap();
+    assert_eq!(plan.completion_time, 100.0);
+}
+
+#[test]
+fn test_order_batches_follow_override_windows() {
+    use aniimax::models::{ItemOverride, OverrideField, OverrideOperation, OverrideScope, OverrideTimeline, ProductionItem};
+    use std::collections::HashMap;
+
+    let crop = ProductionItem {
+        name: "basic".to_string(),
+        facility: "Farmland".to_string(),
+        raw_materials: None,
+        required_amount: None,
+        cost: Some(0.0),
+        sell_currency: "coins".to_string(),
+        sell_value: 1.0,
+        production_time: 100.0,
+        yield_amount: 10,
+        energy_cost: None,
+        energy_value: None,
+        facility_level: 1,
+        module_requirement: None,
+        requires_fertilizer: false,
+        consumable_values: HashMap::new(),
+        sale_curve: None,
+        level_stats: Vec::new(),
+    };
+    let items = vec![crop];
+    let mut counts = default_facility_counts();
+    counts.farmland = (1, 1);
+    let modules = ModuleLevels::default();
+    let order = parse_order("basic=50").unwrap();
+
+    // Crops grow twice as fast from 200s in
+    let boost = ItemOverride {
+        scope: OverrideScope::Facility("Farmland".to_string()),
+        field: OverrideField::ProductionTime,
+        operation: OverrideOperation::Multiply(0.5),
+        start: Some(200.0),
+        end: None,
+    };
+    let options = PlanningOptions {
+        overrides: OverrideTimeline::new(&items, &[boost]),
+        ..Default::default()
+    };
+
+    let plain = plan_order(&items, &order, &counts, &modules, &PlanningOptions::default()).unwrap();
+    assert_eq!(plain.completion_time, 500.0);
+    // Two batches at the usual pace, then three at the boosted one
+    let plan = plan_order(&items, &order, &counts, &modules, &options).unwrap();
+    assert_eq!(plan.completion_time, 350.0);
+}
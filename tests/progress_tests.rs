@@ -0,0 +1,111 @@
+//! Tests for planning around batches already in progress.
+
+use aniimax::data::load_all_data;
+use aniimax::models::{CurrencyValuation, FacilityCounts, Inventory, ModuleLevels, PlanningOptions};
+use aniimax::optimizer::{calculate_efficiencies, find_best_production_path_with_options};
+use aniimax::orders::{parse_order, plan_order};
+use aniimax::progress::{credit_in_progress, parse_duration, parse_in_progress};
+use std::path::Path;
+
+fn default_facility_counts() -> FacilityCounts {
+    FacilityCounts {
+        farmland: (4, 3),
+        woodland: (1, 3),
+        mineral_pile: (1, 3),
+        carousel_mill: (1, 3),
+        jukebox_dryer: (1, 3),
+        crafting_table: (1, 3),
+        dance_pad_polisher: (1, 3),
+        aniipod_maker: (1, 3),
+        nimbus_bed: (1, 1),
+        instance_levels: Default::default(),
+    }
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("2h").unwrap(), 7200.0);
+    assert_eq!(parse_duration("1h 30m").unwrap(), 5400.0);
+    assert_eq!(parse_duration("90s").unwrap(), 90.0);
+    assert_eq!(parse_duration("0.25").unwrap(), 900.0);
+    assert!(parse_duration("").is_err());
+    assert!(parse_duration("30").is_ok());
+    assert!(parse_duration("30x").is_err());
+    assert!(parse_duration("-1").is_err());
+}
+
+#[test]
+fn test_parse_in_progress() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let batches = parse_in_progress("rice*3=45m, rice_processed=10m", &items, &counts).unwrap();
+
+    let rice = items.iter().find(|i| i.name == "rice").unwrap();
+    assert_eq!(batches[0].facility, "Farmland");
+    assert_eq!(batches[0].facilities, 3);
+    assert_eq!(batches[0].remaining, 2700.0);
+    assert_eq!(batches[0].units, 3 * rice.yield_amount);
+    // Rice is milled, processed rice is only sold
+    assert!(!batches[0].sold);
+    assert!(batches[1].sold);
+
+    let mut inventory = Inventory::default();
+    let processed = items.iter().find(|i| i.name == "rice_processed").unwrap();
+    let value = credit_in_progress(&batches, &items, &CurrencyValuation::new("coins"), &mut inventory);
+    assert_eq!(inventory.get("rice"), 3 * rice.yield_amount);
+    assert_eq!(value, processed.sell_value * processed.yield_amount as f64);
+
+    assert!(parse_in_progress("rice*3=45m,wheat*2=1h", &items, &counts).is_err());
+    assert!(parse_in_progress("moonberry=1h", &items, &counts).is_err());
+    assert!(parse_in_progress("rice*x=1h", &items, &counts).is_err());
+    assert!(parse_in_progress("rice", &items, &counts).is_err());
+}
+
+#[test]
+fn test_in_progress_batches_hold_facilities() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let efficiencies: Vec<_> = calculate_efficiencies(&items, "coins", &counts, &modules)
+        .into_iter()
+        .filter(|e| e.item.name == "rice_processed")
+        .collect();
+    if efficiencies.is_empty() {
+        return;
+    }
+
+    let idle = find_best_production_path_with_options(&efficiencies, 20000.0, false, 0.0, &counts, &PlanningOptions::default())
+        .expect("Should find a path");
+    let mut options = PlanningOptions {
+        in_progress: parse_in_progress("wheat*3=2h", &items, &counts).unwrap(),
+        ..Default::default()
+    };
+    credit_in_progress(&options.in_progress, &items, &CurrencyValuation::new("coins"), &mut options.inventory);
+    let busy = find_best_production_path_with_options(&efficiencies, 20000.0, false, 0.0, &counts, &options)
+        .expect("Should find a path");
+    // Three of four plots are tied up for two hours
+    assert!(busy.total_time > idle.total_time + 3600.0);
+    assert_eq!(busy.total_profit, idle.total_profit);
+
+    // Stock still growing only arrives when its batches finish
+    let order = parse_order("rice_processed=5").unwrap();
+    let options = PlanningOptions {
+        inventory: Inventory::parse("rice=100").unwrap(),
+        in_progress: parse_in_progress("rice*4=3h", &items, &counts).unwrap(),
+        ..Default::default()
+    };
+    let plan = plan_order(&items, &order, &counts, &modules, &options).unwrap();
+    let rice = plan.materials.iter().find(|m| m.name == "rice").unwrap();
+    assert_eq!(rice.batches, 0);
+    assert!(plan.completion_time > 3.0 * 3600.0);
+}
@@ -0,0 +1,341 @@
+//! Tests for upgrade costs and return-on-investment planning.
+
+use aniimax::data::{load_all_data, load_upgrades};
+use aniimax::models::{
+    CurrencyBalance, FacilityCounts, ItemOverride, ModuleLevels, OverrideField, OverrideOperation, OverrideScope,
+    ProgressionGoal, UpgradeCost, UpgradeKind,
+};
+use aniimax::upgrades::{
+    apply_upgrade, calculate_upgrade_roi, find_fastest_path, next_upgrades, plan_progression, unpriced_upgrades,
+};
+use std::path::Path;
+
+fn default_facility_counts() -> FacilityCounts {
+    FacilityCounts {
+        farmland: (4, 3),
+        woodland: (1, 1),
+        mineral_pile: (1, 1),
+        carousel_mill: (1, 1),
+        jukebox_dryer: (1, 1),
+        crafting_table: (1, 1),
+        dance_pad_polisher: (1, 1),
+        aniipod_maker: (1, 1),
+        nimbus_bed: (0, 1),
+        instance_levels: Default::default(),
+    }
+}
+
+fn upgrade(kind: UpgradeKind, target: &str, level: u32, coins: f64) -> UpgradeCost {
+    UpgradeCost {
+        kind,
+        target: target.to_string(),
+        level,
+        coins,
+        coupons: 0.0,
+    }
+}
+
+#[test]
+fn test_apply_upgrade() {
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+
+    let (c, _) = apply_upgrade(&upgrade(UpgradeKind::FacilityLevel, "Farmland", 4, 0.0), &counts, &modules);
+    assert_eq!(c.get_level("Farmland"), 4);
+    assert_eq!(c.get_count("Farmland"), 4);
+
+    let (c, _) = apply_upgrade(&upgrade(UpgradeKind::FacilitySlot, "Nimbus Bed", 1, 0.0), &counts, &modules);
+    assert_eq!(c.get_count("Nimbus Bed"), 1);
+
+    let (_, m) = apply_upgrade(&upgrade(UpgradeKind::Module, "kitchen_module", 2, 0.0), &counts, &modules);
+    assert_eq!(m.get_level("kitchen_module"), 2);
+}
+
+#[test]
+fn test_next_upgrades_only_one_step_ahead() {
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let upgrades = vec![
+        upgrade(UpgradeKind::FacilityLevel, "Farmland", 4, 100.0),
+        upgrade(UpgradeKind::FacilityLevel, "Farmland", 5, 100.0),
+        upgrade(UpgradeKind::FacilitySlot, "Farmland", 5, 100.0),
+        upgrade(UpgradeKind::FacilitySlot, "Farmland", 6, 100.0),
+        upgrade(UpgradeKind::Module, "ecological_module", 1, 100.0),
+        upgrade(UpgradeKind::Module, "ecological_module", 2, 100.0),
+    ];
+
+    let next = next_upgrades(&upgrades, &counts, &modules);
+    let described: Vec<String> = next.iter().map(|u| u.describe()).collect();
+
+    assert_eq!(described, vec!["Farmland Lv.4", "Farmland slot #5", "ecological_module Lv.1"]);
+}
+
+#[test]
+fn test_unpriced_upgrades_are_reported() {
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let upgrades = vec![
+        upgrade(UpgradeKind::FacilitySlot, "Farmland", 5, 2000.0),
+        upgrade(UpgradeKind::Module, "kitchen_module", 1, 100.0),
+    ];
+
+    let missing: Vec<String> = unpriced_upgrades(&upgrades, &counts, &modules).iter().map(|u| u.describe()).collect();
+
+    assert!(!missing.contains(&"Farmland slot #5".to_string()));
+    assert!(!missing.contains(&"kitchen_module Lv.1".to_string()));
+    assert!(missing.contains(&"Nimbus Bed Lv.2".to_string()));
+    assert!(missing.contains(&"ecological_module Lv.1".to_string()));
+    let priced = next_upgrades(&upgrades, &counts, &modules).len();
+    assert_eq!(missing.len() + priced, FacilityCounts::FACILITY_NAMES.len() * 2 + ModuleLevels::MODULE_NAMES.len());
+}
+
+#[test]
+fn test_upgrade_roi_payback() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let upgrades = vec![upgrade(UpgradeKind::FacilitySlot, "Farmland", 5, 2000.0)];
+    let balance = CurrencyBalance { coins: 1000.0, coupons: 0.0 };
+
+    let rois = calculate_upgrade_roi(&items, "coins", &counts, &modules, &upgrades, Some(&balance));
+
+    assert_eq!(rois.len(), 1);
+    let roi = &rois[0];
+    assert!(roi.upgraded_profit_per_second > roi.current_profit_per_second);
+    let gain = roi.upgraded_profit_per_second - roi.current_profit_per_second;
+    let payback = roi.payback_time.expect("An extra plot should pay for itself");
+    assert!((payback - 2000.0 / gain).abs() < 1e-6);
+    assert!(!roi.affordable, "1000 coins can't buy a 2000 coin upgrade");
+}
+
+#[test]
+fn test_upgrade_roi_in_other_currency_has_no_payback() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let coupon_slot = UpgradeCost {
+        coupons: 500.0,
+        ..upgrade(UpgradeKind::FacilitySlot, "Farmland", 5, 0.0)
+    };
+    let upgrades = vec![coupon_slot, upgrade(UpgradeKind::FacilitySlot, "Woodland", 2, 2000.0)];
+
+    let rois = calculate_upgrade_roi(&items, "coins", &counts, &modules, &upgrades, None);
+
+    // Coin profit can't repay coupons, so the plot ranks after the coin-priced upgrade
+    assert_eq!(rois.len(), 2);
+    assert_eq!(rois[0].upgrade.target, "Woodland");
+    assert!(rois[0].payback_time.is_some());
+    let plot = &rois[1];
+    assert!(plot.upgraded_profit_per_second > plot.current_profit_per_second);
+    assert_eq!(plot.payback_time, None);
+}
+
+#[test]
+fn test_load_upgrades() {
+    let path = Path::new("data/upgrades.csv");
+    if !path.exists() {
+        return;
+    }
+
+    let upgrades = load_upgrades(path).expect("Failed to load upgrades");
+    assert!(!upgrades.is_empty());
+
+    for u in &upgrades {
+        assert!(u.level > 0, "Upgrade level should be positive");
+        assert!(u.coins >= 0.0 && u.coupons >= 0.0, "Prices should be non-negative");
+    }
+}
+
+#[test]
+fn test_plan_progression_reaches_goal() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let upgrades = vec![
+        upgrade(UpgradeKind::FacilitySlot, "Farmland", 5, 2000.0),
+        upgrade(UpgradeKind::FacilitySlot, "Farmland", 6, 3000.0),
+        upgrade(UpgradeKind::FacilityLevel, "Farmland", 4, 6000.0),
+    ];
+
+    let mut goal_counts = counts.clone();
+    goal_counts.farmland = (6, 4);
+    let goal = ProgressionGoal {
+        facility_counts: Some(goal_counts),
+        ..Default::default()
+    };
+
+    let plan = plan_progression(
+        &items,
+        &[],
+        "coins",
+        &counts,
+        &modules,
+        &upgrades,
+        &CurrencyBalance::default(),
+        &goal,
+    );
+
+    assert!(plan.goal_reached);
+    assert_eq!(plan.milestones.len(), 3);
+    let last = plan.milestones.last().unwrap();
+    assert_eq!(last.facility_counts.farmland, (6, 4));
+
+    // Milestones happen in order and each one leaves a non-negative balance
+    let mut previous = 0.0;
+    for milestone in &plan.milestones {
+        assert!(milestone.purchase_time >= previous);
+        assert!(milestone.balance_after.coins >= 0.0);
+        previous = milestone.purchase_time;
+    }
+}
+
+#[test]
+fn test_plan_progression_respects_horizon() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let upgrades = load_upgrades(&data_dir.join("upgrades.csv")).expect("Failed to load upgrades");
+    let goal = ProgressionGoal {
+        time_horizon: Some(4.0 * 3600.0),
+        ..Default::default()
+    };
+
+    let plan = plan_progression(
+        &items,
+        &[],
+        "coins",
+        &counts,
+        &modules,
+        &upgrades,
+        &CurrencyBalance::default(),
+        &goal,
+    );
+
+    assert!(!plan.goal_reached, "No configuration goal was set");
+    assert!(plan.total_time <= 4.0 * 3600.0);
+    for milestone in &plan.milestones {
+        assert!(milestone.purchase_time <= 4.0 * 3600.0);
+    }
+}
+
+#[test]
+fn test_plan_progression_honours_override_windows() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let upgrades = vec![
+        upgrade(UpgradeKind::FacilitySlot, "Farmland", 5, 2000.0),
+        upgrade(UpgradeKind::FacilitySlot, "Farmland", 6, 3000.0),
+        upgrade(UpgradeKind::FacilityLevel, "Farmland", 4, 6000.0),
+    ];
+    let mut goal_counts = counts.clone();
+    goal_counts.farmland = (6, 4);
+    let goal = ProgressionGoal {
+        facility_counts: Some(goal_counts),
+        ..Default::default()
+    };
+    let double_prices = |start: Option<f64>, end: Option<f64>| ItemOverride {
+        scope: OverrideScope::Global,
+        field: OverrideField::SellValue,
+        operation: OverrideOperation::Multiply(2.0),
+        start,
+        end,
+    };
+    let plan_with = |overrides: &[ItemOverride]| {
+        plan_progression(
+            &items,
+            overrides,
+            "coins",
+            &counts,
+            &modules,
+            &upgrades,
+            &CurrencyBalance::default(),
+            &goal,
+        )
+    };
+
+    let baseline = plan_with(&[]);
+    let first_purchase = baseline.milestones[0].purchase_time;
+
+    // An event that starts after the plan is over changes nothing
+    let too_late = plan_with(&[double_prices(Some(baseline.total_time + 3600.0), None)]);
+    assert_eq!(too_late.total_time, baseline.total_time);
+
+    // An event running from the start speeds up the first purchase
+    let early = plan_with(&[double_prices(None, Some(first_purchase))]);
+    assert!(early.milestones[0].purchase_time < first_purchase);
+
+    // An event starting after the first purchase only speeds up the later ones
+    let later = plan_with(&[double_prices(Some(first_purchase), None)]);
+    assert_eq!(later.milestones[0].purchase_time, first_purchase);
+    assert!(later.total_time < baseline.total_time);
+}
+
+#[test]
+fn test_plan_progression_earns_both_currencies_side_by_side() {
+    let data_dir = Path::new("data");
+    if !data_dir.exists() {
+        return;
+    }
+
+    let items = load_all_data(data_dir).expect("Failed to load data");
+    let counts = default_facility_counts();
+    let modules = ModuleLevels::default();
+    let both = UpgradeCost {
+        coupons: 2000.0,
+        ..upgrade(UpgradeKind::FacilitySlot, "Farmland", 5, 5000.0)
+    };
+    let mut goal_counts = counts.clone();
+    goal_counts.farmland = (5, 3);
+    let goal = ProgressionGoal {
+        facility_counts: Some(goal_counts),
+        ..Default::default()
+    };
+
+    let plan = plan_progression(
+        &items,
+        &[],
+        "coins",
+        &counts,
+        &modules,
+        &[both],
+        &CurrencyBalance::default(),
+        &goal,
+    );
+    assert!(plan.goal_reached);
+    let milestone = &plan.milestones[0];
+    assert_eq!(milestone.production.len(), 2);
+
+    // Coupons come from the Mineral Pile while the plots earn the coins, so
+    // the purchase comes sooner than earning one currency after the other
+    let coupons = find_fastest_path(&items, "coupons", &counts, &modules, 2000.0).unwrap();
+    let coins = find_fastest_path(&items, "coins", &counts, &modules, 5000.0).unwrap();
+    assert!(milestone.purchase_time < coupons.total_time + coins.total_time);
+    assert!(milestone.purchase_time >= coupons.total_time);
+    assert!(milestone.balance_after.coins >= 0.0 && milestone.balance_after.coupons >= 0.0);
+}
@@ -158,6 +158,16 @@ function displayResults(result) {
         stepsList.appendChild(infoEl);
     }
 
+    // Income and expenses by currency (seeds are paid in coins even for coupon items)
+    if (result.ledger && result.ledger.length > 0) {
+        const ledgerEl = document.createElement('div');
+        ledgerEl.className = 'ledger-info';
+        ledgerEl.innerHTML = '<strong>Ledger</strong>' + result.ledger
+            .map(e => `${e.currency}: +${formatNumber(e.income)} income, -${formatNumber(e.expenses)} expenses, ${formatNumber(e.net)} net`)
+            .join('<br>');
+        stepsList.appendChild(ledgerEl);
+    }
+
     // Add parallel production info if applicable
     if (parallelMode && result.steps && result.steps.length > 1) {
         const infoEl = document.createElement('div');
@@ -254,6 +264,7 @@ function displayResults(result) {
                     <div class="step-meta">
                         ${step.time_seconds > 0 ? `Time: ${formatTime(step.time_seconds)}` : ''}
                         ${step.energy !== null && step.energy > 0 ? `<br>Energy: ${formatNumber(step.energy)}` : ''}
+                        ${step.energy_gained !== null && step.energy_gained > 0 ? `<br>Energy gained: ${formatNumber(step.energy_gained)}` : ''}
                     </div>
                 `;
                 chainContainer.appendChild(stepEl);
@@ -290,6 +301,7 @@ function displayResults(result) {
                 <div class="step-meta">
                     ${step.time_seconds > 0 ? `Time: ${formatTime(step.time_seconds)}` : ''}
                     ${step.energy !== null && step.energy > 0 ? `<br>Energy: ${formatNumber(step.energy)}` : ''}
+                    ${step.energy_gained !== null && step.energy_gained > 0 ? `<br>Energy gained: ${formatNumber(step.energy_gained)}` : ''}
                 </div>
             `;
             stepsList.appendChild(stepEl);